@@ -1,5 +1,20 @@
-use std::path::Path;
-use anyhow::Result;
+use std::borrow::Cow;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use anyhow::{Context, Result};
+use rodio::buffer::SamplesBuffer;
+use rodio::{Decoder, OutputStream, Sample, Sink, Source};
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+/// Fade-out máximo: más largo que esto ya no suena a notificación
+pub const MAX_FADE_MS: u64 = 2000;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum SoundType {
@@ -34,31 +49,402 @@ pub fn get_available_sounds() -> Vec<String> {
     ]
 }
 
+/// Sonido predefinido embebido en el binario (`assets/sounds`).
+pub struct Asset {
+    pub bytes: &'static [u8],
+    /// Formato, por la extensión del archivo original
+    pub format: &'static str,
+}
+
+macro_rules! asset {
+    ($file:literal, $format:literal) => {
+        Asset { bytes: include_bytes!(concat!("../../assets/sounds/", $file)), format: $format }
+    };
+}
+
+impl SoundType {
+    /// Archivo de `assets/sounds` que suena para cada tipo.
+    pub fn asset(&self) -> Asset {
+        match self {
+            Self::Woodensaw => asset!("woodensaw.wav", "wav"),
+            Self::ChatMessage => asset!("chatmessage.mp3", "mp3"),
+            Self::Tutick => asset!("tutick.wav", "wav"),
+            Self::Click => asset!("click.wav", "wav"),
+            Self::Tap => asset!("tap.m4a", "m4a"),
+            Self::Tap2 => asset!("tap2.m4a", "m4a"),
+            Self::Coin => asset!("coin.wav", "wav"),
+            Self::Stepsand => asset!("stepsand.mp3", "mp3"),
+            Self::Glass => asset!("glass.wav", "wav"),
+            Self::Signal => asset!("signal.wav", "wav"),
+            Self::Complete => asset!("complete.wav", "wav"),
+            Self::Thuddry => asset!("thuddry.wav", "wav"),
+        }
+    }
+
+    /// Ganancia para igualar la sonoridad de los sonidos predefinidos
+    /// (1.0 = sin cambio; los más fuertes se atenúan).
+    pub fn gain(&self) -> f32 {
+        match self {
+            Self::Woodensaw => 0.6,
+            Self::ChatMessage => 0.9,
+            Self::Tutick => 1.0,
+            Self::Click => 1.2,
+            Self::Tap => 1.2,
+            Self::Tap2 => 1.1,
+            Self::Coin => 0.7,
+            Self::Stepsand => 1.0,
+            Self::Glass => 0.8,
+            Self::Signal => 0.7,
+            Self::Complete => 0.8,
+            Self::Thuddry => 1.0,
+        }
+    }
+
+    /// Sonido predefinido a partir de su nombre (ver `get_available_sounds`).
+    pub fn from_name(name: &str) -> Result<Self> {
+        Ok(match name.to_lowercase().as_str() {
+            "woodensaw" => Self::Woodensaw,
+            "chatmessage" => Self::ChatMessage,
+            "tutick" => Self::Tutick,
+            "click" => Self::Click,
+            "tap" => Self::Tap,
+            "tap2" => Self::Tap2,
+            "coin" => Self::Coin,
+            "stepsand" => Self::Stepsand,
+            "glass" => Self::Glass,
+            "signal" => Self::Signal,
+            "complete" => Self::Complete,
+            "thuddry" => Self::Thuddry,
+            _ => anyhow::bail!(
+                "Unknown sound: {} (available: {})",
+                name,
+                get_available_sounds().join(", ")
+            ),
+        })
+    }
+}
+
 pub fn validate_custom_sound(path: &Path) -> Result<()> {
     if !path.exists() {
         anyhow::bail!("Sound file not found: {:?}", path);
     }
-    
+    custom_format(path).map(|_| ())
+}
+
+/// Formato de un archivo propio, por su extensión.
+fn custom_format(path: &Path) -> Result<&'static str> {
     let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
-    match ext.to_lowercase().as_str() {
-        "wav" | "mp3" | "ogg" | "flac" | "m4a" => Ok(()),
+    Ok(match ext.to_lowercase().as_str() {
+        "wav" => "wav",
+        "mp3" => "mp3",
+        "ogg" => "ogg",
+        "flac" => "flac",
+        "m4a" => "m4a",
         _ => anyhow::bail!("Unsupported audio format: {}", ext),
+    })
+}
+
+/// Volumen de reproducción: el configurado por la ganancia del sonido,
+/// limitado a 0.0-1.0 (un valor no numérico cuenta como silencio).
+pub fn playback_volume(volume: f32, gain: f32) -> f32 {
+    let volume = volume * gain;
+    if volume.is_nan() {
+        0.0
+    } else {
+        volume.clamp(0.0, 1.0)
     }
 }
 
+/// Duración del fade-out, limitada a `MAX_FADE_MS`.
+pub fn fade_duration(fade_ms: u64) -> Duration {
+    Duration::from_millis(fade_ms.min(MAX_FADE_MS))
+}
+
+/// Backend de reproducción disponible, o error si no hay ninguno.
+pub fn check_backend() -> Result<&'static str> {
+    OutputStream::try_default().context("No audio output device")?;
+    Ok("rodio, default output device")
+}
+
+/// Reproduce un sonido predefinido hasta el final. Bloquea: desde el runtime
+/// async hay que llamarla con `spawn_blocking`.
 pub fn play_sound(
-    _sound_type: SoundType,
-    _volume: f32,
+    sound_type: SoundType,
+    volume: f32,
+    fade_ms: u64,
 ) -> Result<()> {
-    // Placeholder: La reproducción de audio se implementaría con rodio
-    // Por ahora solo es un stub
-    Ok(())
+    let asset = sound_type.asset();
+    let volume = playback_volume(volume, sound_type.gain());
+    play(Cow::Borrowed(asset.bytes), asset.format, volume, fade_duration(fade_ms))
 }
 
+/// Reproduce un archivo propio; el formato sale de su extensión.
 pub fn play_custom_sound(
-    _path: &Path,
-    _volume: f32,
+    path: &Path,
+    volume: f32,
+    fade_ms: u64,
 ) -> Result<()> {
-    // Placeholder: La reproducción de audio personalizado
+    let format = custom_format(path)?;
+    let bytes = std::fs::read(path).with_context(|| format!("Cannot read sound file {}", path.display()))?;
+    play(Cow::Owned(bytes), format, playback_volume(volume, 1.0), fade_duration(fade_ms))
+}
+
+/// Lo que suena para un nombre de la configuración (`sound_type`,
+/// `failure_sound_type`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum SoundSource {
+    Builtin(SoundType),
+    Custom(PathBuf),
+}
+
+/// Un sonido predefinido si el nombre lo es y, si no, un archivo propio, que
+/// debe existir y tener un formato admitido.
+pub fn resolve_sound(name: &str) -> Result<SoundSource> {
+    if let Ok(sound_type) = SoundType::from_name(name) {
+        return Ok(SoundSource::Builtin(sound_type));
+    }
+    let path = Path::new(name);
+    // Sin separadores ni extensión, lo más probable es un nombre mal escrito
+    if !path.exists() && path.extension().is_none() && path.components().count() == 1 {
+        SoundType::from_name(name)?;
+    }
+    validate_custom_sound(path)?;
+    Ok(SoundSource::Custom(path.to_path_buf()))
+}
+
+/// Reproduce un sonido predefinido o un archivo personalizado por su nombre
+/// (`config --preview`, prueba del panel de configuración, avisos de fin de
+/// tarea).
+pub fn play_named(name: &str, volume: f32, fade_ms: u64) -> Result<()> {
+    play_source(resolve_sound(name)?, volume, fade_ms)
+}
+
+/// Reproduce un sonido ya resuelto con `resolve_sound`.
+pub fn play_source(source: SoundSource, volume: f32, fade_ms: u64) -> Result<()> {
+    match source {
+        SoundSource::Builtin(sound_type) => play_sound(sound_type, volume, fade_ms),
+        SoundSource::Custom(path) => play_custom_sound(&path, volume, fade_ms),
+    }
+}
+
+type Decoded = Box<dyn Source<Item = f32> + Send>;
+
+/// Decodifica `bytes` con el decodificador de `format` (la extensión del
+/// archivo), sin adivinarlo por el contenido.
+fn decode(bytes: Cow<'static, [u8]>, format: &str) -> Result<Decoded> {
+    let data = Cursor::new(bytes);
+    let decoded = match format {
+        "wav" => Decoder::new_wav(data),
+        "mp3" => Decoder::new_mp3(data),
+        "flac" => Decoder::new_flac(data),
+        "ogg" => Decoder::new_vorbis(data),
+        "m4a" => return decode_m4a(data.into_inner()).context("Cannot decode m4a audio"),
+        _ => anyhow::bail!("Unsupported audio format: {}", format),
+    };
+    let decoded = decoded.with_context(|| format!("Cannot decode {} audio", format))?;
+    Ok(Box::new(decoded.convert_samples()))
+}
+
+/// AAC en MP4 con symphonia directamente: rodio no le pasa a symphonia el
+/// tamaño de los datos, y sin él no encuentra el índice (`moov`) cuando va
+/// detrás del audio, como en los m4a de `assets/sounds`. Se decodifica
+/// entero de una vez; son sonidos cortos.
+fn decode_m4a(bytes: Cow<'static, [u8]>) -> Result<Decoded> {
+    let stream = MediaSourceStream::new(Box::new(Cursor::new(bytes)), Default::default());
+    let mut hint = Hint::new();
+    hint.with_extension("m4a");
+    let mut probed = symphonia::default::get_probe().format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())?;
+    let track = probed.format.default_track().context("no audio track")?;
+    let track_id = track.id;
+    let mut decoder = symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+    let mut samples = Vec::new();
+    let mut spec = None;
+    loop {
+        let packet = match probed.format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = decoder.decode(&packet)?;
+        let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, *decoded.spec());
+        spec.get_or_insert(*decoded.spec());
+        buffer.copy_interleaved_ref(decoded);
+        samples.extend_from_slice(buffer.samples());
+    }
+    let spec = spec.context("no audio frames")?;
+    Ok(Box::new(SamplesBuffer::new(spec.channels.count() as u16, spec.rate, samples)))
+}
+
+/// Decodifica `bytes` (en `format`) y los reproduce en la salida por
+/// defecto con `volume` y un fade-out de `fade` al final, hasta que acaban.
+fn play(bytes: Cow<'static, [u8]>, format: &str, volume: f32, fade: Duration) -> Result<()> {
+    let source = decode(bytes, format)?;
+    // El stream tiene que vivir hasta el final de la reproducción
+    let (_stream, handle) = OutputStream::try_default().context("No audio output device")?;
+    let sink = Sink::try_new(&handle).context("Cannot open the audio output")?;
+    sink.set_volume(volume);
+    sink.append(FadeOut::new(source, fade));
+    sink.sleep_until_end();
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Atenúa linealmente hasta el silencio los últimos `fade` de un sonido. Si
+/// el decodificador no sabe la duración total (p.ej. algunos mp3), suena
+/// sin fade-out.
+struct FadeOut<S> {
+    source: S,
+    /// Muestra (contando todos los canales) en la que empieza el fade
+    start: u64,
+    /// Muestras que dura el fade; 0 sin fade
+    length: u64,
+    position: u64,
+}
+
+impl<S: Source> FadeOut<S>
+where
+    S::Item: Sample,
+{
+    fn new(source: S, fade: Duration) -> Self {
+        let per_second = source.sample_rate() as f64 * source.channels() as f64;
+        let samples = |d: Duration| (d.as_secs_f64() * per_second) as u64;
+        let (start, length) = match source.total_duration() {
+            Some(total) if !fade.is_zero() => {
+                let length = samples(fade.min(total));
+                (samples(total).saturating_sub(length), length)
+            }
+            _ => (0, 0),
+        };
+        Self { source, start, length, position: 0 }
+    }
+
+    /// Ganancia de la muestra actual: 1.0 antes del fade y de ahí a 0.0.
+    fn gain(&self) -> f32 {
+        if self.length == 0 || self.position < self.start {
+            return 1.0;
+        }
+        let left = (self.start + self.length).saturating_sub(self.position);
+        left as f32 / self.length as f32
+    }
+}
+
+impl<S: Source> Iterator for FadeOut<S>
+where
+    S::Item: Sample,
+{
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<S::Item> {
+        let sample = self.source.next()?;
+        let gain = self.gain();
+        self.position += 1;
+        Some(if gain < 1.0 { sample.amplify(gain) } else { sample })
+    }
+}
+
+impl<S: Source> Source for FadeOut<S>
+where
+    S::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.source.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.source.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.source.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.source.total_duration()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_listed_sound_has_an_asset() {
+        let sounds = get_available_sounds();
+        assert_eq!(sounds.len(), 12);
+        for name in &sounds {
+            let sound_type = SoundType::from_name(name).unwrap();
+            assert!(!sound_type.asset().bytes.is_empty(), "{}", name);
+            assert_eq!(resolve_sound(name).unwrap(), SoundSource::Builtin(sound_type));
+        }
+    }
+
+    #[test]
+    fn unknown_preview_name_is_an_error() {
+        let err = play_named("nosuchsound", 0.5, 0).unwrap_err();
+        assert!(err.to_string().starts_with("Unknown sound: nosuchsound (available: woodensaw,"), "{}", err);
+    }
+
+    #[test]
+    fn gain_table_scales_the_configured_volume() {
+        assert_eq!(SoundType::Woodensaw.gain(), 0.6);
+        assert_eq!(SoundType::Click.gain(), 1.2);
+        assert!((playback_volume(0.5, SoundType::Woodensaw.gain()) - 0.3).abs() < 1e-6);
+        assert!((playback_volume(0.5, SoundType::Click.gain()) - 0.6).abs() < 1e-6);
+        for name in get_available_sounds() {
+            let gain = SoundType::from_name(&name).unwrap().gain();
+            assert!((0.5..=1.5).contains(&gain), "{}: {}", name, gain);
+        }
+    }
+
+    #[test]
+    fn fade_duration_is_clamped() {
+        assert_eq!(fade_duration(0), Duration::ZERO);
+        assert_eq!(fade_duration(150), Duration::from_millis(150));
+        assert_eq!(fade_duration(MAX_FADE_MS), Duration::from_millis(MAX_FADE_MS));
+        assert_eq!(fade_duration(60_000), Duration::from_millis(MAX_FADE_MS));
+    }
+
+    #[test]
+    fn bundled_sounds_decode() {
+        for name in get_available_sounds() {
+            let asset = SoundType::from_name(&name).unwrap().asset();
+            let source = decode(Cow::Borrowed(asset.bytes), asset.format).unwrap_or_else(|e| panic!("{}: {:#}", name, e));
+            assert!(source.channels() > 0 && source.sample_rate() > 0, "{}", name);
+            assert!(source.take(1000).count() > 0, "{} no tiene muestras", name);
+        }
+    }
+
+    #[test]
+    fn undecodable_audio_is_an_error() {
+        let Err(err) = decode(Cow::Borrowed(b"not audio"), "wav") else { panic!("se esperaba un error") };
+        assert!(err.to_string().contains("Cannot decode wav audio"), "{:#}", err);
+        let wav = SoundType::Complete.asset().bytes;
+        assert!(decode(Cow::Borrowed(wav), "aiff").is_err());
+    }
+
+    #[test]
+    fn volume_is_clamped() {
+        assert_eq!(playback_volume(1.0, 1.2), 1.0);
+        assert_eq!(playback_volume(5.0, 1.0), 1.0);
+        assert_eq!(playback_volume(-0.5, 1.0), 0.0);
+        assert_eq!(playback_volume(f32::NAN, 1.0), 0.0);
+        assert_eq!(playback_volume(f32::INFINITY, 0.5), 1.0);
+        assert_eq!(playback_volume(0.5, 1.0), 0.5);
+    }
+
+    #[test]
+    fn fade_out_ramps_the_tail_to_silence() {
+        // 1 s a 10 Hz en mono: las 3 últimas muestras se atenúan
+        let source = rodio::buffer::SamplesBuffer::new(1, 10, vec![1.0f32; 10]);
+        let samples: Vec<f32> = FadeOut::new(source, Duration::from_millis(300)).collect();
+        assert_eq!(&samples[..7], &[1.0; 7]);
+        assert_eq!(&samples[7..], &[1.0, 2.0 / 3.0, 1.0 / 3.0]);
+
+        let source = rodio::buffer::SamplesBuffer::new(1, 10, vec![1.0f32; 10]);
+        let samples: Vec<f32> = FadeOut::new(source, Duration::ZERO).collect();
+        assert_eq!(samples, [1.0; 10]);
+    }
+}
@@ -0,0 +1,228 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Muestras mínimas por ventana antes de decidir un ajuste.
+const MIN_WINDOW: usize = 10;
+
+/// La concurrencia adaptativa puede subir hasta este múltiplo de `--max-concurrent`.
+pub const CEILING_FACTOR: usize = 4;
+
+/// Controlador de `--target-latency`: acumula latencias y, cada ventana
+/// (al menos tantas muestras como peticiones simultáneas), compara su p90 con
+/// el objetivo. Por debajo del 80% del objetivo suma una petición; por encima
+/// lo reduce un 25% (AIMD), para reaccionar rápido cuando el servidor se satura.
+#[derive(Debug)]
+pub struct LatencyController {
+    target: Duration,
+    min: usize,
+    max: usize,
+    window: Vec<Duration>,
+}
+
+impl LatencyController {
+    pub fn new(target: Duration, min: usize, max: usize) -> Self {
+        Self { target, min: min.max(1), max: max.max(min.max(1)), window: Vec::new() }
+    }
+
+    /// Añade una latencia observada con `limit` peticiones permitidas y
+    /// devuelve el nuevo límite si la ventana está completa y hay que cambiarlo.
+    pub fn observe(&mut self, latency: Duration, limit: usize) -> Option<usize> {
+        self.window.push(latency);
+        if self.window.len() < limit.max(MIN_WINDOW) {
+            return None;
+        }
+        let p90 = percentile(&mut self.window, 90);
+        self.window.clear();
+        let next = if p90 > self.target {
+            (limit * 3 / 4).min(limit - 1)
+        } else if p90 < self.target.mul_f64(0.8) {
+            limit + 1
+        } else {
+            limit
+        };
+        let next = next.clamp(self.min, self.max);
+        (next != limit).then_some(next)
+    }
+}
+
+/// Percentil `p` (0-100) de las muestras, por el método del rango más cercano.
+fn percentile(samples: &mut [Duration], p: usize) -> Duration {
+    samples.sort_unstable();
+    let rank = (samples.len() * p).div_ceil(100).max(1);
+    samples[rank - 1]
+}
+
+/// Semáforo de tamaño variable gobernado por un `LatencyController`.
+///
+/// Crecer añade permisos; encoger retira los libres y, si no bastan, deja una
+/// deuda que se cobra quedándose con los permisos según se devuelven, de modo
+/// que las peticiones en curso nunca se interrumpen.
+pub struct AdaptiveLimiter {
+    semaphore: Arc<Semaphore>,
+    state: Mutex<LimiterState>,
+}
+
+struct LimiterState {
+    limit: usize,
+    debt: usize,
+    controller: LatencyController,
+}
+
+impl AdaptiveLimiter {
+    pub fn new(initial: usize, controller: LatencyController) -> Arc<Self> {
+        let initial = initial.clamp(controller.min, controller.max);
+        Arc::new(Self {
+            semaphore: Arc::new(Semaphore::new(initial)),
+            state: Mutex::new(LimiterState { limit: initial, debt: 0, controller }),
+        })
+    }
+
+    /// Límite fijo: el controlador nunca lo mueve.
+    pub fn fixed(limit: usize) -> Arc<Self> {
+        Self::new(limit, LatencyController::new(Duration::ZERO, limit, limit))
+    }
+
+    /// Concurrencia permitida en este momento.
+    pub fn limit(&self) -> usize {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).limit
+    }
+
+    pub async fn acquire(self: &Arc<Self>) -> anyhow::Result<AdaptivePermit> {
+        let permit = self.semaphore.clone().acquire_owned().await?;
+        Ok(AdaptivePermit { permit: Some(permit), limiter: self.clone() })
+    }
+
+    /// Registra la latencia de una petición y aplica el ajuste que decida el controlador.
+    pub fn observe(&self, latency: Duration) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let limit = state.limit;
+        let Some(next) = state.controller.observe(latency, limit) else {
+            return;
+        };
+        if next > limit {
+            let grow = next - limit;
+            let forgiven = grow.min(state.debt);
+            state.debt -= forgiven;
+            self.semaphore.add_permits(grow - forgiven);
+        } else {
+            let shrink = limit - next;
+            let forgotten = self.semaphore.forget_permits(shrink);
+            state.debt += shrink - forgotten;
+        }
+        state.limit = next;
+    }
+}
+
+/// Permiso de `AdaptiveLimiter`; al soltarlo vuelve al semáforo salvo que
+/// haya deuda pendiente de un recorte.
+pub struct AdaptivePermit {
+    permit: Option<OwnedSemaphorePermit>,
+    limiter: Arc<AdaptiveLimiter>,
+}
+
+impl Drop for AdaptivePermit {
+    fn drop(&mut self) {
+        let mut state = self.limiter.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.debt > 0 {
+            state.debt -= 1;
+            if let Some(permit) = self.permit.take() {
+                permit.forget();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGET: Duration = Duration::from_millis(100);
+
+    /// Alimenta al controlador con ventanas completas de `latency` y devuelve
+    /// el límite tras cada ajuste.
+    fn feed(controller: &mut LatencyController, mut limit: usize, latency: Duration, windows: usize) -> Vec<usize> {
+        let mut limits = Vec::new();
+        for _ in 0..windows {
+            for _ in 0..limit.max(MIN_WINDOW) {
+                if let Some(next) = controller.observe(latency, limit) {
+                    limit = next;
+                }
+            }
+            limits.push(limit);
+        }
+        limits
+    }
+
+    #[test]
+    fn low_latency_grows_one_step_per_window_up_to_max() {
+        let mut controller = LatencyController::new(TARGET, 1, 6);
+        let limits = feed(&mut controller, 4, Duration::from_millis(20), 4);
+        assert_eq!(limits, vec![5, 6, 6, 6]);
+    }
+
+    #[test]
+    fn high_latency_shrinks_multiplicatively_down_to_min() {
+        let mut controller = LatencyController::new(TARGET, 2, 64);
+        let limits = feed(&mut controller, 40, Duration::from_millis(300), 8);
+        assert_eq!(limits, vec![30, 22, 16, 12, 9, 6, 4, 3]);
+        let limits = feed(&mut controller, 3, Duration::from_millis(300), 2);
+        assert_eq!(limits, vec![2, 2]);
+    }
+
+    #[test]
+    fn latency_near_the_target_keeps_the_limit() {
+        let mut controller = LatencyController::new(TARGET, 1, 64);
+        assert_eq!(feed(&mut controller, 8, Duration::from_millis(90), 3), vec![8, 8, 8]);
+    }
+
+    #[test]
+    fn the_p90_decides_not_the_mean() {
+        let mut controller = LatencyController::new(TARGET, 1, 64);
+        // Un 20% de muestras lentas arrastra el p90 por encima del objetivo
+        let mut next = None;
+        for i in 0..10 {
+            let latency = if i < 2 { Duration::from_secs(1) } else { Duration::from_millis(10) };
+            next = controller.observe(latency, 8);
+        }
+        assert_eq!(next, Some(6));
+        // Con un 10% de lentas, el p90 cae en las rápidas
+        for i in 0..10 {
+            let latency = if i < 1 { Duration::from_secs(1) } else { Duration::from_millis(10) };
+            next = controller.observe(latency, 6);
+        }
+        assert_eq!(next, Some(7));
+    }
+
+    #[tokio::test]
+    async fn limiter_settles_and_shrinking_never_cancels_permits() {
+        let limiter = AdaptiveLimiter::new(4, LatencyController::new(TARGET, 1, 8));
+        let mut held = Vec::new();
+        for _ in 0..4 {
+            held.push(limiter.acquire().await.unwrap());
+        }
+        // Una ventana lenta con los 4 permisos en uso: el recorte queda como deuda
+        for _ in 0..MIN_WINDOW {
+            limiter.observe(Duration::from_millis(500));
+        }
+        assert_eq!(limiter.limit(), 3);
+        held.clear();
+        assert_eq!(limiter.semaphore.available_permits(), 3);
+
+        for _ in 0..4 * MIN_WINDOW {
+            limiter.observe(Duration::from_millis(10));
+        }
+        assert_eq!(limiter.limit(), 7);
+        assert_eq!(limiter.semaphore.available_permits(), 7);
+    }
+
+    #[test]
+    fn fixed_limiter_never_moves() {
+        let limiter = AdaptiveLimiter::fixed(5);
+        for _ in 0..100 {
+            limiter.observe(Duration::from_secs(10));
+        }
+        assert_eq!(limiter.limit(), 5);
+    }
+}
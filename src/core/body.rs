@@ -0,0 +1,97 @@
+use crate::core::Downzer;
+
+/// Cuerpo de `--data`/`--data-file` para webrequest. Puede llevar
+/// placeholders (`{"user":"admin","pass":"FUZZW1"}`), que se sustituyen con
+/// la combinación de cada URL igual que en la plantilla, de modo que cada
+/// petición envía su propio payload.
+#[derive(Debug, Clone)]
+pub struct BodyTemplate {
+    template: String,
+    /// Posiciones de la combinación que son rangos (ver `substitute_placeholders`)
+    range_count: usize,
+}
+
+impl BodyTemplate {
+    pub fn new(template: String, range_count: usize) -> Self {
+        Self { template, range_count }
+    }
+
+    /// Cuerpo para la combinación `words`.
+    pub fn resolve(&self, words: &[String]) -> String {
+        Downzer::substitute_placeholders(&self.template, words, self.range_count)
+    }
+
+    pub fn len(&self) -> usize {
+        self.template.len()
+    }
+
+    /// `Content-Type` que se envía si no hay uno en `--header`: JSON o XML
+    /// si lo parece y, si no, formulario, como `curl --data`.
+    pub fn content_type(&self) -> &'static str {
+        let start = self.template.trim_start();
+        if start.starts_with('{') || start.starts_with('[') {
+            "application/json"
+        } else if start.starts_with('<') {
+            "application/xml"
+        } else {
+            "application/x-www-form-urlencoded"
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Target, Targets};
+    use crate::testutil::{self, MockServer, Response};
+
+    #[test]
+    fn placeholders_are_substituted_per_combination() {
+        let body = BodyTemplate::new(r#"{"id":FUZZR,"user":"FUZZW1","pass":"FUZZW2"}"#.to_string(), 1);
+        let words: Vec<String> = ["7", "admin", "s3cret"].iter().map(|w| w.to_string()).collect();
+        assert_eq!(body.resolve(&words), r#"{"id":7,"user":"admin","pass":"s3cret"}"#);
+    }
+
+    #[test]
+    fn content_type_follows_the_body() {
+        let content_type = |body: &str| BodyTemplate::new(body.to_string(), 0).content_type();
+        assert_eq!(content_type("  {\"a\":1}"), "application/json");
+        assert_eq!(content_type("[1,2]"), "application/json");
+        assert_eq!(content_type("<a>FUZZW1</a>"), "application/xml");
+        assert_eq!(content_type("user=FUZZW1&x=1"), "application/x-www-form-urlencoded");
+    }
+
+    #[tokio::test]
+    async fn webrequest_posts_each_substituted_body() {
+        let server = MockServer::start(|_| Response::new(200)).await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testutil::mode_config("webrequest", dir.path());
+        config.method = Some("POST".to_string());
+        config.body = Some(BodyTemplate::new("user=admin&pass=FUZZW1".to_string(), 0));
+        let targets: Vec<Target> = ["a1", "b2"]
+            .iter()
+            .map(|w| Target { url: server.url("/login"), words: vec![w.to_string()], indices: vec![] })
+            .collect();
+        testutil::run_targets(config, Targets::new(targets.into_iter(), 2)).await;
+
+        let mut bodies: Vec<String> = server.requests().iter().map(|r| String::from_utf8(r.body.clone()).unwrap()).collect();
+        bodies.sort();
+        assert_eq!(bodies, ["user=admin&pass=a1", "user=admin&pass=b2"]);
+        for request in server.requests() {
+            assert_eq!(request.method, "POST");
+            assert_eq!(request.header("content-type"), Some("application/x-www-form-urlencoded"));
+        }
+    }
+
+    #[tokio::test]
+    async fn an_explicit_content_type_is_kept() {
+        let server = MockServer::start(|_| Response::new(200)).await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testutil::mode_config("webrequest", dir.path());
+        config.method = Some("PUT".to_string());
+        config.body = Some(BodyTemplate::new("{\"a\":1}".to_string(), 0));
+        config.headers = crate::core::headers::HeaderTemplates::new(vec![("Content-Type".into(), "text/plain".into())], 0);
+        testutil::run(config, vec![server.url("/")]).await;
+        assert_eq!(server.requests()[0].header("content-type"), Some("text/plain"));
+    }
+}
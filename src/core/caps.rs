@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use anyhow::Result;
+
+/// Límite de archivos por extensión (`--cap-per-extension ext=N`), compartido
+/// por todas las descargas de la tarea.
+#[derive(Debug, Default)]
+pub struct ExtensionCaps {
+    limits: HashMap<String, usize>,
+    used: Mutex<HashMap<String, usize>>,
+}
+
+impl ExtensionCaps {
+    pub fn new(limits: HashMap<String, usize>) -> Self {
+        Self { limits, used: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.limits.is_empty()
+    }
+
+    /// Reserva un hueco para un archivo de extensión `ext`, o `None` si el
+    /// cupo de ese tipo está agotado. Los tipos sin límite siempre caben.
+    /// El hueco se devuelve al soltar la reserva salvo que se llame a `keep`.
+    pub fn reserve(&self, ext: Option<&str>) -> Option<CapSlot<'_>> {
+        let Some((ext, limit)) = ext.and_then(|e| self.limits.get_key_value(e)) else {
+            return Some(CapSlot { caps: self, ext: None });
+        };
+        let mut used = self.used.lock().unwrap_or_else(|e| e.into_inner());
+        let count = used.entry(ext.clone()).or_insert(0);
+        if *count >= *limit {
+            return None;
+        }
+        *count += 1;
+        Some(CapSlot { caps: self, ext: Some(ext.clone()) })
+    }
+
+    fn release(&self, ext: &str) {
+        let mut used = self.used.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(count) = used.get_mut(ext) {
+            *count = count.saturating_sub(1);
+        }
+    }
+}
+
+/// Hueco reservado en `ExtensionCaps`. Una descarga que no llega a guardarse
+/// (error, filtro de tamaño...) lo libera para la siguiente.
+pub struct CapSlot<'a> {
+    caps: &'a ExtensionCaps,
+    ext: Option<String>,
+}
+
+impl CapSlot<'_> {
+    /// El archivo se ha guardado: el hueco queda consumido.
+    pub fn keep(mut self) {
+        self.ext = None;
+    }
+}
+
+impl Drop for CapSlot<'_> {
+    fn drop(&mut self) {
+        if let Some(ext) = &self.ext {
+            self.caps.release(ext);
+        }
+    }
+}
+
+/// Interpreta `ext=N` (`pdf=100`, `.PDF=100`): extensión en minúsculas y sin punto.
+pub fn parse_cap(spec: &str) -> Result<(String, usize)> {
+    let (ext, limit) = spec
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("Invalid --cap-per-extension '{}' (expected ext=N)", spec))?;
+    let ext = ext.trim().trim_start_matches('.').to_lowercase();
+    if ext.is_empty() {
+        anyhow::bail!("Invalid --cap-per-extension '{}': empty extension", spec);
+    }
+    let limit = limit
+        .trim()
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid --cap-per-extension '{}': '{}' is not a count", spec, limit.trim()))?;
+    Ok((ext, limit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn caps(spec: &str) -> ExtensionCaps {
+        ExtensionCaps::new(HashMap::from([parse_cap(spec).unwrap()]))
+    }
+
+    #[test]
+    fn file_past_the_cap_is_refused() {
+        let caps = caps("pdf=2");
+        caps.reserve(Some("pdf")).unwrap().keep();
+        caps.reserve(Some("pdf")).unwrap().keep();
+        assert!(caps.reserve(Some("pdf")).is_none());
+        // Otros tipos y los archivos sin extensión no tienen límite
+        assert!(caps.reserve(Some("png")).is_some());
+        assert!(caps.reserve(None).is_some());
+    }
+
+    #[test]
+    fn released_slot_goes_to_the_next_file() {
+        let caps = caps("pdf=1");
+        let slot = caps.reserve(Some("pdf")).unwrap();
+        assert!(caps.reserve(Some("pdf")).is_none());
+        drop(slot);
+        assert!(caps.reserve(Some("pdf")).is_some());
+    }
+
+    #[test]
+    fn cap_specs_are_normalized_and_validated() {
+        assert_eq!(parse_cap(".PDF=100").unwrap(), ("pdf".to_string(), 100));
+        assert!(parse_cap("pdf").is_err());
+        assert!(parse_cap("=3").is_err());
+        assert!(parse_cap("pdf=many").is_err());
+    }
+
+    #[tokio::test]
+    async fn downloads_past_the_cap_are_skipped() {
+        use crate::testutil::{self, MockServer, Response};
+
+        let server = MockServer::start(|_| Response::new(200).body("data")).await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testutil::mode_config("download", dir.path());
+        config.max_concurrent = 1;
+        config.extension_caps = HashMap::from([("pdf".to_string(), 2)]);
+        let urls = ["/a.pdf", "/b.pdf", "/c.pdf", "/d.txt"].iter().map(|p| server.url(p)).collect();
+        let result = testutil::run(config, urls).await;
+        assert_eq!(result.successful, 3);
+        let names: Vec<String> = testutil::files_in(dir.path())
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, ["a.pdf", "b.pdf", "d.txt"]);
+    }
+}
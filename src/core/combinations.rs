@@ -0,0 +1,204 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+/// Máximo de combinaciones que `--random` puede barajar: el orden aleatorio se
+/// obtiene barajando índices, que sí ocupan memoria (8 bytes por combinación).
+pub const MAX_SHUFFLED_COMBINATIONS: usize = 10_000_000;
+
+/// RNG para el orden aleatorio: con `--seed` el orden es reproducible.
+pub fn shuffle_rng(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    }
+}
+
+/// Generador perezoso de combinaciones.
+///
+/// En lugar de materializar el producto cartesiano, cada combinación se
+/// calcula a partir de su índice (odómetro en base mixta, la última lista
+/// varía más rápido), así que la memoria no depende del número total.
+pub struct CombinationIter {
+    lists: Vec<Vec<String>>,
+    parallel: bool,
+    total: usize,
+    next: usize,
+    // Orden barajado de índices (--random)
+    order: Option<Vec<usize>>,
+}
+
+impl CombinationIter {
+    pub fn new(lists: Vec<Vec<String>>, parallel: bool) -> Self {
+        let total = Self::count(&lists, parallel);
+        Self { lists, parallel, total, next: 0, order: None }
+    }
+
+    /// Número de combinaciones que producen `lists` (sin generarlas).
+    pub fn count(lists: &[Vec<String>], parallel: bool) -> usize {
+        if lists.is_empty() || lists.iter().any(|l| l.is_empty()) {
+            0
+        } else if parallel {
+            lists[0].len()
+        } else {
+            lists.iter().fold(1usize, |acc, l| acc.saturating_mul(l.len()))
+        }
+    }
+
+    /// Igual que `new`, pero recorre las combinaciones en orden aleatorio.
+    pub fn shuffled(lists: Vec<Vec<String>>, parallel: bool, seed: Option<u64>) -> anyhow::Result<Self> {
+        let mut iter = Self::new(lists, parallel);
+        if iter.total > MAX_SHUFFLED_COMBINATIONS {
+            anyhow::bail!(
+                "--random would need to shuffle {} combinations (max {}). Reduce the lists, add --max-combos or drop --random",
+                iter.total,
+                MAX_SHUFFLED_COMBINATIONS
+            );
+        }
+        let mut order: Vec<usize> = (0..iter.total).collect();
+        order.shuffle(&mut shuffle_rng(seed));
+        iter.order = Some(order);
+        Ok(iter)
+    }
+
+    /// Muestra aleatoria de `amount` combinaciones distintas, en orden aleatorio.
+    /// Solo reserva memoria para la muestra, no para el total.
+    pub fn sampled(lists: Vec<Vec<String>>, parallel: bool, amount: usize, seed: Option<u64>) -> Self {
+        let mut iter = Self::new(lists, parallel);
+        let amount = amount.min(iter.total);
+        let order = rand::seq::index::sample(&mut shuffle_rng(seed), iter.total, amount).into_vec();
+        iter.total = amount;
+        iter.order = Some(order);
+        iter
+    }
+
+    /// Limita el recorrido a las primeras `max` combinaciones.
+    pub fn truncated(mut self, max: usize) -> Self {
+        self.total = self.total.min(max);
+        if let Some(order) = &mut self.order {
+            order.truncate(max);
+        }
+        self
+    }
+
+    /// Número total de combinaciones.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// Recorre las combinaciones junto con la posición de cada valor en su
+    /// lista de origen (para trazar un resultado hasta la entrada de la wordlist).
+    pub fn indexed(mut self) -> impl Iterator<Item = (Vec<String>, Vec<usize>)> {
+        std::iter::from_fn(move || {
+            let indices = self.next_indices()?;
+            Some((self.words_at(&indices), indices))
+        })
+    }
+
+    fn next_indices(&mut self) -> Option<Vec<usize>> {
+        if self.next >= self.total {
+            return None;
+        }
+        let position = self.next;
+        self.next += 1;
+        let index = match &self.order {
+            Some(order) => order[position],
+            None => position,
+        };
+        Some(self.indices_at(index))
+    }
+
+    fn indices_at(&self, mut index: usize) -> Vec<usize> {
+        if self.parallel {
+            return self.lists.iter().map(|l| index % l.len()).collect();
+        }
+
+        let mut indices = vec![0; self.lists.len()];
+        for (slot, list) in indices.iter_mut().zip(&self.lists).rev() {
+            *slot = index % list.len();
+            index /= list.len();
+        }
+        indices
+    }
+
+    fn words_at(&self, indices: &[usize]) -> Vec<String> {
+        self.lists.iter().zip(indices).map(|(list, &i)| list[i].clone()).collect()
+    }
+}
+
+impl Iterator for CombinationIter {
+    type Item = Vec<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let indices = self.next_indices()?;
+        Some(self.words_at(&indices))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(prefix: &str, len: usize) -> Vec<String> {
+        (0..len).map(|i| format!("{}{}", prefix, i)).collect()
+    }
+
+    #[test]
+    fn odometer_order_matches_the_cartesian_product() {
+        let lists = vec![list("a", 2), list("b", 3), list("c", 2)];
+        let mut expected = Vec::new();
+        for a in &lists[0] {
+            for b in &lists[1] {
+                for c in &lists[2] {
+                    expected.push(vec![a.clone(), b.clone(), c.clone()]);
+                }
+            }
+        }
+        let iter = CombinationIter::new(lists, false);
+        assert_eq!(iter.total(), 12);
+        assert_eq!(iter.collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn huge_products_are_generated_on_demand() {
+        // 10^12 combinaciones: materializarlas no cabría en memoria
+        let lists = vec![list("a", 10_000), list("b", 10_000), list("c", 10_000)];
+        let mut iter = CombinationIter::new(lists, false).indexed();
+        assert_eq!(iter.next(), Some((vec!["a0".into(), "b0".into(), "c0".into()], vec![0, 0, 0])));
+        assert_eq!(iter.next(), Some((vec!["a0".into(), "b0".into(), "c1".into()], vec![0, 0, 1])));
+        assert_eq!(CombinationIter::count(&[list("a", 10_000), list("b", 10_000), list("c", 10_000)], false), 1_000_000_000_000);
+    }
+
+    #[test]
+    fn parallel_walks_the_lists_in_step() {
+        let combos: Vec<_> = CombinationIter::new(vec![list("a", 3), list("b", 2)], true).collect();
+        assert_eq!(combos, [vec!["a0", "b0"], vec!["a1", "b1"], vec!["a2", "b0"]]);
+    }
+
+    #[test]
+    fn empty_list_yields_nothing() {
+        assert_eq!(CombinationIter::new(vec![list("a", 3), Vec::new()], false).count(), 0);
+    }
+
+    fn shuffled(seed: u64) -> Vec<Vec<String>> {
+        CombinationIter::shuffled(vec![list("a", 10), list("b", 10)], false, Some(seed)).unwrap().collect()
+    }
+
+    #[test]
+    fn same_seed_same_order() {
+        assert_eq!(shuffled(42), shuffled(42));
+        assert_ne!(shuffled(42), shuffled(43));
+        // Es una permutación: están todas las combinaciones
+        let mut sorted = shuffled(42);
+        sorted.sort();
+        assert_eq!(sorted, CombinationIter::new(vec![list("a", 10), list("b", 10)], false).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn same_seed_same_sample() {
+        let sample = |seed| CombinationIter::sampled(vec![list("a", 100)], false, 5, Some(seed)).collect::<Vec<_>>();
+        assert_eq!(sample(1), sample(1));
+        assert_ne!(sample(1), sample(2));
+        assert_eq!(sample(1).len(), 5);
+    }
+}
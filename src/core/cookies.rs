@@ -0,0 +1,326 @@
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, HeaderValue, COOKIE, SET_COOKIE};
+use url::Url;
+
+/// Cookie guardada en el jar.
+#[derive(Debug, Clone)]
+struct StoredCookie {
+    name: String,
+    value: String,
+    /// Dominio en minúsculas y sin punto inicial
+    domain: String,
+    /// Solo el host exacto (sin atributo `Domain`); si no, también sus subdominios
+    host_only: bool,
+    path: String,
+    secure: bool,
+    /// Segundos Unix; `None` para cookies de sesión
+    expires: Option<i64>,
+}
+
+impl StoredCookie {
+    fn expired(&self, now: i64) -> bool {
+        self.expires.is_some_and(|t| t <= now)
+    }
+
+    fn matches(&self, url: &Url, now: i64) -> bool {
+        let Some(host) = url.host_str().map(str::to_ascii_lowercase) else {
+            return false;
+        };
+        let domain_ok = host == self.domain
+            || (!self.host_only && host.ends_with(&format!(".{}", self.domain)));
+        domain_ok
+            && path_matches(url.path(), &self.path)
+            && (!self.secure || url.scheme() == "https")
+            && !self.expired(now)
+    }
+}
+
+/// Cookie jar de `--cookie-jar`: guarda las cookies de los `Set-Cookie`
+/// recibidos y las envía en las peticiones siguientes a los hosts que
+/// correspondan (dominio, ruta, `Secure` y caducidad).
+///
+/// Se carga de y se guarda en un archivo en formato Netscape, el de
+/// `curl -b/-c`. Las cookies de sesión se escriben con caducidad 0, como hace curl.
+#[derive(Debug, Default)]
+pub struct CookieJar {
+    cookies: Mutex<Vec<StoredCookie>>,
+}
+
+impl CookieJar {
+    /// Carga el jar; si el archivo no existe, empieza vacío.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let data = match std::fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(anyhow::anyhow!("Cannot read cookie jar {}: {}", path.display(), e)),
+        };
+        let mut cookies = Vec::new();
+        for (n, line) in data.lines().enumerate() {
+            // curl marca las HttpOnly con este prefijo; el resto de `#` son comentarios
+            let line = line.strip_prefix("#HttpOnly_").unwrap_or(line);
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [domain, subdomains, cookie_path, secure, expires, name, value] = fields[..] else {
+                anyhow::bail!("Invalid cookie jar {}: line {} does not have 7 fields", path.display(), n + 1);
+            };
+            let expires: i64 = expires
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid cookie jar {}: bad expiry on line {}", path.display(), n + 1))?;
+            cookies.push(StoredCookie {
+                name: name.to_string(),
+                value: value.to_string(),
+                domain: domain.trim_start_matches('.').to_ascii_lowercase(),
+                host_only: !subdomains.eq_ignore_ascii_case("TRUE"),
+                path: cookie_path.to_string(),
+                secure: secure.eq_ignore_ascii_case("TRUE"),
+                expires: (expires != 0).then_some(expires),
+            });
+        }
+        Ok(Self { cookies: Mutex::new(cookies) })
+    }
+
+    /// Escribe las cookies vigentes en formato Netscape.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let now = Utc::now().timestamp();
+        let cookies = self.cookies.lock().unwrap_or_else(|e| e.into_inner());
+        let mut out = BufWriter::new(std::fs::File::create(path)?);
+        writeln!(out, "# Netscape HTTP Cookie File")?;
+        for cookie in cookies.iter().filter(|c| !c.expired(now)) {
+            let domain = if cookie.host_only { cookie.domain.clone() } else { format!(".{}", cookie.domain) };
+            writeln!(
+                out,
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                domain,
+                if cookie.host_only { "FALSE" } else { "TRUE" },
+                cookie.path,
+                if cookie.secure { "TRUE" } else { "FALSE" },
+                cookie.expires.unwrap_or(0),
+                cookie.name,
+                cookie.value
+            )?;
+        }
+        out.flush()
+    }
+
+    /// Número de cookies guardadas (incluidas las caducadas aún no purgadas).
+    pub fn len(&self) -> usize {
+        self.cookies.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Añade a `headers` las cookies del jar que corresponden a `url`. Las que
+    /// ya van en la cabecera `Cookie` (`--cookie`, `-H`) tienen prioridad: el
+    /// jar no repite un nombre explícito.
+    pub fn apply(&self, url: &str, headers: &mut HeaderMap) {
+        let Ok(url) = Url::parse(url) else {
+            return;
+        };
+        let now = Utc::now().timestamp();
+        let mut explicit: Vec<String> = headers.get_all(COOKIE).iter().filter_map(|v| v.to_str().ok()).map(String::from).collect();
+        let names: Vec<&str> = explicit
+            .iter()
+            .flat_map(|h| h.split(';'))
+            .filter_map(|pair| pair.split_once('=').map(|(n, _)| n.trim()))
+            .collect();
+
+        let mut matching: Vec<StoredCookie> = {
+            let cookies = self.cookies.lock().unwrap_or_else(|e| e.into_inner());
+            cookies
+                .iter()
+                .filter(|c| c.matches(&url, now) && !names.contains(&c.name.as_str()))
+                .cloned()
+                .collect()
+        };
+        if matching.is_empty() {
+            return;
+        }
+        // Las rutas más específicas primero (RFC 6265, 5.4)
+        matching.sort_by_key(|c| std::cmp::Reverse(c.path.len()));
+        explicit.extend(matching.iter().map(|c| format!("{}={}", c.name, c.value)));
+        if let Ok(value) = HeaderValue::from_str(&explicit.join("; ")) {
+            headers.insert(COOKIE, value);
+        }
+    }
+
+    /// Guarda las cookies de los `Set-Cookie` de una respuesta de `url`. Una
+    /// cookie caducada (`Max-Age=0`, `Expires` pasado) borra la que hubiera.
+    pub fn store(&self, url: &Url, headers: &HeaderMap) {
+        let now = Utc::now().timestamp();
+        let mut cookies = self.cookies.lock().unwrap_or_else(|e| e.into_inner());
+        for header in headers.get_all(SET_COOKIE).iter().filter_map(|v| v.to_str().ok()) {
+            let Some(cookie) = parse_set_cookie(header, url, now) else {
+                continue;
+            };
+            cookies.retain(|c| !(c.name == cookie.name && c.domain == cookie.domain && c.path == cookie.path));
+            if !cookie.expired(now) {
+                cookies.push(cookie);
+            }
+        }
+    }
+}
+
+/// Interpreta un `Set-Cookie` recibido de `url`. Se ignoran las cookies sin
+/// nombre y las que declaran un `Domain` ajeno al host.
+fn parse_set_cookie(header: &str, url: &Url, now: i64) -> Option<StoredCookie> {
+    let host = url.host_str()?.to_ascii_lowercase();
+    let mut parts = header.split(';');
+    let (name, value) = parts.next()?.split_once('=')?;
+    let name = name.trim();
+    if name.is_empty() {
+        return None;
+    }
+    let mut cookie = StoredCookie {
+        name: name.to_string(),
+        value: value.trim().to_string(),
+        domain: host.clone(),
+        host_only: true,
+        path: default_path(url.path()),
+        secure: false,
+        expires: None,
+    };
+    let mut max_age = None;
+    for attr in parts {
+        let (key, val) = attr.split_once('=').unwrap_or((attr, ""));
+        let val = val.trim();
+        match key.trim().to_ascii_lowercase().as_str() {
+            "domain" if !val.is_empty() => {
+                let domain = val.trim_start_matches('.').to_ascii_lowercase();
+                if host != domain && !host.ends_with(&format!(".{}", domain)) {
+                    return None;
+                }
+                cookie.domain = domain;
+                cookie.host_only = false;
+            }
+            "path" if val.starts_with('/') => cookie.path = val.to_string(),
+            "secure" => cookie.secure = true,
+            "expires" => {
+                if let Ok(date) = DateTime::parse_from_rfc2822(val) {
+                    cookie.expires = Some(date.timestamp());
+                }
+            }
+            "max-age" => max_age = val.parse::<i64>().ok(),
+            _ => {}
+        }
+    }
+    // Max-Age manda sobre Expires
+    if let Some(secs) = max_age {
+        cookie.expires = Some(if secs <= 0 { now } else { now.saturating_add(secs) });
+    }
+    Some(cookie)
+}
+
+/// Ruta por defecto de una cookie: el directorio de la ruta de la petición.
+fn default_path(path: &str) -> String {
+    match path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(i) => path[..i].to_string(),
+    }
+}
+
+fn path_matches(request: &str, cookie: &str) -> bool {
+    request == cookie
+        || (request.starts_with(cookie)
+            && (cookie.ends_with('/') || request[cookie.len()..].starts_with('/')))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::testutil::{self, MockServer, Response};
+
+    fn set_cookies(values: &[&str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in values {
+            headers.append(SET_COOKIE, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    fn cookie_for(jar: &CookieJar, url: &str) -> Option<String> {
+        let mut headers = HeaderMap::new();
+        jar.apply(url, &mut headers);
+        headers.get(COOKIE).map(|v| v.to_str().unwrap().to_string())
+    }
+
+    #[test]
+    fn cookies_follow_domain_path_and_secure() {
+        let jar = CookieJar::default();
+        let url = Url::parse("http://www.example.com/app/login").unwrap();
+        jar.store(&url, &set_cookies(&["host=1", "wide=2; Domain=example.com; Path=/", "tls=3; Secure; Path=/", "evil=4; Domain=other.com"]));
+
+        assert_eq!(cookie_for(&jar, "http://www.example.com/app/x").as_deref(), Some("host=1; wide=2"));
+        assert_eq!(cookie_for(&jar, "https://www.example.com/").as_deref(), Some("wide=2; tls=3"));
+        assert_eq!(cookie_for(&jar, "http://api.example.com/").as_deref(), Some("wide=2"));
+        assert_eq!(cookie_for(&jar, "http://other.com/"), None);
+    }
+
+    #[test]
+    fn expired_cookies_delete_the_stored_one() {
+        let jar = CookieJar::default();
+        let url = Url::parse("http://h/").unwrap();
+        jar.store(&url, &set_cookies(&["sid=1"]));
+        jar.store(&url, &set_cookies(&["sid=1; Max-Age=0"]));
+        assert!(jar.is_empty());
+    }
+
+    #[test]
+    fn the_jar_round_trips_through_a_netscape_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("jar.txt");
+        let jar = CookieJar::default();
+        jar.store(&Url::parse("http://h/a/b").unwrap(), &set_cookies(&["sid=x", "pref=y; Domain=h; Path=/; Max-Age=3600"]));
+        jar.save(&path).unwrap();
+
+        let loaded = CookieJar::load(&path).unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(cookie_for(&loaded, "http://h/a/c").as_deref(), Some("sid=x; pref=y"));
+        assert!(CookieJar::load(&dir.path().join("missing")).unwrap().is_empty());
+
+        std::fs::write(&path, "h\tFALSE\t/\n").unwrap();
+        assert!(CookieJar::load(&path).unwrap_err().to_string().contains("7 fields"));
+    }
+
+    #[tokio::test]
+    async fn a_provided_cookie_is_sent() {
+        let server = MockServer::start(|_| Response::new(200)).await;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("jar.txt");
+        std::fs::write(&path, "# Netscape HTTP Cookie File\n127.0.0.1\tFALSE\t/\tFALSE\t0\tsid\tabc\n").unwrap();
+
+        let mut config = testutil::mode_config("webrequest", dir.path());
+        config.cookie_jar = Some(Arc::new(CookieJar::load(&path).unwrap()));
+        testutil::run(config, vec![server.url("/")]).await;
+        assert_eq!(server.requests()[0].header("cookie"), Some("sid=abc"));
+    }
+
+    #[tokio::test]
+    async fn set_cookie_is_kept_for_the_rest_of_the_run() {
+        let server = MockServer::start(|r| match r.path.as_str() {
+            "/login" => Response::new(200).header("Set-Cookie", "sid=fresh; Path=/"),
+            _ => Response::new(200),
+        })
+        .await;
+        let dir = tempfile::tempdir().unwrap();
+        let jar = Arc::new(CookieJar::default());
+        let mut config = testutil::mode_config("webrequest", dir.path());
+        config.max_concurrent = 1;
+        config.cookie_jar = Some(jar.clone());
+        testutil::run(config, vec![server.url("/login"), server.url("/profile")]).await;
+
+        let requests = server.requests();
+        assert_eq!(requests[0].header("cookie"), None);
+        assert_eq!(requests[1].path, "/profile");
+        assert_eq!(requests[1].header("cookie"), Some("sid=fresh"));
+        assert_eq!(jar.len(), 1);
+    }
+}
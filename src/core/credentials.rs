@@ -0,0 +1,146 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::core::Downzer;
+
+/// Credenciales de `--auth` para los modos de red (ssh, ftp...). El valor
+/// admite placeholders, que se sustituyen con la combinación de cada
+/// objetivo: `--auth FUZZW1:FUZZW2` cruza una lista de usuarios con una de
+/// contraseñas y `--auth FUZZW1` con `-w combos.txt` lee `user:pass` por línea.
+#[derive(Debug, Clone)]
+pub struct CredentialTemplate {
+    template: String,
+    /// Posiciones de la combinación que son rangos (ver `substitute_placeholders`)
+    range_count: usize,
+}
+
+impl CredentialTemplate {
+    pub fn new(template: String, range_count: usize) -> Self {
+        Self { template, range_count }
+    }
+
+    /// Usuario y contraseña para la combinación `words`, separados por el
+    /// primer `:` (la contraseña puede llevar más). `None` si no hay `:`.
+    pub fn resolve(&self, words: &[String]) -> Option<(String, String)> {
+        let value = Downzer::substitute_placeholders(&self.template, words, self.range_count);
+        let (user, pass) = value.split_once(':')?;
+        Some((user.to_string(), pass.to_string()))
+    }
+}
+
+/// Lee un fichero de `--combo`: un `user:password` por línea, separados por
+/// el primer `:` sin escapar. `\:` es un `:` literal y `\\` una barra, así
+/// que un usuario con `:` se escribe `us\:er:pass`; tras el separador, los
+/// `:` ya son parte de la contraseña y no hace falta escaparlos. Se ignoran
+/// las líneas vacías.
+pub fn load_combos(path: &Path) -> Result<Vec<(String, String)>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Cannot read --combo file {}", path.display()))?;
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(n, line)| {
+            parse_combo(line).ok_or_else(|| {
+                anyhow::anyhow!("Invalid line {} in --combo file {}: expected 'user:password'", n + 1, path.display())
+            })
+        })
+        .collect()
+}
+
+/// Separa una línea `user:password` con los escapes de `load_combos`.
+pub fn parse_combo(line: &str) -> Option<(String, String)> {
+    let mut user = String::new();
+    let mut pass = String::new();
+    let mut separated = false;
+    let mut chars = line.strip_suffix('\r').unwrap_or(line).chars();
+    while let Some(c) = chars.next() {
+        let field = if separated { &mut pass } else { &mut user };
+        match c {
+            '\\' => match chars.next() {
+                Some(next @ (':' | '\\')) => field.push(next),
+                Some(next) => {
+                    field.push('\\');
+                    field.push(next);
+                }
+                None => field.push('\\'),
+            },
+            ':' if !separated => separated = true,
+            c => field.push(c),
+        }
+    }
+    separated.then_some((user, pass))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair(user: &str, pass: &str) -> Option<(String, String)> {
+        Some((user.to_string(), pass.to_string()))
+    }
+
+    #[test]
+    fn combo_splits_at_the_first_colon() {
+        assert_eq!(parse_combo("admin:secret"), pair("admin", "secret"));
+        assert_eq!(parse_combo("admin:a:b:c"), pair("admin", "a:b:c"));
+        assert_eq!(parse_combo("admin:"), pair("admin", ""));
+        assert_eq!(parse_combo(":secret"), pair("", "secret"));
+        assert_eq!(parse_combo("admin:secret\r"), pair("admin", "secret"));
+        assert_eq!(parse_combo("admin"), None);
+    }
+
+    #[test]
+    fn combo_escaped_separators() {
+        // `\:` es un ':' literal en el usuario; tras el separador ya no hace falta
+        assert_eq!(parse_combo(r"us\:er:pass"), pair("us:er", "pass"));
+        assert_eq!(parse_combo(r"us\:er:p\:a:ss"), pair("us:er", "p:a:ss"));
+        assert_eq!(parse_combo(r"a\\:b"), pair(r"a\", "b"));
+        // Otros escapes y una barra final quedan tal cual
+        assert_eq!(parse_combo(r"do\main:pa\ss\"), pair(r"do\main", r"pa\ss\"));
+        // Todo escapado: no hay separador
+        assert_eq!(parse_combo(r"user\:pass"), None);
+    }
+
+    #[test]
+    fn combo_file_skips_blank_lines_and_reports_bad_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("combos.txt");
+        std::fs::write(&path, "admin:admin\n\n  \nroot:t\\:o:or\r\n").unwrap();
+        assert_eq!(
+            load_combos(&path).unwrap(),
+            [("admin".to_string(), "admin".to_string()), ("root".to_string(), "t:o:or".to_string())]
+        );
+
+        std::fs::write(&path, "admin:admin\nguest\n").unwrap();
+        let err = load_combos(&path).unwrap_err().to_string();
+        assert!(err.contains("Invalid line 2"), "{}", err);
+        assert!(load_combos(&dir.path().join("missing.txt")).is_err());
+    }
+
+    #[test]
+    fn auth_template_resolves_per_combination() {
+        let words = ["admin".to_string(), "p:w".to_string()];
+        let auth = CredentialTemplate::new("FUZZW1:FUZZW2".to_string(), 0);
+        assert_eq!(auth.resolve(&words), pair("admin", "p:w"));
+        // Modificadores como en la URL
+        let auth = CredentialTemplate::new("FUZZW1:upper:FUZZW1:b64".to_string(), 0);
+        assert_eq!(auth.resolve(&words), pair("ADMIN", "YWRtaW4="));
+        // Un valor con ':' en el usuario lo parte por ahí
+        let auth = CredentialTemplate::new("FUZZW2:x".to_string(), 0);
+        assert_eq!(auth.resolve(&words), pair("p", "w:x"));
+        assert_eq!(CredentialTemplate::new("FUZZW1".to_string(), 0).resolve(&words), None);
+    }
+
+    #[test]
+    fn auth_values_are_not_substituted_again() {
+        // Una palabra que parece un placeholder se inserta literal
+        let words = ["FUZZW2".to_string(), "FUZZW1:url".to_string()];
+        let auth = CredentialTemplate::new("FUZZW1:FUZZW2".to_string(), 0);
+        assert_eq!(auth.resolve(&words), pair("FUZZW2", "FUZZW1:url"));
+        // Sin valor para el placeholder, queda tal cual
+        let auth = CredentialTemplate::new("root:FUZZW9".to_string(), 0);
+        assert_eq!(auth.resolve(&words), pair("root", "FUZZW9"));
+    }
+}
@@ -1,6 +1,6 @@
 use rusqlite::{Connection, params};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use anyhow::Result;
 use crate::core::task::TaskStatus;
 
@@ -22,8 +22,12 @@ pub struct Database {
 
 impl Database {
     pub fn new() -> Result<Self> {
-        let db_path = Self::db_path();
-        let conn = Connection::open(db_path)?;
+        Self::open(&Self::db_path())
+    }
+
+    /// Abre (o crea) la base de datos en `path`; `:memory:` la deja en memoria.
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)?;
         
         conn.execute(
             "CREATE TABLE IF NOT EXISTS tasks (
@@ -42,7 +46,7 @@ impl Database {
         Ok(Self { conn })
     }
     
-    fn db_path() -> PathBuf {
+    pub fn db_path() -> PathBuf {
         let mut path = dirs::data_local_dir().unwrap_or_else(|| PathBuf::from("."));
         path.push("downzer");
         std::fs::create_dir_all(&path).ok();
@@ -50,10 +54,23 @@ impl Database {
         path
     }
     
-    pub fn insert_task(&self, task: &TaskRecord) -> Result<()> {
+    /// Comprueba que la base de datos admite escrituras (`downzer doctor`).
+    pub fn check_writable(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS doctor_probe (x INTEGER);
+             DROP TABLE doctor_probe;",
+        )?;
+        Ok(())
+    }
+
+    /// Inserta la tarea o, si ya hay una fila con ese id, la sustituye.
+    pub fn upsert_task(&self, task: &TaskRecord) -> Result<()> {
         self.conn.execute(
             "INSERT INTO tasks (id, url_template, total, completed, status, pid, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+             ON CONFLICT(id) DO UPDATE SET url_template=excluded.url_template, total=excluded.total,
+                 completed=excluded.completed, status=excluded.status, pid=excluded.pid,
+                 created_at=excluded.created_at, updated_at=excluded.updated_at",
             params![
                 task.id,
                 task.url_template,
@@ -67,7 +84,7 @@ impl Database {
         )?;
         Ok(())
     }
-    
+
     pub fn update_task(&self, task: &TaskRecord) -> Result<()> {
         self.conn.execute(
             "UPDATE tasks SET total=?1, completed=?2, status=?3, updated_at=?4 WHERE id=?5",
@@ -76,6 +93,31 @@ impl Database {
         Ok(())
     }
     
+    /// Cambia solo el estado de una tarea (p.ej. al pausarla por IPC).
+    pub fn set_task_status(&self, id: u32, status: TaskStatus, updated_at: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE tasks SET status=?1, updated_at=?2 WHERE id=?3",
+            params![status.to_string(), updated_at, id],
+        )?;
+        Ok(())
+    }
+
+    /// Cambia solo el progreso de una tarea.
+    pub fn set_task_progress(&self, id: u32, completed: usize, updated_at: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE tasks SET completed=?1, updated_at=?2 WHERE id=?3",
+            params![completed, updated_at, id],
+        )?;
+        Ok(())
+    }
+
+    /// Id más alto registrado (0 si no hay tareas), para no reutilizar ids
+    /// de ejecuciones anteriores.
+    pub fn max_task_id(&self) -> Result<u32> {
+        let id: Option<u32> = self.conn.query_row("SELECT MAX(id) FROM tasks", [], |row| row.get(0))?;
+        Ok(id.unwrap_or(0))
+    }
+
     pub fn get_task(&self, id: u32) -> Result<Option<TaskRecord>> {
         let mut stmt = self.conn.prepare(
             "SELECT id, url_template, total, completed, status, pid, created_at, updated_at 
@@ -85,17 +127,7 @@ impl Database {
         let mut rows = stmt.query(params![id])?;
         
         if let Some(row) = rows.next()? {
-            let status_str: String = row.get(4)?;
-            Ok(Some(TaskRecord {
-                id: row.get(0)?,
-                url_template: row.get(1)?,
-                total: row.get(2)?,
-                completed: row.get(3)?,
-                status: TaskStatus::from_string(&status_str),
-                pid: row.get(5)?,
-                created_at: row.get(6)?,
-                updated_at: row.get(7)?,
-            }))
+            Ok(Some(task_from_row(row)?))
         } else {
             Ok(None)
         }
@@ -107,19 +139,7 @@ impl Database {
              FROM tasks WHERE status IN ('Running', 'Paused', 'Queued')"
         )?;
         
-        let tasks = stmt.query_map([], |row| {
-            let status_str: String = row.get(4)?;
-            Ok(TaskRecord {
-                id: row.get(0)?,
-                url_template: row.get(1)?,
-                total: row.get(2)?,
-                completed: row.get(3)?,
-                status: TaskStatus::from_string(&status_str),
-                pid: row.get(5)?,
-                created_at: row.get(6)?,
-                updated_at: row.get(7)?,
-            })
-        })?;
+        let tasks = stmt.query_map([], task_from_row)?;
         
         let mut result = Vec::new();
         for task in tasks {
@@ -128,8 +148,193 @@ impl Database {
         Ok(result)
     }
     
-    pub fn delete_task(&self, id: u32) -> Result<()> {
-        self.conn.execute("DELETE FROM tasks WHERE id=?1", params![id])?;
-        Ok(())
+    /// Las `limit` tareas más recientes (por id) con alguno de los estados
+    /// de `statuses`; sin estados, con cualquiera.
+    pub fn get_recent_tasks(&self, limit: usize, statuses: &[TaskStatus]) -> Result<Vec<TaskRecord>> {
+        let filter = if statuses.is_empty() {
+            String::new()
+        } else {
+            let placeholders: Vec<String> = (1..=statuses.len()).map(|i| format!("?{}", i)).collect();
+            format!("WHERE status IN ({})", placeholders.join(", "))
+        };
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, url_template, total, completed, status, pid, created_at, updated_at
+             FROM tasks {} ORDER BY id DESC LIMIT {}",
+            filter,
+            limit.min(i64::MAX as usize)
+        ))?;
+        let names: Vec<String> = statuses.iter().map(|s| s.to_string()).collect();
+        let tasks = stmt.query_map(rusqlite::params_from_iter(names.iter()), task_from_row)?;
+        Ok(tasks.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Borra las tareas terminadas (Completed, Failed, Stopped) o, con `all`,
+    /// todas. Devuelve cuántas se borraron.
+    pub fn clear_tasks(&self, all: bool) -> Result<usize> {
+        let deleted = if all {
+            self.conn.execute("DELETE FROM tasks", [])?
+        } else {
+            self.conn.execute("DELETE FROM tasks WHERE status IN ('Completed', 'Failed', 'Stopped')", [])?
+        };
+        Ok(deleted)
+    }
+}
+
+/// Fila de `tasks` con las columnas en el orden de los `SELECT` de arriba.
+fn task_from_row(row: &rusqlite::Row) -> rusqlite::Result<TaskRecord> {
+    let status_str: String = row.get(4)?;
+    Ok(TaskRecord {
+        id: row.get(0)?,
+        url_template: row.get(1)?,
+        total: row.get(2)?,
+        completed: row.get(3)?,
+        status: TaskStatus::from_string(&status_str),
+        pid: row.get(5)?,
+        created_at: row.get(6)?,
+        updated_at: row.get(7)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::downzer::Config;
+    use crate::core::task::TaskInfo;
+    use crate::core::{ClientOptions, Downzer};
+
+    fn task_info(id: u32) -> TaskInfo {
+        TaskInfo {
+            id,
+            url_template: "http://h/FUZZW1".to_string(),
+            total: 10,
+            completed: 0,
+            status: TaskStatus::Running,
+            start_time: std::time::Instant::now(),
+            live: None,
+        }
+    }
+
+    /// Instancia nueva sobre la base de datos compartida en memoria `name`,
+    /// como la de un proceso que arranca tras el anterior.
+    fn instance(name: &str) -> std::sync::Arc<Downzer> {
+        let db = Database::open(Path::new(&format!("file:{}?mode=memory&cache=shared", name))).unwrap();
+        Downzer::with_database(ClientOptions::default(), Config::default(), db).unwrap()
+    }
+
+    #[test]
+    fn progress_and_status_updates_touch_only_their_columns() {
+        let db = Database::open(Path::new(":memory:")).unwrap();
+        let record = TaskRecord {
+            id: 3,
+            url_template: "t".to_string(),
+            total: 10,
+            completed: 0,
+            status: TaskStatus::Running,
+            pid: Some(42),
+            created_at: "2026-01-01T00:00:00+00:00".to_string(),
+            updated_at: "2026-01-01T00:00:00+00:00".to_string(),
+        };
+        db.upsert_task(&record).unwrap();
+        db.set_task_progress(3, 7, "2026-01-01T00:00:05+00:00").unwrap();
+        db.set_task_status(3, TaskStatus::Paused, "2026-01-01T00:00:09+00:00").unwrap();
+
+        let stored = db.get_task(3).unwrap().unwrap();
+        assert_eq!((stored.completed, stored.status, stored.total, stored.pid), (7, TaskStatus::Paused, 10, Some(42)));
+        assert_eq!(stored.created_at, record.created_at);
+        assert_eq!(stored.updated_at, "2026-01-01T00:00:09+00:00");
+        assert_eq!(db.get_active_tasks().unwrap().len(), 1);
+        assert_eq!(db.max_task_id().unwrap(), 3);
+        assert!(db.get_task(4).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn a_task_survives_a_simulated_restart() {
+        // La base de datos compartida vive mientras quede una conexión abierta
+        let _keep = Database::open(Path::new("file:restart?mode=memory&cache=shared")).unwrap();
+
+        let first = instance("restart");
+        let id = first.allocate_task_id().await;
+        first.add_task(task_info(id)).await.unwrap();
+        first.update_task_progress(id, 4).await;
+        first.set_task_status(id, TaskStatus::Paused).await;
+        drop(first);
+
+        // El proceso nuevo no tiene la tarea en memoria, pero la lee de la base de datos
+        let second = instance("restart");
+        assert!(!second.tasks.read().await.contains_key(&id));
+        let stored = second.db.lock().await.get_task(id).unwrap().unwrap();
+        assert_eq!((stored.completed, stored.status, stored.total), (4, TaskStatus::Paused, 10));
+        assert_eq!(stored.pid, Some(std::process::id()));
+        let created = chrono::DateTime::parse_from_rfc3339(&stored.created_at).unwrap();
+        assert!(chrono::DateTime::parse_from_rfc3339(&stored.updated_at).unwrap() >= created);
+        let active: Vec<u32> = second.db.lock().await.get_active_tasks().unwrap().iter().map(|t| t.id).collect();
+        assert_eq!(active, [id]);
+
+        // Y no reutiliza su id
+        assert_eq!(second.allocate_task_id().await, id + 1);
+    }
+
+    /// Base de datos en memoria con una tarea por estado, de id 1 en adelante.
+    fn history_db(statuses: &[TaskStatus]) -> Database {
+        let db = Database::open(Path::new(":memory:")).unwrap();
+        for (i, status) in statuses.iter().enumerate() {
+            let id = i as u32 + 1;
+            let at = format!("2026-01-01T00:00:{:02}+00:00", id);
+            db.upsert_task(&TaskRecord {
+                id,
+                url_template: format!("http://h/{}/FUZZW1", id),
+                total: 10,
+                completed: id as usize,
+                status: *status,
+                pid: None,
+                created_at: at.clone(),
+                updated_at: at,
+            })
+            .unwrap();
+        }
+        db
+    }
+
+    fn ids(tasks: &[TaskRecord]) -> Vec<u32> {
+        tasks.iter().map(|t| t.id).collect()
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn recent_tasks_newest_first_with_limit_and_status_filter() {
+        use TaskStatus::*;
+        let db = history_db(&[Completed, Running, Failed, Completed, Stopped, Paused]);
+        assert_eq!(ids(&db.get_recent_tasks(usize::MAX, &[]).unwrap()), [6, 5, 4, 3, 2, 1]);
+        assert_eq!(ids(&db.get_recent_tasks(2, &[]).unwrap()), [6, 5]);
+        let finished = [Completed, Failed, Stopped];
+        assert_eq!(ids(&db.get_recent_tasks(10, &finished).unwrap()), [5, 4, 3, 1]);
+        assert_eq!(ids(&db.get_recent_tasks(10, &[Failed]).unwrap()), [3]);
+        assert_eq!(ids(&db.get_recent_tasks(0, &[]).unwrap()), Vec::<u32>::new());
+        // Las columnas vuelven tal como se guardaron
+        let task = &db.get_recent_tasks(1, &[Failed]).unwrap()[0];
+        assert_eq!((task.url_template.as_str(), task.completed, task.total), ("http://h/3/FUZZW1", 3, 10));
+        assert_eq!(task.created_at, "2026-01-01T00:00:03+00:00");
+    }
+
+    #[test]
+    fn empty_history() {
+        let db = history_db(&[]);
+        assert!(db.get_recent_tasks(20, &[]).unwrap().is_empty());
+        assert!(db.get_recent_tasks(20, &[TaskStatus::Completed]).unwrap().is_empty());
+        // Con solo tareas en marcha, el historial de terminadas está vacío
+        let db = history_db(&[TaskStatus::Running, TaskStatus::Paused]);
+        assert!(db.get_recent_tasks(20, &[TaskStatus::Completed, TaskStatus::Failed, TaskStatus::Stopped]).unwrap().is_empty());
+        assert_eq!(serde_json::to_string(&db.get_recent_tasks(20, &[TaskStatus::Failed]).unwrap()).unwrap(), "[]");
+    }
+
+    #[test]
+    fn clear_removes_only_finished_tasks_unless_all() {
+        use TaskStatus::*;
+        let db = history_db(&[Completed, Running, Failed, Paused, Stopped, Queued]);
+        assert_eq!(db.clear_tasks(false).unwrap(), 3);
+        assert_eq!(ids(&db.get_recent_tasks(10, &[]).unwrap()), [6, 4, 2]);
+        // Una segunda pasada ya no encuentra nada que borrar
+        assert_eq!(db.clear_tasks(false).unwrap(), 0);
+        assert_eq!(db.clear_tasks(true).unwrap(), 3);
+        assert!(db.get_recent_tasks(10, &[]).unwrap().is_empty());
+    }
+}
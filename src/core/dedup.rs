@@ -0,0 +1,134 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::hash::Hasher;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Deduplicación por contenido de los archivos descargados (`--dedup-by content-hash`).
+///
+/// Compartido entre todos los workers de una tarea: guarda hash → rutas ya
+/// almacenadas. Un hash igual se confirma comparando los bytes antes de borrar nada.
+pub struct ContentDedup {
+    seen: Mutex<HashMap<u64, Vec<PathBuf>>>,
+    hardlink: bool,
+}
+
+impl ContentDedup {
+    pub fn new(hardlink: bool) -> Self {
+        Self { seen: Mutex::new(HashMap::new()), hardlink }
+    }
+
+    /// Registra `path`. Si su contenido ya existía, elimina `path` (o lo
+    /// sustituye por un hardlink con `--hardlink-dupes`) y devuelve el original.
+    ///
+    /// Los archivos se comparan sin el candado, para no frenar al resto de
+    /// workers; como las rutas de un hash solo se añaden, al volver a tomarlo
+    /// basta con comparar también las que hayan llegado entretanto.
+    pub fn check(&self, path: &Path) -> io::Result<Option<PathBuf>> {
+        let hash = hash_file(path)?;
+        let mut compared = 0;
+        loop {
+            let candidates = {
+                let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+                let paths = seen.entry(hash).or_default();
+                if paths.len() == compared {
+                    paths.push(path.to_path_buf());
+                    return Ok(None);
+                }
+                paths[compared..].to_vec()
+            };
+
+            for original in &candidates {
+                if same_contents(original, path)? {
+                    fs::remove_file(path)?;
+                    if self.hardlink {
+                        fs::hard_link(original, path)?;
+                    }
+                    return Ok(Some(original.clone()));
+                }
+            }
+            compared += candidates.len();
+        }
+    }
+}
+
+fn hash_file(path: &Path) -> io::Result<u64> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut hasher = DefaultHasher::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.write(&buf[..n]);
+    }
+    Ok(hasher.finish())
+}
+
+fn same_contents(a: &Path, b: &Path) -> io::Result<bool> {
+    if fs::metadata(a)?.len() != fs::metadata(b)?.len() {
+        return Ok(false);
+    }
+    let mut ra = BufReader::new(File::open(a)?);
+    let mut rb = BufReader::new(File::open(b)?);
+    let mut ba = [0u8; 64 * 1024];
+    let mut bb = [0u8; 64 * 1024];
+    loop {
+        let n = ra.read(&mut ba)?;
+        if n == 0 {
+            return Ok(true);
+        }
+        rb.read_exact(&mut bb[..n])?;
+        if ba[..n] != bb[..n] {
+            return Ok(false);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicate_is_removed_or_hardlinked() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, body: &str| {
+            let path = dir.path().join(name);
+            fs::write(&path, body).unwrap();
+            path
+        };
+        for hardlink in [false, true] {
+            let dedup = ContentDedup::new(hardlink);
+            let first = write("first", "same");
+            assert_eq!(dedup.check(&first).unwrap(), None);
+            assert_eq!(dedup.check(&write("other", "different")).unwrap(), None);
+            let second = write("second", "same");
+            assert_eq!(dedup.check(&second).unwrap(), Some(first));
+            assert_eq!(second.exists(), hardlink);
+        }
+    }
+
+    #[test]
+    fn concurrent_duplicates_keep_a_single_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let dedup = ContentDedup::new(false);
+        let paths: Vec<PathBuf> = (0..8)
+            .map(|i| {
+                let path = dir.path().join(format!("copy{}", i));
+                fs::write(&path, vec![7u8; 256 * 1024]).unwrap();
+                path
+            })
+            .collect();
+        let kept: Vec<bool> = std::thread::scope(|scope| {
+            let handles: Vec<_> = paths.iter().map(|path| scope.spawn(|| dedup.check(path).unwrap().is_none())).collect();
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        });
+
+        // Aunque las comparaciones se solapen, solo queda una copia
+        assert_eq!(kept.iter().filter(|kept| **kept).count(), 1, "{:?}", kept);
+        assert_eq!(paths.iter().filter(|path| path.exists()).count(), 1);
+    }
+}
@@ -1,24 +1,55 @@
+use base64::prelude::{Engine as _, BASE64_STANDARD, BASE64_URL_SAFE_NO_PAD};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use futures::StreamExt;
 use regex::Regex;
 use reqwest::{Client, Proxy};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::fs::{self, File};
-use std::io::{BufRead, BufReader, Write};
+use std::io::{BufRead, BufReader, BufWriter, Write};
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
 use tokio::sync::RwLock;
 
+use crate::core::targets::{Target, Targets, UrlFilters};
 use crate::core::task::{TaskInfo, TaskStatus};
-use crate::core::db::Database;
+use crate::core::db::{Database, TaskRecord};
+use crate::core::dedup::ContentDedup;
+use crate::core::drain::join_before;
+use crate::core::caps::ExtensionCaps;
+use crate::core::filename::{derived_extension, part_path, FileNamer, NameTemplate, PART_SUFFIX};
+use crate::core::live::LiveStats;
+use crate::core::hook::ExecHook;
+use crate::core::hostlimit::HostLimiter;
+use crate::core::cookies::CookieJar;
+use crate::core::headers::HeaderTemplates;
+use crate::core::output::OutputLine;
+use crate::core::redirect;
+use crate::core::progress::byte_progress_bar;
+use crate::core::throttle::{Pacer, RateLimiter};
+use crate::core::report::{load_manifest, merge_manifest, write_manifest, ManifestEntry, ReportRow, ReportSink};
+
+/// Caracteres que `FUZZW1:url` codifica: todo salvo los no reservados (RFC 3986)
+const URL_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 pub struct Config {
     pub sound_enabled: bool,
     pub sound_min_duration: u64,
     pub sound_volume: f32,
+    /// Fade-out de los sonidos de aviso en ms (máx. 2000)
+    pub sound_fade_ms: u64,
     pub sound_on_task_complete: bool,
     pub sound_on_all_complete: bool,
     pub sound_type: String,
+    /// Sonido propio cuando una tarea termina en `Failed`
+    pub sound_on_failure: bool,
+    pub failure_sound_type: String,
+    /// Valores por defecto de las opciones de la línea de comandos, con las
+    /// claves de `--show-config` (`max_concurrent`, `header`...)
+    #[serde(skip_serializing_if = "serde_json::Map::is_empty")]
+    pub options: serde_json::Map<String, serde_json::Value>,
 }
 
 impl Default for Config {
@@ -27,34 +58,156 @@ impl Default for Config {
             sound_enabled: true,
             sound_min_duration: 10,
             sound_volume: 0.5,
+            sound_fade_ms: 150,
             sound_on_task_complete: false,
             sound_on_all_complete: true,
             sound_type: "woodensaw".to_string(),
+            sound_on_failure: false,
+            failure_sound_type: "thuddry".to_string(),
+            options: serde_json::Map::new(),
         }
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct Stats {
+    pub processed: usize,
     pub downloaded: usize,
     pub total_bytes: u64,
     pub ignored: usize,
     pub errors: usize,
     pub not_found: usize,
+    pub duplicates: usize,
+    pub bytes_saved: u64,
+    /// Descargas abortadas al vencer `--drain-timeout`
+    pub timeouts: usize,
+    /// Descartadas por `--min-size`/`--max-size`
+    pub filtered_size: usize,
+    /// Omitidas por haber agotado el cupo de su extensión
+    pub capped: usize,
 }
 
 impl Stats {
     pub fn new() -> Self {
         Self {
+            processed: 0,
             downloaded: 0,
             total_bytes: 0,
             ignored: 0,
             errors: 0,
             not_found: 0,
+            duplicates: 0,
+            bytes_saved: 0,
+            timeouts: 0,
+            filtered_size: 0,
+            capped: 0,
         }
     }
 }
 
+/// Per-task settings for `execute_download_task`.
+#[derive(Debug, Clone)]
+pub struct DownloadOptions {
+    pub output_dir: PathBuf,
+    pub content_types: Vec<String>,
+    pub max_concurrent: usize,
+    /// Máximo de descargas simultáneas contra un mismo host (`--max-per-host`)
+    pub max_per_host: Option<usize>,
+    pub max_filename_length: usize,
+    /// Ruta de cada descarga dentro de `output_dir` (`--name-template`)
+    pub name_template: Option<NameTemplate>,
+    /// Replicar la ruta de la URL bajo `output_dir` (`--preserve-path`)
+    pub preserve_path: bool,
+    /// Cabeceras de `--header` (con placeholders)
+    pub headers: HeaderTemplates,
+    /// Cookie jar de `--cookie-jar`
+    pub cookie_jar: Option<Arc<CookieJar>>,
+    /// Conservar las credenciales en redirecciones a otro origen (`--location-trusted`)
+    pub location_trusted: bool,
+    /// Comando a ejecutar tras cada descarga correcta (`{}` = ruta del archivo)
+    pub exec: Option<String>,
+    /// Imprimir las líneas por URL en orden de generación (`--ordered-output`)
+    pub ordered_output: bool,
+    /// Omitir las líneas de error por URL (`--quiet-errors`)
+    pub quiet_errors: bool,
+    /// Eliminar descargas con contenido repetido (`--dedup-by content-hash`)
+    pub dedup_content: bool,
+    /// Sustituir los duplicados por hardlinks al original (`--hardlink-dupes`)
+    pub hardlink_dupes: bool,
+    /// Informe por URL con la procedencia de cada una (`--report`)
+    pub report: Option<PathBuf>,
+    /// Manifiesto a escribir al terminar (`--manifest`)
+    pub manifest: Option<PathBuf>,
+    /// Añadir al manifiesto existente en lugar de reemplazarlo (`--only-new`)
+    pub manifest_append: bool,
+    /// Tamaño mínimo/máximo en bytes de los archivos a conservar
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    /// Continuar descargas parciales (`.part`) con peticiones Range (`--resume`)
+    pub resume: bool,
+    /// Ancho de banda total de la tarea (`--rate-limit`)
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+    /// Ritmo de las descargas (`--delay`)
+    pub pacer: Option<Arc<Pacer>>,
+    /// Cupos por extensión (`--cap-per-extension`)
+    pub extension_caps: Arc<ExtensionCaps>,
+    /// Plazo para las descargas pendientes una vez lanzadas todas
+    pub drain_timeout: Option<Duration>,
+    pub show_progress: bool,
+    /// Contadores compartidos con el muestreador de `--interval-stats`
+    pub live: Arc<LiveStats>,
+    pub verbose: u8,
+    pub debug: bool,
+}
+
+/// Resultado de `download_file`. Los errores de red/E/S van por `Err`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DownloadOutcome {
+    Saved { path: PathBuf, bytes: u64, status: u16, content_type: String },
+    NotFound,
+    /// El Content-Type no pasa el filtro `-c`
+    Ignored,
+    /// Respuesta distinta de 2xx (salvo 404)
+    HttpError(u16),
+    /// Fuera de `--min-size`/`--max-size` (tamaño visto hasta descartarla)
+    FilteredSize(u64),
+    /// Cupo de `--cap-per-extension` agotado para esta extensión
+    Capped(String),
+}
+
+/// Resultado de una descarga individual, agregado en `Stats`.
+#[derive(Default)]
+struct DownloadResult {
+    bytes: u64,
+    downloaded: usize,
+    ignored: usize,
+    errors: usize,
+    not_found: usize,
+    duplicates: usize,
+    bytes_saved: u64,
+    filtered_size: usize,
+    capped: usize,
+    /// Código HTTP y resultado para `--report`
+    status: Option<u16>,
+    outcome: &'static str,
+    /// Archivo y Content-Type de lo guardado, para `--manifest`
+    path: Option<PathBuf>,
+    content_type: Option<String>,
+    line: Option<OutputLine>,
+}
+
+/// Opciones del cliente HTTP compartido (`Downzer::new`).
+#[derive(Debug, Clone, Default)]
+pub struct ClientOptions {
+    pub proxy: Option<String>,
+    pub timeout: u64,
+    /// Versión mínima/máxima de TLS (`--tls-min`, `--tls-max`)
+    pub tls_min: Option<reqwest::tls::Version>,
+    pub tls_max: Option<reqwest::tls::Version>,
+    /// Una conexión nueva por petición (`--no-keepalive`)
+    pub no_keepalive: bool,
+}
+
 pub struct Downzer {
     pub client: Client,
     pub config: Arc<RwLock<Config>>,
@@ -64,20 +217,46 @@ pub struct Downzer {
 }
 
 impl Downzer {
-    pub async fn new(proxy: Option<String>, timeout: u64) -> anyhow::Result<Arc<Self>> {
+    pub async fn new(options: ClientOptions, config: Config) -> anyhow::Result<Arc<Self>> {
+        Self::with_database(options, config, Database::new()?)
+    }
+
+    /// Como `new`, pero con una base de datos ya abierta.
+    pub fn with_database(options: ClientOptions, config: Config, db: Database) -> anyhow::Result<Arc<Self>> {
         let mut client_builder = Client::builder()
-            .timeout(Duration::from_secs(timeout))
+            .timeout(Duration::from_secs(options.timeout))
             .gzip(true)
-            .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36");
+            .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
+            // Las redirecciones se siguen en `redirect::send`, que decide qué
+            // cabeceras conservar al cambiar de origen
+            .redirect(reqwest::redirect::Policy::none());
 
-        if let Some(proxy_url) = proxy {
+        if let Some(proxy_url) = options.proxy {
             let proxy = Proxy::all(&proxy_url)?;
             client_builder = client_builder.proxy(proxy);
         }
 
+        if let (Some(min), Some(max)) = (options.tls_min, options.tls_max) {
+            if min > max {
+                anyhow::bail!("--tls-min is higher than --tls-max");
+            }
+        }
+        if let Some(min) = options.tls_min {
+            client_builder = client_builder.min_tls_version(min);
+        }
+        if let Some(max) = options.tls_max {
+            client_builder = client_builder.max_tls_version(max);
+        }
+
+        // Sin conexiones ociosas en el pool y con `Connection: close` para que
+        // el servidor tampoco la mantenga abierta
+        if options.no_keepalive {
+            let mut headers = reqwest::header::HeaderMap::new();
+            headers.insert(reqwest::header::CONNECTION, reqwest::header::HeaderValue::from_static("close"));
+            client_builder = client_builder.pool_max_idle_per_host(0).default_headers(headers);
+        }
+
         let client = client_builder.build()?;
-        let config = Self::load_config();
-        let db = Database::new()?;
 
         Ok(Arc::new(Self {
             client,
@@ -100,6 +279,24 @@ impl Downzer {
         Config::default()
     }
 
+    /// Carga un archivo de configuración concreto (`--config`), JSON o YAML según
+    /// la extensión. Los campos ausentes toman el valor por defecto.
+    pub fn load_config_file(path: &Path) -> anyhow::Result<Config> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Cannot read config file {}: {}", path.display(), e))?;
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        let config = match ext.as_deref() {
+            Some("yaml") | Some("yml") => serde_yaml::from_str(&content)
+                .map_err(|e| anyhow::anyhow!("Invalid YAML in {}: {}", path.display(), e))?,
+            _ => serde_json::from_str(&content)
+                .map_err(|e| anyhow::anyhow!("Invalid JSON in {}: {}", path.display(), e))?,
+        };
+        Ok(config)
+    }
+
     pub fn config_path() -> PathBuf {
         let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
         path.push("downzer");
@@ -108,56 +305,165 @@ impl Downzer {
         path
     }
 
-    pub fn save_config(config: &Config) -> anyhow::Result<()> {
-        let path = Self::config_path();
-        let content = serde_json::to_string_pretty(config)?;
-        fs::write(path, content)?;
+    /// Guarda la configuración en `path`, en YAML o JSON según la extensión
+    /// (como `load_config_file`).
+    pub fn save_config_file(path: &Path, config: &Config) -> anyhow::Result<()> {
+        let ext = path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase());
+        let content = match ext.as_deref() {
+            Some("yaml") | Some("yml") => serde_yaml::to_string(config)?,
+            _ => serde_json::to_string_pretty(config)?,
+        };
+        fs::write(path, content)
+            .map_err(|e| anyhow::anyhow!("Cannot write config file {}: {}", path.display(), e))?;
         Ok(())
     }
 
     pub async fn parse_range(spec: &str) -> anyhow::Result<Vec<String>> {
-        let re = Regex::new(r"^(\d+)-(\d+)$")?;
+        if spec.starts_with("0x") {
+            return Self::parse_hex_range(spec);
+        }
+        if spec.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            return Self::parse_alpha_range(spec);
+        }
+
+        let re = Regex::new(r"^(\d+)-(\d+)(?::(\d+))?$")?;
         if let Some(caps) = re.captures(spec) {
             let start: usize = caps[1].parse()?;
             let end: usize = caps[2].parse()?;
-            if end < start {
-                anyhow::bail!("Invalid range: end < start");
-            }
-            Ok((start..=end).map(|n| n.to_string()).collect())
+            let step = Self::parse_range_step(caps.get(3))?;
+            let width = Self::range_pad_width(&caps[1], &caps[2]);
+            Ok(Self::expand_range(start, end, step)
+                .into_iter()
+                .map(|n| format!("{:0width$}", n, width = width))
+                .collect())
         } else {
-            anyhow::bail!("Invalid range format: {}. Expected: start-end", spec);
+            anyhow::bail!("Invalid range format: {}. Expected: start-end[:step]", spec);
         }
     }
 
-    pub async fn parse_wordlist(token: &str) -> anyhow::Result<Vec<String>> {
-        Self::read_list_from_token(token)
+    /// Rango hexadecimal `0x10-0xff`, emitido en minúsculas sin prefijo.
+    fn parse_hex_range(spec: &str) -> anyhow::Result<Vec<String>> {
+        let re = Regex::new(r"^0x([0-9a-fA-F]+)-0x([0-9a-fA-F]+)(?::(\d+))?$")?;
+        let Some(caps) = re.captures(spec) else {
+            anyhow::bail!("Invalid hex range: {}. Expected: 0xstart-0xend[:step]", spec);
+        };
+        let start = usize::from_str_radix(&caps[1], 16)?;
+        let end = usize::from_str_radix(&caps[2], 16)?;
+        let step = Self::parse_range_step(caps.get(3))?;
+        let width = Self::range_pad_width(&caps[1], &caps[2]);
+        Ok(Self::expand_range(start, end, step)
+            .into_iter()
+            .map(|n| format!("{:0width$x}", n, width = width))
+            .collect())
     }
 
-    fn read_list_from_token(token: &str) -> anyhow::Result<Vec<String>> {
+    /// Rango alfabético `a-z` / `A-Z`, o tipo odómetro `aa-zz` (aa, ab, ..., zz).
+    fn parse_alpha_range(spec: &str) -> anyhow::Result<Vec<String>> {
+        let re = Regex::new(r"^([a-zA-Z]+)-([a-zA-Z]+)(?::(\d+))?$")?;
+        let Some(caps) = re.captures(spec) else {
+            anyhow::bail!("Invalid alphabetic range: {}. Expected: a-z, aa-zz[:step]", spec);
+        };
+        let (first, last) = (&caps[1], &caps[2]);
+        if first.len() != last.len() {
+            anyhow::bail!("Invalid alphabetic range: {} and {} must have the same length", first, last);
+        }
+        let upper = first.starts_with(|c: char| c.is_ascii_uppercase());
+        let same_case = |b: &str| b.chars().all(|c| c.is_ascii_uppercase() == upper);
+        if !same_case(first) || !same_case(last) {
+            anyhow::bail!("Invalid alphabetic range: {} mixes upper and lower case", spec);
+        }
+
+        let base = if upper { b'A' } else { b'a' };
+        // Con 14 letras o más el índice ya no cabe en un usize de 64 bits
+        let to_index = |b: &str| {
+            b.bytes()
+                .try_fold(0usize, |acc, c| acc.checked_mul(26)?.checked_add((c - base) as usize))
+                .ok_or_else(|| anyhow::anyhow!("Invalid alphabetic range: {} has too many letters", spec))
+        };
+        let width = first.len();
+        let step = Self::parse_range_step(caps.get(3))?;
+
+        Ok(Self::expand_range(to_index(first)?, to_index(last)?, step)
+            .into_iter()
+            .map(|mut n| {
+                let mut word = vec![base; width];
+                for slot in word.iter_mut().rev() {
+                    *slot = base + (n % 26) as u8;
+                    n /= 26;
+                }
+                String::from_utf8(word).unwrap_or_default()
+            })
+            .collect())
+    }
+
+    fn parse_range_step(step: Option<regex::Match>) -> anyhow::Result<usize> {
+        let step: usize = match step {
+            Some(m) => m.as_str().parse()?,
+            None => 1,
+        };
+        if step == 0 {
+            anyhow::bail!("Invalid range step: must be greater than 0");
+        }
+        Ok(step)
+    }
+
+    // Si algún extremo lleva ceros a la izquierda (p.ej. 0001-0100), se
+    // rellenan todos los valores hasta el ancho del extremo más largo
+    fn range_pad_width(start: &str, end: &str) -> usize {
+        let padded = [start, end].iter().any(|b| b.len() > 1 && b.starts_with('0'));
+        if padded { start.len().max(end.len()) } else { 0 }
+    }
+
+    // end < start genera la secuencia descendente (start, start-1, ..., end)
+    fn expand_range(start: usize, end: usize, step: usize) -> Vec<usize> {
+        if end < start {
+            (end..=start).rev().step_by(step).collect()
+        } else {
+            (start..=end).step_by(step).collect()
+        }
+    }
+
+    /// `strip_comments` descarta las líneas de archivo que empiezan por `#`
+    /// (cabeceras tipo SecLists); desactivable con `--no-comment-strip`.
+    pub async fn parse_wordlist(token: &str, strip_comments: bool) -> anyhow::Result<Vec<String>> {
+        Self::read_list_from_token(token, strip_comments)
+    }
+
+    fn read_list_from_token(token: &str, strip_comments: bool) -> anyhow::Result<Vec<String>> {
         if token == "+" {
             return Ok(vec!["+".to_string()]);
         }
 
+        // "-" = leer la lista de stdin (p.ej. `cat words.txt | downzer URL -w -`)
+        if token == "-" {
+            return Ok(Self::read_list_from_reader(std::io::stdin().lock(), strip_comments));
+        }
+
         let path = Path::new(token);
         if path.exists() {
             let file = File::open(path)?;
-            let reader = BufReader::new(file);
-            let items: Vec<String> = reader
-                .lines()
-                .filter_map(|l| l.ok())
-                .flat_map(|l| l.split(',').map(|s| s.trim().to_string()).collect::<Vec<_>>())
-                .filter(|s| !s.is_empty())
-                .collect();
-            Ok(items)
+            Ok(Self::read_list_from_reader(BufReader::new(file), strip_comments))
         } else {
             Ok(token.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
         }
     }
 
-    fn process_wordlists(tokens: &[String]) -> anyhow::Result<Vec<Vec<String>>> {
+    /// Una entrada por línea (o varias separadas por comas), sin vacías.
+    pub fn read_list_from_reader(reader: impl BufRead, strip_comments: bool) -> Vec<String> {
+        reader
+            .lines()
+            .map_while(Result::ok)
+            .filter(|l| !(strip_comments && l.trim_start().starts_with('#')))
+            .flat_map(|l| l.split(',').map(|s| s.trim().to_string()).collect::<Vec<_>>())
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+
+    /// Lee las wordlists y concatena las adyacentes unidas con `+` (`-w a.txt + b.txt`).
+    pub fn process_wordlists(tokens: &[String], strip_comments: bool) -> anyhow::Result<Vec<Vec<String>>> {
         let mut raw_lists = Vec::new();
         for token in tokens {
-            let list = Self::read_list_from_token(token)?;
+            let list = Self::read_list_from_token(token, strip_comments)?;
             raw_lists.push(list);
         }
 
@@ -187,81 +493,72 @@ impl Downzer {
         Ok(processed)
     }
 
-    pub fn generate_combinations(
-        lists: &[Vec<String>],
-        parallel: bool,
-        random: bool,
-    ) -> Vec<Vec<String>> {
-        if parallel {
-            let len = lists[0].len();
-            let mut combinations = Vec::new();
-            for i in 0..len {
-                let mut combo = Vec::new();
-                for list in lists {
-                    combo.push(list[i % list.len()].clone());
-                }
-                combinations.push(combo);
-            }
-            if random {
-                use rand::seq::SliceRandom;
-                let mut rng = rand::thread_rng();
-                combinations.shuffle(&mut rng);
-            }
-            combinations
-        } else if lists.len() == 1 {
-            let mut combinations: Vec<Vec<String>> = lists[0].iter().map(|s| vec![s.clone()]).collect();
-            if random {
-                use rand::seq::SliceRandom;
-                let mut rng = rand::thread_rng();
-                combinations.shuffle(&mut rng);
-            }
-            combinations
-        } else {
-            fn cartesian_product(lists: &[Vec<String>], current: Vec<String>, index: usize, result: &mut Vec<Vec<String>>) {
-                if index >= lists.len() {
-                    result.push(current);
-                    return;
-                }
-                for item in &lists[index] {
-                    let mut new_current = current.clone();
-                    new_current.push(item.clone());
-                    cartesian_product(lists, new_current, index + 1, result);
-                }
-            }
+    /// Une varias listas en una sola dimensión, sin duplicados y conservando
+    /// el orden de primera aparición (p.ej. `-r 20-25` ∪ `-w 80,443`).
+    pub fn union_lists(lists: &[Vec<String>]) -> Vec<String> {
+        let mut seen = std::collections::HashSet::new();
+        lists
+            .iter()
+            .flatten()
+            .filter(|item| seen.insert(item.as_str()))
+            .cloned()
+            .collect()
+    }
 
-            let mut combinations = Vec::new();
-            cartesian_product(lists, Vec::new(), 0, &mut combinations);
-            
-            if random {
-                use rand::seq::SliceRandom;
-                let mut rng = rand::thread_rng();
-                combinations.shuffle(&mut rng);
-            }
-            combinations
-        }
+    /// Quita los elementos repetidos de una lista conservando el orden (`--dedup`).
+    /// Devuelve la lista y el número de duplicados eliminados.
+    pub fn dedup_list(list: Vec<String>) -> (Vec<String>, usize) {
+        let before = list.len();
+        let deduped = Self::union_lists(&[list]);
+        let removed = before - deduped.len();
+        (deduped, removed)
     }
 
+    /// Descarga `url` en la ruta que `namer` elija para ella (`idx` es su
+    /// posición en la tarea). El cuerpo se escribe en `<ruta>.part` y se
+    /// renombra al terminar; con `--resume` se continúa un `.part` previo.
     pub async fn download_file(
         &self,
         url: &str,
-        dest: &Path,
-        content_types: &[String],
-        verbose: u8,
-        debug: bool,
-    ) -> anyhow::Result<(u64, String, u16)> {
-        if debug {
+        words: &[String],
+        namer: &FileNamer,
+        idx: usize,
+        options: &DownloadOptions,
+        show_progress: bool,
+    ) -> anyhow::Result<DownloadOutcome> {
+        if options.debug {
             println!("[DEBUG] Downloading: {}", url);
         }
 
-        let response = self.client.get(url).send().await?;
+        // Para reanudar hay que saber el destino antes de pedir nada, así que
+        // solo se intenta cuando el nombre sale de la URL
+        let early_dest = if options.resume { namer.claim_from_url(url, idx) } else { None };
+        let resume_from = early_dest
+            .as_deref()
+            .and_then(|dest| fs::metadata(part_path(dest)).ok())
+            .map(|meta| meta.len())
+            .filter(|len| *len > 0);
+
+        let headers = options.headers.resolve(words)?;
+        let jar = options.cookie_jar.as_deref();
+        let mut request = self.client.get(url).headers(headers.clone());
+        if let Some(offset) = resume_from {
+            request = request.header(reqwest::header::RANGE, format!("bytes={}-", offset));
+        }
+        let mut response = redirect::send(&self.client, request, jar, options.location_trusted).await?;
+        // 416: el `.part` no encaja con el recurso actual; se descarga de nuevo
+        if resume_from.is_some() && response.status().as_u16() == 416 {
+            let request = self.client.get(url).headers(headers);
+            response = redirect::send(&self.client, request, jar, options.location_trusted).await?;
+        }
         let status = response.status().as_u16();
 
         if status == 404 {
-            return Err(anyhow::anyhow!("NOT_FOUND"));
+            return Ok(DownloadOutcome::NotFound);
         }
 
         if !response.status().is_success() {
-            return Err(anyhow::anyhow!("HTTP {}", status));
+            return Ok(DownloadOutcome::HttpError(status));
         }
 
         let content_type = response
@@ -271,114 +568,371 @@ impl Downzer {
             .unwrap_or("")
             .to_lowercase();
 
-        if !content_types.is_empty() {
-            let matches = content_types.iter().any(|ct| content_type.contains(ct));
+        if !options.content_types.is_empty() {
+            let matches = options.content_types.iter().any(|ct| content_type.contains(ct));
             if !matches {
-                return Err(anyhow::anyhow!("IGNORED"));
+                return Ok(DownloadOutcome::Ignored);
             }
         }
 
-        let content_length = response.content_length().unwrap_or(0);
-        let bytes = response.bytes().await?;
-
-        fs::create_dir_all(dest.parent().unwrap())?;
-        let mut file = File::create(dest)?;
-        file.write_all(&bytes)?;
+        // Un 200 en lugar de 206 significa que el servidor ignora el rango: se empieza de cero
+        let offset = match resume_from {
+            Some(offset) if status == 206 => {
+                let start = response
+                    .headers()
+                    .get(reqwest::header::CONTENT_RANGE)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(content_range_start);
+                if start != Some(offset) {
+                    anyhow::bail!("unexpected Content-Range in resumed download (expected start {})", offset);
+                }
+                offset
+            }
+            _ => 0,
+        };
 
-        if verbose >= 2 {
-            println!("[OK] {} ({} bytes)", dest.display(), bytes.len());
+        // Con Content-Length, lo que no cabe en los límites se descarta sin descargarlo
+        if let Some(len) = response.content_length().map(|len| len + offset) {
+            if !size_in_bounds(len, options.min_size, options.max_size) {
+                return Ok(DownloadOutcome::FilteredSize(len));
+            }
         }
 
-        Ok((content_length, content_type, status))
-    }
+        // El hueco del cupo se reserva antes de descargar para que las descargas
+        // concurrentes no lo superen, y se libera si el archivo no se llega a guardar
+        let slot = if options.extension_caps.is_empty() {
+            None
+        } else {
+            let ext = derived_extension(url, response.headers());
+            match options.extension_caps.reserve(ext.as_deref()) {
+                Some(slot) => Some(slot),
+                None => return Ok(DownloadOutcome::Capped(ext.unwrap_or_default())),
+            }
+        };
 
-    pub async fn get_task_status(&self, task_id: u32) -> Option<TaskStatus> {
-        let tasks = self.tasks.read().await;
-        tasks.get(&task_id).map(|t| t.status)
+        let dest = match early_dest {
+            Some(dest) => dest,
+            None => namer.path_for(url, response.headers(), idx, status),
+        };
+        let part = part_path(&dest);
+        create_parent_dir(&dest)?;
+        // El cuerpo se escribe por trozos según llega, sin cargarlo entero en memoria
+        let file = if offset > 0 {
+            fs::OpenOptions::new().append(true).open(&part)?
+        } else {
+            File::create(&part)?
+        };
+        let mut file = BufWriter::new(file);
+        let pb = show_progress.then(|| byte_progress_bar(response.content_length().map(|len| len + offset)));
+        let mut stream = response.bytes_stream();
+        let mut written = offset;
+        if let Some(pb) = &pb {
+            pb.set_position(written);
+        }
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            if let Some(limiter) = &options.rate_limiter {
+                limiter.consume(chunk.len()).await;
+            }
+            written += chunk.len() as u64;
+            // Sin Content-Length (o si mentía) el máximo se comprueba al vuelo
+            if options.max_size.is_some_and(|max| written > max) {
+                drop(file);
+                fs::remove_file(&part)?;
+                return Ok(DownloadOutcome::FilteredSize(written));
+            }
+            file.write_all(&chunk)?;
+            if let Some(pb) = &pb {
+                pb.set_position(written);
+            }
+        }
+        file.flush()?;
+        drop(file);
+        if let Some(pb) = &pb {
+            pb.finish_and_clear();
+        }
+        if options.min_size.is_some_and(|min| written < min) {
+            fs::remove_file(&part)?;
+            return Ok(DownloadOutcome::FilteredSize(written));
+        }
+        fs::rename(&part, &dest)?;
+        if let Some(slot) = slot {
+            slot.keep();
+        }
+
+        Ok(DownloadOutcome::Saved { path: dest, bytes: written, status, content_type })
     }
 
+    /// Cambia el estado de la tarea en memoria y en la base de datos.
     pub async fn set_task_status(&self, task_id: u32, status: TaskStatus) {
         let mut tasks = self.tasks.write().await;
         if let Some(task) = tasks.get_mut(&task_id) {
             task.status = status;
         }
+        drop(tasks);
+        let now = chrono::Utc::now().to_rfc3339();
+        if let Err(e) = self.db.lock().await.set_task_status(task_id, status, &now) {
+            eprintln!("[ERROR] Cannot persist status of task #{}: {}", task_id, e);
+        }
     }
 
-    pub async fn add_task(&self, task: TaskInfo) {
-        let mut tasks = self.tasks.write().await;
-        tasks.insert(task.id, task);
+    /// Siguiente id de tarea. Se cuenta también con las tareas guardadas en la
+    /// base de datos para no pisar las de ejecuciones anteriores (ni las de
+    /// otro proceso que haya arrancado entretanto).
+    pub async fn allocate_task_id(&self) -> u32 {
+        let mut next_id = self.next_task_id.write().await;
+        let stored = self.db.lock().await.max_task_id().unwrap_or(0);
+        let id = (*next_id).max(stored + 1);
+        *next_id = id + 1;
+        id
     }
 
+    /// Registra la tarea en memoria y en la base de datos; devuelve la fila
+    /// guardada, que sirve de base para volcar el progreso. Si la base de
+    /// datos falla, la tarea queda igualmente registrada en memoria.
+    pub async fn add_task(&self, task: TaskInfo) -> anyhow::Result<TaskRecord> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let record = TaskRecord {
+            id: task.id,
+            url_template: task.url_template.clone(),
+            total: task.total,
+            completed: task.completed,
+            status: task.status,
+            pid: Some(std::process::id()),
+            created_at: now.clone(),
+            updated_at: now,
+        };
+        self.tasks.write().await.insert(task.id, task);
+        self.db.lock().await.upsert_task(&record)?;
+        Ok(record)
+    }
+
+    /// Cambia el progreso de la tarea en memoria y en la base de datos.
     pub async fn update_task_progress(&self, task_id: u32, completed: usize) {
         let mut tasks = self.tasks.write().await;
         if let Some(task) = tasks.get_mut(&task_id) {
             task.completed = completed;
         }
+        drop(tasks);
+        let now = chrono::Utc::now().to_rfc3339();
+        if let Err(e) = self.db.lock().await.set_task_progress(task_id, completed, &now) {
+            eprintln!("[ERROR] Cannot persist progress of task #{}: {}", task_id, e);
+        }
     }
 
-    pub async fn get_task_info(&self, task_id: u32) -> Option<TaskInfo> {
-        let tasks = self.tasks.read().await;
-        tasks.get(&task_id).cloned()
+    /// Lista de `--exclude`/`--include`: si el argumento es un archivo existente
+    /// se lee una entrada por línea; si no, se separa por comas o espacios.
+    pub fn parse_filter_list(token: Option<&str>) -> anyhow::Result<Vec<String>> {
+        let Some(token) = token else {
+            return Ok(Vec::new());
+        };
+        let path = Path::new(token);
+        if path.is_file() {
+            let reader = BufReader::new(File::open(path)?);
+            return Ok(reader
+                .lines()
+                .map_while(Result::ok)
+                .map(|l| l.trim().to_string())
+                .filter(|l| !l.is_empty())
+                .collect());
+        }
+        Ok(token
+            .split([',', ' '])
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string())
+            .collect())
     }
 
-    pub fn process_url_template(
+    /// Sustituye los placeholders de `template` con cada combinación y
+    /// filtra las URLs según se piden, sin reunirlas en memoria. Cada
+    /// combinación llega con los índices de sus valores en las listas de origen
+    /// (ver `CombinationIter::indexed`).
+    pub fn process_url_template_iter(
         template: &str,
-        combinations: Vec<Vec<String>>,
-        exclude: Option<&str>,
-    ) -> anyhow::Result<Vec<String>> {
-        let mut urls = Vec::new();
-        let exclude_set: std::collections::HashSet<_> = exclude
-            .unwrap_or("")
-            .split(|c| c == ',' || c == ' ')
-            .filter(|s| !s.is_empty())
-            .collect();
+        combinations: impl Iterator<Item = (Vec<String>, Vec<usize>)>,
+        range_count: usize,
+        filters: UrlFilters,
+    ) -> impl Iterator<Item = Target> {
+        let template = template.to_string();
+        combinations.filter_map(move |(combo, indices)| {
+            let url = Self::substitute_placeholders(&template, &combo, range_count);
+            filters.keeps(&url).then_some(Target { url, words: combo, indices })
+        })
+    }
 
-        for combo in combinations {
-            let mut url = template.to_string();
-            
-            // Reemplazar FUZZW1, FUZZW2, etc
-            for (i, value) in combo.iter().enumerate() {
-                let placeholder = format!("FUZZW{}", i + 1);
-                if url.contains(&placeholder) {
-                    url = url.replace(&placeholder, value);
+    /// Forma normalizada de `url` para deduplicar (`--strip-query`, `--strip-fragment`).
+    /// Las URLs que no se pueden parsear se devuelven tal cual.
+    pub fn normalize_url(url: &str, strip_query: bool, strip_fragment: bool) -> String {
+        match url::Url::parse(url) {
+            Ok(mut parsed) => {
+                if strip_query {
+                    parsed.set_query(None);
+                }
+                if strip_fragment {
+                    parsed.set_fragment(None);
                 }
+                parsed.to_string()
             }
+            Err(_) => url.to_string(),
+        }
+    }
 
-            // Reemplazar FUZZR si existe
-            if url.contains("FUZZR") && !combo.is_empty() {
-                url = url.replace("FUZZR", &combo[0]);
+    /// Sustituye los placeholders de `template` con los valores de `combo`.
+    ///
+    /// Las primeras `range_count` posiciones de la combinación son los rangos
+    /// (`FUZZR1`, `FUZZR2`, ...; `FUZZR` equivale a `FUZZR1`) y el resto las
+    /// wordlists (`FUZZW1`, `FUZZW2`, ...). `FUZZ` a secas (estilo ffuf) es
+    /// siempre la primera lista, sea rango o wordlist; sin rangos, `FUZZR` y
+    /// `FUZZR1` también la toman (p.ej. tras `--union`). Los sufijos (`:upper`,
+    /// `:lower`, `:cap`, `:url`, `:b64`, `:b64url`) transforman el valor y se
+    /// aplican de izquierda a derecha (`FUZZW1:cap:url`); sin sufijo se inserta
+    /// tal cual.
+    pub fn substitute_placeholders(template: &str, combo: &[String], range_count: usize) -> String {
+        static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+        let re = PLACEHOLDER.get_or_init(|| {
+            Regex::new(r"FUZZ(?:([RW])(\d*)|\b)((?::(?:b64url|b64|url|upper|lower|cap)\b)*)").expect("valid placeholder regex")
+        });
+        let (ranges, words) = combo.split_at(range_count.min(combo.len()));
+
+        // Una sola pasada: los valores insertados no se vuelven a sustituir
+        re.replace_all(template, |caps: &regex::Captures| {
+            let kind = caps.get(1).map_or("", |m| m.as_str());
+            let index: Option<usize> = caps.get(2).and_then(|m| m.as_str().parse().ok());
+            let value = match (kind, index) {
+                ("", _) => combo.first(),
+                ("R", None) | ("R", Some(1)) => ranges.first().or(combo.first()),
+                ("R", Some(n)) => n.checked_sub(1).and_then(|i| ranges.get(i)),
+                ("W", Some(n)) => n.checked_sub(1).and_then(|i| words.get(i)),
+                _ => None,
+            };
+            match value {
+                Some(value) => caps[3]
+                    .split(':')
+                    .filter(|m| !m.is_empty())
+                    .fold(value.clone(), |v, m| Self::apply_modifier(&v, m)),
+                None => caps[0].to_string(),
             }
+        })
+        .into_owned()
+    }
 
-            if !exclude_set.contains(url.as_str()) {
-                urls.push(url);
+    /// Aplica un modificador de placeholder a un valor.
+    fn apply_modifier(value: &str, modifier: &str) -> String {
+        match modifier {
+            "upper" => value.to_uppercase(),
+            "lower" => value.to_lowercase(),
+            // Primera letra en mayúscula y el resto en minúscula
+            "cap" => {
+                let mut chars = value.chars();
+                match chars.next() {
+                    Some(first) => first.to_uppercase().chain(chars.as_str().to_lowercase().chars()).collect(),
+                    None => String::new(),
+                }
             }
+            "url" => utf8_percent_encode(value, URL_ENCODE_SET).to_string(),
+            "b64" => BASE64_STANDARD.encode(value),
+            // Alfabeto URL-safe sin relleno, como en los segmentos JWT
+            "b64url" => BASE64_URL_SAFE_NO_PAD.encode(value),
+            _ => value.to_string(),
         }
-
-        Ok(urls)
     }
 
     pub async fn execute_download_task(
         &self,
         task_id: u32,
-        _url_template: &str,
-        urls: Vec<String>,
-        output_dir: &Path,
-        content_types: &[String],
-        max_concurrent: usize,
-        verbose: u8,
-        debug: bool,
+        targets: Targets,
+        options: &DownloadOptions,
     ) -> anyhow::Result<Stats> {
         let mut stats = Stats::new();
+        let verbose = options.verbose;
+        // Con una sola URL se muestra el progreso en bytes de esa descarga
+        let byte_progress = options.show_progress && targets.total() == 1;
+
+        // Usar un semáforo para limitar concurrencia. El permiso se obtiene
+        // antes de lanzar cada descarga para no adelantarse al generador.
+        let semaphore = Arc::new(tokio::sync::Semaphore::new(options.max_concurrent));
+        let mut pending = VecDeque::new();
 
-        // Usar un semáforo para limitar concurrencia
-        let semaphore = Arc::new(tokio::sync::Semaphore::new(max_concurrent));
-        let mut handles = vec![];
+        let shared_options = Arc::new(options.clone());
+        let namer = Arc::new(
+            FileNamer::new(options.output_dir.clone(), options.max_filename_length, options.name_template.clone())
+                .preserve_path(options.preserve_path),
+        );
+        let dedup = options
+            .dedup_content
+            .then(|| Arc::new(ContentDedup::new(options.hardlink_dupes)));
+        let host_limiter = options.max_per_host.map(|n| Arc::new(HostLimiter::new(n)));
+        let mut manifest = Vec::new();
+        // Que ninguna descarga pise el manifiesto si cae dentro del directorio de salida
+        if let Some(rel) = options.manifest.as_ref().and_then(|p| p.strip_prefix(&options.output_dir).ok()) {
+            namer.exclude(&rel.to_string_lossy());
+        }
+
+        let mut report = match &options.report {
+            Some(path) => Some(ReportSink::create(path)
+                .map_err(|e| anyhow::anyhow!("Cannot create report {}: {}", path.display(), e))?),
+            None => None,
+        };
 
-        for (idx, url) in urls.iter().enumerate() {
-            let url = url.clone();
-            let sem = semaphore.clone();
-            let output_dir = output_dir.to_path_buf();
-            let content_types = content_types.to_vec();
+        let live = options.live.clone();
+        let mut record = |result: Option<DownloadResult>, target: &Target| {
+            if let Some(result) = result {
+                live.record(result.errors > 0, result.bytes);
+                if let Some(sink) = &mut report {
+                    let bytes = (result.bytes > 0).then_some(result.bytes);
+                    if let Err(e) = sink.write(&ReportRow::new(target, result.status, result.outcome, bytes)) {
+                        eprintln!("[ERROR] --report: {}", e);
+                        report = None;
+                    }
+                }
+                if options.manifest.is_some() {
+                    manifest.push(ManifestEntry {
+                        url: target.url.clone(),
+                        file: result
+                            .path
+                            .as_ref()
+                            .and_then(|p| p.file_name())
+                            .map(|name| name.to_string_lossy().into_owned()),
+                        status: result.status,
+                        bytes: (result.bytes > 0).then_some(result.bytes),
+                        content_type: result.content_type,
+                        outcome: result.outcome.to_string(),
+                    });
+                }
+                if let Some(line) = result.line {
+                    line.emit();
+                }
+                stats.processed += 1;
+                stats.total_bytes += result.bytes;
+                stats.downloaded += result.downloaded;
+                stats.ignored += result.ignored;
+                stats.errors += result.errors;
+                stats.not_found += result.not_found;
+                stats.duplicates += result.duplicates;
+                stats.bytes_saved += result.bytes_saved;
+                stats.filtered_size += result.filtered_size;
+                stats.capped += result.capped;
+            }
+        };
+
+        for (idx, target) in targets.enumerate() {
+            let reported = target.clone();
+            let Target { url, words: combo, .. } = target;
+            let permit = semaphore.clone().acquire_owned().await?;
+            if let Some(pacer) = &options.pacer {
+                pacer.wait().await;
+                if pacer.stopped() {
+                    break;
+                }
+            }
+            let namer = namer.clone();
+            let task_options = shared_options.clone();
+            let exec = options.exec.clone();
+            let ordered = options.ordered_output;
+            let show_errors = verbose >= 1 && !options.quiet_errors;
+            let dedup = dedup.clone();
+            let host_limiter = host_limiter.clone();
+            let live = options.live.clone();
             let self_client = self.client.clone();
             let self_tasks = self.tasks.clone();
             let self_config = self.config.clone();
@@ -386,7 +940,14 @@ impl Downzer {
             let self_db = self.db.clone();
 
             let handle = tokio::spawn(async move {
-                let _guard = sem.acquire().await.ok()?;
+                let _permit = permit;
+                // Con --max-per-host se espera además un hueco en el host de la URL;
+                // mientras tanto la descarga no cuenta como activa
+                let _host_permit = match &host_limiter {
+                    Some(limiter) => Some(limiter.acquire(&url).await.ok()?),
+                    None => None,
+                };
+                let _active = live.begin();
                 
                 // Verificar si la tarea fue pausada/detenida
                 let tasks_lock = self_tasks.read().await;
@@ -397,10 +958,6 @@ impl Downzer {
                 }
                 drop(tasks_lock);
 
-                // Generar nombre de archivo
-                let filename = format!("download_{:06}", idx);
-                let dest = output_dir.join(&filename);
-
                 // Crear cliente temporal para descarga
                 let downzer_temp = Downzer {
                     client: self_client,
@@ -411,48 +968,175 @@ impl Downzer {
                 };
 
                 // Intentar descarga
-                match downzer_temp.download_file(&url, &dest, &content_types, verbose, debug).await {
-                    Ok((size, _, _)) => {
-                        let mut tasks_mut = self_tasks.write().await;
-                        if let Some(t) = tasks_mut.get_mut(&task_id) {
-                            t.completed += 1;
+                let outcome = downzer_temp.download_file(&url, &combo, &namer, idx, &task_options, byte_progress).await;
+
+                let mut tasks_mut = self_tasks.write().await;
+                if let Some(t) = tasks_mut.get_mut(&task_id) {
+                    t.completed += 1;
+                }
+                drop(tasks_mut);
+
+                let (err_status, err_msg) = match outcome {
+                    Ok(DownloadOutcome::Saved { path: dest, bytes: size, status, content_type }) => {
+                        let content_type = (!content_type.is_empty()).then_some(content_type);
+                        // Contenido ya descargado: no se guarda dos veces ni se ejecuta --exec
+                        if let Some(dedup) = &dedup {
+                            match dedup.check(&dest) {
+                                Ok(Some(original)) => {
+                                    let mut line = None;
+                                    if verbose >= 2 {
+                                        line = OutputLine::Stdout(format!(
+                                            "[DUP] {} = {} ({} bytes)",
+                                            url,
+                                            original.display(),
+                                            size
+                                        ))
+                                        .deliver(ordered);
+                                    }
+                                    return Some(DownloadResult {
+                                        bytes: size,
+                                        downloaded: 1,
+                                        duplicates: 1,
+                                        bytes_saved: size,
+                                        status: Some(status),
+                                        outcome: "duplicate",
+                                        path: Some(original),
+                                        content_type,
+                                        line,
+                                        ..Default::default()
+                                    });
+                                }
+                                Ok(None) => {}
+                                Err(e) => {
+                                    if show_errors {
+                                        eprintln!("[ERROR] dedup {}: {}", dest.display(), e);
+                                    }
+                                }
+                            }
                         }
-                        Some((size, 1, 0, 0, 0))
-                    }
-                    Err(e) => {
-                        let err_msg = e.to_string();
-                        let mut tasks_mut = self_tasks.write().await;
-                        if let Some(t) = tasks_mut.get_mut(&task_id) {
-                            t.completed += 1;
+
+                        let mut line = None;
+                        if verbose >= 2 {
+                            line = OutputLine::Stdout(format!("[OK] {} ({} bytes)", dest.display(), size))
+                                .deliver(ordered);
                         }
-                        
-                        if err_msg.contains("NOT_FOUND") {
-                            Some((0, 0, 1, 0, 1))
-                        } else if err_msg.contains("IGNORED") {
-                            Some((0, 0, 1, 0, 0))
-                        } else {
-                            if verbose >= 1 {
-                                eprintln!("[ERROR] {}: {}", url, err_msg);
+
+                        if let Some(cmd) = &exec {
+                            let hook = ExecHook { command: cmd, dest: &dest, url: &url, status, words: &combo };
+                            if let Err(e) = hook.run().await {
+                                if show_errors {
+                                    eprintln!("[ERROR] --exec {}: {}", dest.display(), e);
+                                }
                             }
-                            Some((0, 0, 0, 1, 0))
                         }
+                        return Some(DownloadResult {
+                            bytes: size,
+                            downloaded: 1,
+                            status: Some(status),
+                            outcome: "saved",
+                            path: Some(dest),
+                            content_type,
+                            line,
+                            ..Default::default()
+                        });
                     }
+                    Ok(DownloadOutcome::NotFound) => {
+                        return Some(DownloadResult {
+                            ignored: 1,
+                            not_found: 1,
+                            status: Some(404),
+                            outcome: "not_found",
+                            ..Default::default()
+                        });
+                    }
+                    Ok(DownloadOutcome::Ignored) => {
+                        return Some(DownloadResult { ignored: 1, outcome: "ignored", ..Default::default() });
+                    }
+                    Ok(DownloadOutcome::FilteredSize(size)) => {
+                        let mut line = None;
+                        if verbose >= 2 {
+                            line = OutputLine::Stdout(format!("[SIZE] {} ({} bytes)", url, size)).deliver(ordered);
+                        }
+                        return Some(DownloadResult {
+                            ignored: 1,
+                            filtered_size: 1,
+                            outcome: "filtered_size",
+                            line,
+                            ..Default::default()
+                        });
+                    }
+                    Ok(DownloadOutcome::Capped(ext)) => {
+                        let mut line = None;
+                        if verbose >= 2 {
+                            line = OutputLine::Stdout(format!("[CAP] {} (.{} limit reached)", url, ext)).deliver(ordered);
+                        }
+                        return Some(DownloadResult {
+                            ignored: 1,
+                            capped: 1,
+                            outcome: "capped",
+                            line,
+                            ..Default::default()
+                        });
+                    }
+                    Ok(DownloadOutcome::HttpError(status)) => (Some(status), format!("HTTP {}", status)),
+                    Err(e) => (None, request_error_message(e.as_ref())),
+                };
+
+                let mut line = None;
+                if show_errors {
+                    line = OutputLine::Stderr(format!("[ERROR] {}: {}", url, err_msg))
+                        .deliver(ordered);
                 }
+                Some(DownloadResult {
+                    errors: 1,
+                    status: err_status,
+                    outcome: if err_status.is_some() { "http_error" } else { "error" },
+                    line,
+                    ..Default::default()
+                })
             });
 
-            handles.push(handle);
+            pending.push_back((handle, reported));
+
+            // Recoger en orden los resultados ya terminados para no acumular handles
+            while pending.front().is_some_and(|(h, _): &(tokio::task::JoinHandle<_>, _)| h.is_finished()) {
+                if let Some((handle, target)) = pending.pop_front() {
+                    record(handle.await.ok().flatten(), &target);
+                }
+            }
         }
 
-        // Esperar a que todas las tareas terminen
-        for handle in handles {
-            if let Ok(Some((bytes, downloaded, ignored, errors, not_found))) = handle.await {
-                stats.total_bytes += bytes;
-                stats.downloaded += downloaded;
-                stats.ignored += ignored;
-                stats.errors += errors;
-                stats.not_found += not_found;
+        // Esperar a que todas las tareas terminen (con --drain-timeout, como mucho
+        // hasta el plazo; lo que quede se aborta y cuenta como timeout)
+        let deadline = options.drain_timeout.map(|t| tokio::time::Instant::now() + t);
+        let mut aborted = 0;
+        while let Some((mut handle, target)) = pending.pop_front() {
+            match join_before(&mut handle, deadline).await {
+                Some(result) => record(result.ok().flatten(), &target),
+                None => {
+                    handle.abort();
+                    aborted += 1;
+                    record(Some(DownloadResult { errors: 1, outcome: "timeout", ..Default::default() }), &target);
+                }
             }
         }
+        if let Some(sink) = report {
+            sink.finish()?;
+        }
+        if let Some(path) = &options.manifest {
+            let entries = if options.manifest_append {
+                merge_manifest(load_manifest(path)?, manifest)
+            } else {
+                manifest
+            };
+            create_parent_dir(path)?;
+            write_manifest(path, task_id, &entries)
+                .map_err(|e| anyhow::anyhow!("Cannot write manifest {}: {}", path.display(), e))?;
+        }
+        stats.timeouts = aborted;
+        if aborted > 0 && options.show_progress {
+            eprintln!("[!] Drain timeout: aborted {} pending download(s)", aborted);
+        }
 
         // Marcar tarea como completada
         self.set_task_status(task_id, TaskStatus::Completed).await;
@@ -463,9 +1147,793 @@ impl Downzer {
             println!("  Ignored: {}", stats.ignored);
             println!("  Not Found: {}", stats.not_found);
             println!("  Errors: {}", stats.errors);
+            if stats.filtered_size > 0 {
+                println!("  Filtered by size: {}", stats.filtered_size);
+            }
+            if stats.capped > 0 {
+                println!("  Skipped by extension cap: {}", stats.capped);
+            }
+            if stats.timeouts > 0 {
+                println!("  Drain timeouts: {}", stats.timeouts);
+            }
             println!("  Total bytes: {}", stats.total_bytes);
+            if options.dedup_content {
+                println!("  Duplicates: {} ({} bytes saved)", stats.duplicates, stats.bytes_saved);
+            }
         }
 
         Ok(stats)
     }
-}
\ No newline at end of file
+}
+
+/// Versión de TLS a partir de `--tls-min`/`--tls-max` ("1.2" o "1.3").
+pub fn parse_tls_version(version: &str) -> anyhow::Result<reqwest::tls::Version> {
+    match version {
+        "1.2" => Ok(reqwest::tls::Version::TLS_1_2),
+        "1.3" => Ok(reqwest::tls::Version::TLS_1_3),
+        "1.0" | "1.1" => anyhow::bail!("TLS {} is not supported (only 1.2 and 1.3)", version),
+        _ => anyhow::bail!("Unknown TLS version: {} (use 1.2 or 1.3)", version),
+    }
+}
+
+/// Mensaje de un error de petición con toda su cadena de causas. Los fallos de
+/// handshake por versión de TLS se señalan explícitamente.
+pub fn request_error_message(err: &(dyn std::error::Error + 'static)) -> String {
+    let mut parts = vec![err.to_string()];
+    let mut source = err.source();
+    while let Some(e) = source {
+        let text = e.to_string();
+        if !parts.iter().any(|p| p.contains(&text)) {
+            parts.push(text);
+        }
+        source = e.source();
+    }
+    let message = parts.join(": ");
+    let lower = message.to_lowercase();
+    if lower.contains("protocolversion") || lower.contains("protocol version") {
+        format!("TLS version not accepted (check --tls-min/--tls-max): {}", message)
+    } else {
+        message
+    }
+}
+
+/// Interpreta un tamaño con sufijo binario opcional: `500`, `500K`, `10M`,
+/// `1.5G`, `2MB`, `2MiB` (K = 1024).
+pub fn parse_size(spec: &str) -> anyhow::Result<u64> {
+    let spec = spec.trim();
+    let split = spec
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(spec.len());
+    let (number, suffix) = spec.split_at(split);
+    let value: f64 = number
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid size '{}': expected a number like 500K or 10M", spec))?;
+    let multiplier: u64 = match suffix.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        "T" | "TB" | "TIB" => 1 << 40,
+        other => anyhow::bail!("Invalid size '{}': unknown suffix '{}' (use K, M, G or T)", spec, other),
+    };
+    Ok((value * multiplier as f64).round() as u64)
+}
+
+/// Crea el directorio de `path` si hace falta. Un nombre sin directorio
+/// (p.ej. "file.bin") va al directorio actual, que ya existe.
+fn create_parent_dir(path: &Path) -> std::io::Result<()> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => fs::create_dir_all(dir),
+        _ => Ok(()),
+    }
+}
+
+fn size_in_bounds(size: u64, min: Option<u64>, max: Option<u64>) -> bool {
+    min.is_none_or(|min| size >= min) && max.is_none_or(|max| size <= max)
+}
+
+/// Primer byte de una cabecera `Content-Range: bytes <inicio>-<fin>/<total>`.
+fn content_range_start(value: &str) -> Option<u64> {
+    value.trim().strip_prefix("bytes ")?.split('-').next()?.trim().parse().ok()
+}
+
+/// Longitud del sufijo de hash que añade `truncate_filename` (`_` y 8 dígitos hex).
+pub const HASH_SUFFIX_LEN: usize = 9;
+
+/// Mínimo de `--max-filename-length`: el sufijo de hash más el `.part` de la
+/// descarga en curso.
+pub const MIN_FILENAME_LENGTH: usize = HASH_SUFFIX_LEN + PART_SUFFIX.len();
+
+/// Acorta `name` a como mucho `max_len` bytes, conservando la extensión y
+/// añadiendo un hash corto del nombre completo para evitar colisiones. Si la
+/// extensión no cabe junto al hash se descarta; `max_len` no puede ser menor
+/// que `HASH_SUFFIX_LEN`.
+pub fn truncate_filename(name: &str, max_len: usize) -> String {
+    debug_assert!(max_len >= HASH_SUFFIX_LEN, "max_len below the hash suffix");
+    if name.len() <= max_len {
+        return name.to_string();
+    }
+
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() && !ext.is_empty() && ext.len() <= 16 => {
+            (stem, format!(".{}", ext))
+        }
+        _ => (name, String::new()),
+    };
+
+    let hash = format!("_{:08x}", fnv1a_32(name.as_bytes()));
+    let suffix = if hash.len() + ext.len() <= max_len { hash + &ext } else { hash };
+    let mut budget = max_len.saturating_sub(suffix.len()).min(stem.len());
+    while !stem.is_char_boundary(budget) {
+        budget -= 1;
+    }
+
+    format!("{}{}", &stem[..budget], suffix)
+}
+
+// FNV-1a: hash estable entre ejecuciones y versiones de Rust
+fn fnv1a_32(bytes: &[u8]) -> u32 {
+    let mut hash: u32 = 0x811c9dc5;
+    for b in bytes {
+        hash ^= *b as u32;
+        hash = hash.wrapping_mul(0x01000193);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::combinations::CombinationIter;
+
+    #[test]
+    fn truncate_filename_keeps_extension_and_stable_hash() {
+        let name = format!("{}.pdf", "a".repeat(300));
+        let truncated = truncate_filename(&name, 64);
+        assert_eq!(truncated.len(), 64);
+        assert!(truncated.ends_with(".pdf"));
+        assert_eq!(truncated, truncate_filename(&name, 64));
+        let hash = &truncated[truncated.len() - 4 - 8..truncated.len() - 4];
+        assert_eq!(hash, format!("{:08x}", fnv1a_32(name.as_bytes())));
+        assert_ne!(truncated, truncate_filename(&format!("{}.pdf", "b".repeat(300)), 64));
+    }
+
+    #[test]
+    fn truncate_filename_leaves_short_names() {
+        assert_eq!(truncate_filename("report.pdf", 255), "report.pdf");
+    }
+
+    #[test]
+    fn truncate_filename_never_exceeds_limit() {
+        let name = format!("{}.{}", "x".repeat(40), "e".repeat(16));
+        for max_len in HASH_SUFFIX_LEN..40 {
+            let truncated = truncate_filename(&name, max_len);
+            assert!(truncated.len() <= max_len, "{} > {}", truncated.len(), max_len);
+        }
+        // Sin sitio para la extensión junto al hash, se descarta
+        assert!(!truncate_filename(&name, HASH_SUFFIX_LEN + 5).contains('.'));
+    }
+
+    #[test]
+    fn truncate_filename_respects_char_boundaries() {
+        let name = format!("{}.txt", "ñ".repeat(100));
+        let truncated = truncate_filename(&name, 30);
+        assert!(truncated.len() <= 30);
+        assert!(truncated.ends_with(".txt"));
+    }
+
+    async fn range(spec: &str) -> Vec<String> {
+        Downzer::parse_range(spec).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn range_with_step() {
+        assert_eq!(range("0-10:2").await, ["0", "2", "4", "6", "8", "10"]);
+        // `end` solo entra si cae en un paso
+        assert_eq!(range("0-9:3").await, ["0", "3", "6", "9"]);
+        assert_eq!(range("1-10:4").await, ["1", "5", "9"]);
+    }
+
+    #[tokio::test]
+    async fn range_step_larger_than_span() {
+        assert_eq!(range("3-5:10").await, ["3"]);
+    }
+
+    #[tokio::test]
+    async fn range_zero_step_is_an_error() {
+        let err = Downzer::parse_range("0-10:0").await.unwrap_err();
+        assert!(err.to_string().contains("step"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn descending_range() {
+        assert_eq!(range("5-1").await, ["5", "4", "3", "2", "1"]);
+        assert_eq!(range("10-0:5").await, ["10", "5", "0"]);
+        assert_eq!(range("1-5").await, ["1", "2", "3", "4", "5"]);
+        assert_eq!(range("7-7").await, ["7"]);
+    }
+
+    #[tokio::test]
+    async fn zero_padded_range() {
+        assert_eq!(range("008-012").await, ["008", "009", "010", "011", "012"]);
+        // El ancho es el del extremo más largo
+        assert_eq!(range("01-100:50").await, ["001", "051"]);
+        assert_eq!(range("8-12").await, ["8", "9", "10", "11", "12"]);
+        assert_eq!(range("0-2").await, ["0", "1", "2"]);
+    }
+
+    #[tokio::test]
+    async fn hex_range() {
+        assert_eq!(range("0x1-0xa").await, ["1", "2", "3", "4", "5", "6", "7", "8", "9", "a"]);
+        assert_eq!(range("0x0e-0x10").await, ["0e", "0f", "10"]);
+        assert_eq!(range("0xFE-0xff").await, ["fe", "ff"]);
+    }
+
+    #[tokio::test]
+    async fn malformed_hex_range_is_an_error() {
+        for spec in ["0x1-0xg", "0x-0xa", "0x1-a", "0x1"] {
+            let err = Downzer::parse_range(spec).await.unwrap_err();
+            assert!(err.to_string().contains("Invalid hex range"), "{}: {}", spec, err);
+        }
+    }
+
+    #[tokio::test]
+    async fn alphabetic_range() {
+        assert_eq!(range("a-e").await, ["a", "b", "c", "d", "e"]);
+        assert_eq!(range("aa-ac").await, ["aa", "ab", "ac"]);
+        assert_eq!(range("AY-BB").await, ["AY", "AZ", "BA", "BB"]);
+        assert_eq!(range("c-a").await, ["c", "b", "a"]);
+    }
+
+    #[tokio::test]
+    async fn invalid_alphabetic_ranges_are_errors() {
+        for spec in ["a-zz", "a-Z", "aB-aC"] {
+            assert!(Downzer::parse_range(spec).await.is_err(), "{}", spec);
+        }
+    }
+
+    #[tokio::test]
+    async fn alphabetic_range_too_wide_is_an_error_not_an_overflow() {
+        let err = Downzer::parse_range("aaaaaaaaaaaaaa-zzzzzzzzzzzzzz").await.unwrap_err();
+        assert!(err.to_string().contains("too many letters"), "{}", err);
+        // 13 letras aún caben
+        let spec = format!("{}-{}b", "z".repeat(13), "z".repeat(12));
+        assert!(Downzer::parse_range(&spec).await.is_ok());
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn independent_range_placeholders() {
+        let lists = vec![strings(&["1", "2"]), strings(&["a", "b"]), strings(&["x"])];
+        let urls: Vec<String> = CombinationIter::new(lists, false)
+            .map(|combo| Downzer::substitute_placeholders("http://h/FUZZR1/FUZZR2/FUZZW1?p=FUZZR", &combo, 2))
+            .collect();
+        assert_eq!(
+            urls,
+            ["http://h/1/a/x?p=1", "http://h/1/b/x?p=1", "http://h/2/a/x?p=2", "http://h/2/b/x?p=2"]
+        );
+        // Un índice sin rango queda literal
+        let combo = strings(&["1", "x"]);
+        assert_eq!(Downzer::substitute_placeholders("/FUZZR2/FUZZW1", &combo, 1), "/FUZZR2/x");
+    }
+
+    #[test]
+    fn urls_differing_only_by_query_collapse_when_stripping() {
+        let urls = strings(&["http://h/a?x=1", "http://h/a?x=2#top", "http://h/b?x=1", "http://h/a"]);
+        let dedup = |strip_query, strip_fragment| -> Vec<String> {
+            Targets::from_urls(urls.clone())
+                .dedup_by(move |url| Downzer::normalize_url(url, strip_query, strip_fragment))
+                .map(|target| target.url)
+                .collect()
+        };
+        // Se conserva la primera URL de cada grupo, tal cual se pidió
+        assert_eq!(dedup(true, true), ["http://h/a?x=1", "http://h/b?x=1"]);
+        assert_eq!(dedup(true, false), ["http://h/a?x=1", "http://h/a?x=2#top", "http://h/b?x=1"]);
+        assert_eq!(dedup(false, true), urls);
+        assert_eq!(Downzer::normalize_url("http://h/a?x=2#top", false, true), "http://h/a?x=2");
+        assert_eq!(Downzer::normalize_url("http://h/a?x=2#top", true, true), "http://h/a");
+        assert_eq!(Downzer::normalize_url("not a url?x", true, true), "not a url?x");
+    }
+
+    #[test]
+    fn wordlist_comments_and_blank_lines() {
+        let file = "# SecLists header\n\nadmin\n   # indented comment\n#payload, login\nuser\n";
+        let read = |strip| Downzer::read_list_from_reader(std::io::Cursor::new(file), strip);
+        assert_eq!(read(true), ["admin", "user"]);
+        // --no-comment-strip: las líneas con # son payloads
+        assert_eq!(read(false), ["# SecLists header", "admin", "# indented comment", "#payload", "login", "user"]);
+    }
+
+    #[test]
+    fn wordlist_file_strips_comments_by_default() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("words.txt");
+        std::fs::write(&path, "#comment\nadmin\n\nroot\n").unwrap();
+        let lists = Downzer::process_wordlists(&[path.display().to_string()], true).unwrap();
+        assert_eq!(lists, [strings(&["admin", "root"])]);
+    }
+
+    #[test]
+    fn bare_filename_needs_no_directory() {
+        create_parent_dir(Path::new("file.bin")).unwrap();
+        create_parent_dir(Path::new("/")).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("a/b/file.bin");
+        create_parent_dir(&nested).unwrap();
+        assert!(dir.path().join("a/b").is_dir());
+    }
+
+    #[test]
+    fn dedup_keeps_first_seen_order() {
+        let (list, removed) = Downzer::dedup_list(strings(&["b", "a", "b", "c", "a", "b"]));
+        assert_eq!(list, ["b", "a", "c"]);
+        assert_eq!(removed, 3);
+
+        let (list, removed) = Downzer::dedup_list(strings(&["x", "y"]));
+        assert_eq!((list, removed), (strings(&["x", "y"]), 0));
+    }
+
+    #[test]
+    fn dedup_applies_per_combined_list() {
+        let lists = Downzer::process_wordlists(&strings(&["a,b", "+", "b,c", "b"]), true).unwrap();
+        assert_eq!(lists, [strings(&["a", "b", "b", "c"]), strings(&["b"])]);
+        let deduped: Vec<_> = lists.into_iter().map(|list| Downzer::dedup_list(list).0).collect();
+        assert_eq!(deduped, [strings(&["a", "b", "c"]), strings(&["b"])]);
+    }
+
+    #[test]
+    fn stdin_wordlist_uses_the_file_rules() {
+        let stdin = std::io::Cursor::new("admin, root\n  guest \n\n,test,\r\n");
+        assert_eq!(Downzer::read_list_from_reader(stdin, true), ["admin", "root", "guest", "test"]);
+    }
+
+    fn substitute(template: &str, words: &[&str]) -> String {
+        Downzer::substitute_placeholders(template, &strings(words), 0)
+    }
+
+    #[test]
+    fn url_modifier_percent_encodes_only_when_present() {
+        assert_eq!(substitute("/q=FUZZW1:url", &["a b&c"]), "/q=a%20b%26c");
+        assert_eq!(substitute("/q=FUZZW1", &["a b&c"]), "/q=a b&c");
+        // Los no reservados de RFC 3986 quedan igual
+        assert_eq!(substitute("FUZZW1:url", &["A-z_0.9~/?"]), "A-z_0.9~%2F%3F");
+    }
+
+    #[test]
+    fn base64_modifiers() {
+        // "??>" usa los caracteres que distinguen los dos alfabetos
+        assert_eq!(substitute("FUZZW1:b64", &["??>"]), "Pz8+");
+        assert_eq!(substitute("FUZZW1:b64url", &["??>"]), "Pz8-");
+        // Estándar con relleno; URL-safe sin él
+        assert_eq!(substitute("FUZZW1:b64", &["ab"]), "YWI=");
+        assert_eq!(substitute("FUZZW1:b64url", &["ab"]), "YWI");
+        assert_eq!(substitute("[FUZZW1:b64][FUZZW1:b64url]", &[""]), "[][]");
+        // Encadenado con :url, que codifica el relleno y los '+'/'/'
+        assert_eq!(substitute("FUZZW1:b64:url", &["ab?>"]), "YWI%2FPg%3D%3D");
+    }
+
+    #[test]
+    fn streaming_template_yields_the_filtered_urls() {
+        let lists = vec![strings(&["1", "2"]), strings(&["admin", "img.png", "login"])];
+        let filters = UrlFilters::new(strings(&["http://h/2/login"]), Vec::new())
+            .with_exclude_regex(Some(r"\.png$"))
+            .unwrap();
+        let targets: Vec<Target> = Downzer::process_url_template_iter(
+            "http://h/FUZZR/FUZZW1",
+            CombinationIter::new(lists, false).indexed(),
+            1,
+            filters,
+        )
+        .collect();
+        let urls: Vec<&str> = targets.iter().map(|t| t.url.as_str()).collect();
+        assert_eq!(urls, ["http://h/1/admin", "http://h/1/login", "http://h/2/admin"]);
+        // Cada objetivo conserva su combinación y los índices de origen
+        assert_eq!(targets[1].words, ["1", "login"]);
+        assert_eq!(targets[1].indices, [0, 2]);
+    }
+
+    #[test]
+    fn case_modifiers() {
+        assert_eq!(substitute("FUZZW1:upper/FUZZW1:lower/FUZZW1:cap", &["admin"]), "ADMIN/admin/Admin");
+        assert_eq!(substitute("FUZZW1:cap", &["aDMIN"]), "Admin");
+        assert_eq!(substitute("FUZZW1:cap", &["ñandú"]), "Ñandú");
+        assert_eq!(substitute("[FUZZW1:cap]", &[""]), "[]");
+        // Un sufijo desconocido no es un modificador: queda como texto
+        assert_eq!(substitute("FUZZW1:title", &["admin"]), "admin:title");
+    }
+
+    #[test]
+    fn bare_fuzz_binds_to_the_first_list() {
+        assert_eq!(substitute("http://h/FUZZ", &["admin"]), "http://h/admin");
+        assert_eq!(substitute("http://h/FUZZ/FUZZ.bak", &["admin"]), "http://h/admin/admin.bak");
+        assert_eq!(substitute("http://h/FUZZ/FUZZW2", &["admin", "login"]), "http://h/admin/login");
+        // Con rangos, FUZZ es el primer rango
+        let combo = strings(&["7", "admin"]);
+        assert_eq!(Downzer::substitute_placeholders("/FUZZ/FUZZW1", &combo, 1), "/7/admin");
+        // FUZZ dentro de una palabra no es un placeholder
+        assert_eq!(substitute("/FUZZING/FUZZ", &["x"]), "/FUZZING/x");
+    }
+
+    #[test]
+    fn exclusion_list_inline() {
+        assert_eq!(Downzer::parse_filter_list(None).unwrap(), Vec::<String>::new());
+        let list = Downzer::parse_filter_list(Some("http://h/a,http://h/b http://h/c")).unwrap();
+        assert_eq!(list, ["http://h/a", "http://h/b", "http://h/c"]);
+    }
+
+    #[test]
+    fn exclusion_list_from_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("exclude.txt");
+        // En un archivo cada línea es una URL entera, aunque lleve comas o espacios
+        std::fs::write(&path, "http://h/a?x=1,2\n\n  http://h/b  \n").unwrap();
+        let list = Downzer::parse_filter_list(Some(&path.display().to_string())).unwrap();
+        assert_eq!(list, ["http://h/a?x=1,2", "http://h/b"]);
+    }
+
+    /// `DownloadOptions` con los valores por defecto de la CLI.
+    fn download_options(dir: &Path) -> DownloadOptions {
+        DownloadOptions {
+            output_dir: dir.to_path_buf(),
+            content_types: Vec::new(),
+            max_concurrent: 4,
+            max_per_host: None,
+            max_filename_length: 255,
+            name_template: None,
+            preserve_path: false,
+            headers: HeaderTemplates::default(),
+            cookie_jar: None,
+            location_trusted: false,
+            exec: None,
+            ordered_output: false,
+            quiet_errors: false,
+            dedup_content: false,
+            hardlink_dupes: false,
+            report: None,
+            manifest: None,
+            manifest_append: false,
+            min_size: None,
+            max_size: None,
+            resume: false,
+            rate_limiter: None,
+            pacer: None,
+            extension_caps: Arc::new(ExtensionCaps::new(HashMap::new())),
+            drain_timeout: None,
+            show_progress: false,
+            live: Arc::new(LiveStats::default()),
+            verbose: 0,
+            debug: false,
+        }
+    }
+
+    /// Descarga `url` con `options` y devuelve el resultado.
+    async fn fetch(url: &str, options: &DownloadOptions) -> DownloadOutcome {
+        let namer = FileNamer::new(options.output_dir.clone(), options.max_filename_length, None);
+        crate::testutil::downzer().download_file(url, &[], &namer, 0, options, false).await.unwrap()
+    }
+
+    fn file_server() -> impl Fn(&crate::testutil::Request) -> crate::testutil::Response {
+        use crate::testutil::Response;
+        |req| match req.path.as_str() {
+            "/doc.pdf" => Response::new(200).header("Content-Type", "application/pdf").body("%PDF-1.4"),
+            "/page.html" => Response::new(200).header("Content-Type", "text/html").body("<html></html>"),
+            "/secret.pdf" => Response::new(403),
+            _ => Response::new(404),
+        }
+    }
+
+    #[tokio::test]
+    async fn download_outcome_for_a_saved_file() {
+        let server = crate::testutil::MockServer::start(file_server()).await;
+        let dir = tempfile::tempdir().unwrap();
+        let outcome = fetch(&server.url("/doc.pdf"), &download_options(dir.path())).await;
+        assert_eq!(
+            outcome,
+            DownloadOutcome::Saved {
+                path: dir.path().join("doc.pdf"),
+                bytes: 8,
+                status: 200,
+                content_type: "application/pdf".to_string(),
+            }
+        );
+        assert_eq!(fs::read(dir.path().join("doc.pdf")).unwrap(), b"%PDF-1.4");
+    }
+
+    #[tokio::test]
+    async fn download_outcome_for_missing_and_failed_urls() {
+        let server = crate::testutil::MockServer::start(file_server()).await;
+        let dir = tempfile::tempdir().unwrap();
+        let options = download_options(dir.path());
+        assert_eq!(fetch(&server.url("/NOT_FOUND.pdf"), &options).await, DownloadOutcome::NotFound);
+        assert_eq!(fetch(&server.url("/secret.pdf"), &options).await, DownloadOutcome::HttpError(403));
+        assert!(crate::testutil::files_in(dir.path()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn download_outcome_for_a_filtered_content_type() {
+        let server = crate::testutil::MockServer::start(file_server()).await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut options = download_options(dir.path());
+        options.content_types = vec!["pdf".to_string()];
+        assert_eq!(fetch(&server.url("/page.html"), &options).await, DownloadOutcome::Ignored);
+        assert!(matches!(fetch(&server.url("/doc.pdf"), &options).await, DownloadOutcome::Saved { .. }));
+        assert_eq!(crate::testutil::files_in(dir.path()), vec![dir.path().join("doc.pdf")]);
+    }
+
+    /// Versiones de TLS que ofrece el ClientHello de un cliente construido con
+    /// `options` (la extensión `supported_versions`, o la versión heredada si no la envía).
+    async fn offered_tls_versions(options: ClientOptions) -> Vec<u16> {
+        use tokio::io::AsyncReadExt;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("https://localhost:{}/", listener.local_addr().unwrap().port());
+        let db = Database::open(Path::new(":memory:")).unwrap();
+        let downzer = Downzer::with_database(options, Config::default(), db).unwrap();
+        let request = tokio::spawn(async move { downzer.client.get(url).send().await });
+
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut hello = vec![0; 5];
+        stream.read_exact(&mut hello).await.unwrap();
+        let len = u16::from_be_bytes([hello[3], hello[4]]) as usize;
+        hello.resize(5 + len, 0);
+        stream.read_exact(&mut hello[5..]).await.unwrap();
+        drop(stream);
+        assert!(request.await.unwrap().is_err());
+
+        // Registro (5) + cabecera de handshake (4) + versión (2) + random (32)
+        let legacy = u16::from_be_bytes([hello[9], hello[10]]);
+        let mut at = 43;
+        at += 1 + hello[at] as usize;
+        at += 2 + u16::from_be_bytes([hello[at], hello[at + 1]]) as usize;
+        at += 1 + hello[at] as usize;
+        let end = at + 2 + u16::from_be_bytes([hello[at], hello[at + 1]]) as usize;
+        at += 2;
+        while at < end {
+            let kind = u16::from_be_bytes([hello[at], hello[at + 1]]);
+            let size = u16::from_be_bytes([hello[at + 2], hello[at + 3]]) as usize;
+            if kind == 0x002b {
+                return hello[at + 5..at + 4 + size].chunks(2).map(|v| u16::from_be_bytes([v[0], v[1]])).collect();
+            }
+            at += 4 + size;
+        }
+        vec![legacy]
+    }
+
+    #[tokio::test]
+    async fn tls_bounds_restrict_the_offered_versions() {
+        let tls13 = Some(reqwest::tls::Version::TLS_1_3);
+        let tls12 = Some(reqwest::tls::Version::TLS_1_2);
+        let only_13 = ClientOptions { timeout: 5, tls_min: tls13, ..Default::default() };
+        assert_eq!(offered_tls_versions(only_13).await, vec![0x0304]);
+        let only_12 = ClientOptions { timeout: 5, tls_max: tls12, ..Default::default() };
+        assert_eq!(offered_tls_versions(only_12).await, vec![0x0303]);
+        let both = ClientOptions { timeout: 5, ..Default::default() };
+        let mut versions = offered_tls_versions(both).await;
+        versions.sort();
+        assert_eq!(versions, vec![0x0303, 0x0304]);
+    }
+
+    #[test]
+    fn tls_min_above_max_is_rejected() {
+        let options = ClientOptions {
+            tls_min: Some(parse_tls_version("1.3").unwrap()),
+            tls_max: Some(parse_tls_version("1.2").unwrap()),
+            ..Default::default()
+        };
+        let db = Database::open(Path::new(":memory:")).unwrap();
+        let err = Downzer::with_database(options, Config::default(), db).err().unwrap();
+        assert!(err.to_string().contains("--tls-min"), "{}", err);
+        assert!(parse_tls_version("1.1").is_err());
+        assert!(parse_tls_version("2").is_err());
+    }
+
+    #[tokio::test]
+    async fn large_bodies_are_streamed_to_disk() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        const CHUNK: usize = 64 * 1024;
+        const CHUNKS: usize = 512;
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/big.bin", listener.local_addr().unwrap());
+        // El servidor genera el cuerpo por trozos para no ocupar memoria él tampoco
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut byte = [0];
+            while !request.ends_with(b"\r\n\r\n") {
+                stream.read_exact(&mut byte).await.unwrap();
+                request.push(byte[0]);
+            }
+            let head = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", CHUNK * CHUNKS);
+            stream.write_all(head.as_bytes()).await.unwrap();
+            let chunk = vec![7u8; CHUNK];
+            for _ in 0..CHUNKS {
+                stream.write_all(&chunk).await.unwrap();
+            }
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        let options = download_options(dir.path());
+        let (outcome, peak) = crate::testutil::peak_memory(fetch(&url, &options)).await;
+        server.await.unwrap();
+        let size = (CHUNK * CHUNKS) as u64;
+        assert!(matches!(outcome, DownloadOutcome::Saved { bytes, .. } if bytes == size), "{:?}", outcome);
+        assert_eq!(fs::metadata(dir.path().join("big.bin")).unwrap().len(), size);
+        assert!(peak < 4 * 1024 * 1024, "pico de {} bytes para un cuerpo de {}", peak, size);
+    }
+
+    const RESUMABLE: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+    /// Sirve `RESUMABLE` en `/file.bin`, respetando `Range` si `ranges` es `true`.
+    async fn range_server(ranges: bool) -> crate::testutil::MockServer {
+        use crate::testutil::Response;
+        crate::testutil::MockServer::start(move |req| {
+            let start = req
+                .header("range")
+                .and_then(|r| r.strip_prefix("bytes="))
+                .and_then(|r| r.strip_suffix('-'))
+                .and_then(|r| r.parse::<usize>().ok())
+                .filter(|_| ranges);
+            match start {
+                Some(start) if start >= RESUMABLE.len() => Response::new(416),
+                Some(start) => Response::new(206)
+                    .header("Content-Range", &format!("bytes {}-{}/{}", start, RESUMABLE.len() - 1, RESUMABLE.len()))
+                    .body(&RESUMABLE[start..]),
+                None => Response::new(200).header("Accept-Ranges", "bytes").body(RESUMABLE),
+            }
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn partial_download_is_resumed_with_range() {
+        let server = range_server(true).await;
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("file.bin.part"), &RESUMABLE[..10]).unwrap();
+        let mut options = download_options(dir.path());
+        options.resume = true;
+
+        let outcome = fetch(&server.url("/file.bin"), &options).await;
+        assert!(matches!(outcome, DownloadOutcome::Saved { bytes: 36, status: 206, .. }), "{:?}", outcome);
+        assert_eq!(fs::read(dir.path().join("file.bin")).unwrap(), RESUMABLE);
+        assert!(!dir.path().join("file.bin.part").exists());
+        assert_eq!(server.requests()[0].header("range"), Some("bytes=10-"));
+    }
+
+    #[tokio::test]
+    async fn server_without_ranges_restarts_the_download() {
+        let server = range_server(false).await;
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("file.bin.part"), b"stale data").unwrap();
+        let mut options = download_options(dir.path());
+        options.resume = true;
+
+        let outcome = fetch(&server.url("/file.bin"), &options).await;
+        assert!(matches!(outcome, DownloadOutcome::Saved { bytes: 36, status: 200, .. }), "{:?}", outcome);
+        assert_eq!(fs::read(dir.path().join("file.bin")).unwrap(), RESUMABLE);
+    }
+
+    #[tokio::test]
+    async fn unsatisfiable_range_downloads_from_scratch() {
+        let server = range_server(true).await;
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("file.bin.part"), vec![b'x'; 50]).unwrap();
+        let mut options = download_options(dir.path());
+        options.resume = true;
+
+        let outcome = fetch(&server.url("/file.bin"), &options).await;
+        assert!(matches!(outcome, DownloadOutcome::Saved { bytes: 36, status: 200, .. }), "{:?}", outcome);
+        assert_eq!(fs::read(dir.path().join("file.bin")).unwrap(), RESUMABLE);
+        assert_eq!(server.requests().len(), 2);
+        assert_eq!(server.requests()[1].header("range"), None);
+    }
+
+    #[test]
+    fn size_suffixes_are_binary_multiples() {
+        assert_eq!(parse_size("1K").unwrap(), 1024);
+        assert_eq!(parse_size("2M").unwrap(), 2 * 1024 * 1024);
+        assert_eq!(parse_size("1.5k").unwrap(), 1536);
+        assert_eq!(parse_size("3GiB").unwrap(), 3 << 30);
+        assert_eq!(parse_size("700").unwrap(), 700);
+        assert_eq!(parse_size(" 10 MB ").unwrap(), 10 << 20);
+    }
+
+    #[test]
+    fn bad_sizes_are_errors() {
+        let err = parse_size("5X").unwrap_err();
+        assert!(err.to_string().contains("unknown suffix 'X'"), "{}", err);
+        assert!(parse_size("").is_err());
+        assert!(parse_size("M").is_err());
+        assert!(parse_size("1.2.3K").is_err());
+    }
+
+    async fn sized_server() -> crate::testutil::MockServer {
+        use crate::testutil::Response;
+        crate::testutil::MockServer::start(|req| match req.path.as_str() {
+            "/tiny.html" => Response::new(200).body("oops"),
+            "/big.iso" => Response::new(200).body(vec![0; 4096]),
+            _ => Response::new(200).body(vec![1; 1024]),
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn oversized_response_is_rejected_from_content_length() {
+        let server = sized_server().await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut options = download_options(dir.path());
+        options.max_size = Some(parse_size("2K").unwrap());
+        assert_eq!(fetch(&server.url("/big.iso"), &options).await, DownloadOutcome::FilteredSize(4096));
+        assert!(matches!(fetch(&server.url("/ok.bin"), &options).await, DownloadOutcome::Saved { bytes: 1024, .. }));
+        assert_eq!(crate::testutil::files_in(dir.path()), vec![dir.path().join("ok.bin")]);
+    }
+
+    #[tokio::test]
+    async fn undersized_download_is_deleted() {
+        let server = sized_server().await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut options = download_options(dir.path());
+        options.min_size = Some(parse_size("1K").unwrap());
+        assert_eq!(fetch(&server.url("/tiny.html"), &options).await, DownloadOutcome::FilteredSize(4));
+        assert!(matches!(fetch(&server.url("/ok.bin"), &options).await, DownloadOutcome::Saved { bytes: 1024, .. }));
+        assert_eq!(crate::testutil::files_in(dir.path()), vec![dir.path().join("ok.bin")]);
+    }
+
+    #[tokio::test]
+    async fn size_filtered_downloads_are_counted() {
+        let server = sized_server().await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut options = download_options(dir.path());
+        options.min_size = Some(1000);
+        options.max_size = Some(2000);
+        let urls = vec![server.url("/tiny.html"), server.url("/big.iso"), server.url("/ok.bin")];
+        let stats = crate::testutil::downzer().execute_download_task(1, Targets::from_urls(urls), &options).await.unwrap();
+        assert_eq!((stats.downloaded, stats.filtered_size, stats.errors), (1, 2, 0));
+    }
+
+    #[tokio::test]
+    async fn no_keepalive_asks_for_a_fresh_connection() {
+        let server = crate::testutil::MockServer::start(|_| crate::testutil::Response::new(200)).await;
+        for no_keepalive in [false, true] {
+            let options = ClientOptions { timeout: 5, no_keepalive, ..Default::default() };
+            let db = Database::open(Path::new(":memory:")).unwrap();
+            let downzer = Downzer::with_database(options, Config::default(), db).unwrap();
+            downzer.client.get(server.url("/")).send().await.unwrap();
+        }
+        let connection: Vec<Option<String>> =
+            server.requests().iter().map(|r| r.header("connection").map(str::to_string)).collect();
+        assert_eq!(connection, [None, Some("close".to_string())]);
+    }
+
+    #[test]
+    fn old_config_files_get_the_failure_sound_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        // Configuración guardada antes de los avisos de fallo
+        let json = dir.path().join("config.json");
+        std::fs::write(
+            &json,
+            r#"{"sound_enabled":true,"sound_min_duration":3,"sound_volume":0.8,"sound_on_task_complete":true,"sound_on_all_complete":false,"sound_type":"coin"}"#,
+        )
+        .unwrap();
+        let yaml = dir.path().join("config.yaml");
+        std::fs::write(&yaml, "sound_enabled: true\nsound_min_duration: 3\nsound_volume: 0.8\nsound_type: coin\n").unwrap();
+
+        for path in [json, yaml] {
+            let config = Downzer::load_config_file(&path).unwrap();
+            assert_eq!((config.sound_min_duration, config.sound_volume), (3, 0.8), "{}", path.display());
+            assert_eq!(config.sound_type, "coin");
+            assert!(!config.sound_on_failure);
+            assert_eq!(config.failure_sound_type, "thuddry");
+            assert_eq!(config.sound_fade_ms, 150);
+        }
+        // El sonido de fallo por defecto es uno de los predefinidos
+        assert_eq!(
+            crate::audio::sound::resolve_sound(&Config::default().failure_sound_type).unwrap(),
+            crate::audio::sound::SoundSource::Builtin(crate::audio::sound::SoundType::Thuddry)
+        );
+    }
+}
@@ -0,0 +1,14 @@
+use tokio::task::{JoinError, JoinHandle};
+use tokio::time::Instant;
+
+/// Espera a `handle` como mucho hasta `deadline` (`--drain-timeout`).
+/// Devuelve `None` si se alcanzó el plazo; el llamador decide si abortarlo.
+pub async fn join_before<T>(
+    handle: &mut JoinHandle<T>,
+    deadline: Option<Instant>,
+) -> Option<Result<T, JoinError>> {
+    match deadline {
+        Some(deadline) => tokio::time::timeout_at(deadline, handle).await.ok(),
+        None => Some(handle.await),
+    }
+}
@@ -0,0 +1,498 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use percent_encoding::percent_decode_str;
+use reqwest::header::{HeaderMap, CONTENT_DISPOSITION, CONTENT_TYPE};
+
+use crate::core::downzer::{truncate_filename, HASH_SUFFIX_LEN};
+
+/// Las descargas se escriben en `<nombre>.part` y se renombran al terminar.
+pub const PART_SUFFIX: &str = ".part";
+
+/// Ruta del archivo parcial de `dest`.
+pub fn part_path(dest: &Path) -> PathBuf {
+    let mut path = dest.as_os_str().to_owned();
+    path.push(PART_SUFFIX);
+    PathBuf::from(path)
+}
+
+/// Marcadores de `--name-template`.
+const TEMPLATE_TOKENS: &[&str] = &["index", "host", "basename", "ext", "status"];
+
+/// Plantilla de ruta de `--name-template` (p.ej. `{host}/{index}_{basename}`),
+/// relativa al directorio de salida. `/` separa subdirectorios.
+#[derive(Debug, Clone)]
+pub struct NameTemplate(String);
+
+impl NameTemplate {
+    pub fn parse(spec: &str) -> anyhow::Result<Self> {
+        if spec.trim().is_empty() {
+            anyhow::bail!("--name-template cannot be empty");
+        }
+        let mut rest = spec;
+        while let Some(start) = rest.find('{') {
+            let Some(len) = rest[start..].find('}') else {
+                anyhow::bail!("Unclosed '{{' in --name-template '{}'", spec);
+            };
+            let token = &rest[start + 1..start + len];
+            if !TEMPLATE_TOKENS.contains(&token) {
+                anyhow::bail!(
+                    "Unknown token {{{}}} in --name-template (available: {})",
+                    token,
+                    TEMPLATE_TOKENS.iter().map(|t| format!("{{{}}}", t)).collect::<Vec<_>>().join(", ")
+                );
+            }
+            rest = &rest[start + len + 1..];
+        }
+        Ok(Self(spec.to_string()))
+    }
+
+    fn uses(&self, token: &str) -> bool {
+        self.0.contains(&format!("{{{}}}", token))
+    }
+
+    /// Sustituye los marcadores y sanea cada componente de la ruta: los vacíos,
+    /// `.` y `..` se descartan, así que el resultado nunca sale del directorio.
+    fn expand(&self, vars: &TemplateVars) -> Option<String> {
+        let expanded = self
+            .0
+            .replace("{index}", &vars.index.to_string())
+            .replace("{host}", vars.host)
+            .replace("{basename}", vars.basename)
+            .replace("{ext}", &file_extension(vars.basename).unwrap_or_default())
+            .replace("{status}", &vars.status.map(|s| s.to_string()).unwrap_or_default());
+        let parts: Vec<String> = expanded.split(['/', '\\']).filter_map(sanitize_filename).collect();
+        (!parts.is_empty()).then(|| parts.join("/"))
+    }
+}
+
+struct TemplateVars<'a> {
+    index: usize,
+    host: &'a str,
+    basename: &'a str,
+    status: Option<u16>,
+}
+
+/// Elige dónde se guarda cada descarga dentro del directorio de salida.
+///
+/// El nombre sale del último segmento de la URL o, si no sirve, de la cabecera
+/// `Content-Disposition`; `download_{idx}` queda como último recurso. Con
+/// `--name-template` ese nombre es `{basename}` dentro de la plantilla, y con
+/// `--preserve-path` la ruta replica la de la URL. Las rutas repetidas dentro
+/// de la tarea reciben un sufijo `_1`, `_2`...
+pub struct FileNamer {
+    dir: PathBuf,
+    max_len: usize,
+    template: Option<NameTemplate>,
+    preserve_path: bool,
+    claimed: Mutex<HashSet<String>>,
+}
+
+impl FileNamer {
+    pub fn new(dir: PathBuf, max_len: usize, template: Option<NameTemplate>) -> Self {
+        // Se reserva sitio para el sufijo `.part` del archivo en curso
+        let max_len = max_len.saturating_sub(PART_SUFFIX.len()).max(HASH_SUFFIX_LEN);
+        Self { dir, max_len, template, preserve_path: false, claimed: Mutex::new(HashSet::new()) }
+    }
+
+    /// Guarda cada descarga en `<host>/<path de la URL>` (`--preserve-path`).
+    pub fn preserve_path(mut self, preserve: bool) -> Self {
+        self.preserve_path = preserve;
+        self
+    }
+
+    /// Aparta `name` para que ninguna descarga lo use (p.ej. `manifest.json`).
+    pub fn exclude(&self, name: &str) {
+        self.claim(name);
+    }
+
+    /// Reserva una ruta para la respuesta de `url` (la `idx`-ésima de la tarea).
+    pub fn path_for(&self, url: &str, headers: &HeaderMap, idx: usize, status: u16) -> PathBuf {
+        let basename = url_filename(url)
+            .or_else(|| {
+                headers
+                    .get(CONTENT_DISPOSITION)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(content_disposition_filename)
+            })
+            .unwrap_or_else(|| format!("download_{:06}", idx));
+        self.reserve(&self.relative_path(url, &basename, idx, Some(status)))
+    }
+
+    /// Reserva la ruta antes de enviar la petición (lo necesita `--resume`),
+    /// si ya se conoce: el nombre debe salir de la URL y la plantilla no
+    /// puede depender del `{status}`.
+    pub fn claim_from_url(&self, url: &str, idx: usize) -> Option<PathBuf> {
+        if self.preserve_path {
+            return mirror_path(url).map(|path| self.reserve(&path));
+        }
+        if self.template.as_ref().is_some_and(|t| t.uses("status")) {
+            return None;
+        }
+        let basename = url_filename(url)?;
+        Some(self.reserve(&self.relative_path(url, &basename, idx, None)))
+    }
+
+    fn relative_path(&self, url: &str, basename: &str, idx: usize, status: Option<u16>) -> String {
+        if self.preserve_path {
+            if let Some(path) = mirror_path(url) {
+                return path;
+            }
+        }
+        let Some(template) = &self.template else {
+            return basename.to_string();
+        };
+        let host = reqwest::Url::parse(url).ok().and_then(|u| u.host_str().map(str::to_string)).unwrap_or_default();
+        template
+            .expand(&TemplateVars { index: idx, host: &host, basename, status })
+            .unwrap_or_else(|| basename.to_string())
+    }
+
+    /// Reserva `path` (relativa, componentes separados por `/`) truncando cada
+    /// componente a la longitud máxima.
+    fn reserve(&self, path: &str) -> PathBuf {
+        let truncated: Vec<String> = path.split('/').map(|part| truncate_filename(part, self.max_len)).collect();
+        self.dir.join(self.claim(&truncated.join("/")))
+    }
+
+    fn claim(&self, name: &str) -> String {
+        let mut claimed = self.claimed.lock().unwrap_or_else(|e| e.into_inner());
+        if claimed.insert(name.to_string()) {
+            return name.to_string();
+        }
+
+        // El sufijo va en el último componente, antes de su extensión
+        let (dir, file) = match name.rsplit_once('/') {
+            Some((dir, file)) => (format!("{}/", dir), file),
+            None => (String::new(), name),
+        };
+        let (stem, ext) = match file.rsplit_once('.') {
+            Some((stem, ext)) if !stem.is_empty() => (stem, format!(".{}", ext)),
+            _ => (file, String::new()),
+        };
+        let mut n = 1;
+        loop {
+            let candidate = format!("{}{}", dir, truncate_filename(&format!("{}_{}{}", stem, n, ext), self.max_len));
+            if claimed.insert(candidate.clone()) {
+                return candidate;
+            }
+            n += 1;
+        }
+    }
+}
+
+/// Último segmento del path de `url`, decodificado y saneado. La query y el
+/// fragmento no forman parte del nombre.
+pub fn url_filename(url: &str) -> Option<String> {
+    let parsed = reqwest::Url::parse(url).ok()?;
+    let segment = parsed.path_segments()?.next_back()?;
+    sanitize_filename(&percent_decode_str(segment).decode_utf8_lossy())
+}
+
+/// Ruta relativa que replica la URL: `https://host/a/b/c.png` → `host/a/b/c.png`.
+/// Un puerto explícito se añade al host (`host_8080`), los segmentos `.`, `..`
+/// y vacíos se descartan, y una ruta acabada en `/` recibe `index.html`. La
+/// query y el fragmento no forman parte de la ruta.
+pub fn mirror_path(url: &str) -> Option<String> {
+    let parsed = reqwest::Url::parse(url).ok()?;
+    let host = match parsed.port() {
+        Some(port) => format!("{}_{}", parsed.host_str()?, port),
+        None => parsed.host_str()?.to_string(),
+    };
+    let mut parts = vec![sanitize_filename(&host)?];
+    let segments: Vec<&str> = parsed.path_segments().map(|s| s.collect()).unwrap_or_default();
+    parts.extend(
+        segments
+            .iter()
+            .filter_map(|segment| sanitize_filename(&percent_decode_str(segment).decode_utf8_lossy())),
+    );
+    // Índice de directorio: la URL termina en `/` (o no tiene path)
+    if segments.last().is_none_or(|last| last.is_empty()) {
+        parts.push("index.html".to_string());
+    }
+    Some(parts.join("/"))
+}
+
+/// Extensión (en minúsculas y sin punto) del nombre que `FileNamer` daría a
+/// la respuesta; si ese nombre no tiene, la que corresponde al `Content-Type`.
+pub fn derived_extension(url: &str, headers: &HeaderMap) -> Option<String> {
+    let name = url_filename(url).or_else(|| {
+        headers
+            .get(CONTENT_DISPOSITION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(content_disposition_filename)
+    });
+    name.as_deref().and_then(file_extension).or_else(|| {
+        let content_type = headers.get(CONTENT_TYPE)?.to_str().ok()?;
+        content_type_extension(content_type).map(str::to_string)
+    })
+}
+
+/// Extensión de `name`, sin contar los nombres ocultos tipo `.bashrc`.
+pub fn file_extension(name: &str) -> Option<String> {
+    match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() && !ext.is_empty() => Some(ext.to_lowercase()),
+        _ => None,
+    }
+}
+
+/// Extensión habitual de los tipos MIME más comunes.
+pub fn content_type_extension(content_type: &str) -> Option<&'static str> {
+    let mime = content_type.split(';').next()?.trim().to_ascii_lowercase();
+    Some(match mime.as_str() {
+        "application/pdf" => "pdf",
+        "application/zip" => "zip",
+        "application/gzip" | "application/x-gzip" => "gz",
+        "application/json" => "json",
+        "application/xml" | "text/xml" => "xml",
+        "application/javascript" | "text/javascript" => "js",
+        "application/msword" => "doc",
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.document" => "docx",
+        "application/vnd.ms-excel" => "xls",
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet" => "xlsx",
+        "text/html" => "html",
+        "text/plain" => "txt",
+        "text/csv" => "csv",
+        "text/css" => "css",
+        "image/jpeg" => "jpg",
+        "image/png" => "png",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        "audio/mpeg" => "mp3",
+        "video/mp4" => "mp4",
+        _ => return None,
+    })
+}
+
+/// Nombre de `Content-Disposition`, prefiriendo `filename*` (RFC 5987) a `filename`.
+pub fn content_disposition_filename(value: &str) -> Option<String> {
+    let mut plain = None;
+    let mut extended = None;
+    for (key, val) in disposition_params(value) {
+        match key.as_str() {
+            // charset'idioma'valor-codificado
+            "filename*" => extended = extended.or_else(|| decode_ext_value(&val)),
+            "filename" => plain = plain.or(Some(val)),
+            _ => {}
+        }
+    }
+    extended.or(plain).and_then(|name| sanitize_filename(&name))
+}
+
+/// Parámetros `clave=valor` tras el tipo de disposición (RFC 6266), con las
+/// claves en minúsculas. Los valores pueden ser tokens o quoted-strings, que
+/// admiten `;` y escapes con `\`.
+fn disposition_params(value: &str) -> Vec<(String, String)> {
+    let mut params = Vec::new();
+    let mut chars = value.chars().peekable();
+    // El tipo (`attachment`, `inline`...) llega hasta el primer `;`
+    chars.by_ref().take_while(|c| *c != ';').for_each(drop);
+    loop {
+        let key: String = chars.by_ref().take_while(|c| *c != '=').collect();
+        let key = key.trim().to_ascii_lowercase();
+        if key.is_empty() {
+            return params;
+        }
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let mut val = String::new();
+        if chars.next_if_eq(&'"').is_some() {
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => val.extend(chars.next()),
+                    '"' => break,
+                    c => val.push(c),
+                }
+            }
+            chars.by_ref().take_while(|c| *c != ';').for_each(drop);
+        } else {
+            val = chars.by_ref().take_while(|c| *c != ';').collect::<String>().trim().to_string();
+        }
+        params.push((key, val));
+    }
+}
+
+/// Valor extendido de RFC 5987 (`UTF-8''nombre%20codificado`). Solo se
+/// admiten los juegos de caracteres UTF-8 e ISO-8859-1.
+fn decode_ext_value(value: &str) -> Option<String> {
+    let mut parts = value.splitn(3, '\'');
+    let charset = parts.next()?.to_ascii_lowercase();
+    let encoded = parts.nth(1)?;
+    let bytes: Vec<u8> = percent_decode_str(encoded).collect();
+    match charset.as_str() {
+        "utf-8" => String::from_utf8(bytes).ok(),
+        "iso-8859-1" => Some(bytes.into_iter().map(char::from).collect()),
+        _ => None,
+    }
+}
+
+/// Deja solo el último componente de `name` y lo reduce a `[A-Za-z0-9._-]`:
+/// el resto de caracteres (espacios, metacaracteres de la shell, no ASCII...)
+/// pasan a `_`, y se quitan los `-` y `.` iniciales para que el nombre no se
+/// tome por una opción ni quede oculto. `None` si no queda un nombre usable.
+pub fn sanitize_filename(name: &str) -> Option<String> {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or(name).trim();
+    let cleaned: String = base
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-') { c } else { '_' })
+        .collect();
+    let cleaned = cleaned.trim_start_matches(['-', '.']);
+    (!cleaned.is_empty()).then(|| cleaned.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn disposition(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_DISPOSITION, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    fn namer() -> FileNamer {
+        FileNamer::new(PathBuf::from("out"), 255, None)
+    }
+
+    #[test]
+    fn name_comes_from_the_last_url_segment() {
+        let path = namer().path_for("https://example.com/docs/report.pdf?v=2#top", &HeaderMap::new(), 0, 200);
+        assert_eq!(path, Path::new("out/report.pdf"));
+    }
+
+    #[test]
+    fn name_falls_back_to_content_disposition_then_index() {
+        let namer = namer();
+        let headers = disposition("attachment; filename=\"annual report.pdf\"");
+        assert_eq!(namer.path_for("https://example.com/get/", &headers, 0, 200), Path::new("out/annual_report.pdf"));
+        assert_eq!(namer.path_for("https://example.com/", &HeaderMap::new(), 7, 200), Path::new("out/download_000007"));
+    }
+
+    #[test]
+    fn colliding_names_get_a_numeric_suffix() {
+        let namer = namer();
+        let none = HeaderMap::new();
+        assert_eq!(namer.path_for("https://a.example/report.pdf", &none, 0, 200), Path::new("out/report.pdf"));
+        assert_eq!(namer.path_for("https://b.example/report.pdf", &none, 1, 200), Path::new("out/report_1.pdf"));
+        assert_eq!(namer.path_for("https://c.example/x/report.pdf", &none, 2, 200), Path::new("out/report_2.pdf"));
+    }
+
+    #[test]
+    fn quoted_filenames_may_contain_separators() {
+        assert_eq!(content_disposition_filename("attachment; filename=\"a;b.txt\"").as_deref(), Some("a_b.txt"));
+        assert_eq!(
+            content_disposition_filename("attachment; filename=\"say \\\"hi\\\".txt\"; size=10").as_deref(),
+            Some("say__hi_.txt")
+        );
+        assert_eq!(content_disposition_filename("inline; filename=plain.csv").as_deref(), Some("plain.csv"));
+        assert_eq!(content_disposition_filename("attachment"), None);
+    }
+
+    #[test]
+    fn extended_filename_is_preferred_and_decoded() {
+        let value = "attachment; filename=\"fallback.pdf\"; filename*=UTF-8''na%C3%AFve%20plan.pdf";
+        assert_eq!(content_disposition_filename(value).as_deref(), Some("na_ve_plan.pdf"));
+        let latin1 = "attachment; filename*=iso-8859-1'es'caf%E9.txt";
+        assert_eq!(content_disposition_filename(latin1).as_deref(), Some("caf_.txt"));
+        // Un juego de caracteres desconocido deja el nombre simple
+        let unknown = "attachment; filename*=koi8-r''x.txt; filename=y.txt";
+        assert_eq!(content_disposition_filename(unknown).as_deref(), Some("y.txt"));
+    }
+
+    #[test]
+    fn sanitized_names_are_shell_safe() {
+        assert_eq!(sanitize_filename("../../etc/passwd").as_deref(), Some("passwd"));
+        assert_eq!(sanitize_filename("a;rm -rf $(x) `y` & z.sh").as_deref(), Some("a_rm_-rf___x___y____z.sh"));
+        assert_eq!(sanitize_filename("-rf").as_deref(), Some("rf"));
+        assert_eq!(sanitize_filename(".bashrc").as_deref(), Some("bashrc"));
+        assert_eq!(sanitize_filename("C:\\temp\\x.bin").as_deref(), Some("x.bin"));
+        assert_eq!(sanitize_filename(".."), None);
+        assert_eq!(sanitize_filename("  "), None);
+    }
+
+    fn templated(spec: &str) -> FileNamer {
+        FileNamer::new(PathBuf::from("out"), 255, Some(NameTemplate::parse(spec).unwrap()))
+    }
+
+    #[test]
+    fn template_with_nested_directories() {
+        let namer = templated("{host}/{index}_{basename}");
+        let path = namer.path_for("https://files.example.com/a/report.pdf", &HeaderMap::new(), 3, 200);
+        assert_eq!(path, Path::new("out/files.example.com/3_report.pdf"));
+    }
+
+    #[test]
+    fn template_by_extension_and_status() {
+        let namer = templated("{ext}/{status}/{basename}");
+        assert_eq!(namer.path_for("http://h/x/data.JSON", &HeaderMap::new(), 0, 200), Path::new("out/json/200/data.JSON"));
+        // Sin extensión el componente queda vacío y se descarta
+        assert_eq!(namer.path_for("http://h/README", &HeaderMap::new(), 1, 203), Path::new("out/203/README"));
+    }
+
+    #[test]
+    fn template_cannot_escape_the_output_directory() {
+        let namer = templated("../{basename}");
+        assert_eq!(namer.path_for("http://h/a.txt", &HeaderMap::new(), 0, 200), Path::new("out/a.txt"));
+        let namer = templated("/{host}/./{basename}");
+        assert_eq!(namer.path_for("http://h/a.txt", &HeaderMap::new(), 0, 200), Path::new("out/h/a.txt"));
+    }
+
+    #[test]
+    fn invalid_templates_are_rejected() {
+        assert!(NameTemplate::parse("").is_err());
+        assert!(NameTemplate::parse("{index").is_err());
+        let err = NameTemplate::parse("{name}").unwrap_err();
+        assert!(err.to_string().contains("{index}, {host}, {basename}, {ext}, {status}"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn templated_downloads_create_intermediate_directories() {
+        use crate::testutil::{self, MockServer, Response};
+
+        let server = MockServer::start(|_| Response::new(200).body("x")).await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testutil::mode_config("download", dir.path());
+        config.name_template = Some(NameTemplate::parse("{ext}/{index}_{basename}").unwrap());
+        testutil::run(config, vec![server.url("/a.pdf"), server.url("/b.txt")]).await;
+        assert_eq!(std::fs::read(dir.path().join("pdf/0_a.pdf")).unwrap(), b"x");
+        assert_eq!(std::fs::read(dir.path().join("txt/1_b.txt")).unwrap(), b"x");
+    }
+
+    fn mirrored() -> FileNamer {
+        FileNamer::new(PathBuf::from("out"), 255, None).preserve_path(true)
+    }
+
+    #[test]
+    fn preserve_path_mirrors_nested_paths() {
+        let namer = mirrored();
+        let none = HeaderMap::new();
+        assert_eq!(namer.path_for("https://host/a/b/c.png?x=1", &none, 0, 200), Path::new("out/host/a/b/c.png"));
+        assert_eq!(namer.path_for("http://host:8080/a/c.png", &none, 1, 200), Path::new("out/host_8080/a/c.png"));
+        assert_eq!(namer.path_for("https://host/a/../../etc/passwd", &none, 2, 200), Path::new("out/host/etc/passwd"));
+        assert_eq!(mirror_path("https://host/a/%2e%2e/%2e%2e/x").as_deref(), Some("host/x"));
+    }
+
+    #[test]
+    fn preserve_path_uses_index_html_for_directories() {
+        assert_eq!(mirror_path("https://host/docs/").as_deref(), Some("host/docs/index.html"));
+        assert_eq!(mirror_path("https://host").as_deref(), Some("host/index.html"));
+        // La ruta se conoce antes de pedir nada, así que vale para --resume
+        assert_eq!(mirrored().claim_from_url("https://host/a/", 0), Some(PathBuf::from("out/host/a/index.html")));
+    }
+
+    #[tokio::test]
+    async fn preserved_paths_are_created_on_disk() {
+        use crate::testutil::{self, MockServer, Response};
+
+        let server = MockServer::start(|req| Response::new(200).body(req.path.clone())).await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testutil::mode_config("download", dir.path());
+        config.preserve_path = true;
+        testutil::run(config, vec![server.url("/a/b/c.png"), server.url("/a/")]).await;
+        let host = format!("127.0.0.1_{}", server.addr().port());
+        assert_eq!(std::fs::read_to_string(dir.path().join(&host).join("a/b/c.png")).unwrap(), "/a/b/c.png");
+        assert_eq!(std::fs::read_to_string(dir.path().join(&host).join("a/index.html")).unwrap(), "/a/");
+    }
+}
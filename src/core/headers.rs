@@ -0,0 +1,280 @@
+use anyhow::Result;
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, COOKIE};
+
+use crate::core::Downzer;
+
+/// Cabeceras de `--header` que se añaden a cada petición. Los valores pueden
+/// llevar placeholders (`Authorization: Bearer FUZZW1`), que se sustituyen
+/// con la combinación de cada URL igual que en la plantilla. Lo mismo vale
+/// para las cookies de `--cookie` (`session=FUZZW1`), que van en `Cookie`, y
+/// para las credenciales de `--auth`/`--bearer`, que van en `Authorization`.
+#[derive(Debug, Clone, Default)]
+pub struct HeaderTemplates {
+    headers: Vec<(String, String)>,
+    cookies: Vec<String>,
+    auth: Option<Credentials>,
+    /// Posiciones de la combinación que son rangos (ver `substitute_placeholders`)
+    range_count: usize,
+}
+
+impl HeaderTemplates {
+    pub fn new(headers: Vec<(String, String)>, range_count: usize) -> Self {
+        Self { headers, cookies: Vec::new(), auth: None, range_count }
+    }
+
+    /// Cookies de `--cookie`, en el orden de la línea de comandos.
+    pub fn with_cookies(mut self, cookies: Vec<String>) -> Self {
+        self.cookies = cookies;
+        self
+    }
+
+    /// Credenciales de `--auth` o `--bearer`.
+    pub fn with_auth(mut self, auth: Option<Credentials>) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// Cabeceras para la combinación `words`. Falla si un valor sustituido no
+    /// es válido en HTTP (p.ej. una palabra con salto de línea).
+    pub fn resolve(&self, words: &[String]) -> Result<HeaderMap> {
+        let mut map = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let value = Downzer::substitute_placeholders(value, words, self.range_count);
+            let value = HeaderValue::from_str(&value)
+                .map_err(|_| anyhow::anyhow!("Invalid value for header '{}': {:?}", name, value))?;
+            map.append(HeaderName::from_bytes(name.as_bytes())?, value);
+        }
+        if !self.cookies.is_empty() {
+            // Un `-H 'Cookie: ...'` explícito se conserva y las cookies se añaden detrás
+            let mut pairs: Vec<String> = map.get_all(COOKIE).iter().filter_map(|v| v.to_str().ok()).map(String::from).collect();
+            pairs.extend(self.cookies.iter().map(|c| Downzer::substitute_placeholders(c, words, self.range_count)));
+            let value = pairs.join("; ");
+            let value = HeaderValue::from_str(&value)
+                .map_err(|_| anyhow::anyhow!("Invalid value for header 'Cookie': {:?}", value))?;
+            map.insert(COOKIE, value);
+        }
+        // Como en curl, un `-H 'Authorization: ...'` explícito manda sobre las credenciales
+        if let Some(auth) = self.auth.as_ref().filter(|_| !map.contains_key(AUTHORIZATION)) {
+            let value = match auth {
+                Credentials::Basic(userpass) => {
+                    let userpass = Downzer::substitute_placeholders(userpass, words, self.range_count);
+                    format!("Basic {}", BASE64_STANDARD.encode(userpass))
+                }
+                Credentials::Bearer(token) => {
+                    format!("Bearer {}", Downzer::substitute_placeholders(token, words, self.range_count))
+                }
+            };
+            let value = HeaderValue::from_str(&value)
+                .map_err(|_| anyhow::anyhow!("Invalid value for header 'Authorization': {:?}", value))?;
+            map.insert(AUTHORIZATION, value);
+        }
+        Ok(map)
+    }
+}
+
+/// Credenciales de `--auth user:pass` (HTTP Basic) o `--bearer <token>`. El
+/// valor se guarda sin codificar para sustituir antes sus placeholders.
+#[derive(Debug, Clone)]
+pub enum Credentials {
+    Basic(String),
+    Bearer(String),
+}
+
+impl Credentials {
+    /// Interpreta `--auth 'user:pass'`: el usuario no puede ir vacío y la
+    /// contraseña sí, pero el `:` es obligatorio, salvo que lo aporte un
+    /// placeholder (`--auth FUZZW1` sobre una lista de `user:pass`).
+    pub fn basic(spec: &str) -> Result<Self> {
+        match spec.split_once(':') {
+            Some((user, _)) if !user.is_empty() => Ok(Self::Basic(spec.to_string())),
+            None if spec.contains("FUZZ") => Ok(Self::Basic(spec.to_string())),
+            _ => anyhow::bail!("Invalid --auth '{}' (expected 'user:password')", spec),
+        }
+    }
+
+    /// Interpreta `--bearer <token>`, que tiene que poder ir en una cabecera.
+    pub fn bearer(token: &str) -> Result<Self> {
+        let token = token.trim();
+        if token.is_empty() || HeaderValue::from_str(token).is_err() {
+            anyhow::bail!("Invalid --bearer token: empty or with invalid characters");
+        }
+        Ok(Self::Bearer(token.to_string()))
+    }
+}
+
+/// Interpreta `--cookie 'k=v'` o varias a la vez (`'k=v; k2=v2'`): cada par
+/// necesita nombre y `=`; el valor puede llevar placeholders.
+pub fn parse_cookie(spec: &str) -> Result<String> {
+    let pairs: Vec<&str> = spec.split(';').map(str::trim).filter(|p| !p.is_empty()).collect();
+    if pairs.is_empty() {
+        anyhow::bail!("Invalid --cookie '{}': empty", spec);
+    }
+    for pair in &pairs {
+        match pair.split_once('=') {
+            Some((name, _)) if !name.trim().is_empty() && HeaderName::from_bytes(name.trim().as_bytes()).is_ok() => {}
+            _ => anyhow::bail!("Invalid --cookie '{}': '{}' is not a name=value pair", spec, pair),
+        }
+    }
+    let cookie = pairs.join("; ");
+    if HeaderValue::from_str(&cookie).is_err() {
+        anyhow::bail!("Invalid --cookie '{}': the value contains invalid characters", spec);
+    }
+    Ok(cookie)
+}
+
+/// Interpreta `--header 'Name: Value'`. El valor puede ir vacío, pero el nombre
+/// tiene que ser un token HTTP válido.
+pub fn parse_header(spec: &str) -> Result<(String, String)> {
+    let (name, value) = spec
+        .split_once(':')
+        .ok_or_else(|| anyhow::anyhow!("Invalid --header '{}' (expected 'Name: Value')", spec))?;
+    let name = name.trim();
+    if HeaderName::from_bytes(name.as_bytes()).is_err() {
+        anyhow::bail!("Invalid --header '{}': '{}' is not a valid header name", spec, name);
+    }
+    let value = value.trim();
+    if HeaderValue::from_str(value).is_err() {
+        anyhow::bail!("Invalid --header '{}': the value contains invalid characters", spec);
+    }
+    Ok((name.to_string(), value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::targets::{Target, Targets};
+    use crate::testutil::{self, MockServer, Response};
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| w.to_string()).collect()
+    }
+
+    /// Un objetivo por palabra, con la palabra como `FUZZW1`.
+    fn fuzz_targets(server: &MockServer, list: &[&str]) -> Targets {
+        let targets: Vec<Target> = list
+            .iter()
+            .map(|w| Target { url: server.url(&format!("/{}", w)), words: vec![w.to_string()], indices: vec![] })
+            .collect();
+        let total = targets.len();
+        Targets::new(targets.into_iter(), total)
+    }
+
+    #[test]
+    fn header_spec_is_split_and_trimmed() {
+        assert_eq!(parse_header("X-Token:  abc ").unwrap(), ("X-Token".to_string(), "abc".to_string()));
+        assert_eq!(parse_header("X-Empty:").unwrap(), ("X-Empty".to_string(), String::new()));
+        // Solo el primer `:` separa el nombre
+        assert_eq!(parse_header("Referer: http://h/x").unwrap().1, "http://h/x");
+    }
+
+    #[test]
+    fn malformed_headers_are_rejected() {
+        for spec in ["NoColon", ": value", "Bad Name: v", "X\u{e9}: v", "X-Ok: a\u{7}b"] {
+            let err = parse_header(spec).unwrap_err();
+            assert!(err.to_string().starts_with("Invalid --header"), "{}: {}", spec, err);
+        }
+    }
+
+    #[test]
+    fn placeholders_in_values_are_substituted() {
+        let templates = HeaderTemplates::new(vec![("X-User".into(), "FUZZW1".into()), ("X-Id".into(), "id-FUZZR".into())], 1);
+        let map = templates.resolve(&words(&["7", "admin"])).unwrap();
+        assert_eq!(map["x-user"], "admin");
+        assert_eq!(map["x-id"], "id-7");
+    }
+
+    #[test]
+    fn repeated_headers_are_all_sent() {
+        let templates = HeaderTemplates::new(vec![("Accept".into(), "a/b".into()), ("Accept".into(), "c/d".into())], 0);
+        let map = templates.resolve(&[]).unwrap();
+        let values: Vec<_> = map.get_all("accept").iter().collect();
+        assert_eq!(values, ["a/b", "c/d"]);
+    }
+
+    #[test]
+    fn a_substituted_value_that_is_not_valid_http_fails() {
+        let templates = HeaderTemplates::new(vec![("X-User".into(), "FUZZW1".into())], 0);
+        let err = templates.resolve(&words(&["a\nb"])).unwrap_err();
+        assert!(err.to_string().contains("X-User"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn webrequest_sends_the_headers_of_each_combination() {
+        let server = MockServer::start(|_| Response::new(200)).await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testutil::mode_config("webrequest", dir.path());
+        config.headers = HeaderTemplates::new(vec![("X-Word".into(), "w-FUZZW1".into())], 0);
+        testutil::run_targets(config, fuzz_targets(&server, &["a", "b"])).await;
+
+        let mut sent: Vec<_> = server
+            .requests()
+            .iter()
+            .map(|r| (r.path.clone(), r.header("x-word").unwrap_or_default().to_string()))
+            .collect();
+        sent.sort();
+        assert_eq!(sent, [("/a".to_string(), "w-a".to_string()), ("/b".to_string(), "w-b".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn download_sends_the_headers() {
+        let server = MockServer::start(|_| Response::new(200).body("x")).await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testutil::mode_config("download", dir.path());
+        config.headers = HeaderTemplates::new(vec![("X-Word".into(), "FUZZW1".into())], 0);
+        testutil::run_targets(config, fuzz_targets(&server, &["f.txt"])).await;
+
+        let requests = server.requests();
+        assert!(!requests.is_empty());
+        assert!(requests.iter().all(|r| r.header("x-word") == Some("f.txt")), "{:?}", requests);
+    }
+
+    #[test]
+    fn cookies_carry_the_value_of_each_combination() {
+        let templates = HeaderTemplates::new(vec![], 0).with_cookies(vec!["session=FUZZW1".into(), "lang=es".into()]);
+        for word in ["tok1", "tok2"] {
+            let map = templates.resolve(&words(&[word])).unwrap();
+            assert_eq!(map[COOKIE], format!("session={}; lang=es", word).as_str());
+        }
+    }
+
+    #[test]
+    fn cookies_are_appended_to_an_explicit_cookie_header() {
+        let templates = HeaderTemplates::new(vec![("Cookie".into(), "a=1".into())], 0).with_cookies(vec!["b=FUZZW1".into()]);
+        let map = templates.resolve(&words(&["2"])).unwrap();
+        assert_eq!(map.get_all(COOKIE).iter().count(), 1);
+        assert_eq!(map[COOKIE], "a=1; b=2");
+    }
+
+    #[test]
+    fn explicit_cookies_win_over_the_jar() {
+        let jar = crate::core::cookies::CookieJar::default();
+        let url = url::Url::parse("http://h/").unwrap();
+        let mut set = HeaderMap::new();
+        set.append(reqwest::header::SET_COOKIE, HeaderValue::from_static("session=stored"));
+        set.append(reqwest::header::SET_COOKIE, HeaderValue::from_static("theme=dark"));
+        jar.store(&url, &set);
+
+        let templates = HeaderTemplates::new(vec![], 0).with_cookies(vec!["session=FUZZW1".into()]);
+        let mut map = templates.resolve(&words(&["fuzzed"])).unwrap();
+        jar.apply(url.as_str(), &mut map);
+        assert_eq!(map[COOKIE], "session=fuzzed; theme=dark");
+    }
+
+    #[tokio::test]
+    async fn webrequest_sends_the_cookie_of_each_combination() {
+        let server = MockServer::start(|_| Response::new(200)).await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testutil::mode_config("webrequest", dir.path());
+        config.headers = HeaderTemplates::new(vec![], 0).with_cookies(vec!["session=FUZZW1".into()]);
+        testutil::run_targets(config, fuzz_targets(&server, &["s1", "s2"])).await;
+
+        let mut sent: Vec<_> = server
+            .requests()
+            .iter()
+            .map(|r| (r.path.clone(), r.header("cookie").unwrap_or_default().to_string()))
+            .collect();
+        sent.sort();
+        assert_eq!(sent, [("/s1".to_string(), "session=s1".to_string()), ("/s2".to_string(), "session=s2".to_string())]);
+    }
+}
@@ -0,0 +1,123 @@
+use anyhow::Result;
+use std::path::Path;
+use tokio::process::Command;
+
+/// Hook `--exec` lanzado tras una descarga correcta.
+///
+/// El comando recibe `{}` sustituido por la ruta del archivo y, además, la
+/// procedencia de la descarga en variables de entorno:
+/// `DOWNZER_FILE`, `DOWNZER_URL`, `DOWNZER_STATUS` y `DOWNZER_WORD1`,
+/// `DOWNZER_WORD2`, ...
+///
+/// La ruta sale de la URL o de la cabecera `Content-Disposition`, así que la
+/// elige el servidor: nunca se pega en el texto del comando. `{}` pasa a ser
+/// una referencia entrecomillada al argumento (`"$1"` en `sh`,
+/// `"%DOWNZER_FILE%"` en `cmd`) y el shell la expande sin interpretarla.
+pub struct ExecHook<'a> {
+    pub command: &'a str,
+    pub dest: &'a Path,
+    pub url: &'a str,
+    pub status: u16,
+    pub words: &'a [String],
+}
+
+impl ExecHook<'_> {
+    pub fn env_vars(&self) -> Vec<(String, String)> {
+        let mut vars = vec![
+            ("DOWNZER_FILE".to_string(), self.dest.to_string_lossy().into_owned()),
+            ("DOWNZER_URL".to_string(), self.url.to_string()),
+            ("DOWNZER_STATUS".to_string(), self.status.to_string()),
+        ];
+        for (i, word) in self.words.iter().enumerate() {
+            vars.push((format!("DOWNZER_WORD{}", i + 1), word.clone()));
+        }
+        vars
+    }
+
+    /// `command` con cada `{}` (también `'{}'` y `"{}"`, que ya venían
+    /// entrecomillados) cambiado por `reference`.
+    fn script(&self, reference: &str) -> String {
+        self.command
+            .replace("'{}'", reference)
+            .replace("\"{}\"", reference)
+            .replace("{}", reference)
+    }
+
+    pub fn build_command(&self) -> Command {
+        #[cfg(windows)]
+        let mut cmd = {
+            let mut c = Command::new("cmd");
+            c.arg("/C").arg(self.script("\"%DOWNZER_FILE%\""));
+            c
+        };
+        // sh -c '<script>' sh <ruta>: la ruta llega como $1
+        #[cfg(not(windows))]
+        let mut cmd = {
+            let mut c = Command::new("sh");
+            c.arg("-c").arg(self.script("\"$1\"")).arg("sh").arg(self.dest);
+            c
+        };
+
+        cmd.envs(self.env_vars());
+        cmd
+    }
+
+    pub async fn run(&self) -> Result<()> {
+        let status = self.build_command().status().await?;
+        if !status.success() {
+            anyhow::bail!("command exited with {}", status);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn hook<'a>(command: &'a str, dest: &'a Path, words: &'a [String]) -> ExecHook<'a> {
+        ExecHook { command, dest, url: "http://host/a b", status: 200, words }
+    }
+
+    #[tokio::test]
+    async fn child_sees_provenance_env_vars() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("env.txt");
+        let dest = dir.path().join("file.bin");
+        let words = vec!["admin".to_string(), "x y".to_string()];
+        let command = format!("env | grep '^DOWNZER_' | sort > '{}'", out.display());
+        hook(&command, &dest, &words).run().await.unwrap();
+
+        let env = std::fs::read_to_string(&out).unwrap();
+        let lines: Vec<&str> = env.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                format!("DOWNZER_FILE={}", dest.display()).as_str(),
+                "DOWNZER_STATUS=200",
+                "DOWNZER_URL=http://host/a b",
+                "DOWNZER_WORD1=admin",
+                "DOWNZER_WORD2=x y",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn hostile_filename_is_not_interpreted_by_the_shell() {
+        let dir = tempfile::tempdir().unwrap();
+        let name = "x;touch semicolon$(touch subst)`touch backtick`";
+        let dest = dir.path().join(name);
+        std::fs::write(&dest, b"data").unwrap();
+        let out = dir.path().join("out.txt");
+        // Las tres formas de escribir el marcador acaban igual; una inyección
+        // crearía los archivos en `dir`
+        for placeholder in ["{}", "'{}'", "\"{}\""] {
+            let command = format!("cd '{}' && printf %s {} > out.txt", dir.path().display(), placeholder);
+            hook(&command, &dest, &[]).run().await.unwrap();
+            assert_eq!(std::fs::read_to_string(&out).unwrap(), dest.display().to_string());
+        }
+        for injected in ["semicolon", "subst", "backtick"] {
+            assert!(!dir.path().join(injected).exists(), "{} was executed", injected);
+        }
+    }
+}
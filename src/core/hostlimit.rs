@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Límite de peticiones simultáneas por host (`--max-per-host`), aparte del
+/// semáforo global de `--max-concurrent`.
+pub struct HostLimiter {
+    per_host: usize,
+    hosts: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+impl HostLimiter {
+    pub fn new(per_host: usize) -> Self {
+        Self { per_host, hosts: Mutex::new(HashMap::new()) }
+    }
+
+    /// Espera un hueco para el host de `url`; se libera al soltar el permiso.
+    pub async fn acquire(&self, url: &str) -> anyhow::Result<OwnedSemaphorePermit> {
+        let semaphore = {
+            let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+            hosts
+                .entry(host_key(url))
+                .or_insert_with(|| Arc::new(Semaphore::new(self.per_host)))
+                .clone()
+        };
+        Ok(semaphore.acquire_owned().await?)
+    }
+}
+
+/// `host:puerto` de la URL (con el puerto por defecto del esquema), de modo que
+/// `http://a` y `http://a:80` cuentan como el mismo host. Las URLs inválidas
+/// comparten una misma entrada: su petición fallará igualmente.
+fn host_key(url: &str) -> String {
+    match url::Url::parse(url) {
+        Ok(parsed) => format!(
+            "{}:{}",
+            parsed.host_str().unwrap_or_default().to_ascii_lowercase(),
+            parsed.port_or_known_default().unwrap_or(0)
+        ),
+        Err(_) => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[test]
+    fn default_ports_share_a_key() {
+        assert_eq!(host_key("http://Example.com/a"), host_key("http://example.com:80/b"));
+        assert_ne!(host_key("http://example.com/"), host_key("https://example.com/"));
+        assert_ne!(host_key("http://example.com:8080/"), host_key("http://example.com/"));
+    }
+
+    #[tokio::test]
+    async fn permits_are_counted_per_host() {
+        let limiter = HostLimiter::new(2);
+        let a1 = limiter.acquire("http://a/1").await.unwrap();
+        let _a2 = limiter.acquire("http://a/2").await.unwrap();
+        // Otro host no espera aunque `a` esté lleno
+        let _b = limiter.acquire("http://b/1").await.unwrap();
+        let third = tokio::time::timeout(Duration::from_millis(50), limiter.acquire("http://a/3")).await;
+        assert!(third.is_err());
+        drop(a1);
+        assert!(tokio::time::timeout(Duration::from_millis(50), limiter.acquire("http://a/3")).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn no_more_than_n_downloads_hit_one_host() {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        // Servidor que cuenta las peticiones en curso según la cabecera Host
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let active: Arc<Mutex<HashMap<String, usize>>> = Arc::default();
+        let peak = Arc::new(AtomicUsize::new(0));
+        let (server_active, server_peak) = (active.clone(), peak.clone());
+        let server = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let (active, peak) = (server_active.clone(), server_peak.clone());
+                tokio::spawn(async move {
+                    let mut reader = BufReader::new(stream);
+                    let mut host = String::new();
+                    let mut line = String::new();
+                    while reader.read_line(&mut line).await.unwrap() > 2 {
+                        if let Some(value) = line.to_ascii_lowercase().strip_prefix("host:") {
+                            host = value.trim().to_string();
+                        }
+                        line.clear();
+                    }
+                    let now = {
+                        let mut active = active.lock().unwrap();
+                        let count = active.entry(host.clone()).or_default();
+                        *count += 1;
+                        *count
+                    };
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    *active.lock().unwrap().get_mut(&host).unwrap() -= 1;
+                    let mut stream = reader.into_inner();
+                    stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok").await.unwrap();
+                });
+            }
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = crate::testutil::mode_config("download", dir.path());
+        config.max_concurrent = 16;
+        config.max_per_host = Some(2);
+        let urls = (0..8)
+            .flat_map(|i| [format!("http://127.0.0.1:{}/a{}", port, i), format!("http://localhost:{}/b{}", port, i)])
+            .collect();
+        let result = crate::testutil::run(config, urls).await;
+        server.abort();
+        assert_eq!(result.successful, 16);
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,255 @@
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+
+use crate::core::db::TaskRecord;
+use crate::core::Downzer;
+use crate::core::task::TaskStatus;
+
+/// Contadores de la ejecución en curso, compartidos entre el ejecutor y el
+/// muestreador de `--interval-stats`.
+#[derive(Debug, Default)]
+pub struct LiveStats {
+    pub completed: AtomicUsize,
+    pub errors: AtomicUsize,
+    pub bytes: AtomicU64,
+    pub active: AtomicUsize,
+}
+
+impl LiveStats {
+    pub fn record(&self, error: bool, bytes: u64) {
+        self.completed.fetch_add(1, Ordering::Relaxed);
+        if error {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Marca una petición en vuelo hasta que se suelta el guard.
+    pub fn begin(self: &Arc<Self>) -> ActiveGuard {
+        self.active.fetch_add(1, Ordering::Relaxed);
+        ActiveGuard(self.clone())
+    }
+}
+
+pub struct ActiveGuard(Arc<LiveStats>);
+
+impl Drop for ActiveGuard {
+    fn drop(&mut self) {
+        self.0.active.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Escribe una fila CSV por segundo con el estado de `LiveStats` (`--interval-stats`).
+pub struct IntervalSampler {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<io::Result<()>>,
+}
+
+impl IntervalSampler {
+    /// Abre `path` en modo append (con cabecera si está vacío) y arranca el muestreo.
+    pub fn start(path: &Path, stats: Arc<LiveStats>, shutdown: Arc<AtomicBool>) -> io::Result<Self> {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        if file.metadata()?.len() == 0 {
+            writeln!(file, "timestamp,completed,rate,errors,bytes,active")?;
+        }
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_flag = stop.clone();
+        let handle = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(Duration::from_secs(1));
+            ticker.tick().await;
+            let mut last_completed = 0;
+            loop {
+                ticker.tick().await;
+                let completed = stats.completed.load(Ordering::Relaxed);
+                writeln!(
+                    file,
+                    "{},{},{},{},{},{}",
+                    chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, false),
+                    completed,
+                    completed - last_completed,
+                    stats.errors.load(Ordering::Relaxed),
+                    stats.bytes.load(Ordering::Relaxed),
+                    stats.active.load(Ordering::Relaxed),
+                )?;
+                file.flush()?;
+                last_completed = completed;
+                if stop_flag.load(Ordering::SeqCst) || shutdown.load(Ordering::SeqCst) {
+                    return Ok(());
+                }
+            }
+        });
+
+        Ok(Self { stop, handle })
+    }
+
+    /// Detiene el muestreo tras escribir una última fila.
+    pub async fn stop(self) -> anyhow::Result<()> {
+        self.stop.store(true, Ordering::SeqCst);
+        self.handle.await??;
+        Ok(())
+    }
+}
+
+/// Persiste el progreso de la tarea cada cierto tiempo (`--flush-db-interval`)
+/// en lugar de en cada petición: lee el contador compartido de `LiveStats`,
+/// que download y webrequest avanzan con cada respuesta, y lo pasa a
+/// `Downzer::update_task_progress`. Solo escribe si el progreso ha cambiado.
+pub struct ProgressFlusher {
+    downzer: Arc<Downzer>,
+    record: TaskRecord,
+    stats: Arc<LiveStats>,
+    handle: JoinHandle<()>,
+}
+
+impl ProgressFlusher {
+    /// Arranca el volcado periódico de una tarea ya registrada (`Downzer::add_task`).
+    pub fn start(downzer: Arc<Downzer>, record: TaskRecord, stats: Arc<LiveStats>, interval: Duration) -> Self {
+        let (task_downzer, task_stats) = (downzer.clone(), stats.clone());
+        let (id, mut flushed) = (record.id, record.completed);
+        let handle = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let completed = task_stats.completed.load(Ordering::Relaxed);
+                if completed != flushed {
+                    task_downzer.update_task_progress(id, completed).await;
+                    flushed = completed;
+                }
+            }
+        });
+
+        Self { downzer, record, stats, handle }
+    }
+
+    /// Detiene el volcado y escribe el estado final. `completed` sustituye al
+    /// contador de `LiveStats` en los modos que no lo usan.
+    pub async fn finish(self, status: TaskStatus, completed: Option<usize>) -> anyhow::Result<()> {
+        self.handle.abort();
+        let _ = self.handle.await;
+        let mut record = self.record;
+        record.completed = completed.unwrap_or_else(|| self.stats.completed.load(Ordering::Relaxed));
+        record.status = status;
+        record.updated_at = chrono::Utc::now().to_rfc3339();
+        self.downzer.db.lock().await.update_task(&record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::db::Database;
+
+    #[tokio::test]
+    async fn sampler_writes_one_row_per_second() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stats.csv");
+        let stats = Arc::new(LiveStats::default());
+        let sampler = IntervalSampler::start(&path, stats.clone(), Arc::new(AtomicBool::new(false))).unwrap();
+
+        stats.record(false, 100);
+        stats.record(true, 0);
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        stats.record(false, 50);
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+        sampler.stop().await.unwrap();
+
+        let csv = std::fs::read_to_string(&path).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("timestamp,completed,rate,errors,bytes,active"));
+        let rows: Vec<Vec<&str>> = lines.map(|l| l.split(',').collect()).collect();
+        assert_eq!(rows.len(), 3, "{}", csv);
+        let values: Vec<&[&str]> = rows.iter().map(|r| &r[1..]).collect();
+        assert_eq!(values, [&["2", "2", "1", "100", "0"][..], &["3", "1", "1", "150", "0"], &["3", "0", "1", "150", "0"]]);
+
+        let times: Vec<_> = rows.iter().map(|r| chrono::DateTime::parse_from_rfc3339(r[0]).unwrap()).collect();
+        for pair in times.windows(2) {
+            let gap = (pair[1] - pair[0]).num_milliseconds();
+            assert!((800..=1200).contains(&gap), "{} ms entre filas", gap);
+        }
+    }
+
+    #[tokio::test]
+    async fn existing_file_keeps_a_single_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stats.csv");
+        std::fs::write(&path, "timestamp,completed,rate,errors,bytes,active\n").unwrap();
+        let sampler = IntervalSampler::start(&path, Arc::new(LiveStats::default()), Arc::new(AtomicBool::new(false))).unwrap();
+        sampler.stop().await.unwrap();
+        let csv = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(csv.matches("timestamp").count(), 1);
+        assert_eq!(csv.lines().count(), 2);
+    }
+
+    async fn stored(db: &Arc<tokio::sync::Mutex<Database>>, id: u32) -> TaskRecord {
+        db.lock().await.get_task(id).unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn flusher_persists_at_the_interval_and_on_finish() {
+        let downzer = crate::testutil::downzer();
+        let stats = Arc::new(LiveStats::default());
+        let record = downzer
+            .add_task(crate::core::task::TaskInfo {
+                id: 1,
+                url_template: "http://h/FUZZW1".to_string(),
+                total: 10,
+                completed: 0,
+                status: TaskStatus::Running,
+                start_time: std::time::Instant::now(),
+                live: Some(stats.clone()),
+            })
+            .await
+            .unwrap();
+        let flusher = ProgressFlusher::start(downzer.clone(), record, stats.clone(), Duration::from_millis(200));
+
+        for _ in 0..3 {
+            stats.record(false, 0);
+        }
+        // Antes del primer tick la base de datos sigue sin tocar
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(stored(&downzer.db, 1).await.completed, 0);
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        let flushed = stored(&downzer.db, 1).await;
+        assert_eq!(flushed.completed, 3);
+        assert_eq!(flushed.status, TaskStatus::Running);
+        // También la copia en memoria que sirve `List`
+        assert_eq!(downzer.tasks.read().await[&1].completed, 3);
+
+        // Sin progreso nuevo no se vuelve a escribir
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert_eq!(stored(&downzer.db, 1).await.updated_at, flushed.updated_at);
+
+        stats.record(true, 0);
+        flusher.finish(TaskStatus::Completed, None).await.unwrap();
+        let finished = stored(&downzer.db, 1).await;
+        assert_eq!((finished.completed, finished.status), (4, TaskStatus::Completed));
+    }
+
+    #[tokio::test]
+    async fn finish_prefers_the_explicit_count() {
+        let downzer = crate::testutil::downzer();
+        let record = TaskRecord {
+            id: 2,
+            url_template: "t".to_string(),
+            total: 5,
+            completed: 0,
+            status: TaskStatus::Running,
+            pid: None,
+            created_at: String::new(),
+            updated_at: String::new(),
+        };
+        downzer.db.lock().await.upsert_task(&record).unwrap();
+        let flusher = ProgressFlusher::start(downzer.clone(), record, Arc::new(LiveStats::default()), Duration::from_secs(60));
+        flusher.finish(TaskStatus::Failed, Some(5)).await.unwrap();
+        let finished = stored(&downzer.db, 2).await;
+        assert_eq!((finished.completed, finished.status), (5, TaskStatus::Failed));
+    }
+}
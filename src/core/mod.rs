@@ -1,8 +1,31 @@
 pub mod downzer;
-pub mod worker;
 pub mod task;
 pub mod db;
+pub mod combinations;
+pub mod targets;
+pub mod hook;
+pub mod output;
+pub mod progress;
+pub mod dedup;
+pub mod schedule;
+pub mod live;
+pub mod drain;
+pub mod filename;
+pub mod sigv4;
+pub mod report;
+pub mod retry;
+pub mod caps;
+pub mod hostlimit;
+pub mod throttle;
+pub mod adaptive;
+pub mod headers;
+pub mod cookies;
+pub mod redirect;
+pub mod body;
+pub mod scanxml;
+pub mod credentials;
 
 // Re-exports útiles
-pub use downzer::Downzer;
+pub use downzer::{ClientOptions, Downzer, DownloadOptions};
 pub use task::TaskInfo;
+pub use targets::{RepeatMode, Target, Targets, UrlFilters};
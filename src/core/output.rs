@@ -0,0 +1,63 @@
+/// Línea de salida asociada a una petición concreta.
+///
+/// Con `--ordered-output` los workers devuelven la línea al colector en vez de
+/// imprimirla; el colector espera los handles en orden de generación, así que
+/// las líneas salen en ese orden a medida que se completan prefijos contiguos.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputLine {
+    Stdout(String),
+    Stderr(String),
+}
+
+#[cfg(test)]
+thread_local! {
+    static CAPTURED: std::cell::RefCell<Option<Vec<OutputLine>>> = const { std::cell::RefCell::new(None) };
+}
+
+/// Tests: desde aquí las líneas emitidas en este hilo se guardan en vez de
+/// imprimirse (los tests de tokio corren en un solo hilo).
+#[cfg(test)]
+pub fn start_capture() {
+    CAPTURED.with(|c| *c.borrow_mut() = Some(Vec::new()));
+}
+
+/// Tests: líneas guardadas desde `start_capture`.
+#[cfg(test)]
+pub fn captured() -> Vec<OutputLine> {
+    CAPTURED.with(|c| c.borrow_mut().take().unwrap_or_default())
+}
+
+impl OutputLine {
+    pub fn emit(&self) {
+        #[cfg(test)]
+        if CAPTURED.with(|c| c.borrow_mut().as_mut().map(|lines| lines.push(self.clone())).is_some()) {
+            return;
+        }
+        match self {
+            OutputLine::Stdout(line) => println!("{}", line),
+            OutputLine::Stderr(line) => eprintln!("{}", line),
+        }
+    }
+
+    /// Imprime la línea ya, o la devuelve para que la imprima el colector.
+    pub fn deliver(self, ordered: bool) -> Option<Self> {
+        if ordered {
+            Some(self)
+        } else {
+            self.emit();
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ordered_lines_are_handed_back_to_the_collector() {
+        let line = OutputLine::Stdout("[200] http://h/a".to_string());
+        assert_eq!(line.clone().deliver(true), Some(line));
+        assert_eq!(OutputLine::Stderr("x".to_string()).deliver(false), None);
+    }
+}
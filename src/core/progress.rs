@@ -0,0 +1,71 @@
+use indicatif::{ProgressBar, ProgressStyle};
+
+/// Barra de progreso por bytes para una descarga individual.
+///
+/// Con `Content-Length` conocido muestra barra y porcentaje; si no, un
+/// spinner con los bytes recibidos hasta el momento.
+pub fn byte_progress_bar(total: Option<u64>) -> ProgressBar {
+    match total {
+        Some(total) => {
+            let pb = ProgressBar::new(total);
+            pb.set_style(
+                ProgressStyle::with_template(
+                    "{spinner:.green} [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})",
+                )
+                .unwrap_or_else(|_| ProgressStyle::default_bar())
+                .progress_chars("=> "),
+            );
+            pb
+        }
+        None => {
+            let pb = ProgressBar::new_spinner();
+            pb.set_style(
+                ProgressStyle::with_template("{spinner:.green} {bytes} ({bytes_per_sec})")
+                    .unwrap_or_else(|_| ProgressStyle::default_spinner()),
+            );
+            pb
+        }
+    }
+}
+
+
+/// Barra de `downzer watch`: elementos procesados de una tarea de otro
+/// proceso. El mensaje lleva el estado y el tiempo restante que calcula el
+/// servidor, que conoce el ritmo desde el inicio de la tarea.
+pub fn task_progress_bar(total: u64) -> ProgressBar {
+    let pb = ProgressBar::new(total);
+    pb.set_style(
+        ProgressStyle::with_template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} ({percent}%) {msg}")
+            .unwrap_or_else(|_| ProgressStyle::default_bar())
+            .progress_chars("=> "),
+    );
+    pb
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fraction(pb: &ProgressBar) -> Option<f64> {
+        pb.length().map(|len| pb.position() as f64 / len as f64)
+    }
+
+    #[test]
+    fn known_length_reports_the_streamed_fraction() {
+        let pb = byte_progress_bar(Some(1000));
+        assert_eq!(fraction(&pb), Some(0.0));
+        for (streamed, expected) in [(250, 0.25), (500, 0.5), (1000, 1.0)] {
+            pb.set_position(streamed);
+            assert_eq!(fraction(&pb), Some(expected));
+        }
+    }
+
+    #[test]
+    fn unknown_length_counts_bytes_without_a_total() {
+        let pb = byte_progress_bar(None);
+        pb.inc(4096);
+        pb.inc(100);
+        assert_eq!(pb.position(), 4196);
+        assert_eq!(fraction(&pb), None);
+    }
+}
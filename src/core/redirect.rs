@@ -0,0 +1,163 @@
+use reqwest::header::{
+    HeaderMap, AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE, COOKIE, LOCATION, PROXY_AUTHORIZATION, WWW_AUTHENTICATE,
+};
+use reqwest::{Client, Method, Request, RequestBuilder, Response, StatusCode};
+use url::Url;
+
+use crate::core::cookies::CookieJar;
+
+/// Saltos como máximo; tras el último se devuelve la propia redirección.
+pub const MAX_REDIRECTS: usize = 10;
+
+/// Envía la petición siguiendo las redirecciones (el cliente se construye sin
+/// seguirlas). Cuando un salto cambia de origen (esquema, host o puerto) se
+/// quitan las credenciales (`Authorization`, `Cookie`...), como hace curl,
+/// salvo con `trusted` (`--location-trusted`). Con `--cookie-jar`, el jar
+/// añade sus cookies en cada salto y guarda los `Set-Cookie` de todas las respuestas.
+pub async fn send(
+    client: &Client,
+    request: RequestBuilder,
+    jar: Option<&CookieJar>,
+    trusted: bool,
+) -> reqwest::Result<Response> {
+    let mut request = request.build()?;
+    // Las cabeceras sin las cookies del jar, que se recalculan para cada URL
+    let mut base_headers = request.headers().clone();
+    if let Some(jar) = jar {
+        let url = request.url().to_string();
+        jar.apply(&url, request.headers_mut());
+    }
+
+    for _ in 0..MAX_REDIRECTS {
+        let retry = request.try_clone();
+        let response = client.execute(request).await?;
+        if let Some(jar) = jar {
+            jar.store(response.url(), response.headers());
+        }
+        let (Some(previous), Some(next)) = (retry, redirect_target(&response)) else {
+            return Ok(response);
+        };
+
+        let cross_origin = !same_origin(previous.url(), &next);
+        if cross_origin && !trusted {
+            strip_credentials(&mut base_headers);
+        }
+        let mut headers = base_headers.clone();
+        let mut method = previous.method().clone();
+        let mut body = previous.body().and_then(|b| b.as_bytes()).map(|b| b.to_vec());
+        // 303 siempre, y 301/302 tras un POST, pasan a GET sin cuerpo (como los navegadores)
+        let status = response.status();
+        if (status == StatusCode::SEE_OTHER && method != Method::HEAD)
+            || (matches!(status, StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND) && method == Method::POST)
+        {
+            method = Method::GET;
+            body = None;
+            headers.remove(CONTENT_TYPE);
+            headers.remove(CONTENT_LENGTH);
+        }
+
+        let mut next_request = Request::new(method, next);
+        *next_request.timeout_mut() = previous.timeout().copied();
+        if let Some(body) = body {
+            *next_request.body_mut() = Some(body.into());
+        }
+        if let Some(jar) = jar {
+            jar.apply(next_request.url().as_str(), &mut headers);
+        }
+        *next_request.headers_mut() = headers;
+        request = next_request;
+    }
+    client.execute(request).await
+}
+
+/// URL a la que redirige la respuesta, si es una redirección con `Location` válido.
+fn redirect_target(response: &Response) -> Option<Url> {
+    if !matches!(
+        response.status(),
+        StatusCode::MOVED_PERMANENTLY
+            | StatusCode::FOUND
+            | StatusCode::SEE_OTHER
+            | StatusCode::TEMPORARY_REDIRECT
+            | StatusCode::PERMANENT_REDIRECT
+    ) {
+        return None;
+    }
+    let location = response.headers().get(LOCATION)?.to_str().ok()?;
+    let next = response.url().join(location).ok()?;
+    matches!(next.scheme(), "http" | "https").then_some(next)
+}
+
+fn same_origin(a: &Url, b: &Url) -> bool {
+    a.scheme() == b.scheme() && a.host_str() == b.host_str() && a.port_or_known_default() == b.port_or_known_default()
+}
+
+fn strip_credentials(headers: &mut HeaderMap) {
+    headers.remove(AUTHORIZATION);
+    headers.remove(COOKIE);
+    headers.remove("cookie2");
+    headers.remove(PROXY_AUTHORIZATION);
+    headers.remove(WWW_AUTHENTICATE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{self, MockServer, Response};
+
+    /// Servidor que redirige `/go` a `target` y responde 200 al resto.
+    async fn redirector(target: String) -> MockServer {
+        MockServer::start(move |req| match req.path.as_str() {
+            "/go" => Response::new(302).header("Location", &target),
+            _ => Response::new(200),
+        })
+        .await
+    }
+
+    async fn follow(url: &str, trusted: bool) -> reqwest::Response {
+        let client = &testutil::downzer().client;
+        let request = client.get(url).header(AUTHORIZATION, "Bearer secret").header(COOKIE, "sid=1").header("X-Keep", "1");
+        send(client, request, None, trusted).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn credentials_are_dropped_on_a_cross_origin_redirect() {
+        let other = MockServer::start(|_| Response::new(200)).await;
+        let origin = redirector(other.url("/land")).await;
+        let response = follow(&origin.url("/go"), false).await;
+
+        assert_eq!(response.status(), 200);
+        let landed = &other.requests()[0];
+        assert_eq!(landed.path, "/land");
+        assert_eq!(landed.header("authorization"), None);
+        assert_eq!(landed.header("cookie"), None);
+        assert_eq!(landed.header("x-keep"), Some("1"));
+    }
+
+    #[tokio::test]
+    async fn credentials_are_kept_on_a_same_origin_redirect() {
+        let origin = redirector("/land".to_string()).await;
+        follow(&origin.url("/go"), false).await;
+
+        let landed = &origin.requests()[1];
+        assert_eq!(landed.path, "/land");
+        assert_eq!(landed.header("authorization"), Some("Bearer secret"));
+        assert_eq!(landed.header("cookie"), Some("sid=1"));
+    }
+
+    #[tokio::test]
+    async fn location_trusted_keeps_them_across_origins() {
+        let other = MockServer::start(|_| Response::new(200)).await;
+        let origin = redirector(other.url("/land")).await;
+        follow(&origin.url("/go"), true).await;
+        assert_eq!(other.requests()[0].header("authorization"), Some("Bearer secret"));
+    }
+
+    #[test]
+    fn origins_compare_scheme_host_and_port() {
+        let url = |s: &str| Url::parse(s).unwrap();
+        assert!(same_origin(&url("http://h/a"), &url("http://h:80/b")));
+        assert!(!same_origin(&url("http://h/"), &url("https://h/")));
+        assert!(!same_origin(&url("http://h/"), &url("http://h:8080/")));
+        assert!(!same_origin(&url("http://h/"), &url("http://sub.h/")));
+    }
+}
@@ -0,0 +1,251 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use chrono::Utc;
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::targets::Target;
+
+/// Fila del informe por URL (`--report`): resultado y procedencia, es decir,
+/// los valores de la combinación y su posición en cada lista de origen.
+#[derive(Debug, Serialize)]
+pub struct ReportRow<'a> {
+    pub url: &'a str,
+    /// Código HTTP; `None` si no hubo respuesta (timeout, error de conexión)
+    pub status: Option<u16>,
+    pub outcome: &'a str,
+    pub bytes: Option<u64>,
+    pub words: &'a [String],
+    pub indices: &'a [usize],
+}
+
+impl<'a> ReportRow<'a> {
+    pub fn new(target: &'a Target, status: Option<u16>, outcome: &'a str, bytes: Option<u64>) -> Self {
+        Self { url: &target.url, status, outcome, bytes, words: &target.words, indices: &target.indices }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReportFormat {
+    Csv,
+    Json,
+}
+
+/// Escribe el informe según se completan las peticiones: CSV si la ruta
+/// acaba en `.csv` y un array JSON en cualquier otro caso.
+pub struct ReportSink {
+    format: ReportFormat,
+    out: BufWriter<File>,
+    rows: usize,
+}
+
+impl ReportSink {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let format = match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => ReportFormat::Csv,
+            _ => ReportFormat::Json,
+        };
+        let mut out = BufWriter::new(File::create(path)?);
+        if format == ReportFormat::Json {
+            write!(out, "[")?;
+        }
+        Ok(Self { format, out, rows: 0 })
+    }
+
+    pub fn write(&mut self, row: &ReportRow) -> io::Result<()> {
+        match self.format {
+            ReportFormat::Json => {
+                let sep = if self.rows == 0 { "\n  " } else { ",\n  " };
+                write!(self.out, "{}{}", sep, serde_json::to_string(row)?)?;
+            }
+            ReportFormat::Csv => {
+                // Una columna por placeholder; el número lo fija la primera fila
+                if self.rows == 0 {
+                    let mut header = vec!["url".to_string(), "status".into(), "outcome".into(), "bytes".into()];
+                    header.extend((1..=row.words.len()).map(|i| format!("word{}", i)));
+                    header.extend((1..=row.indices.len()).map(|i| format!("index{}", i)));
+                    writeln!(self.out, "{}", header.join(","))?;
+                }
+                let mut fields = vec![
+                    csv_field(row.url),
+                    row.status.map(|s| s.to_string()).unwrap_or_default(),
+                    csv_field(row.outcome),
+                    row.bytes.map(|b| b.to_string()).unwrap_or_default(),
+                ];
+                fields.extend(row.words.iter().map(|w| csv_field(w)));
+                fields.extend(row.indices.iter().map(|i| i.to_string()));
+                writeln!(self.out, "{}", fields.join(","))?;
+            }
+        }
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// Cierra el formato (el `]` del array JSON) y vuelca a disco.
+    pub fn finish(mut self) -> io::Result<()> {
+        if self.format == ReportFormat::Json {
+            writeln!(self.out, "{}]", if self.rows == 0 { "" } else { "\n" })?;
+        }
+        self.out.flush()
+    }
+}
+
+/// Índice de `--save-responses` (`index.csv` en el directorio): qué archivo
+/// guarda el cuerpo de cada URL, con su código y su tipo.
+pub struct ResponseIndex {
+    out: BufWriter<File>,
+}
+
+impl ResponseIndex {
+    pub const FILE: &'static str = "index.csv";
+
+    pub fn create(dir: &Path) -> io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let mut out = BufWriter::new(File::create(dir.join(Self::FILE))?);
+        writeln!(out, "index,url,status,file,bytes,content_type")?;
+        Ok(Self { out })
+    }
+
+    pub fn write(&mut self, index: usize, url: &str, status: u16, file: &str, bytes: u64, content_type: &str) -> io::Result<()> {
+        writeln!(
+            self.out,
+            "{},{},{},{},{},{}",
+            index,
+            csv_field(url),
+            status,
+            csv_field(file),
+            bytes,
+            csv_field(content_type)
+        )
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Nombre por defecto del manifiesto de `--manifest` dentro del directorio de salida.
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Entrada del manifiesto: qué se hizo con cada URL. `file` es el nombre dentro
+/// del directorio de salida (para un duplicado, el del archivo que se conserva).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub url: String,
+    pub file: Option<String>,
+    pub status: Option<u16>,
+    pub bytes: Option<u64>,
+    pub content_type: Option<String>,
+    pub outcome: String,
+}
+
+impl ManifestEntry {
+    /// La URL ya tiene un resultado definitivo; los errores de red, los
+    /// timeouts y los HTTP distintos de 404 se vuelven a intentar con `--only-new`.
+    pub fn is_final(&self) -> bool {
+        !matches!(self.outcome.as_str(), "error" | "timeout" | "http_error")
+    }
+}
+
+#[derive(Serialize)]
+struct Manifest<'a> {
+    task_id: u32,
+    generated_at: String,
+    entries: &'a [ManifestEntry],
+}
+
+#[derive(Deserialize)]
+struct ManifestFile {
+    entries: Vec<ManifestEntry>,
+}
+
+/// Entradas de un manifiesto ya escrito; si el archivo no existe, ninguna.
+pub fn load_manifest(path: &Path) -> anyhow::Result<Vec<ManifestEntry>> {
+    let data = match std::fs::read(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(anyhow::anyhow!("Cannot read manifest {}: {}", path.display(), e)),
+    };
+    let manifest: ManifestFile = serde_json::from_slice(&data)
+        .map_err(|e| anyhow::anyhow!("Invalid manifest {}: {}", path.display(), e))?;
+    Ok(manifest.entries)
+}
+
+/// URLs del manifiesto con resultado definitivo (las que `--only-new` omite).
+pub fn completed_urls(entries: &[ManifestEntry]) -> HashSet<String> {
+    entries.iter().filter(|e| e.is_final()).map(|e| e.url.clone()).collect()
+}
+
+/// Añade `new` a las entradas anteriores, sustituyendo las de URLs que se han
+/// vuelto a procesar (p.ej. un error que ahora se ha descargado).
+pub fn merge_manifest(previous: Vec<ManifestEntry>, new: Vec<ManifestEntry>) -> Vec<ManifestEntry> {
+    let reprocessed: HashSet<&str> = new.iter().map(|e| e.url.as_str()).collect();
+    let mut merged: Vec<ManifestEntry> =
+        previous.into_iter().filter(|e| !reprocessed.contains(e.url.as_str())).collect();
+    merged.extend(new);
+    merged
+}
+
+/// Escribe el manifiesto de la tarea `task_id`, con las entradas en orden de generación.
+pub fn write_manifest(path: &Path, task_id: u32, entries: &[ManifestEntry]) -> io::Result<()> {
+    let manifest = Manifest { task_id, generated_at: Utc::now().to_rfc3339(), entries };
+    let mut out = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut out, &manifest)?;
+    writeln!(out)?;
+    out.flush()
+}
+
+pub(crate) fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(url: &str, words: &[&str], indices: &[usize]) -> Target {
+        Target { url: url.to_string(), words: words.iter().map(|w| w.to_string()).collect(), indices: indices.to_vec() }
+    }
+
+    fn write_report(name: &str) -> String {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(name);
+        let mut sink = ReportSink::create(&path).unwrap();
+        let hit = target("http://h/admin/7", &["admin", "7"], &[2, 6]);
+        let miss = target("http://h/a,b/8", &["a,b", "8"], &[0, 7]);
+        sink.write(&ReportRow::new(&hit, Some(200), "ok", Some(12))).unwrap();
+        sink.write(&ReportRow::new(&miss, None, "timeout", None)).unwrap();
+        sink.finish().unwrap();
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn csv_rows_carry_words_and_indices() {
+        assert_eq!(
+            write_report("report.csv"),
+            "url,status,outcome,bytes,word1,word2,index1,index2\n\
+             http://h/admin/7,200,ok,12,admin,7,2,6\n\
+             \"http://h/a,b/8\",,timeout,,\"a,b\",8,0,7\n"
+        );
+    }
+
+    #[test]
+    fn json_rows_carry_words_and_indices() {
+        let rows: serde_json::Value = serde_json::from_str(&write_report("report.json")).unwrap();
+        assert_eq!(
+            rows,
+            serde_json::json!([
+                {"url": "http://h/admin/7", "status": 200, "outcome": "ok", "bytes": 12, "words": ["admin", "7"], "indices": [2, 6]},
+                {"url": "http://h/a,b/8", "status": null, "outcome": "timeout", "bytes": null, "words": ["a,b", "8"], "indices": [0, 7]},
+            ])
+        );
+    }
+}
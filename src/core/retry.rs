@@ -0,0 +1,189 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use rand::rngs::StdRng;
+use rand::Rng;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+
+/// Espera entre reintentos cuando la respuesta no trae `Retry-After` (`--backoff`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backoff {
+    /// Siempre la base
+    Fixed,
+    /// `min(cap, base * 2^intento)`
+    Exponential,
+    /// Aleatoria en `[0, min(cap, base * 2^intento)]`, para que los reintentos
+    /// de distintas peticiones no coincidan
+    FullJitter,
+}
+
+impl Backoff {
+    pub fn parse(name: &str) -> anyhow::Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "fixed" => Ok(Self::Fixed),
+            "exponential" => Ok(Self::Exponential),
+            "full-jitter" => Ok(Self::FullJitter),
+            other => anyhow::bail!("Invalid --backoff '{}' (expected fixed, exponential or full-jitter)", other),
+        }
+    }
+}
+
+/// Reintentos de respuestas limitadas por tasa (`--retries`, `--retry-503`,
+/// `--max-retry-after`, `--backoff`).
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Reintentos como máximo por URL; 0 los desactiva
+    pub max_retries: u32,
+    /// Reintentar también los 503 (Service Unavailable)
+    pub on_503: bool,
+    /// Un `Retry-After` mayor que esto no se espera: la respuesta se da por fallida
+    pub max_wait: Duration,
+    pub backoff: Backoff,
+    pub base: Duration,
+    pub cap: Duration,
+    /// RNG del jitter, compartido por todas las peticiones (sembrado con `--seed`)
+    pub rng: Arc<Mutex<StdRng>>,
+}
+
+impl RetryPolicy {
+    /// Cuánto esperar antes de repetir una petición que respondió `status`
+    /// tras `attempt` reintentos, o `None` si no se debe reintentar.
+    pub fn wait_for(&self, status: StatusCode, headers: &HeaderMap, attempt: u32) -> Option<Duration> {
+        let retryable = status == StatusCode::TOO_MANY_REQUESTS
+            || (self.on_503 && status == StatusCode::SERVICE_UNAVAILABLE);
+        if !retryable || attempt >= self.max_retries {
+            return None;
+        }
+        let wait = headers
+            .get(RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| parse_retry_after(v, Utc::now()))
+            .unwrap_or_else(|| self.backoff_delay(attempt));
+        (wait <= self.max_wait).then_some(wait)
+    }
+
+    /// Espera del reintento número `attempt` (desde 0) según `backoff`.
+    pub fn backoff_delay(&self, attempt: u32) -> Duration {
+        let ceiling = self
+            .base
+            .checked_mul(2u32.saturating_pow(attempt))
+            .map_or(self.cap, |d| d.min(self.cap));
+        match self.backoff {
+            Backoff::Fixed => self.base,
+            Backoff::Exponential => ceiling,
+            Backoff::FullJitter => {
+                let mut rng = self.rng.lock().unwrap_or_else(|e| e.into_inner());
+                Duration::from_millis(rng.gen_range(0..=ceiling.as_millis() as u64))
+            }
+        }
+    }
+}
+
+/// Interpreta `Retry-After`: segundos (`120`) o una fecha HTTP
+/// (`Wed, 21 Oct 2015 07:28:00 GMT`), relativa a `now`. Una fecha ya
+/// pasada equivale a no esperar.
+pub fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?.with_timezone(&Utc);
+    Some((date - now).to_std().unwrap_or(Duration::ZERO))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use rand::SeedableRng;
+    use reqwest::header::HeaderValue;
+
+    fn policy(max_retries: u32, on_503: bool) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            on_503,
+            max_wait: Duration::from_secs(60),
+            backoff: Backoff::Fixed,
+            base: Duration::from_millis(250),
+            cap: Duration::from_secs(5),
+            rng: Arc::new(Mutex::new(StdRng::seed_from_u64(0))),
+        }
+    }
+
+    fn retry_after(value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static(value));
+        headers
+    }
+
+    #[test]
+    fn retry_after_accepts_seconds_and_http_dates() {
+        let now = Utc.with_ymd_and_hms(2015, 10, 21, 7, 27, 30).unwrap();
+        assert_eq!(parse_retry_after("1", now), Some(Duration::from_secs(1)));
+        assert_eq!(parse_retry_after(" 120 ", now), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT", now), Some(Duration::from_secs(30)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("soon", now), None);
+        assert_eq!(parse_retry_after("-5", now), None);
+    }
+
+    #[test]
+    fn only_rate_limited_responses_are_retried() {
+        let policy = policy(2, false);
+        let headers = retry_after("1");
+        assert_eq!(policy.wait_for(StatusCode::TOO_MANY_REQUESTS, &headers, 0), Some(Duration::from_secs(1)));
+        assert_eq!(policy.wait_for(StatusCode::TOO_MANY_REQUESTS, &headers, 2), None);
+        assert_eq!(policy.wait_for(StatusCode::SERVICE_UNAVAILABLE, &headers, 0), None);
+        assert_eq!(policy.wait_for(StatusCode::INTERNAL_SERVER_ERROR, &headers, 0), None);
+        let with_503 = RetryPolicy { on_503: true, ..policy };
+        assert_eq!(with_503.wait_for(StatusCode::SERVICE_UNAVAILABLE, &headers, 0), Some(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn missing_or_excessive_retry_after() {
+        let policy = RetryPolicy { max_wait: Duration::from_secs(10), ..policy(3, false) };
+        // Sin cabecera se usa --backoff
+        assert_eq!(policy.wait_for(StatusCode::TOO_MANY_REQUESTS, &HeaderMap::new(), 0), Some(Duration::from_millis(250)));
+        // Más de --max-retry-after: la respuesta se da por fallida
+        assert_eq!(policy.wait_for(StatusCode::TOO_MANY_REQUESTS, &retry_after("3600"), 0), None);
+    }
+
+    #[test]
+    fn full_jitter_stays_within_its_bounds() {
+        let policy = RetryPolicy { backoff: Backoff::FullJitter, ..policy(10, false) };
+        for attempt in 0..8 {
+            let ceiling = (policy.base * 2u32.pow(attempt)).min(policy.cap);
+            for _ in 0..200 {
+                let delay = policy.backoff_delay(attempt);
+                assert!(delay <= ceiling, "intento {}: {:?} > {:?}", attempt, delay, ceiling);
+            }
+        }
+        // Con la espera tope, los valores se reparten por todo el intervalo
+        let delays: Vec<Duration> = (0..200).map(|_| policy.backoff_delay(7)).collect();
+        assert!(delays.iter().any(|d| *d < policy.cap / 4));
+        assert!(delays.iter().any(|d| *d > policy.cap * 3 / 4));
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_jitter() {
+        let delays = || {
+            let policy = RetryPolicy { backoff: Backoff::FullJitter, ..policy(10, false) };
+            (0..20).map(|attempt| policy.backoff_delay(attempt % 6)).collect::<Vec<_>>()
+        };
+        assert_eq!(delays(), delays());
+    }
+
+    #[test]
+    fn fixed_and_exponential_backoff() {
+        let fixed = policy(10, false);
+        assert_eq!(fixed.backoff_delay(5), Duration::from_millis(250));
+        let exponential = RetryPolicy { backoff: Backoff::Exponential, ..policy(10, false) };
+        let delays: Vec<u128> = (0..6).map(|a| exponential.backoff_delay(a).as_millis()).collect();
+        assert_eq!(delays, [250, 500, 1000, 2000, 4000, 5000]);
+        assert_eq!(exponential.backoff_delay(40), Duration::from_secs(5));
+        assert!(Backoff::parse("linear").is_err());
+        assert_eq!(Backoff::parse("Full-Jitter").unwrap(), Backoff::FullJitter);
+    }
+}
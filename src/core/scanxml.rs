@@ -0,0 +1,232 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::net::IpAddr;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+
+/// Puerto de un host en el informe XML.
+#[derive(Debug, Clone)]
+pub struct ScannedPort {
+    pub port: u16,
+    /// Estado con los nombres de nmap: open, closed, filtered, open|filtered
+    pub state: &'static str,
+    pub banner: Option<String>,
+}
+
+/// Resultado de un host: la dirección contra la que se escaneó y sus puertos.
+#[derive(Debug, Clone)]
+pub struct ScannedHost {
+    pub addr: IpAddr,
+    pub ports: Vec<ScannedPort>,
+}
+
+/// Escaneo completo para `--scan-output-xml`, en formato `nmaprun` (el de
+/// `nmap -oX`) para que lo lean las herramientas que ya entienden nmap.
+pub struct ScanRun {
+    /// `tcp` o `udp`
+    pub protocol: &'static str,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// Hosts por nombre tal como aparecían en los objetivos
+    pub hosts: BTreeMap<String, ScannedHost>,
+}
+
+impl ScanRun {
+    /// Escribe el documento. Como nmap, los puertos cerrados de cada host no
+    /// se listan uno a uno sino en `<extraports>`, y los banners van en un
+    /// `<script id="banner">`, como los del script `banner` de nmap.
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        let args: Vec<String> = std::env::args().collect();
+        let scan_type = if self.protocol == "udp" { "udp" } else { "connect" };
+        let mut services: Vec<u16> = self.hosts.values().flat_map(|h| h.ports.iter().map(|p| p.port)).collect();
+        services.sort_unstable();
+        services.dedup();
+
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(out, "<!DOCTYPE nmaprun>")?;
+        writeln!(
+            out,
+            r#"<nmaprun scanner="downzer" args="{}" start="{}" startstr="{}" version="{}" xmloutputversion="1.05">"#,
+            escape(&args.join(" ")),
+            self.start.timestamp(),
+            self.start.format("%a %b %e %H:%M:%S %Y"),
+            env!("CARGO_PKG_VERSION")
+        )?;
+        writeln!(
+            out,
+            r#"<scaninfo type="{}" protocol="{}" numservices="{}" services="{}"/>"#,
+            scan_type,
+            self.protocol,
+            services.len(),
+            service_ranges(&services)
+        )?;
+
+        for (name, host) in &self.hosts {
+            writeln!(out, r#"<host starttime="{}" endtime="{}">"#, self.start.timestamp(), self.end.timestamp())?;
+            writeln!(out, r#"<status state="up" reason="user-set" reason_ttl="0"/>"#)?;
+            let addrtype = if host.addr.is_ipv4() { "ipv4" } else { "ipv6" };
+            writeln!(out, r#"<address addr="{}" addrtype="{}"/>"#, host.addr, addrtype)?;
+            if name.parse::<IpAddr>().is_ok() {
+                writeln!(out, "<hostnames/>")?;
+            } else {
+                writeln!(out, r#"<hostnames><hostname name="{}" type="user"/></hostnames>"#, escape(name))?;
+            }
+
+            writeln!(out, "<ports>")?;
+            let closed = host.ports.iter().filter(|p| p.state == "closed").count();
+            if closed > 0 {
+                let reason = if self.protocol == "udp" { "port-unreach" } else { "conn-refused" };
+                writeln!(
+                    out,
+                    r#"<extraports state="closed" count="{}"><extrareasons reason="{}" count="{}"/></extraports>"#,
+                    closed, reason, closed
+                )?;
+            }
+            let mut ports: Vec<&ScannedPort> = host.ports.iter().filter(|p| p.state != "closed").collect();
+            ports.sort_by_key(|p| p.port);
+            for port in ports {
+                write!(
+                    out,
+                    r#"<port protocol="{}" portid="{}"><state state="{}" reason="{}" reason_ttl="0"/>"#,
+                    self.protocol,
+                    port.port,
+                    port.state,
+                    state_reason(self.protocol, port.state)
+                )?;
+                if let Some(banner) = &port.banner {
+                    write!(out, r#"<script id="banner" output="{}"/>"#, escape(banner))?;
+                }
+                writeln!(out, "</port>")?;
+            }
+            writeln!(out, "</ports>")?;
+            writeln!(out, "</host>")?;
+        }
+
+        let elapsed = (self.end - self.start).num_milliseconds() as f64 / 1000.0;
+        writeln!(out, "<runstats>")?;
+        writeln!(
+            out,
+            r#"<finished time="{}" timestr="{}" elapsed="{:.2}" summary="downzer done; {} IP address(es) scanned in {:.2} seconds" exit="success"/>"#,
+            self.end.timestamp(),
+            self.end.format("%a %b %e %H:%M:%S %Y"),
+            elapsed,
+            self.hosts.len(),
+            elapsed
+        )?;
+        writeln!(out, r#"<hosts up="{}" down="0" total="{}"/>"#, self.hosts.len(), self.hosts.len())?;
+        writeln!(out, "</runstats>")?;
+        writeln!(out, "</nmaprun>")?;
+        out.flush()
+    }
+}
+
+/// Motivo del estado con los nombres que usa nmap.
+fn state_reason(protocol: &str, state: &str) -> &'static str {
+    match (protocol, state) {
+        ("tcp", "open") => "syn-ack",
+        ("udp", "open") => "udp-response",
+        (_, "open|filtered") => "no-response",
+        (_, "filtered") => "no-response",
+        _ => "unknown",
+    }
+}
+
+/// Lista de puertos compactada en rangos (`22,80,8000-8002`), como el atributo `services`.
+fn service_ranges(ports: &[u16]) -> String {
+    let mut ranges: Vec<String> = Vec::new();
+    let mut i = 0;
+    while i < ports.len() {
+        let mut j = i;
+        while j + 1 < ports.len() && ports[j + 1] == ports[j] + 1 {
+            j += 1;
+        }
+        ranges.push(if i == j { ports[i].to_string() } else { format!("{}-{}", ports[i], ports[j]) });
+        i = j + 1;
+    }
+    ranges.join(",")
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn port(port: u16, state: &'static str, banner: Option<&str>) -> ScannedPort {
+        ScannedPort { port, state, banner: banner.map(String::from) }
+    }
+
+    fn written(run: &ScanRun) -> String {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scan.xml");
+        run.write(&path).unwrap();
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn emitted_xml_has_the_nmap_host_and_port_nodes() {
+        let hosts = BTreeMap::from([
+            (
+                "router.lan".to_string(),
+                ScannedHost {
+                    addr: "10.0.0.1".parse().unwrap(),
+                    ports: vec![port(443, "open", None), port(22, "open", Some("SSH-2.0 <x> & \"y\"")), port(23, "closed", None), port(25, "closed", None)],
+                },
+            ),
+            ("::1".to_string(), ScannedHost { addr: "::1".parse().unwrap(), ports: vec![port(8080, "filtered", None)] }),
+        ]);
+        let start = Utc::now();
+        let xml = written(&ScanRun { protocol: "tcp", start, end: start, hosts });
+
+        let options = roxmltree::ParsingOptions { allow_dtd: true, ..Default::default() };
+        let doc = roxmltree::Document::parse_with_options(&xml, options).unwrap();
+        let root = doc.root_element();
+        assert_eq!(root.tag_name().name(), "nmaprun");
+        let info = root.children().find(|n| n.has_tag_name("scaninfo")).unwrap();
+        assert_eq!((info.attribute("type"), info.attribute("services")), (Some("connect"), Some("22-23,25,443,8080")));
+
+        let hosts: Vec<_> = root.children().filter(|n| n.has_tag_name("host")).collect();
+        assert_eq!(hosts.len(), 2);
+        let summary = |host: &roxmltree::Node| -> (String, Vec<(String, String)>) {
+            let addr = host.children().find(|n| n.has_tag_name("address")).unwrap().attribute("addr").unwrap().to_string();
+            let ports = host
+                .descendants()
+                .filter(|n| n.has_tag_name("port"))
+                .map(|p| {
+                    let state = p.children().find(|n| n.has_tag_name("state")).unwrap();
+                    (p.attribute("portid").unwrap().to_string(), state.attribute("state").unwrap().to_string())
+                })
+                .collect();
+            (addr, ports)
+        };
+        assert_eq!(summary(&hosts[0]), ("::1".to_string(), vec![("8080".to_string(), "filtered".to_string())]));
+        let (addr, ports) = summary(&hosts[1]);
+        assert_eq!(addr, "10.0.0.1");
+        assert_eq!(ports, [("22".to_string(), "open".to_string()), ("443".to_string(), "open".to_string())]);
+
+        // Los cerrados van agrupados y el banner, escapado, en su script
+        let extra = hosts[1].descendants().find(|n| n.has_tag_name("extraports")).unwrap();
+        assert_eq!((extra.attribute("state"), extra.attribute("count")), (Some("closed"), Some("2")));
+        let script = hosts[1].descendants().find(|n| n.has_tag_name("script")).unwrap();
+        assert_eq!(script.attribute("output"), Some("SSH-2.0 <x> & \"y\""));
+        let name = hosts[1].descendants().find(|n| n.has_tag_name("hostname")).unwrap();
+        assert_eq!(name.attribute("name"), Some("router.lan"));
+    }
+
+    #[test]
+    fn udp_states_use_the_udp_reasons() {
+        assert_eq!(state_reason("udp", "open"), "udp-response");
+        assert_eq!(state_reason("udp", "open|filtered"), "no-response");
+        assert_eq!(state_reason("tcp", "open"), "syn-ack");
+        assert_eq!(service_ranges(&[1, 2, 3, 5, 7, 8]), "1-3,5,7-8");
+    }
+}
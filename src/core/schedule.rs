@@ -0,0 +1,83 @@
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use cron::Schedule;
+
+/// Interpreta una expresión cron (`--schedule`). Acepta el formato clásico de
+/// 5 campos (minuto hora día mes día-semana) además del de 6/7 campos con segundos.
+pub fn parse_schedule(expr: &str) -> anyhow::Result<Schedule> {
+    let expr = expr.trim();
+    let full = if expr.split_whitespace().count() == 5 {
+        format!("0 {}", expr)
+    } else {
+        expr.to_string()
+    };
+    Schedule::from_str(&full).map_err(|e| anyhow::anyhow!("Invalid --schedule '{}': {}", expr, e))
+}
+
+/// Siguiente disparo estrictamente posterior a `after`.
+pub fn next_fire(schedule: &Schedule, after: DateTime<Local>) -> Option<DateTime<Local>> {
+    schedule.after(&after).next()
+}
+
+/// Espera hasta `when`, comprobando `shutdown` cada medio segundo.
+/// Devuelve `false` si se pidió parar antes de llegar.
+pub async fn sleep_until(when: DateTime<Local>, shutdown: &AtomicBool) -> bool {
+    loop {
+        if shutdown.load(Ordering::SeqCst) {
+            return false;
+        }
+        let remaining = match (when - Local::now()).to_std() {
+            Ok(remaining) if !remaining.is_zero() => remaining,
+            _ => return true,
+        };
+        tokio::time::sleep(remaining.min(Duration::from_millis(500))).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32, hour: u32, min: u32, sec: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 1, day, hour, min, sec).unwrap()
+    }
+
+    #[test]
+    fn next_fire_of_a_five_field_expression() {
+        let schedule = parse_schedule("*/15 * * * *").unwrap();
+        assert_eq!(next_fire(&schedule, at(15, 10, 7, 30)), Some(at(15, 10, 15, 0)));
+        // Estrictamente posterior: en el minuto exacto salta al siguiente
+        assert_eq!(next_fire(&schedule, at(15, 10, 15, 0)), Some(at(15, 10, 30, 0)));
+    }
+
+    #[test]
+    fn next_fire_skips_to_the_next_weekday() {
+        // 2024-01-19 es viernes
+        let schedule = parse_schedule("0 9 * * Mon-Fri").unwrap();
+        assert_eq!(next_fire(&schedule, at(19, 10, 0, 0)), Some(at(22, 9, 0, 0)));
+    }
+
+    #[test]
+    fn six_field_expression_keeps_its_seconds() {
+        let schedule = parse_schedule("30 0 12 * * *").unwrap();
+        assert_eq!(next_fire(&schedule, at(15, 10, 0, 0)), Some(at(15, 12, 0, 30)));
+    }
+
+    #[test]
+    fn invalid_expression_is_an_error() {
+        let err = parse_schedule("every monday").unwrap_err();
+        assert!(err.to_string().starts_with("Invalid --schedule 'every monday'"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn sleep_until_honors_shutdown() {
+        let shutdown = AtomicBool::new(true);
+        assert!(!sleep_until(Local::now() + chrono::Duration::hours(1), &shutdown).await);
+        shutdown.store(false, Ordering::SeqCst);
+        assert!(sleep_until(Local::now(), &shutdown).await);
+    }
+}
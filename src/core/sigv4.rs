@@ -0,0 +1,296 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+/// SigV4 solo deja sin codificar los caracteres "unreserved" de RFC 3986.
+const SIGV4_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'_').remove(b'.').remove(b'~');
+
+const ALGORITHM: &str = "AWS4-HMAC-SHA256";
+
+/// Firma de peticiones con AWS Signature Version 4 (`--sigv4 region:service`).
+#[derive(Clone)]
+pub struct SigV4 {
+    pub region: String,
+    pub service: String,
+    pub access_key: String,
+    secret_key: String,
+    pub session_token: Option<String>,
+}
+
+// A mano para no volcar la clave secreta en los logs de depuración
+impl fmt::Debug for SigV4 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SigV4")
+            .field("region", &self.region)
+            .field("service", &self.service)
+            .field("access_key", &self.access_key)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SigV4 {
+    /// `spec` tiene la forma `region:service` (p.ej. `us-east-1:execute-api`).
+    pub fn new(
+        spec: &str,
+        access_key: String,
+        secret_key: String,
+        session_token: Option<String>,
+    ) -> anyhow::Result<Self> {
+        let (region, service) = spec
+            .split_once(':')
+            .map(|(r, s)| (r.trim(), s.trim()))
+            .filter(|(r, s)| !r.is_empty() && !s.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("Invalid --sigv4 '{}' (expected region:service)", spec))?;
+        Ok(Self {
+            region: region.to_string(),
+            service: service.to_string(),
+            access_key,
+            secret_key,
+            session_token,
+        })
+    }
+
+    /// Cabeceras que hay que añadir a la petición `method url` con cuerpo
+    /// `body` para firmarla en el instante `now`. La URL debe ser ya la final
+    /// (placeholders sustituidos), porque la firma la cubre entera.
+    pub fn sign(
+        &self,
+        method: &str,
+        url: &str,
+        body: &[u8],
+        now: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<(&'static str, String)>> {
+        let url = Url::parse(url)?;
+        let host = url
+            .host_str()
+            .ok_or_else(|| anyhow::anyhow!("Cannot sign a URL without host: {}", url))?;
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", host, port),
+            None => host.to_string(),
+        };
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let payload_hash = hex::encode(Sha256::digest(body));
+
+        let mut added: Vec<(&'static str, String)> = vec![("x-amz-date", amz_date.clone())];
+        // S3 exige el hash del cuerpo como cabecera; el resto de servicios no
+        if self.service == "s3" {
+            added.push(("x-amz-content-sha256", payload_hash.clone()));
+        }
+        if let Some(token) = &self.session_token {
+            added.push(("x-amz-security-token", token.clone()));
+        }
+
+        let mut signed: Vec<(String, String)> = added.iter().map(|(k, v)| (k.to_string(), v.clone())).collect();
+        signed.push(("host".to_string(), host));
+
+        let canonical = canonical_request(method, &url, &signed, &payload_hash, self.service != "s3");
+        let scope = format!("{}/{}/{}/aws4_request", &amz_date[..8], self.region, self.service);
+        let string_to_sign = format!(
+            "{}\n{}\n{}\n{}",
+            ALGORITHM,
+            amz_date,
+            scope,
+            hex::encode(Sha256::digest(canonical.as_bytes()))
+        );
+
+        let signing_key = [self.region.as_str(), self.service.as_str(), "aws4_request"].iter().fold(
+            hmac_sha256(format!("AWS4{}", self.secret_key).as_bytes(), &amz_date[..8]),
+            |key, part| hmac_sha256(&key, part),
+        );
+        let signature = hex::encode(hmac_sha256(&signing_key, &string_to_sign));
+
+        added.push((
+            "authorization",
+            format!(
+                "{} Credential={}/{}, SignedHeaders={}, Signature={}",
+                ALGORITHM,
+                self.access_key,
+                scope,
+                signed_header_names(&signed),
+                signature
+            ),
+        ));
+        Ok(added)
+    }
+}
+
+/// Petición canónica de SigV4. `headers` son las cabeceras firmadas (en
+/// cualquier orden y capitalización). Salvo en S3, cada segmento del path se
+/// codifica de nuevo sobre su forma ya codificada (`double_encode`).
+pub fn canonical_request(
+    method: &str,
+    url: &Url,
+    headers: &[(String, String)],
+    payload_hash: &str,
+    double_encode: bool,
+) -> String {
+    let path: Vec<String> = url
+        .path()
+        .split('/')
+        .map(|segment| {
+            if double_encode {
+                encode(segment)
+            } else {
+                encode(&percent_decode_str(segment).decode_utf8_lossy())
+            }
+        })
+        .collect();
+    let path = match path.join("/") {
+        p if p.is_empty() => "/".to_string(),
+        p => p,
+    };
+
+    let mut query: Vec<(String, String)> = url
+        .query()
+        .unwrap_or("")
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
+            (
+                encode(&percent_decode_str(k).decode_utf8_lossy()),
+                encode(&percent_decode_str(v).decode_utf8_lossy()),
+            )
+        })
+        .collect();
+    query.sort();
+    let query = query.iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>().join("&");
+
+    let mut canonical_headers: Vec<(String, String)> = headers
+        .iter()
+        .map(|(k, v)| (k.to_lowercase(), v.split_whitespace().collect::<Vec<_>>().join(" ")))
+        .collect();
+    canonical_headers.sort();
+    let header_block: String = canonical_headers.iter().map(|(k, v)| format!("{}:{}\n", k, v)).collect();
+
+    format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method.to_uppercase(),
+        path,
+        query,
+        header_block,
+        signed_header_names(headers),
+        payload_hash
+    )
+}
+
+fn signed_header_names(headers: &[(String, String)]) -> String {
+    let mut names: Vec<String> = headers.iter().map(|(k, _)| k.to_lowercase()).collect();
+    names.sort();
+    names.join(";")
+}
+
+fn encode(value: &str) -> String {
+    utf8_percent_encode(value, SIGV4_ENCODE_SET).to_string()
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC acepta claves de cualquier longitud");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    // Credenciales y fecha de la suite de pruebas de SigV4 publicada por AWS
+    const ACCESS_KEY: &str = "AKIDEXAMPLE";
+    const SECRET_KEY: &str = "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY";
+    const EMPTY_HASH: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    fn signer() -> SigV4 {
+        SigV4::new("us-east-1:service", ACCESS_KEY.to_string(), SECRET_KEY.to_string(), None).unwrap()
+    }
+
+    fn test_date() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap()
+    }
+
+    fn suite_headers() -> Vec<(String, String)> {
+        vec![
+            ("Host".to_string(), "example.amazonaws.com".to_string()),
+            ("X-Amz-Date".to_string(), "20150830T123600Z".to_string()),
+        ]
+    }
+
+    fn authorization(method: &str, url: &str, body: &[u8]) -> String {
+        let headers = signer().sign(method, url, body, test_date()).unwrap();
+        headers.into_iter().find(|(k, _)| *k == "authorization").unwrap().1
+    }
+
+    #[test]
+    fn signing_key_matches_the_documented_example() {
+        let date_key = hmac_sha256(format!("AWS4{}", SECRET_KEY).as_bytes(), "20120215");
+        let key = ["us-east-1", "iam", "aws4_request"].iter().fold(date_key, |key, part| hmac_sha256(&key, part));
+        assert_eq!(hex::encode(key), "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d");
+    }
+
+    #[test]
+    fn get_vanilla_canonical_request() {
+        let url = Url::parse("https://example.amazonaws.com/").unwrap();
+        assert_eq!(
+            canonical_request("GET", &url, &suite_headers(), EMPTY_HASH, true),
+            format!("GET\n/\n\nhost:example.amazonaws.com\nx-amz-date:20150830T123600Z\n\nhost;x-amz-date\n{}", EMPTY_HASH)
+        );
+    }
+
+    #[test]
+    fn query_parameters_are_sorted_and_encoded() {
+        let url = Url::parse("https://example.amazonaws.com/?Param2=value2&Param1=value1").unwrap();
+        let canonical = canonical_request("GET", &url, &suite_headers(), EMPTY_HASH, true);
+        assert_eq!(canonical.lines().nth(2), Some("Param1=value1&Param2=value2"));
+        let url = Url::parse("https://example.amazonaws.com/?a=b%20c&k=%E1%88%B4~").unwrap();
+        let canonical = canonical_request("GET", &url, &suite_headers(), EMPTY_HASH, true);
+        assert_eq!(canonical.lines().nth(2), Some("a=b%20c&k=%E1%88%B4~"));
+    }
+
+    #[test]
+    fn header_values_are_trimmed_and_names_lowercased() {
+        let url = Url::parse("https://example.amazonaws.com/").unwrap();
+        let mut headers = suite_headers();
+        headers.push(("My-Header1".to_string(), "  value1   with  spaces ".to_string()));
+        let canonical = canonical_request("GET", &url, &headers, EMPTY_HASH, true);
+        assert!(canonical.contains("\nmy-header1:value1 with spaces\n"), "{}", canonical);
+        assert!(canonical.contains("\nhost;my-header1;x-amz-date\n"), "{}", canonical);
+    }
+
+    #[test]
+    fn signatures_match_the_aws_test_suite() {
+        let credential = "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, SignedHeaders=host;x-amz-date";
+        let cases = [
+            ("GET", "https://example.amazonaws.com/", "5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"),
+            (
+                "GET",
+                "https://example.amazonaws.com/?Param2=value2&Param1=value1",
+                "b97d918cfa904a5beff61c982a1b6f458b799221646efd99d3219ec94cdf2500",
+            ),
+            ("POST", "https://example.amazonaws.com/", "5da7c1a2acd57cee7505fc6676e4e544621c30862966e37dddb68e92efbe5d6b"),
+        ];
+        for (method, url, signature) in cases {
+            assert_eq!(authorization(method, url, b""), format!("{}, Signature={}", credential, signature), "{} {}", method, url);
+        }
+    }
+
+    #[test]
+    fn session_token_is_signed() {
+        let signer =
+            SigV4::new("us-east-1:service", ACCESS_KEY.to_string(), SECRET_KEY.to_string(), Some("token".to_string())).unwrap();
+        let headers = signer.sign("GET", "https://example.amazonaws.com/", b"", test_date()).unwrap();
+        assert!(headers.contains(&("x-amz-security-token", "token".to_string())));
+        let auth = &headers.iter().find(|(k, _)| *k == "authorization").unwrap().1;
+        assert!(auth.contains("SignedHeaders=host;x-amz-date;x-amz-security-token,"), "{}", auth);
+    }
+
+    #[test]
+    fn invalid_specs_and_urls_are_errors() {
+        assert!(SigV4::new("us-east-1", String::new(), String::new(), None).is_err());
+        assert!(SigV4::new(":s3", String::new(), String::new(), None).is_err());
+        assert!(signer().sign("GET", "not a url", b"", test_date()).is_err());
+    }
+}
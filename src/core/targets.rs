@@ -0,0 +1,231 @@
+use regex::Regex;
+use std::collections::HashSet;
+
+/// Objetivo concreto de una tarea: la URL ya sustituida y la combinación de
+/// palabras que la produjo.
+#[derive(Debug, Clone, Default)]
+pub struct Target {
+    pub url: String,
+    pub words: Vec<String>,
+    /// Posición de cada palabra en su lista de origen (vacío si no se conoce)
+    pub indices: Vec<usize>,
+}
+
+/// Filtros sobre las URLs generadas (`--exclude`, `--exclude-regex`, `--include`).
+#[derive(Debug, Clone, Default)]
+pub struct UrlFilters {
+    exclude: HashSet<String>,
+    exclude_regex: Option<Regex>,
+    include: Vec<String>,
+}
+
+impl UrlFilters {
+    /// `exclude`: URLs a descartar. `include`: si no está vacío, solo se conservan
+    /// las URLs que contienen alguno de estos fragmentos.
+    pub fn new(exclude: Vec<String>, include: Vec<String>) -> Self {
+        Self {
+            exclude: exclude.into_iter().collect(),
+            exclude_regex: None,
+            include,
+        }
+    }
+
+    /// Descarta además las URLs que encajan con `pattern` (`--exclude-regex`).
+    pub fn with_exclude_regex(mut self, pattern: Option<&str>) -> anyhow::Result<Self> {
+        if let Some(pattern) = pattern {
+            let re = Regex::new(pattern)
+                .map_err(|e| anyhow::anyhow!("Invalid --exclude-regex '{}': {}", pattern, e))?;
+            self.exclude_regex = Some(re);
+        }
+        Ok(self)
+    }
+
+    pub fn keeps(&self, url: &str) -> bool {
+        !self.exclude.contains(url)
+            && !self.exclude_regex.as_ref().is_some_and(|re| re.is_match(url))
+            && (self.include.is_empty() || self.include.iter().any(|s| url.contains(s.as_str())))
+    }
+}
+
+/// Orden de las repeticiones de `--repeat`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepeatMode {
+    /// Pasadas completas sobre todos los objetivos: a, b, a, b
+    Interleaved,
+    /// Cada objetivo seguido de sus repeticiones: a, a, b, b
+    Grouped,
+}
+
+/// Flujo de objetivos que consumen los modos, generado bajo demanda.
+pub struct Targets {
+    iter: Box<dyn Iterator<Item = Target> + Send>,
+    total: usize,
+}
+
+impl Targets {
+    /// `total` es el número esperado de objetivos; con exclusiones es una cota superior.
+    pub fn new(iter: impl Iterator<Item = Target> + Send + 'static, total: usize) -> Self {
+        Self { iter: Box::new(iter), total }
+    }
+
+    pub fn from_urls(urls: Vec<String>) -> Self {
+        let total = urls.len();
+        Self::new(urls.into_iter().map(|url| Target { url, ..Default::default() }), total)
+    }
+
+    /// Descarta los objetivos cuya clave (derivada de la URL) ya apareció antes.
+    pub fn dedup_by(self, key: impl Fn(&str) -> String + Send + 'static) -> Self {
+        let total = self.total;
+        let mut seen = HashSet::new();
+        Self::new(self.filter(move |target| seen.insert(key(&target.url))), total)
+    }
+
+    /// Omite las URLs de `known` (`--only-new`). `total` queda como cota superior.
+    pub fn skip_urls(self, known: HashSet<String>) -> Self {
+        let total = self.total;
+        Self::new(self.filter(move |target| !known.contains(&target.url)), total)
+    }
+
+    /// Emite cada objetivo `times` veces (`--repeat`). En modo intercalado la
+    /// primera pasada se va guardando y las siguientes se reproducen desde memoria.
+    pub fn repeat(self, times: usize, mode: RepeatMode) -> Self {
+        if times <= 1 {
+            return self;
+        }
+        let total = self.total.saturating_mul(times);
+        match mode {
+            RepeatMode::Grouped => {
+                Self::new(self.flat_map(move |target| std::iter::repeat_n(target, times)), total)
+            }
+            RepeatMode::Interleaved => {
+                let mut source = Some(self);
+                let mut recorded: Vec<Target> = Vec::new();
+                let mut pass = 1;
+                let mut pos = 0;
+                let iter = std::iter::from_fn(move || {
+                    if let Some(src) = &mut source {
+                        if let Some(target) = src.next() {
+                            recorded.push(target.clone());
+                            return Some(target);
+                        }
+                        source = None;
+                    }
+                    while pass < times && !recorded.is_empty() {
+                        if let Some(target) = recorded.get(pos) {
+                            pos += 1;
+                            return Some(target.clone());
+                        }
+                        pass += 1;
+                        pos = 0;
+                    }
+                    None
+                });
+                Self::new(iter, total)
+            }
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.total
+    }
+}
+
+impl Iterator for Targets {
+    type Item = Target;
+
+    fn next(&mut self) -> Option<Target> {
+        self.iter.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kept(filters: &UrlFilters, urls: &[&str]) -> Vec<String> {
+        urls.iter().filter(|url| filters.keeps(url)).map(|url| url.to_string()).collect()
+    }
+
+    const URLS: [&str; 4] = ["http://h/api/users", "http://h/api/admin", "http://h/static/app.js", "http://h/admin"];
+
+    #[test]
+    fn empty_include_keeps_everything() {
+        assert_eq!(kept(&UrlFilters::default(), &URLS), URLS);
+    }
+
+    #[test]
+    fn include_keeps_urls_with_any_fragment() {
+        let filters = UrlFilters::new(Vec::new(), vec!["/api/".into(), ".js".into()]);
+        assert_eq!(kept(&filters, &URLS), ["http://h/api/users", "http://h/api/admin", "http://h/static/app.js"]);
+    }
+
+    #[test]
+    fn include_and_exclude_both_apply() {
+        let filters = UrlFilters::new(vec!["http://h/api/admin".into()], vec!["/api/".into()]);
+        assert_eq!(kept(&filters, &URLS), ["http://h/api/users"]);
+    }
+
+    #[test]
+    fn exclude_regex_removes_image_extensions() {
+        let filters = UrlFilters::new(Vec::new(), Vec::new())
+            .with_exclude_regex(Some(r"(?i)\.(png|jpe?g|gif)$"))
+            .unwrap();
+        let urls = ["http://h/logo.png", "http://h/a.JPG", "http://h/index.html", "http://h/png/list"];
+        assert_eq!(kept(&filters, &urls), ["http://h/index.html", "http://h/png/list"]);
+    }
+
+    #[test]
+    fn exclude_regex_combines_with_substring_exclude() {
+        let filters = UrlFilters::new(vec!["http://h/index.html".into()], Vec::new())
+            .with_exclude_regex(Some(r"\.png$"))
+            .unwrap();
+        assert_eq!(kept(&filters, &["http://h/logo.png", "http://h/index.html", "http://h/app.js"]), ["http://h/app.js"]);
+    }
+
+    #[test]
+    fn invalid_exclude_regex_is_an_error() {
+        let err = UrlFilters::default().with_exclude_regex(Some("(")).unwrap_err();
+        assert!(err.to_string().starts_with("Invalid --exclude-regex '('"), "{}", err);
+    }
+
+    fn repeated(mode: RepeatMode) -> (usize, Vec<String>) {
+        let targets = Targets::from_urls(vec!["a".to_string(), "b".to_string()]).repeat(3, mode);
+        (targets.total(), targets.map(|t| t.url).collect())
+    }
+
+    #[test]
+    fn repeat_interleaves_full_passes() {
+        assert_eq!(repeated(RepeatMode::Interleaved), (6, vec!["a", "b", "a", "b", "a", "b"].into_iter().map(String::from).collect()));
+    }
+
+    #[test]
+    fn repeat_grouped_keeps_copies_together() {
+        assert_eq!(repeated(RepeatMode::Grouped), (6, vec!["a", "a", "a", "b", "b", "b"].into_iter().map(String::from).collect()));
+    }
+
+    #[tokio::test]
+    async fn repeat_three_makes_three_requests_per_url() {
+        use crate::testutil::{self, MockServer, Response};
+
+        let server = MockServer::start(|_| Response::new(200)).await;
+        let dir = tempfile::tempdir().unwrap();
+        for mode in [RepeatMode::Interleaved, RepeatMode::Grouped] {
+            let before = server.requests().len();
+            let targets = Targets::from_urls(vec![server.url("/a"), server.url("/b")]).repeat(3, mode);
+            let result = testutil::run_targets(testutil::mode_config("webrequest", dir.path()), targets).await;
+            assert_eq!((result.total, result.successful), (6, 6));
+            let paths = &server.paths()[before..];
+            assert_eq!(paths.iter().filter(|p| *p == "/a").count(), 3);
+            assert_eq!(paths.iter().filter(|p| *p == "/b").count(), 3);
+        }
+    }
+
+    #[test]
+    fn known_urls_are_skipped() {
+        let urls = URLS.iter().map(|u| u.to_string()).collect();
+        let known = HashSet::from(["http://h/api/admin".to_string(), "http://elsewhere/".to_string()]);
+        let targets = Targets::from_urls(urls).skip_urls(known);
+        assert_eq!(targets.total(), 4);
+        assert_eq!(targets.map(|t| t.url).collect::<Vec<_>>(), ["http://h/api/users", "http://h/static/app.js", "http://h/admin"]);
+    }
+}
@@ -1,6 +1,11 @@
 use serde::{Serialize, Deserialize};
+use std::fmt;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
 use std::time::Instant;
 
+use crate::core::live::LiveStats;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TaskStatus {
     Queued,
@@ -11,16 +16,24 @@ pub enum TaskStatus {
     Stopped, // añadido para IPC explícito
 }
 
+impl fmt::Display for TaskStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            TaskStatus::Queued => "Queued",
+            TaskStatus::Running => "Running",
+            TaskStatus::Paused => "Paused",
+            TaskStatus::Completed => "Completed",
+            TaskStatus::Failed => "Failed",
+            TaskStatus::Stopped => "Stopped",
+        };
+        f.write_str(name)
+    }
+}
+
 impl TaskStatus {
-    pub fn to_string(&self) -> String {
-        match self {
-            TaskStatus::Queued => "Queued".to_string(),
-            TaskStatus::Running => "Running".to_string(),
-            TaskStatus::Paused => "Paused".to_string(),
-            TaskStatus::Completed => "Completed".to_string(),
-            TaskStatus::Failed => "Failed".to_string(),
-            TaskStatus::Stopped => "Stopped".to_string(),
-        }
+    /// Completed, Failed y Stopped: la tarea ya no vuelve a ejecutarse.
+    pub fn is_terminal(self) -> bool {
+        matches!(self, TaskStatus::Completed | TaskStatus::Failed | TaskStatus::Stopped)
     }
 
     pub fn from_string(s: &str) -> Self {
@@ -44,4 +57,17 @@ pub struct TaskInfo {
     pub completed: usize,
     pub status: TaskStatus,
     pub start_time: Instant,
+    /// Contadores en vivo del modo, si los tiene (ver `progress`)
+    pub live: Option<Arc<LiveStats>>,
+}
+
+impl TaskInfo {
+    /// Elementos procesados: los de `LiveStats`, que llevan todos los modos,
+    /// o `completed` si la tarea no tiene contadores en vivo.
+    pub fn progress(&self) -> usize {
+        match &self.live {
+            Some(live) => live.completed.load(Ordering::Relaxed),
+            None => self.completed,
+        }
+    }
 }
\ No newline at end of file
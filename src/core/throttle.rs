@@ -0,0 +1,207 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::core::downzer::parse_size;
+
+/// Límite de ancho de banda de `--rate-limit`, compartido por todas las
+/// descargas de la tarea (token bucket con capacidad de un segundo).
+///
+/// Cada trozo recibido consume sus bytes del cubo; si no alcanza, el saldo
+/// queda en negativo y la descarga duerme lo que tarda en recuperarse, así
+/// que un trozo mayor que el cubo también pasa, solo que espera más.
+#[derive(Debug)]
+pub struct RateLimiter {
+    bytes_per_sec: u64,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(bytes_per_sec: u64) -> Self {
+        let bytes_per_sec = bytes_per_sec.max(1);
+        Self {
+            bytes_per_sec,
+            bucket: Mutex::new(Bucket { tokens: bytes_per_sec as f64, last: Instant::now() }),
+        }
+    }
+
+    /// Descuenta `bytes` y espera si el cubo ha quedado en negativo.
+    pub async fn consume(&self, bytes: usize) {
+        let wait = {
+            let rate = self.bytes_per_sec as f64;
+            let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+            let refill = now.duration_since(bucket.last).as_secs_f64() * rate;
+            bucket.tokens = (bucket.tokens + refill).min(rate) - bytes as f64;
+            bucket.last = now;
+            (bucket.tokens < 0.0).then(|| Duration::from_secs_f64(-bucket.tokens / rate))
+        };
+        if let Some(wait) = wait {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Interpreta `--rate-limit`: un tamaño por segundo (`500K`, `2M`, `1.5MB/s`).
+pub fn parse_rate(spec: &str) -> anyhow::Result<u64> {
+    let size = spec.trim();
+    let size = size.strip_suffix("/s").unwrap_or(size);
+    let rate = parse_size(size).map_err(|e| anyhow::anyhow!("Invalid --rate-limit '{}': {}", spec, e))?;
+    if rate == 0 {
+        anyhow::bail!("Invalid --rate-limit '{}': must be greater than zero", spec);
+    }
+    Ok(rate)
+}
+
+/// Pausa entre peticiones de `--delay`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delay {
+    /// `<ms>`: milisegundos entre el inicio de una petición y el de la siguiente
+    FixedMs(u64),
+    /// `<sec>x<N>`: pausa de `pause_secs` segundos cada `count` peticiones
+    EveryN { pause_secs: u64, count: usize },
+}
+
+/// Interpreta `--delay`: `250` o `250ms` (ms entre peticiones) o `5x100` (5 s de
+/// pausa cada 100 peticiones).
+pub fn parse_delay(spec: &str) -> anyhow::Result<Delay> {
+    let invalid = |reason: &str| anyhow::anyhow!("Invalid --delay '{}': {}", spec, reason);
+    let spec_trim = spec.trim();
+    match spec_trim.split_once(['x', 'X']) {
+        Some((secs, count)) => {
+            let pause_secs: u64 = secs.trim().parse().map_err(|_| invalid("expected <sec>x<N>, e.g. 5x100"))?;
+            let count: usize = count.trim().parse().map_err(|_| invalid("expected <sec>x<N>, e.g. 5x100"))?;
+            if pause_secs == 0 || count == 0 {
+                return Err(invalid("seconds and N must be greater than zero"));
+            }
+            Ok(Delay::EveryN { pause_secs, count })
+        }
+        None => {
+            let ms = spec_trim.strip_suffix("ms").unwrap_or(spec_trim).trim();
+            let ms: u64 = ms.parse().map_err(|_| invalid("expected milliseconds or <sec>x<N>"))?;
+            if ms == 0 {
+                return Err(invalid("must be greater than zero"));
+            }
+            Ok(Delay::FixedMs(ms))
+        }
+    }
+}
+
+/// Ritmo de `--delay`, compartido por todas las peticiones de la tarea. Un
+/// solo reloj (cuándo puede salir la siguiente) y un contador tras un mutex
+/// que se mantiene durante la espera, así que con concurrencia las
+/// peticiones siguen saliendo de una en una al ritmo pedido.
+#[derive(Debug)]
+pub struct Pacer {
+    delay: Delay,
+    shutdown: Arc<AtomicBool>,
+    state: tokio::sync::Mutex<PacerState>,
+}
+
+#[derive(Debug, Default)]
+struct PacerState {
+    /// Inicio permitido de la siguiente petición (`None`: ya)
+    next: Option<Instant>,
+    started: usize,
+}
+
+impl Pacer {
+    pub fn new(delay: Delay, shutdown: Arc<AtomicBool>) -> Self {
+        Self { delay, shutdown, state: tokio::sync::Mutex::new(PacerState::default()) }
+    }
+
+    /// Espera el turno de la siguiente petición. Se deja de esperar si se
+    /// pide parar, que el llamador comprueba después con [`Pacer::stopped`].
+    pub async fn wait(&self) {
+        let mut state = self.state.lock().await;
+        if let Some(next) = state.next {
+            // A trozos, para atender Ctrl+C durante las pausas largas
+            while !self.shutdown.load(Ordering::SeqCst) {
+                let remaining = next.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    break;
+                }
+                tokio::time::sleep(remaining.min(Duration::from_millis(500))).await;
+            }
+        }
+        let now = Instant::now();
+        state.started += 1;
+        state.next = match self.delay {
+            Delay::FixedMs(ms) => Some(now + Duration::from_millis(ms)),
+            Delay::EveryN { pause_secs, count } => {
+                (state.started % count == 0).then(|| now + Duration::from_secs(pause_secs))
+            }
+        };
+    }
+
+    /// Si se ha pedido parar (`shutdown`) mientras se esperaba el turno.
+    pub fn stopped(&self) -> bool {
+        self.shutdown.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_accepts_size_suffixes() {
+        assert_eq!(parse_rate("500K").unwrap(), 500 * 1024);
+        assert_eq!(parse_rate("2M").unwrap(), 2 << 20);
+        assert_eq!(parse_rate("1.5MB/s").unwrap(), 3 << 19);
+        assert!(parse_rate("0").is_err());
+        assert!(parse_rate("fast").is_err());
+    }
+
+    #[test]
+    fn delay_accepts_ms_and_every_n() {
+        assert_eq!(parse_delay("250").unwrap(), Delay::FixedMs(250));
+        assert_eq!(parse_delay("250ms").unwrap(), Delay::FixedMs(250));
+        assert_eq!(parse_delay("5x100").unwrap(), Delay::EveryN { pause_secs: 5, count: 100 });
+        assert_eq!(parse_delay("5X100").unwrap(), Delay::EveryN { pause_secs: 5, count: 100 });
+    }
+
+    #[test]
+    fn delay_rejects_zero_and_garbage() {
+        for spec in ["0", "0x5", "5x0", "abc"] {
+            assert!(parse_delay(spec).is_err(), "{}", spec);
+        }
+    }
+
+    #[tokio::test]
+    async fn limiter_paces_consumption_to_the_rate() {
+        let limiter = RateLimiter::new(100_000);
+        let started = Instant::now();
+        // El primer segundo sale del cubo lleno; los 150 KB restantes esperan 1,5 s
+        for _ in 0..25 {
+            limiter.consume(10_000).await;
+        }
+        let elapsed = started.elapsed().as_secs_f64();
+        assert!((1.4..1.8).contains(&elapsed), "{:.2}s", elapsed);
+    }
+
+    #[tokio::test]
+    async fn concurrent_downloads_share_the_cap() {
+        use crate::testutil::{self, MockServer, Response};
+
+        const FILE: usize = 100 * 1024;
+        let server = MockServer::start(|_| Response::new(200).body(vec![0; FILE])).await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testutil::mode_config("download", dir.path());
+        config.rate_limit = Some(parse_rate("100K").unwrap());
+        let urls = (0..4).map(|i| server.url(&format!("/f{}.bin", i))).collect();
+        let started = Instant::now();
+        let result = testutil::run(config, urls).await;
+        let elapsed = started.elapsed().as_secs_f64();
+        assert_eq!(result.successful, 4);
+        // 400 KB a 100 KB/s con un segundo de ráfaga inicial: unos 3 s
+        let throughput = (3 * FILE) as f64 / elapsed;
+        assert!((80_000.0..=112_000.0).contains(&throughput), "{:.0} B/s en {:.2}s", throughput, elapsed);
+    }
+}
@@ -0,0 +1,272 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Context;
+use colored::*;
+use interprocess::local_socket::{GenericFilePath, ListenerOptions, ToFsName};
+
+use crate::audio;
+use crate::core::db::Database;
+use crate::core::downzer::Config;
+use crate::core::Downzer;
+use crate::ipc::{self, IpcCommand};
+
+/// Resultado de una comprobación de `downzer doctor`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    /// No impide ejecutar tareas, pero conviene saberlo
+    Warn,
+    Fail,
+    /// No aplica con la configuración actual
+    Skip,
+}
+
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+impl CheckResult {
+    fn new(name: &'static str, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self { name, status, detail: detail.into() }
+    }
+
+    fn from_result(name: &'static str, result: anyhow::Result<String>) -> Self {
+        match result {
+            Ok(detail) => Self::new(name, CheckStatus::Pass, detail),
+            Err(e) => Self::new(name, CheckStatus::Fail, format!("{:#}", e)),
+        }
+    }
+}
+
+/// Qué comprobar: lo que usaría una ejecución con las mismas opciones.
+pub struct DoctorOptions<'a> {
+    pub config_path: Option<&'a Path>,
+    pub outdir: &'a Path,
+    pub proxy: Option<&'a str>,
+    pub timeout: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct DoctorReport {
+    pub checks: Vec<CheckResult>,
+}
+
+impl DoctorReport {
+    /// `true` si ninguna comprobación ha fallado (los avisos no cuentan).
+    pub fn ok(&self) -> bool {
+        self.checks.iter().all(|c| c.status != CheckStatus::Fail)
+    }
+
+    pub fn print(&self) {
+        for check in &self.checks {
+            let label = match check.status {
+                CheckStatus::Pass => "[PASS]".green(),
+                CheckStatus::Warn => "[WARN]".yellow(),
+                CheckStatus::Fail => "[FAIL]".red(),
+                CheckStatus::Skip => "[SKIP]".dimmed(),
+            };
+            println!("{} {:<12} {}", label, check.name, check.detail);
+        }
+        let failed = self.checks.iter().filter(|c| c.status == CheckStatus::Fail).count();
+        if failed == 0 {
+            println!("\n{} All checks passed", "✓".green());
+        } else {
+            println!("\n{} {} check(s) failed", "✗".red(), failed);
+        }
+    }
+}
+
+/// Ejecuta todas las comprobaciones en orden.
+pub async fn run(options: &DoctorOptions<'_>) -> DoctorReport {
+    let (config_check, config) = check_config(options.config_path);
+    let checks = vec![
+        config_check,
+        check_database(),
+        check_ipc(),
+        check_outdir(options.outdir),
+        check_proxy(options.proxy, options.timeout).await,
+        check_audio(config.as_ref()),
+    ];
+    DoctorReport { checks }
+}
+
+/// Carga la configuración de forma estricta: a diferencia de `load_config`,
+/// un archivo inválido es un fallo y no un silencioso "valores por defecto".
+pub fn check_config(path: Option<&Path>) -> (CheckResult, Option<Config>) {
+    let path = path.map(Path::to_path_buf).unwrap_or_else(Downzer::config_path);
+    if !path.exists() {
+        return (
+            CheckResult::new("config", CheckStatus::Pass, format!("{} not found, using defaults", path.display())),
+            Some(Config::default()),
+        );
+    }
+    match Downzer::load_config_file(&path) {
+        Ok(config) => (CheckResult::new("config", CheckStatus::Pass, path.display().to_string()), Some(config)),
+        Err(e) => (CheckResult::new("config", CheckStatus::Fail, format!("{:#}", e)), None),
+    }
+}
+
+pub fn check_database() -> CheckResult {
+    CheckResult::from_result("database", (|| {
+        let db = Database::new()?;
+        db.check_writable()?;
+        Ok(format!("{} (read/write)", Database::db_path().display()))
+    })())
+}
+
+/// Con una instancia en marcha basta con que responda; si no, se crea un
+/// socket de prueba junto al real sin tocar este.
+pub fn check_ipc() -> CheckResult {
+    match ipc::send_command(&IpcCommand::List) {
+        Ok(ipc::IpcResponse::Error(e)) => {
+            return CheckResult::new("ipc", CheckStatus::Warn, format!("running instance rejects commands: {}", e));
+        }
+        Ok(_) => {
+            return CheckResult::new(
+                "ipc",
+                CheckStatus::Pass,
+                format!("running instance answers on {}", ipc::get_socket_path().display()),
+            );
+        }
+        Err(_) => {}
+    }
+
+    let socket_path = ipc::get_socket_path();
+    let probe = socket_path.with_file_name(format!("downzer_doctor_{}.sock", std::process::id()));
+    let result = (|| {
+        let name = probe.to_string_lossy().to_string().to_fs_name::<GenericFilePath>()?;
+        let listener = ListenerOptions::new().name(name).create_sync()?;
+        drop(listener);
+        Ok(format!("can create sockets in {}", probe.parent().unwrap_or(Path::new(".")).display()))
+    })();
+    fs::remove_file(&probe).ok();
+    CheckResult::from_result("ipc", result)
+}
+
+pub fn check_outdir(outdir: &Path) -> CheckResult {
+    CheckResult::from_result("output dir", (|| {
+        let probe: PathBuf = outdir.join(format!(".downzer_doctor_{}", std::process::id()));
+        fs::create_dir_all(outdir)
+            .and_then(|_| fs::write(&probe, b"ok"))
+            .with_context(|| format!("{} is not writable", outdir.display()))?;
+        fs::remove_file(&probe)?;
+        Ok(format!("{} is writable", outdir.display()))
+    })())
+}
+
+/// Comprueba que se puede abrir una conexión TCP con el proxy configurado.
+pub async fn check_proxy(proxy: Option<&str>, timeout: Duration) -> CheckResult {
+    let Some(proxy) = proxy else {
+        return CheckResult::new("proxy", CheckStatus::Skip, "no --proxy configured");
+    };
+    let result = async {
+        let url = url::Url::parse(proxy)?;
+        let host = url.host_str().ok_or_else(|| anyhow::anyhow!("proxy URL has no host"))?;
+        let port = url.port_or_known_default().unwrap_or(1080);
+        tokio::time::timeout(timeout, tokio::net::TcpStream::connect((host, port)))
+            .await
+            .map_err(|_| anyhow::anyhow!("timed out connecting to {}:{}", host, port))?
+            .with_context(|| format!("cannot connect to {}:{}", host, port))?;
+        Ok(format!("{}:{} reachable", host, port))
+    }
+    .await;
+    CheckResult::from_result("proxy", result)
+}
+
+/// Un fallo de audio no impide descargar: se informa como aviso.
+pub fn check_audio(config: Option<&Config>) -> CheckResult {
+    let Some(config) = config else {
+        return CheckResult::new("audio", CheckStatus::Skip, "config could not be loaded");
+    };
+    if !config.sound_enabled {
+        return CheckResult::new("audio", CheckStatus::Skip, "sounds disabled");
+    }
+    let check_sound = |name: &str| {
+        let sound = Path::new(name);
+        if sound.exists() {
+            audio::sound::validate_custom_sound(sound)
+        } else {
+            audio::sound::SoundType::from_name(name).map(|_| ())
+        }
+    };
+    let mut valid = check_sound(&config.sound_type);
+    if config.sound_on_failure {
+        valid = valid.and_then(|_| check_sound(&config.failure_sound_type).context("failure sound"));
+    }
+    match valid.and_then(|_| audio::sound::check_backend()) {
+        Ok(backend) => CheckResult::new("audio", CheckStatus::Pass, format!("{} ({})", backend, config.sound_type)),
+        Err(e) => CheckResult::new("audio", CheckStatus::Warn, format!("{:#}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_check_loads_or_reports_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.json");
+        let (check, config) = check_config(Some(&missing));
+        assert_eq!((check.name, check.status), ("config", CheckStatus::Pass));
+        assert!(check.detail.contains("using defaults"), "{}", check.detail);
+        assert!(config.is_some());
+
+        let broken = dir.path().join("broken.json");
+        fs::write(&broken, "{ not json").unwrap();
+        let (check, config) = check_config(Some(&broken));
+        assert_eq!(check.status, CheckStatus::Fail);
+        assert!(config.is_none());
+    }
+
+    #[test]
+    fn outdir_check_needs_a_writable_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let check = check_outdir(&dir.path().join("new/sub"));
+        assert_eq!((check.name, check.status), ("output dir", CheckStatus::Pass));
+        assert!(fs::read_dir(dir.path().join("new/sub")).unwrap().next().is_none(), "la sonda debe borrarse");
+
+        let file = dir.path().join("file");
+        fs::write(&file, "").unwrap();
+        assert_eq!(check_outdir(&file).status, CheckStatus::Fail);
+    }
+
+    #[tokio::test]
+    async fn proxy_check_connects_to_the_proxy() {
+        let timeout = Duration::from_secs(2);
+        assert_eq!(check_proxy(None, timeout).await.status, CheckStatus::Skip);
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let check = check_proxy(Some(&format!("http://127.0.0.1:{}", port)), timeout).await;
+        assert_eq!(check.status, CheckStatus::Pass, "{}", check.detail);
+        drop(listener);
+        assert_eq!(check_proxy(Some(&format!("http://127.0.0.1:{}", port)), timeout).await.status, CheckStatus::Fail);
+        assert_eq!(check_proxy(Some("not a url"), timeout).await.status, CheckStatus::Fail);
+    }
+
+    #[test]
+    fn audio_check_is_skipped_without_sounds() {
+        assert_eq!(check_audio(None).status, CheckStatus::Skip);
+        let config = Config { sound_enabled: false, ..Config::default() };
+        assert_eq!(check_audio(Some(&config)).status, CheckStatus::Skip);
+        // Un sonido desconocido es un aviso, no un fallo
+        let config = Config { sound_enabled: true, sound_type: "nope".to_string(), ..Config::default() };
+        assert_eq!(check_audio(Some(&config)).status, CheckStatus::Warn);
+    }
+
+    #[test]
+    fn report_fails_only_on_failed_checks() {
+        let report = |statuses: &[CheckStatus]| DoctorReport {
+            checks: statuses.iter().map(|s| CheckResult::new("x", *s, "")).collect(),
+        };
+        assert!(report(&[CheckStatus::Pass, CheckStatus::Warn, CheckStatus::Skip]).ok());
+        assert!(!report(&[CheckStatus::Pass, CheckStatus::Fail]).ok());
+        assert!(report(&[]).ok());
+    }
+}
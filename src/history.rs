@@ -0,0 +1,167 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::Context;
+use colored::*;
+
+use crate::core::db::{Database, TaskRecord};
+use crate::core::report::csv_field;
+use crate::core::task::TaskStatus;
+
+/// Estados que cuentan como historial cuando no se pide uno concreto.
+const FINISHED: [TaskStatus; 3] = [TaskStatus::Completed, TaskStatus::Failed, TaskStatus::Stopped];
+
+/// `downzer history`: las tareas terminadas más recientes de la base de
+/// datos, o las de un estado concreto con `status`.
+pub fn show(limit: usize, status: Option<&str>, json: bool) -> anyhow::Result<()> {
+    let statuses = match status {
+        Some(name) => vec![parse_status(name)?],
+        None => FINISHED.to_vec(),
+    };
+    let tasks = Database::new()?.get_recent_tasks(limit, &statuses)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&tasks)?);
+        return Ok(());
+    }
+    if tasks.is_empty() {
+        println!("No tasks in history");
+        return Ok(());
+    }
+    println!("{}", "ID\tStatus\tDone\tCreated\t\t\tUpdated\t\t\tURL".cyan());
+    for task in &tasks {
+        print_row(task);
+    }
+    Ok(())
+}
+
+/// `downzer history --clear`: borra las tareas terminadas, o todas con `all`
+/// (también las que figuran en marcha, p.ej. de un proceso que murió).
+pub fn clear(all: bool) -> anyhow::Result<()> {
+    let deleted = Database::new()?.clear_tasks(all)?;
+    println!("{} Removed {} task(s) from history", "✓".green(), deleted);
+    Ok(())
+}
+
+/// `downzer history --export`: todas las tareas, de cualquier estado, como CSV.
+pub fn export(path: &Path) -> anyhow::Result<()> {
+    let tasks = Database::new()?.get_recent_tasks(usize::MAX, &[])?;
+    write_csv(path, &tasks).with_context(|| format!("Cannot write {}", path.display()))?;
+    println!("{} Exported {} task(s) to {}", "✓".green(), tasks.len(), path.display());
+    Ok(())
+}
+
+/// CSV con las columnas de `TaskRecord` en su orden.
+fn write_csv(path: &Path, tasks: &[TaskRecord]) -> std::io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "id,url_template,total,completed,status,pid,created_at,updated_at")?;
+    for task in tasks {
+        writeln!(
+            out,
+            "{},{},{},{},{},{},{},{}",
+            task.id,
+            csv_field(&task.url_template),
+            task.total,
+            task.completed,
+            task.status,
+            task.pid.map(|pid| pid.to_string()).unwrap_or_default(),
+            csv_field(&task.created_at),
+            csv_field(&task.updated_at)
+        )?;
+    }
+    out.flush()
+}
+
+/// Estado por su nombre, sin distinguir mayúsculas (`--status failed`).
+fn parse_status(name: &str) -> anyhow::Result<TaskStatus> {
+    [
+        TaskStatus::Queued,
+        TaskStatus::Running,
+        TaskStatus::Paused,
+        TaskStatus::Completed,
+        TaskStatus::Failed,
+        TaskStatus::Stopped,
+    ]
+    .into_iter()
+    .find(|status| status.to_string().eq_ignore_ascii_case(name))
+    .ok_or_else(|| anyhow::anyhow!("Unknown task status '{}'", name))
+}
+
+fn print_row(task: &TaskRecord) {
+    let status = match task.status {
+        TaskStatus::Completed => task.status.to_string().green(),
+        TaskStatus::Failed => task.status.to_string().red(),
+        TaskStatus::Stopped => task.status.to_string().yellow(),
+        _ => task.status.to_string().normal(),
+    };
+    println!(
+        "{}\t{}\t{}/{}\t{}\t{}\t{}",
+        task.id,
+        status,
+        task.completed,
+        task.total,
+        local_time(&task.created_at),
+        local_time(&task.updated_at),
+        task.url_template
+    );
+}
+
+/// Marca RFC3339 en hora local y sin fracciones; si no se puede leer, tal cual.
+fn local_time(timestamp: &str) -> String {
+    chrono::DateTime::parse_from_rfc3339(timestamp)
+        .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|_| timestamp.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_filter_ignores_case() {
+        assert_eq!(parse_status("failed").unwrap(), TaskStatus::Failed);
+        assert_eq!(parse_status("COMPLETED").unwrap(), TaskStatus::Completed);
+        let err = parse_status("done").unwrap_err();
+        assert!(err.to_string().contains("'done'"), "{}", err);
+    }
+
+    #[test]
+    fn timestamps_fall_back_to_the_stored_text() {
+        assert_eq!(local_time("not a date"), "not a date");
+        let shown = local_time("2026-01-01T12:00:00+00:00");
+        assert_eq!(shown.len(), "2026-01-01 12:00:00".len());
+        assert!(shown.starts_with("2026-01-0") || shown.starts_with("2025-12-31"), "{}", shown);
+    }
+
+    #[test]
+    fn export_keeps_the_record_column_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.csv");
+        let task = |id, url: &str, status, pid| TaskRecord {
+            id,
+            url_template: url.to_string(),
+            total: 10,
+            completed: 4,
+            status,
+            pid,
+            created_at: "2026-01-01T00:00:00+00:00".to_string(),
+            updated_at: "2026-01-01T00:05:00+00:00".to_string(),
+        };
+        let tasks = [
+            task(2, "http://h/a,b/FUZZW1", TaskStatus::Failed, None),
+            task(1, "http://h/FUZZW1", TaskStatus::Completed, Some(321)),
+        ];
+        write_csv(&path, &tasks).unwrap();
+        let csv = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "id,url_template,total,completed,status,pid,created_at,updated_at",
+                "2,\"http://h/a,b/FUZZW1\",10,4,Failed,,2026-01-01T00:00:00+00:00,2026-01-01T00:05:00+00:00",
+                "1,http://h/FUZZW1,10,4,Completed,321,2026-01-01T00:00:00+00:00,2026-01-01T00:05:00+00:00",
+            ]
+        );
+    }
+}
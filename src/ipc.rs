@@ -5,19 +5,80 @@ use interprocess::local_socket::{
     ToFsName,
 };
 use std::{
-    io::{BufRead, BufReader, Write},
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
     sync::{
-        Arc, 
+        Arc, OnceLock,
         atomic::{AtomicBool, Ordering},
     },
     thread,
     path::PathBuf,
+    time::Duration,
 };
 use anyhow::{Result, Context};
 use serde::{Deserialize, Serialize};
 
+use crate::core::db::TaskRecord;
 use crate::core::downzer::Downzer;
-use crate::core::task::TaskStatus;
+use crate::core::task::{TaskInfo, TaskStatus};
+
+/// Versión del protocolo IPC.
+///
+/// - v1: una línea JSON con el comando y otra con la respuesta.
+/// - v2: el cliente abre con la línea `DOWNZER-IPC <versión>` y el servidor
+///   contesta igual con la versión acordada (la menor de las dos). Después,
+///   comando y respuesta viajan como mensajes: el JSON troceado en frames de
+///   como mucho `MAX_FRAME` bytes, cada uno precedido por su longitud (u32
+///   big-endian), y un frame vacío que cierra el mensaje.
+///
+/// - v3: el saludo del cliente lleva además el token del servidor
+///   (`DOWNZER-IPC 3 <token>`), leído del fichero que solo puede leer el
+///   dueño (ver `token_path`). Sin token válido todo comando se responde con
+///   `IpcResponse::Error`, de modo que los clientes v1 y v2 reciben el motivo.
+///
+/// Un servidor v2 sigue atendiendo a clientes v1 (la primera línea no es el
+/// saludo), y un cliente v2 que no recibe saludo vuelve a intentarlo con v1.
+pub const PROTOCOL_VERSION: u32 = 3;
+const HANDSHAKE_PREFIX: &str = "DOWNZER-IPC ";
+const MAX_FRAME: usize = 64 * 1024;
+/// Tamaño máximo de un mensaje reensamblado, para no crecer sin límite
+const MAX_MESSAGE: usize = 64 * 1024 * 1024;
+/// Espera máxima para conectar y para cada lectura por TCP, donde el otro
+/// extremo puede desaparecer sin cerrar la conexión
+const TCP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Conexión por la que viaja el protocolo: el socket local o TCP (`--ipc-tcp`).
+/// Saludo, comandos y respuestas son los mismos en los dos.
+pub trait Transport: Read + Write + Send {}
+
+impl Transport for LocalSocketStream {}
+impl Transport for TcpStream {}
+
+/// A quién habla `send_command`: por defecto a la instancia local, con el
+/// token de su fichero; con `configure_client`, a otra por TCP.
+#[derive(Debug, Clone, Default)]
+struct ClientTarget {
+    tcp: Option<String>,
+    token: Option<String>,
+}
+
+static CLIENT_TARGET: OnceLock<ClientTarget> = OnceLock::new();
+
+/// Fija el destino de los comandos de este proceso: `tcp` es `host:puerto`
+/// de una instancia con `--ipc-tcp` y `token` sustituye al del fichero local.
+pub fn configure_client(tcp: Option<String>, token: Option<String>) {
+    let _ = CLIENT_TARGET.set(ClientTarget { tcp, token });
+}
+
+/// Si los comandos van a una instancia remota (`--ipc-tcp`): su base de
+/// datos no es la local, así que no hay a dónde volver si no responde.
+pub fn is_remote() -> bool {
+    client_target().tcp.is_some()
+}
+
+fn client_target() -> &'static ClientTarget {
+    CLIENT_TARGET.get_or_init(ClientTarget::default)
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum IpcCommand {
@@ -26,32 +87,119 @@ pub enum IpcCommand {
     Resume(Vec<u32>),
     List,
     Status(u32),
+    /// Como `Pause`/`Resume`/`Stop`, para todas las tareas de la instancia
+    PauseAll,
+    ResumeAll,
+    StopAll,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum IpcResponse {
     Ok,
-    TaskList(Vec<(u32, String, String)>),
+    TaskList(Vec<TaskSummary>),
     Error(String),
 }
 
-pub fn get_socket_path() -> PathBuf {
+/// Una tarea tal como la ven `list` y `status`: estado, progreso y tiempo en
+/// marcha, de la memoria de la instancia o, si no está en ella, de la base de
+/// datos.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskSummary {
+    pub id: u32,
+    pub status: TaskStatus,
+    pub url: String,
+    pub completed: usize,
+    pub total: usize,
+    pub elapsed_secs: u64,
+}
+
+/// Campos de `TaskSummary` que publica `downzer list --json`, en ese orden.
+#[derive(Debug, Serialize)]
+pub struct TaskListEntry<'a> {
+    pub id: u32,
+    pub status: TaskStatus,
+    pub url: &'a str,
+    pub completed: usize,
+    pub total: usize,
+}
+
+impl TaskSummary {
+    fn from_info(task: &TaskInfo) -> Self {
+        Self {
+            id: task.id,
+            status: task.status,
+            url: task.url_template.clone(),
+            completed: task.progress(),
+            total: task.total,
+            elapsed_secs: task.start_time.elapsed().as_secs(),
+        }
+    }
+
+    /// Sin la instancia que la lanzó, el tiempo cuenta desde su creación.
+    pub fn from_record(task: TaskRecord) -> Self {
+        let elapsed_secs = chrono::DateTime::parse_from_rfc3339(&task.created_at)
+            .map(|created| (chrono::Utc::now() - created.with_timezone(&chrono::Utc)).num_seconds().max(0) as u64)
+            .unwrap_or(0);
+        Self {
+            id: task.id,
+            status: task.status,
+            url: task.url_template,
+            completed: task.completed,
+            total: task.total,
+            elapsed_secs,
+        }
+    }
+
+    /// Objeto de `downzer list --json`: `{id,status,url,completed,total}`.
+    pub fn list_entry(&self) -> TaskListEntry<'_> {
+        TaskListEntry { id: self.id, status: self.status, url: &self.url, completed: self.completed, total: self.total }
+    }
+
+    pub fn percent(&self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            (self.completed.min(self.total) as f64 / self.total as f64) * 100.0
+        }
+    }
+
+    /// Tiempo restante al ritmo medio hasta ahora; `None` sin progreso todavía.
+    pub fn eta(&self) -> Option<std::time::Duration> {
+        if self.completed == 0 || self.elapsed_secs == 0 || self.completed >= self.total {
+            return None;
+        }
+        let remaining = (self.total - self.completed) as f64;
+        Some(std::time::Duration::from_secs_f64(remaining * self.elapsed_secs as f64 / self.completed as f64))
+    }
+}
+
+/// Directorio del socket y del token: `$XDG_RUNTIME_DIR` (privado del
+/// usuario) si existe y, si no, el temporal del sistema.
+fn runtime_dir() -> PathBuf {
     #[cfg(unix)]
     {
-        let mut path = PathBuf::from("/tmp");
-        path.push("downzer_ipc.sock");
-        path
+        match std::env::var_os("XDG_RUNTIME_DIR") {
+            Some(dir) if !dir.is_empty() && std::path::Path::new(&dir).is_dir() => PathBuf::from(dir),
+            _ => PathBuf::from("/tmp"),
+        }
     }
-    
+
     #[cfg(windows)]
     {
         // En Windows, usar un nombre abstracto que interprocess maneja automáticamente
-        let mut path = std::env::temp_dir();
-        path.push("downzer_ipc.sock");
-        path
+        std::env::temp_dir()
     }
 }
 
+pub fn get_socket_path() -> PathBuf {
+    runtime_dir().join("downzer_ipc.sock")
+}
+
+/// Fichero con el token de la instancia en marcha, legible solo por su dueño.
+pub fn token_path() -> PathBuf {
+    runtime_dir().join("downzer_ipc.token")
+}
+
 pub fn cleanup_old_sockets() -> Result<()> {
     let socket_path = get_socket_path();
     
@@ -59,10 +207,44 @@ pub fn cleanup_old_sockets() -> Result<()> {
     if socket_path.exists() {
         std::fs::remove_file(&socket_path).ok();
     }
+    std::fs::remove_file(token_path()).ok();
     
     Ok(())
 }
 
+/// Escribe el token de esta instancia (`token`, o uno al azar) en
+/// `token_path` con permisos 0600. El fichero se crea de nuevo
+/// (`create_new`): si otro usuario dejó uno en su sitio, no se puede borrar
+/// y el servidor no arranca.
+fn write_token(token: Option<String>) -> Result<String> {
+    use rand::Rng;
+
+    let token = token.unwrap_or_else(|| (0..32).map(|_| format!("{:02x}", rand::thread_rng().gen::<u8>())).collect());
+    let path = token_path();
+    std::fs::remove_file(&path).ok();
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&path).with_context(|| format!("Cannot create IPC token {}", path.display()))?;
+    file.write_all(token.as_bytes())?;
+    Ok(token)
+}
+
+/// Token de la instancia en marcha; vacío si no se puede leer (otro usuario,
+/// o ninguna instancia), con lo que el servidor rechazará el comando.
+fn read_token() -> String {
+    std::fs::read_to_string(token_path()).map(|t| t.trim().to_string()).unwrap_or_default()
+}
+
+/// Comparación sin salir antes de tiempo, para no filtrar el token por tiempos.
+fn token_matches(given: &str, expected: &str) -> bool {
+    given.len() == expected.len() && given.bytes().zip(expected.bytes()).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
 pub fn get_ipc_name() -> Result<interprocess::local_socket::Name<'static>> {
     let path_str = get_socket_path()
         .to_string_lossy()
@@ -73,19 +255,45 @@ pub fn get_ipc_name() -> Result<interprocess::local_socket::Name<'static>> {
         .context("Failed to generate socket name")
 }
 
+/// Abre el listener de `--ipc-tcp`; se hace antes de arrancar el servidor
+/// para que un puerto ocupado se notifique en vez de perderse en su hilo.
+pub fn bind_tcp(addr: &str) -> Result<TcpListener> {
+    let listener = TcpListener::bind(addr).with_context(|| format!("Cannot listen for IPC on {}", addr))?;
+    // No bloqueante para poder mirar `shutdown` entre conexiones
+    listener.set_nonblocking(true)?;
+    Ok(listener)
+}
+
+/// Servidor IPC en el socket local y, con `tcp`, también en ese listener.
+/// `token` fija el token de la instancia; si no, se genera uno al azar. En
+/// los dos casos se escribe en `token_path` para los clientes locales.
 pub fn run_ipc_server(
     downzer: Arc<Downzer>,
     shutdown: Arc<AtomicBool>,
+    tcp: Option<TcpListener>,
+    token: Option<String>,
 ) -> Result<()> {
     // Limpiar socket antigua
     cleanup_old_sockets()?;
     
     let name = get_ipc_name()?;
 
-    let listener = ListenerOptions::new()
-        .name(name)
-        .create_sync()
-        .context("Failed to create IPC listener")?;
+    let options = ListenerOptions::new().name(name);
+    // Solo el dueño puede conectar; el modo se aplica antes del bind
+    #[cfg(unix)]
+    let options = {
+        use interprocess::os::unix::local_socket::ListenerOptionsExt;
+        options.mode(0o600)
+    };
+    let listener = options.create_sync().context("Failed to create IPC listener")?;
+    let token = Arc::new(write_token(token)?);
+
+    if let Some(tcp) = tcp {
+        let downzer = downzer.clone();
+        let shutdown = shutdown.clone();
+        let token = token.clone();
+        thread::spawn(move || serve_tcp(tcp, downzer, shutdown, token));
+    }
 
     // Check shutdown frequently even if no connections
     loop {
@@ -97,9 +305,10 @@ pub fn run_ipc_server(
             Ok(conn) => {
                 let downzer = downzer.clone();
                 let shutdown = shutdown.clone();
+                let token = token.clone();
 
                 thread::spawn(move || {
-                    if let Err(e) = handle_client(conn, downzer, shutdown) {
+                    if let Err(e) = handle_client(conn, downzer, shutdown, &token) {
                         eprintln!("IPC error: {e}");
                     }
                 });
@@ -114,11 +323,77 @@ pub fn run_ipc_server(
     Ok(())
 }
 
-pub fn send_command(cmd: &IpcCommand) -> Result<IpcResponse> {
+/// Bucle de `--ipc-tcp`: como el del socket local, un hilo por conexión.
+fn serve_tcp(listener: TcpListener, downzer: Arc<Downzer>, shutdown: Arc<AtomicBool>, token: Arc<String>) {
+    while !shutdown.load(Ordering::SeqCst) {
+        match listener.accept() {
+            Ok((conn, _)) => {
+                let downzer = downzer.clone();
+                let shutdown = shutdown.clone();
+                let token = token.clone();
+
+                thread::spawn(move || {
+                    let result = conn
+                        .set_nonblocking(false)
+                        .and_then(|_| conn.set_read_timeout(Some(TCP_TIMEOUT)))
+                        .map_err(anyhow::Error::from)
+                        .and_then(|_| handle_client(conn, downzer, shutdown, &token));
+                    if let Err(e) = result {
+                        eprintln!("IPC error: {e}");
+                    }
+                });
+            }
+            Err(_e) => thread::sleep(Duration::from_millis(100)),
+        }
+    }
+}
+
+fn connect() -> Result<Box<dyn Transport>> {
+    if let Some(addr) = &client_target().tcp {
+        let addr = addr
+            .to_socket_addrs()
+            .with_context(|| format!("Cannot resolve {}", addr))?
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Cannot resolve {}", addr))?;
+        let stream = TcpStream::connect_timeout(&addr, TCP_TIMEOUT)
+            .with_context(|| format!("Could not connect to IPC server at {}", addr))?;
+        stream.set_read_timeout(Some(TCP_TIMEOUT))?;
+        return Ok(Box::new(stream));
+    }
     let name = get_ipc_name()?;
-    let mut stream = LocalSocketStream::connect(name)
-        .context("Could not connect to IPC server. Is Downzer running?")?;
-    
+    let stream = LocalSocketStream::connect(name).context("Could not connect to IPC server. Is Downzer running?")?;
+    Ok(Box::new(stream))
+}
+
+pub fn send_command(cmd: &IpcCommand) -> Result<IpcResponse> {
+    let target = client_target();
+    let token = target.token.clone().unwrap_or_else(read_token);
+    match exchange(connect()?, &token, cmd)? {
+        Some(response) => Ok(response),
+        // Un servidor v1 no entiende el saludo y cierra sin responder. Por TCP
+        // no hay servidores v1 a los que volver
+        None if target.tcp.is_some() => anyhow::bail!("The IPC server did not answer the handshake"),
+        None => send_command_v1(cmd),
+    }
+}
+
+/// Saludo v3 con `token` y envío de `cmd` por `conn`. `None` si el servidor
+/// no contesta al saludo (es v1).
+fn exchange(conn: impl Read + Write, token: &str, cmd: &IpcCommand) -> Result<Option<IpcResponse>> {
+    let mut reader = BufReader::new(conn);
+    writeln!(reader.get_mut(), "{}{} {}", HANDSHAKE_PREFIX, PROTOCOL_VERSION, token)?;
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if parse_handshake(&line).is_none() {
+        return Ok(None);
+    }
+    write_message(reader.get_mut(), cmd)?;
+    read_message(&mut reader).map(Some)
+}
+
+/// Protocolo v1: una línea JSON en cada sentido.
+fn send_command_v1(cmd: &IpcCommand) -> Result<IpcResponse> {
+    let mut stream = connect()?;
     let json = serde_json::to_string(cmd)?;
     writeln!(stream, "{}", json)?;
     stream.flush()?;
@@ -132,24 +407,77 @@ pub fn send_command(cmd: &IpcCommand) -> Result<IpcResponse> {
 }
 
 fn handle_client(
-    mut conn: LocalSocketStream,
+    conn: impl Transport,
     downzer: Arc<Downzer>,
     shutdown: Arc<AtomicBool>,
+    token: &str,
 ) -> Result<()> {
-    let mut reader = BufReader::new(&conn);
+    let mut reader = BufReader::new(conn);
     let mut line = String::new();
     reader.read_line(&mut line)?;
 
-    let cmd: IpcCommand = serde_json::from_str(&line)?;
-    let response = handle_command(cmd, downzer, shutdown);
+    let Some((client_version, client_token)) = parse_handshake(&line) else {
+        // Cliente v1: la primera línea ya es el comando, y no trae token
+        let _: IpcCommand = serde_json::from_str(&line)?;
+        let response = IpcResponse::Error("IPC token required: this client is too old".to_string());
+        let json = serde_json::to_string(&response)?;
+        writeln!(reader.get_mut(), "{json}")?;
+        reader.get_mut().flush()?;
+        return Ok(());
+    };
 
-    let json = serde_json::to_string(&response)?;
-    writeln!(conn, "{json}")?;
-    conn.flush()?;
+    let version = client_version.min(PROTOCOL_VERSION);
+    writeln!(reader.get_mut(), "{}{}", HANDSHAKE_PREFIX, version)?;
+    let cmd: IpcCommand = read_message(&mut reader)?;
+    let response = match client_token {
+        Some(given) if token_matches(given, token) => handle_command(cmd, downzer, shutdown),
+        None if client_version < 3 => IpcResponse::Error("IPC token required: this client is too old".to_string()),
+        _ => IpcResponse::Error(format!("Invalid IPC token (the client reads it from {})", token_path().display())),
+    };
+    write_message(reader.get_mut(), &response)?;
 
     Ok(())
 }
 
+/// Versión anunciada en una línea `DOWNZER-IPC <n> [token]`, y el token si lo hay.
+fn parse_handshake(line: &str) -> Option<(u32, Option<&str>)> {
+    let mut fields = line.trim_end().strip_prefix(HANDSHAKE_PREFIX)?.split_whitespace();
+    let version = fields.next()?.parse().ok().filter(|v| *v >= 2)?;
+    Some((version, fields.next()))
+}
+
+/// Escribe `value` como mensaje v2: frames con prefijo de longitud y uno vacío al final.
+pub fn write_message<W: Write, T: Serialize>(writer: &mut W, value: &T) -> Result<()> {
+    let json = serde_json::to_vec(value)?;
+    for chunk in json.chunks(MAX_FRAME) {
+        writer.write_all(&(chunk.len() as u32).to_be_bytes())?;
+        writer.write_all(chunk)?;
+    }
+    writer.write_all(&0u32.to_be_bytes())?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Lee un mensaje v2 completo (hasta el frame vacío) y lo deserializa.
+pub fn read_message<R: Read, T: serde::de::DeserializeOwned>(reader: &mut R) -> Result<T> {
+    let mut message = Vec::new();
+    loop {
+        let mut len = [0u8; 4];
+        reader.read_exact(&mut len).context("IPC connection closed mid-message")?;
+        let len = u32::from_be_bytes(len) as usize;
+        if len == 0 {
+            break;
+        }
+        if len > MAX_FRAME || message.len() + len > MAX_MESSAGE {
+            anyhow::bail!("IPC frame too large ({} bytes)", len);
+        }
+        let start = message.len();
+        message.resize(start + len, 0);
+        reader.read_exact(&mut message[start..])?;
+    }
+    Ok(serde_json::from_slice(&message)?)
+}
+
 fn handle_command(
     cmd: IpcCommand,
     downzer: Arc<Downzer>,
@@ -160,11 +488,13 @@ fn handle_command(
             let tasks = downzer.tasks.blocking_write();
             let mut task_map = tasks;
             
-            for id in ids {
-                if let Some(task) = task_map.get_mut(&id) {
+            for id in &ids {
+                if let Some(task) = task_map.get_mut(id) {
                     task.status = TaskStatus::Stopped;
                 }
             }
+            drop(task_map);
+            persist_status(&downzer, &ids, TaskStatus::Stopped);
             IpcResponse::Ok
         }
 
@@ -172,11 +502,13 @@ fn handle_command(
             let tasks = downzer.tasks.blocking_write();
             let mut task_map = tasks;
             
-            for id in ids {
-                if let Some(task) = task_map.get_mut(&id) {
+            for id in &ids {
+                if let Some(task) = task_map.get_mut(id) {
                     task.status = TaskStatus::Paused;
                 }
             }
+            drop(task_map);
+            persist_status(&downzer, &ids, TaskStatus::Paused);
             IpcResponse::Ok
         }
 
@@ -184,30 +516,394 @@ fn handle_command(
             let tasks = downzer.tasks.blocking_write();
             let mut task_map = tasks;
             
-            for id in ids {
-                if let Some(task) = task_map.get_mut(&id) {
+            for id in &ids {
+                if let Some(task) = task_map.get_mut(id) {
                     task.status = TaskStatus::Running;
                 }
             }
+            drop(task_map);
+            persist_status(&downzer, &ids, TaskStatus::Running);
             IpcResponse::Ok
         }
 
         IpcCommand::List => {
-            let tasks = downzer.tasks.blocking_read();
-            let list: Vec<_> = tasks
-                .iter()
-                .map(|(id, task)| (*id, task.status.to_string(), task.url_template.clone()))
+            // Las tareas activas de la base de datos (también las de otros
+            // procesos); las de este proceso, con su estado en memoria
+            let mut list: std::collections::BTreeMap<u32, TaskSummary> = downzer
+                .db
+                .blocking_lock()
+                .get_active_tasks()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|task| (task.id, TaskSummary::from_record(task)))
                 .collect();
-            IpcResponse::TaskList(list)
+            let tasks = downzer.tasks.blocking_read();
+            for (id, task) in tasks.iter() {
+                list.insert(*id, TaskSummary::from_info(task));
+            }
+            IpcResponse::TaskList(list.into_values().collect())
         }
 
+        IpcCommand::PauseAll => set_all(&downzer, TaskStatus::Paused, |status| status != TaskStatus::Paused),
+        IpcCommand::ResumeAll => set_all(&downzer, TaskStatus::Running, |status| status == TaskStatus::Paused),
+        IpcCommand::StopAll => set_all(&downzer, TaskStatus::Stopped, |_| true),
+
         IpcCommand::Status(id) => {
             let tasks = downzer.tasks.blocking_read();
             if let Some(task) = tasks.get(&id) {
-                IpcResponse::TaskList(vec![(id, task.status.to_string(), task.url_template.clone())])
+                IpcResponse::TaskList(vec![TaskSummary::from_info(task)])
+            } else if let Ok(Some(task)) = downzer.db.blocking_lock().get_task(id) {
+                IpcResponse::TaskList(vec![TaskSummary::from_record(task)])
             } else {
                 IpcResponse::Error(format!("Task {} not found", id))
             }
         }
     }
 }
+
+/// Pasa a `status` todas las tareas de la instancia para las que `applies`
+/// es cierto y guarda el cambio. Las terminadas (Completed, Failed, Stopped)
+/// no se tocan nunca.
+fn set_all(downzer: &Downzer, status: TaskStatus, applies: impl Fn(TaskStatus) -> bool) -> IpcResponse {
+    let mut tasks = downzer.tasks.blocking_write();
+    let mut ids = Vec::new();
+    for (id, task) in tasks.iter_mut() {
+        if !task.status.is_terminal() && applies(task.status) {
+            task.status = status;
+            ids.push(*id);
+        }
+    }
+    drop(tasks);
+    persist_status(downzer, &ids, status);
+    IpcResponse::Ok
+}
+
+/// Guarda en la base de datos el nuevo estado de las tareas.
+fn persist_status(downzer: &Downzer, ids: &[u32], status: TaskStatus) {
+    let now = chrono::Utc::now().to_rfc3339();
+    let db = downzer.db.blocking_lock();
+    for id in ids {
+        if let Err(e) = db.set_task_status(*id, status, &now) {
+            eprintln!("[ERROR] Cannot persist status of task #{}: {}", id, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+
+    /// Atiende una sola conexión TCP con `handle_client` en un hilo aparte.
+    fn serve_once(downzer: Arc<Downzer>, token: &str) -> (SocketAddr, thread::JoinHandle<Result<()>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let token = token.to_string();
+        let handle = thread::spawn(move || {
+            let (conn, _) = listener.accept()?;
+            handle_client(conn, downzer, Arc::new(AtomicBool::new(false)), &token)
+        });
+        (addr, handle)
+    }
+
+    fn request(addr: SocketAddr, token: &str, cmd: &IpcCommand) -> IpcResponse {
+        exchange(TcpStream::connect(addr).unwrap(), token, cmd).unwrap().expect("saludo v3")
+    }
+
+    #[test]
+    fn large_task_list_round_trips_in_frames() {
+        let downzer = crate::testutil::downzer();
+        {
+            let mut tasks = downzer.tasks.blocking_write();
+            for id in 1..=5000 {
+                tasks.insert(
+                    id,
+                    TaskInfo {
+                        id,
+                        url_template: format!("https://example.com/{}/FUZZW1?page=FUZZR", "x".repeat(id as usize % 50)),
+                        total: 10_000,
+                        completed: id as usize,
+                        status: TaskStatus::Running,
+                        start_time: std::time::Instant::now(),
+                        live: None,
+                    },
+                );
+            }
+        }
+        let (addr, server) = serve_once(downzer, "secret");
+        let IpcResponse::TaskList(list) = request(addr, "secret", &IpcCommand::List) else {
+            panic!("se esperaba la lista de tareas");
+        };
+        server.join().unwrap().unwrap();
+        assert_eq!(list.len(), 5000);
+        assert!(list.iter().enumerate().all(|(i, t)| t.id == i as u32 + 1 && t.completed == i + 1));
+        assert_eq!(list[48].url, format!("https://example.com/{}/FUZZW1?page=FUZZR", "x".repeat(49)));
+    }
+
+    #[test]
+    fn messages_larger_than_a_frame_are_split() {
+        let list: Vec<String> = (0..5000).map(|i| format!("entry-{:05}", i)).collect();
+        let mut wire = Vec::new();
+        write_message(&mut wire, &list).unwrap();
+        let json_len = serde_json::to_vec(&list).unwrap().len();
+        let frames = json_len.div_ceil(MAX_FRAME);
+        assert!(frames > 1);
+        assert_eq!(wire.len(), json_len + 4 * (frames + 1));
+        let back: Vec<String> = read_message(&mut std::io::Cursor::new(wire)).unwrap();
+        assert_eq!(back, list);
+    }
+
+    #[test]
+    fn truncated_or_oversized_frames_are_errors() {
+        let mut wire = Vec::new();
+        write_message(&mut wire, &"hello").unwrap();
+        wire.truncate(wire.len() - 4);
+        assert!(read_message::<_, String>(&mut std::io::Cursor::new(wire)).is_err());
+        let oversized = ((MAX_FRAME + 1) as u32).to_be_bytes().to_vec();
+        let err = read_message::<_, String>(&mut std::io::Cursor::new(oversized)).unwrap_err();
+        assert!(err.to_string().contains("too large"), "{}", err);
+    }
+
+    #[test]
+    fn handshake_needs_version_two_or_later() {
+        assert_eq!(parse_handshake("DOWNZER-IPC 3 abc\n"), Some((3, Some("abc"))));
+        assert_eq!(parse_handshake("DOWNZER-IPC 2\r\n"), Some((2, None)));
+        assert_eq!(parse_handshake("DOWNZER-IPC 1"), None);
+        assert_eq!(parse_handshake("{\"List\":null}"), None);
+    }
+
+    fn summary(completed: usize, total: usize, elapsed_secs: u64) -> TaskSummary {
+        TaskSummary { id: 7, status: TaskStatus::Running, url: "http://h/FUZZW1".to_string(), completed, total, elapsed_secs }
+    }
+
+    #[test]
+    fn status_round_trips_through_serde() {
+        let mut wire = Vec::new();
+        write_message(&mut wire, &IpcCommand::Status(7)).unwrap();
+        let cmd: IpcCommand = read_message(&mut std::io::Cursor::new(wire)).unwrap();
+        assert!(matches!(cmd, IpcCommand::Status(7)));
+
+        let mut wire = Vec::new();
+        write_message(&mut wire, &IpcResponse::TaskList(vec![summary(25, 100, 50)])).unwrap();
+        let IpcResponse::TaskList(list) = read_message(&mut std::io::Cursor::new(wire)).unwrap() else {
+            panic!("se esperaba la lista de tareas");
+        };
+        let task = &list[0];
+        assert_eq!((task.id, task.status, task.completed, task.total, task.elapsed_secs), (7, TaskStatus::Running, 25, 100, 50));
+        assert_eq!(task.url, "http://h/FUZZW1");
+    }
+
+    #[test]
+    fn percent_and_eta_follow_the_progress() {
+        assert_eq!(summary(25, 100, 50).percent(), 25.0);
+        assert_eq!(summary(0, 0, 5).percent(), 0.0);
+        // Más hechas que el total (p.ej. reintentos) se quedan en el 100%
+        assert_eq!(summary(120, 100, 5).percent(), 100.0);
+        // 75 restantes al ritmo de 25 cada 50 s
+        assert_eq!(summary(25, 100, 50).eta(), Some(Duration::from_secs(150)));
+        assert_eq!(summary(0, 100, 50).eta(), None);
+        assert_eq!(summary(25, 100, 0).eta(), None);
+        assert_eq!(summary(100, 100, 50).eta(), None);
+    }
+
+    #[test]
+    fn status_reports_live_progress_then_the_database() {
+        let downzer = crate::testutil::downzer();
+        let live = Arc::new(crate::core::live::LiveStats::default());
+        for _ in 0..3 {
+            live.record(false, 0);
+        }
+        downzer.tasks.blocking_write().insert(
+            1,
+            TaskInfo {
+                id: 1,
+                url_template: "http://h/FUZZW1".to_string(),
+                total: 8,
+                completed: 0,
+                status: TaskStatus::Running,
+                start_time: std::time::Instant::now(),
+                live: Some(live),
+            },
+        );
+        downzer
+            .db
+            .blocking_lock()
+            .upsert_task(&TaskRecord {
+                id: 2,
+                url_template: "http://old/FUZZW1".to_string(),
+                total: 5,
+                completed: 5,
+                status: TaskStatus::Completed,
+                pid: None,
+                created_at: chrono::Utc::now().to_rfc3339(),
+                updated_at: chrono::Utc::now().to_rfc3339(),
+            })
+            .unwrap();
+
+        let status = |id| {
+            let (addr, server) = serve_once(downzer.clone(), "secret");
+            let response = request(addr, "secret", &IpcCommand::Status(id));
+            server.join().unwrap().unwrap();
+            response
+        };
+        // De la memoria, con el contador en vivo
+        let IpcResponse::TaskList(list) = status(1) else { panic!("se esperaba la tarea 1") };
+        assert_eq!((list.len(), list[0].completed, list[0].total, list[0].status), (1, 3, 8, TaskStatus::Running));
+        // De la base de datos, si no es de esta instancia
+        let IpcResponse::TaskList(list) = status(2) else { panic!("se esperaba la tarea 2") };
+        assert_eq!((list[0].completed, list[0].total, list[0].status), (5, 5, TaskStatus::Completed));
+        assert!(matches!(status(3), IpcResponse::Error(e) if e == "Task 3 not found"));
+    }
+
+    #[test]
+    fn all_commands_change_every_unfinished_task() {
+        use TaskStatus::*;
+        let downzer = crate::testutil::downzer();
+        let initial = [Running, Paused, Queued, Completed, Failed];
+        for (i, status) in initial.into_iter().enumerate() {
+            let id = i as u32 + 1;
+            let now = chrono::Utc::now().to_rfc3339();
+            let info = TaskInfo {
+                id,
+                url_template: format!("http://h/{}", id),
+                total: 1,
+                completed: 0,
+                status,
+                start_time: std::time::Instant::now(),
+                live: None,
+            };
+            let record = TaskRecord {
+                id,
+                url_template: info.url_template.clone(),
+                total: 1,
+                completed: 0,
+                status,
+                pid: None,
+                created_at: now.clone(),
+                updated_at: now,
+            };
+            downzer.db.blocking_lock().upsert_task(&record).unwrap();
+            downzer.tasks.blocking_write().insert(id, info);
+        }
+        let send = |cmd| {
+            let (addr, server) = serve_once(downzer.clone(), "secret");
+            assert!(matches!(request(addr, "secret", &cmd), IpcResponse::Ok));
+            server.join().unwrap().unwrap();
+            let memory: Vec<TaskStatus> = (1..=5).map(|id| downzer.tasks.blocking_read()[&id].status).collect();
+            let stored: Vec<TaskStatus> =
+                (1..=5).map(|id| downzer.db.blocking_lock().get_task(id).unwrap().unwrap().status).collect();
+            assert_eq!(memory, stored, "la base de datos sigue a la memoria");
+            memory
+        };
+
+        // Las terminadas no se tocan nunca
+        assert_eq!(send(IpcCommand::PauseAll), [Paused, Paused, Paused, Completed, Failed]);
+        assert_eq!(send(IpcCommand::ResumeAll), [Running, Running, Running, Completed, Failed]);
+        assert_eq!(send(IpcCommand::StopAll), [Stopped, Stopped, Stopped, Completed, Failed]);
+        // Ni las paradas vuelven a arrancar
+        assert_eq!(send(IpcCommand::ResumeAll), [Stopped, Stopped, Stopped, Completed, Failed]);
+    }
+
+    #[test]
+    fn a_wrong_token_is_rejected() {
+        let downzer = crate::testutil::downzer();
+        downzer.tasks.blocking_write().insert(
+            1,
+            TaskInfo {
+                id: 1,
+                url_template: "http://h/FUZZW1".to_string(),
+                total: 1,
+                completed: 0,
+                status: TaskStatus::Running,
+                start_time: std::time::Instant::now(),
+                live: None,
+            },
+        );
+        for token in ["wrong", "secre", "secret!", ""] {
+            let (addr, server) = serve_once(downzer.clone(), "secret");
+            let response = request(addr, token, &IpcCommand::StopAll);
+            server.join().unwrap().unwrap();
+            assert!(matches!(&response, IpcResponse::Error(e) if e.starts_with("Invalid IPC token")), "{:?}", response);
+        }
+        // El comando no llegó a ejecutarse
+        assert_eq!(downzer.tasks.blocking_read()[&1].status, TaskStatus::Running);
+
+        let (addr, server) = serve_once(downzer.clone(), "secret");
+        assert!(matches!(request(addr, "secret", &IpcCommand::StopAll), IpcResponse::Ok));
+        server.join().unwrap().unwrap();
+        assert_eq!(downzer.tasks.blocking_read()[&1].status, TaskStatus::Stopped);
+    }
+
+    #[test]
+    fn clients_without_a_token_are_told_why() {
+        // v2: saludo sin token
+        let (addr, server) = serve_once(crate::testutil::downzer(), "secret");
+        let mut reader = BufReader::new(TcpStream::connect(addr).unwrap());
+        writeln!(reader.get_mut(), "{}2", HANDSHAKE_PREFIX).unwrap();
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line.trim_end(), "DOWNZER-IPC 2");
+        write_message(reader.get_mut(), &IpcCommand::List).unwrap();
+        let response: IpcResponse = read_message(&mut reader).unwrap();
+        assert!(matches!(&response, IpcResponse::Error(e) if e.contains("too old")), "{:?}", response);
+        server.join().unwrap().unwrap();
+
+        // v1: el comando en una línea JSON
+        let (addr, server) = serve_once(crate::testutil::downzer(), "secret");
+        let mut reader = BufReader::new(TcpStream::connect(addr).unwrap());
+        writeln!(reader.get_mut(), "{}", serde_json::to_string(&IpcCommand::List).unwrap()).unwrap();
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+        let response: IpcResponse = serde_json::from_str(&line).unwrap();
+        assert!(matches!(&response, IpcResponse::Error(e) if e.contains("too old")), "{:?}", response);
+        server.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn token_comparison_needs_the_whole_token() {
+        assert!(token_matches("abc", "abc"));
+        assert!(!token_matches("abd", "abc"));
+        assert!(!token_matches("ab", "abc"));
+        assert!(!token_matches("", "abc"));
+    }
+
+    #[test]
+    fn tcp_listener_round_trips_commands() {
+        let downzer = crate::testutil::downzer();
+        downzer.tasks.blocking_write().insert(
+            4,
+            TaskInfo {
+                id: 4,
+                url_template: "http://h/FUZZW1".to_string(),
+                total: 2,
+                completed: 1,
+                status: TaskStatus::Running,
+                start_time: std::time::Instant::now(),
+                live: None,
+            },
+        );
+        let listener = bind_tcp("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        // Un puerto ocupado se notifica al abrirlo
+        let err = bind_tcp(&addr.to_string()).unwrap_err();
+        assert!(err.to_string().contains("Cannot listen for IPC"), "{}", err);
+
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let server = {
+            let (downzer, shutdown) = (downzer.clone(), shutdown.clone());
+            thread::spawn(move || serve_tcp(listener, downzer, shutdown, Arc::new("secret".to_string())))
+        };
+
+        // Varias conexiones seguidas sobre el mismo listener
+        assert!(matches!(request(addr, "secret", &IpcCommand::Pause(vec![4])), IpcResponse::Ok));
+        let IpcResponse::TaskList(list) = request(addr, "secret", &IpcCommand::List) else {
+            panic!("se esperaba la lista de tareas");
+        };
+        assert_eq!(list.len(), 1);
+        assert_eq!((list[0].id, list[0].status, list[0].completed, list[0].total), (4, TaskStatus::Paused, 1, 2));
+        assert!(matches!(request(addr, "wrong", &IpcCommand::List), IpcResponse::Error(_)));
+
+        shutdown.store(true, Ordering::SeqCst);
+        server.join().unwrap();
+    }
+}
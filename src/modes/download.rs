@@ -1,20 +1,24 @@
 use anyhow::Result;
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
 use colored::*;
 
-use crate::core::Downzer;
+use crate::core::{Downzer, DownloadOptions, Targets};
+use crate::core::caps::ExtensionCaps;
+use crate::core::throttle::{Pacer, RateLimiter};
+use crate::core::live::IntervalSampler;
 use super::{ModeConfig, ModeResult};
 
 pub async fn execute(
     config: ModeConfig,
     downzer: Arc<Downzer>,
-    urls: Vec<String>,
-    _shutdown: Arc<AtomicBool>,
+    targets: Targets,
+    shutdown: Arc<AtomicBool>,
     task_id: u32,
 ) -> Result<ModeResult> {
     if !config.quiet {
-        println!("{} Modo: Descarga ({} URLs)", "[*]".blue(), urls.len());
+        println!("{} Modo: Descarga ({} URLs)", "[*]".blue(), targets.total());
         if config.verbose >= 2 {
             println!("  Concurrencia: {}", config.max_concurrent);
             println!("  Timeout: {}s", config.timeout);
@@ -30,28 +34,216 @@ pub async fn execute(
         }
     }
 
-    let content_types = Vec::new(); // El filtrado de content-type se hace en main
-    
-    let stats = downzer.execute_download_task(
-        task_id,
-        &config.url_or_target,
-        urls.clone(),
-        &config.outdir,
-        &content_types,
-        config.max_concurrent,
-        config.verbose,
-        false,
-    ).await?;
+    let live = config.live.clone();
+    let sampler = match &config.interval_stats {
+        Some(path) => Some(IntervalSampler::start(path, live.clone(), shutdown.clone())?),
+        None => None,
+    };
+
+    let options = DownloadOptions {
+        output_dir: config.outdir.clone(),
+        content_types: config.content_types.clone(),
+        max_concurrent: config.max_concurrent,
+        max_per_host: config.max_per_host,
+        max_filename_length: config.max_filename_length,
+        name_template: config.name_template.clone(),
+        preserve_path: config.preserve_path,
+        headers: config.headers.clone(),
+        cookie_jar: config.cookie_jar.clone(),
+        location_trusted: config.location_trusted,
+        exec: config.exec.clone(),
+        ordered_output: config.ordered_output,
+        quiet_errors: config.quiet_errors,
+        dedup_content: config.dedup_content,
+        hardlink_dupes: config.hardlink_dupes,
+        min_size: config.min_size,
+        max_size: config.max_size,
+        resume: config.resume,
+        rate_limiter: config.rate_limit.map(|rate| Arc::new(RateLimiter::new(rate))),
+        pacer: config.delay.map(|delay| Arc::new(Pacer::new(delay, shutdown.clone()))),
+        extension_caps: Arc::new(ExtensionCaps::new(config.extension_caps.clone())),
+        report: config.report.clone(),
+        manifest: config.manifest.clone(),
+        manifest_append: config.manifest_append,
+        drain_timeout: config.drain_timeout.map(std::time::Duration::from_secs),
+        show_progress: !config.quiet,
+        live,
+        verbose: config.verbose,
+        debug: false,
+    };
+
+    let stats = downzer.execute_download_task(task_id, targets, &options).await?;
+    if let Some(sampler) = sampler {
+        sampler.stop().await?;
+    }
 
     Ok(ModeResult {
         mode: "download".to_string(),
-        total: urls.len(),
+        total: stats.processed,
         successful: stats.downloaded,
         failed: stats.errors + stats.not_found,
         errors: vec![],
+        status_counts: BTreeMap::new(),
         custom_data: Some(format!(
-            "Descargados: {}, Ignorados: {}, No encontrados: {}, Errores: {}, Bytes: {}",
-            stats.downloaded, stats.ignored, stats.not_found, stats.errors, stats.total_bytes
+            "Descargados: {}, Ignorados: {}, No encontrados: {}, Errores: {}, Bytes: {}, Duplicados: {} ({} bytes ahorrados), Filtrados por tamaño: {}, Omitidos por cupo: {}",
+            stats.downloaded, stats.ignored, stats.not_found, stats.errors, stats.total_bytes,
+            stats.duplicates, stats.bytes_saved, stats.filtered_size, stats.capped
         )),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::core::output::{self, OutputLine};
+    use crate::testutil::{self, MockServer, Response};
+
+    async fn output_lines(quiet_errors: bool) -> Vec<OutputLine> {
+        let server = MockServer::start(|req| match req.path.as_str() {
+            "/ok.txt" => Response::new(200).header("Content-Type", "text/plain").body("hello"),
+            _ => Response::new(500),
+        })
+        .await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testutil::mode_config("download", dir.path());
+        config.verbose = 2;
+        config.quiet_errors = quiet_errors;
+        output::start_capture();
+        testutil::run(config, vec![server.url("/ok.txt"), server.url("/broken.txt")]).await;
+        output::captured()
+    }
+
+    #[tokio::test]
+    async fn quiet_errors_hides_error_lines_only() {
+        let lines = output_lines(true).await;
+        assert!(lines.iter().any(|l| matches!(l, OutputLine::Stdout(s) if s.starts_with("[OK]"))), "{:?}", lines);
+        assert!(!lines.iter().any(|l| matches!(l, OutputLine::Stderr(_))), "{:?}", lines);
+
+        let lines = output_lines(false).await;
+        assert!(lines.iter().any(|l| matches!(l, OutputLine::Stderr(s) if s.contains("HTTP 500"))), "{:?}", lines);
+    }
+
+    #[tokio::test]
+    async fn delay_stops_spawning_downloads_on_shutdown() {
+        use crate::core::throttle::parse_delay;
+        use crate::core::Targets;
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        let server = MockServer::start(|_| Response::new(200).body("data")).await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testutil::mode_config("download", dir.path());
+        // Pausa de 5 s tras cada descarga; Ctrl+C llega durante la primera
+        config.delay = Some(parse_delay("5x1").unwrap());
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let stop = shutdown.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+            stop.store(true, Ordering::SeqCst);
+        });
+        let urls = (0..5).map(|i| server.url(&format!("/{}.txt", i))).collect();
+        let started = std::time::Instant::now();
+        crate::modes::execute_mode(config, testutil::downzer(), Targets::from_urls(urls), shutdown, 1).await.unwrap();
+
+        assert!(started.elapsed().as_secs() < 2, "{:?}", started.elapsed());
+        assert_eq!(server.paths(), ["/0.txt"]);
+    }
+
+    #[tokio::test]
+    async fn identical_bodies_are_stored_once() {
+        let server = MockServer::start(|req| match req.path.as_str() {
+            "/c.txt" => Response::new(200).body("other"),
+            _ => Response::new(200).body("same"),
+        })
+        .await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testutil::mode_config("download", dir.path());
+        config.dedup_content = true;
+        config.max_concurrent = 1;
+        let urls = ["/a.txt", "/b.txt", "/c.txt"].map(|path| server.url(path)).to_vec();
+        let result = testutil::run(config, urls).await;
+
+        assert_eq!(testutil::files_in(dir.path()), [dir.path().join("a.txt"), dir.path().join("c.txt")]);
+        let details = result.custom_data.unwrap();
+        assert!(details.contains("Duplicados: 1 (4 bytes ahorrados)"), "{}", details);
+    }
+
+    #[tokio::test]
+    async fn manifest_records_every_url() {
+        let server = MockServer::start(|req| match req.path.as_str() {
+            "/doc.pdf" => Response::new(200).header("Content-Type", "application/pdf").body("%PDF"),
+            _ => Response::new(404),
+        })
+        .await;
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = dir.path().join("manifest.json");
+        let mut config = testutil::mode_config("download", dir.path());
+        config.manifest = Some(manifest.clone());
+        config.max_concurrent = 1;
+        testutil::run(config, vec![server.url("/doc.pdf"), server.url("/gone.pdf")]).await;
+
+        let written: serde_json::Value = serde_json::from_slice(&std::fs::read(&manifest).unwrap()).unwrap();
+        assert_eq!(written["task_id"], 1);
+        assert!(chrono::DateTime::parse_from_rfc3339(written["generated_at"].as_str().unwrap()).is_ok());
+        assert_eq!(
+            written["entries"],
+            serde_json::json!([
+                {
+                    "url": server.url("/doc.pdf"),
+                    "file": "doc.pdf",
+                    "status": 200,
+                    "bytes": 4,
+                    "content_type": "application/pdf",
+                    "outcome": "saved",
+                },
+                {
+                    "url": server.url("/gone.pdf"),
+                    "file": null,
+                    "status": 404,
+                    "bytes": null,
+                    "content_type": null,
+                    "outcome": "not_found",
+                },
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn only_new_skips_manifest_urls_and_appends_new_ones() {
+        use crate::core::report::{completed_urls, load_manifest};
+        use crate::core::Targets;
+
+        let server = MockServer::start(|req| match req.path.as_str() {
+            "/flaky.txt" => Response::new(500),
+            _ => Response::new(200).body("data"),
+        })
+        .await;
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = dir.path().join("manifest.json");
+        let mut config = testutil::mode_config("download", dir.path());
+        config.manifest = Some(manifest.clone());
+        config.max_concurrent = 1;
+        testutil::run(config.clone(), vec![server.url("/a.txt"), server.url("/flaky.txt")]).await;
+
+        // Segunda pasada como con --only-new: se omite lo ya descargado y se
+        // reintenta lo que falló
+        let known = completed_urls(&load_manifest(&manifest).unwrap());
+        config.manifest_append = true;
+        let urls = vec![server.url("/a.txt"), server.url("/flaky.txt"), server.url("/b.txt")];
+        testutil::run_targets(config, Targets::from_urls(urls).skip_urls(known)).await;
+
+        assert_eq!(server.paths(), ["/a.txt", "/flaky.txt", "/flaky.txt", "/b.txt"]);
+        let entries: Vec<(String, String)> = load_manifest(&manifest)
+            .unwrap()
+            .into_iter()
+            .map(|e| (e.url.trim_start_matches(&server.url("")).to_string(), e.outcome))
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                ("/a.txt".to_string(), "saved".to_string()),
+                ("/flaky.txt".to_string(), "http_error".to_string()),
+                ("/b.txt".to_string(), "saved".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,286 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+use super::network::Attempt;
+
+/// Usuario y contraseña del login anónimo (RFC 1635).
+const ANONYMOUS_USER: &str = "anonymous";
+const ANONYMOUS_PASS: &str = "anonymous@example.com";
+
+/// Entradas del listado que se guardan como mucho por host.
+const MAX_LISTING: usize = 50;
+
+/// Sesión de control FTP mínima: lo justo para el login y un `LIST`. Cada
+/// lectura y escritura tiene su propio `timeout`.
+struct Control {
+    stream: BufReader<TcpStream>,
+    peer: SocketAddr,
+    timeout: Duration,
+}
+
+impl Control {
+    async fn connect(addr: SocketAddr, wait: Duration) -> Result<Self, String> {
+        let stream = match timeout(wait, TcpStream::connect(addr)).await {
+            Ok(Ok(stream)) => stream,
+            Ok(Err(e)) => return Err(format!("connect: {}", e)),
+            Err(_) => return Err("connect: timeout".to_string()),
+        };
+        let mut control = Self { stream: BufReader::new(stream), peer: addr, timeout: wait };
+        // 120 es "espere", al que le sigue el 220 definitivo
+        let mut greeting = control.reply().await?;
+        if greeting.0 == 120 {
+            greeting = control.reply().await?;
+        }
+        if greeting.0 != 220 {
+            return Err(format!("unexpected greeting: {} {}", greeting.0, greeting.1));
+        }
+        Ok(control)
+    }
+
+    /// Lee una respuesta completa, incluidas las multilínea (`230-...` hasta `230 ...`).
+    async fn reply(&mut self) -> Result<(u16, String), String> {
+        let mut first = String::new();
+        self.read_line(&mut first).await?;
+        let code: u16 = first.get(..3).and_then(|c| c.parse().ok()).ok_or_else(|| format!("malformed reply: {}", first.trim_end()))?;
+        if first.as_bytes().get(3) == Some(&b'-') {
+            let end = format!("{} ", code);
+            loop {
+                let mut line = String::new();
+                self.read_line(&mut line).await?;
+                if line.starts_with(&end) {
+                    break;
+                }
+            }
+        }
+        Ok((code, first.get(4..).unwrap_or("").trim_end().to_string()))
+    }
+
+    async fn read_line(&mut self, line: &mut String) -> Result<(), String> {
+        match timeout(self.timeout, self.stream.read_line(line)).await {
+            Ok(Ok(0)) => Err("connection closed".to_string()),
+            Ok(Ok(_)) => Ok(()),
+            Ok(Err(e)) => Err(e.to_string()),
+            Err(_) => Err("timeout".to_string()),
+        }
+    }
+
+    async fn command(&mut self, command: &str) -> Result<(u16, String), String> {
+        let line = format!("{}\r\n", command);
+        match timeout(self.timeout, self.stream.get_mut().write_all(line.as_bytes())).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => return Err(e.to_string()),
+            Err(_) => return Err("timeout".to_string()),
+        }
+        self.reply().await
+    }
+
+    /// `USER`/`PASS`: 230 es válido (también sin pedir contraseña) y un 5xx
+    /// es un rechazo; un 4xx (421 por exceso de conexiones...) no dice nada
+    /// de las credenciales y queda como error.
+    async fn login(&mut self, user: &str, pass: &str) -> Result<Attempt, String> {
+        let (mut code, mut text) = self.command(&format!("USER {}", user)).await?;
+        if code == 331 {
+            (code, text) = self.command(&format!("PASS {}", pass)).await?;
+        }
+        Ok(match code {
+            230 => Attempt::Valid,
+            500..=599 => Attempt::Invalid,
+            _ => Attempt::Error(format!("{} {}", code, text)),
+        })
+    }
+
+    /// Abre el canal de datos en modo pasivo (`EPSV` en IPv6, `PASV` en
+    /// IPv4). De la respuesta de `PASV` solo se usa el puerto: la dirección
+    /// es la del canal de control, que sigue valiendo detrás de NAT.
+    async fn passive(&mut self) -> Result<TcpStream, String> {
+        let port = if self.peer.is_ipv6() {
+            let (code, text) = self.command("EPSV").await?;
+            if code != 229 {
+                return Err(format!("EPSV refused: {} {}", code, text));
+            }
+            // 229 Entering Extended Passive Mode (|||6446|)
+            text.split('|').nth(3).and_then(|p| p.parse().ok()).ok_or_else(|| format!("malformed EPSV reply: {}", text))?
+        } else {
+            let (code, text) = self.command("PASV").await?;
+            if code != 227 {
+                return Err(format!("PASV refused: {} {}", code, text));
+            }
+            // 227 Entering Passive Mode (h1,h2,h3,h4,p1,p2)
+            let fields: Vec<u16> = text
+                .split(|c: char| !c.is_ascii_digit())
+                .filter(|f| !f.is_empty())
+                .filter_map(|f| f.parse().ok())
+                .collect();
+            match fields[..] {
+                [.., p1, p2] if fields.len() >= 6 && p1 < 256 && p2 < 256 => p1 * 256 + p2,
+                _ => return Err(format!("malformed PASV reply: {}", text)),
+            }
+        };
+        match timeout(self.timeout, TcpStream::connect(SocketAddr::new(self.peer.ip(), port))).await {
+            Ok(Ok(stream)) => Ok(stream),
+            Ok(Err(e)) => Err(format!("data connection: {}", e)),
+            Err(_) => Err("data connection: timeout".to_string()),
+        }
+    }
+
+    /// `LIST` del directorio actual; devuelve como mucho `MAX_LISTING` líneas.
+    async fn list(&mut self) -> Result<Vec<String>, String> {
+        let mut data = self.passive().await?;
+        let (code, text) = self.command("LIST").await?;
+        if code != 125 && code != 150 {
+            return Err(format!("LIST refused: {} {}", code, text));
+        }
+        let mut raw = Vec::new();
+        match timeout(self.timeout, data.read_to_end(&mut raw)).await {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => return Err(format!("LIST: {}", e)),
+            Err(_) => return Err("LIST: timeout".to_string()),
+        }
+        drop(data);
+        // El 226 de cierre no cambia el resultado si los datos ya llegaron
+        let _ = self.reply().await;
+        Ok(String::from_utf8_lossy(&raw)
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .take(MAX_LISTING)
+            .map(|l| l.to_string())
+            .collect())
+    }
+
+    async fn quit(mut self) {
+        let _ = self.command("QUIT").await;
+    }
+}
+
+/// Prueba `user`/`pass` contra el servidor FTP de `addr`.
+pub(super) async fn login(addr: SocketAddr, user: &str, pass: &str, wait: Duration) -> Attempt {
+    let mut control = match Control::connect(addr, wait).await {
+        Ok(control) => control,
+        Err(e) => return Attempt::Error(e),
+    };
+    let attempt = control.login(user, pass).await.unwrap_or_else(Attempt::Error);
+    control.quit().await;
+    attempt
+}
+
+/// Comprobación de `--ftp-anon`: login anónimo y listado del directorio raíz.
+/// `Ok(Some(listado))` si se admite, `Ok(None)` si se rechaza. Si el login
+/// entra pero el listado falla, el acceso anónimo cuenta igual, sin listado.
+pub(super) async fn anonymous(addr: SocketAddr, wait: Duration) -> Result<Option<Vec<String>>, String> {
+    let mut control = Control::connect(addr, wait).await?;
+    let result = match control.login(ANONYMOUS_USER, ANONYMOUS_PASS).await? {
+        Attempt::Valid => Ok(Some(control.list().await.unwrap_or_default())),
+        Attempt::Invalid => Ok(None),
+        Attempt::Error(e) => Err(e),
+    };
+    control.quit().await;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil;
+    use tokio::net::TcpListener;
+
+    /// Servidor FTP de pruebas: acepta `admin`/`secret` y, con `anonymous`,
+    /// el login anónimo; `LIST` (tras `PASV`) devuelve dos entradas.
+    async fn ftp_server(anonymous: bool) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(session(BufReader::new(stream), anonymous));
+            }
+        });
+        addr
+    }
+
+    async fn session(mut stream: BufReader<TcpStream>, anonymous: bool) -> std::io::Result<()> {
+        stream.get_mut().write_all(b"220-Welcome\r\n220 test ftpd ready\r\n").await?;
+        let mut user = String::new();
+        let mut data: Option<TcpListener> = None;
+        loop {
+            let mut line = String::new();
+            if stream.read_line(&mut line).await? == 0 {
+                return Ok(());
+            }
+            let (command, arg) = line.trim_end().split_once(' ').unwrap_or((line.trim_end(), ""));
+            let reply = match command {
+                "USER" => {
+                    user = arg.to_string();
+                    "331 password please".to_string()
+                }
+                "PASS" if user == "admin" && arg == "secret" => "230 logged in".to_string(),
+                "PASS" if user == ANONYMOUS_USER && anonymous => "230 anonymous ok".to_string(),
+                "PASS" => "530 login incorrect".to_string(),
+                "PASV" => {
+                    let listener = TcpListener::bind("127.0.0.1:0").await?;
+                    let port = listener.local_addr()?.port();
+                    data = Some(listener);
+                    format!("227 Entering Passive Mode (127,0,0,1,{},{})", port / 256, port % 256)
+                }
+                "LIST" => {
+                    let (mut conn, _) = data.take().expect("PASV antes de LIST").accept().await?;
+                    stream.get_mut().write_all(b"150 listing\r\n").await?;
+                    conn.write_all(b"-rw-r--r-- 1 ftp ftp 12 a.txt\r\ndrwxr-xr-x 2 ftp ftp 0 pub\r\n").await?;
+                    drop(conn);
+                    "226 done".to_string()
+                }
+                "QUIT" => {
+                    stream.get_mut().write_all(b"221 bye\r\n").await?;
+                    return Ok(());
+                }
+                _ => "502 not implemented".to_string(),
+            };
+            stream.get_mut().write_all(format!("{}\r\n", reply).as_bytes()).await?;
+        }
+    }
+
+    const WAIT: Duration = Duration::from_secs(2);
+
+    #[tokio::test]
+    async fn credentials_are_accepted_or_rejected() {
+        let addr = ftp_server(false).await;
+        assert!(matches!(login(addr, "admin", "secret", WAIT).await, Attempt::Valid));
+        assert!(matches!(login(addr, "admin", "wrong", WAIT).await, Attempt::Invalid));
+    }
+
+    #[tokio::test]
+    async fn anonymous_access_lists_the_root() {
+        let listing = anonymous(ftp_server(true).await, WAIT).await.unwrap().unwrap();
+        assert_eq!(listing, ["-rw-r--r-- 1 ftp ftp 12 a.txt", "drwxr-xr-x 2 ftp ftp 0 pub"]);
+        assert_eq!(anonymous(ftp_server(false).await, WAIT).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn a_service_that_is_not_ftp_is_an_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            stream.write_all(b"SSH-2.0-OpenSSH\r\n").await.unwrap();
+        });
+        assert!(matches!(login(addr, "admin", "secret", WAIT).await, Attempt::Error(e) if e.starts_with("malformed reply")));
+    }
+
+    #[tokio::test]
+    async fn ftp_mode_reports_valid_pairs_and_anonymous_hosts() {
+        let addr = ftp_server(true).await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testutil::mode_config("ftp", dir.path());
+        config.combos = Some(vec![("admin".into(), "nope".into()), ("admin".into(), "secret".into())]);
+        config.ftp_anon = true;
+        let result = testutil::run(config, vec![addr.to_string()]).await;
+
+        assert_eq!((result.total, result.successful, result.failed), (2, 2, 0));
+        let details = result.custom_data.unwrap();
+        assert!(details.contains(&format!("Pares: [{} admin:secret]", addr)), "{}", details);
+        assert!(details.contains(&format!("Acceso anónimo: [{}]", addr)), "{}", details);
+        assert!(details.contains("Rechazadas: 1"), "{}", details);
+    }
+}
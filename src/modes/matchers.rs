@@ -0,0 +1,512 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use regex::Regex;
+use reqwest::header::{HeaderMap, HeaderName};
+
+/// Cómo se combinan los predicados de coincidencia (`--filter-mode`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FilterMode {
+    /// Deben cumplirse todos
+    #[default]
+    And,
+    /// Basta con uno
+    Or,
+}
+
+impl FilterMode {
+    pub fn parse(mode: &str) -> Result<Self> {
+        match mode.to_ascii_lowercase().as_str() {
+            "and" => Ok(Self::And),
+            "or" => Ok(Self::Or),
+            other => anyhow::bail!("Invalid --filter-mode '{}' (expected 'and' or 'or')", other),
+        }
+    }
+}
+
+/// Predicado sobre las cabeceras de la respuesta (`--match-header`):
+/// `Name` exige que exista la cabecera y `Name: regex` que algún valor encaje.
+#[derive(Debug, Clone)]
+pub struct HeaderMatcher {
+    name: HeaderName,
+    value: Option<Regex>,
+}
+
+impl HeaderMatcher {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let (name, value) = match spec.split_once(':') {
+            Some((name, value)) => (name.trim(), Some(value.trim())),
+            None => (spec.trim(), None),
+        };
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| anyhow::anyhow!("Invalid header name in --match-header '{}'", spec))?;
+        let value = match value.filter(|v| !v.is_empty()) {
+            Some(pattern) => Some(
+                Regex::new(pattern)
+                    .map_err(|e| anyhow::anyhow!("Invalid regex in --match-header '{}': {}", spec, e))?,
+            ),
+            None => None,
+        };
+        Ok(Self { name, value })
+    }
+
+    pub fn matches(&self, headers: &HeaderMap) -> bool {
+        let mut values = headers.get_all(&self.name).iter();
+        match &self.value {
+            None => values.next().is_some(),
+            Some(re) => values.any(|v| re.is_match(&String::from_utf8_lossy(v.as_bytes()))),
+        }
+    }
+}
+
+/// Lista de valores numéricos: números sueltos y rangos separados por comas
+/// (`200,301-308,403`). La usan los códigos de estado (`--match-code`), el
+/// tamaño, las palabras y las líneas del cuerpo (`--match-size`...) y los
+/// puertos de portscan (`--ports`).
+#[derive(Debug, Clone, Default)]
+pub struct NumberSet(Vec<(u64, u64)>);
+
+/// Códigos de estado de `--match-code` y `--filter-code`.
+pub type StatusCodes = NumberSet;
+
+impl NumberSet {
+    /// Interpreta una lista de códigos de estado (100-999).
+    pub fn parse(spec: &str, flag: &str) -> Result<Self> {
+        Self::parse_within(spec, flag, 100..=999, "status code")
+    }
+
+    /// Interpreta una lista de puertos (1-65535).
+    pub fn parse_ports(spec: &str, flag: &str) -> Result<Self> {
+        Self::parse_within(spec, flag, 1..=65535, "port")
+    }
+
+    /// Interpreta una lista de cantidades (tamaños, palabras, líneas).
+    pub fn parse_counts(spec: &str, flag: &str) -> Result<Self> {
+        Self::parse_within(spec, flag, 0..=u64::MAX, "number")
+    }
+
+    fn parse_within(spec: &str, flag: &str, valid: std::ops::RangeInclusive<u64>, what: &str) -> Result<Self> {
+        let code = |s: &str| {
+            s.trim()
+                .parse::<u64>()
+                .ok()
+                .filter(|c| valid.contains(c))
+                .ok_or_else(|| anyhow::anyhow!("Invalid {} '{}': '{}' is not a {}", flag, spec, s.trim(), what))
+        };
+        let mut ranges = Vec::new();
+        for part in spec.split(',').filter(|p| !p.trim().is_empty()) {
+            let range = match part.split_once('-') {
+                Some((from, to)) => (code(from)?, code(to)?),
+                None => (code(part)?, code(part)?),
+            };
+            if range.0 > range.1 {
+                anyhow::bail!("Invalid {} '{}': empty range '{}'", flag, spec, part.trim());
+            }
+            ranges.push(range);
+        }
+        if ranges.is_empty() {
+            anyhow::bail!("Invalid {} '{}': no {}s", flag, spec, what);
+        }
+        Ok(Self(ranges))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn contains(&self, value: u64) -> bool {
+        self.0.iter().any(|(from, to)| (*from..=*to).contains(&value))
+    }
+
+    /// Todos los valores, en el orden de la lista y sin repetir.
+    pub fn values(&self) -> Vec<u64> {
+        let mut seen = HashSet::new();
+        self.0.iter().flat_map(|(from, to)| *from..=*to).filter(|v| seen.insert(*v)).collect()
+    }
+}
+
+/// Medidas del cuerpo de la respuesta: bytes, palabras (separadas por
+/// espacios en blanco) y líneas.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BodyStats {
+    pub size: u64,
+    pub words: u64,
+    pub lines: u64,
+}
+
+impl BodyStats {
+    pub fn of(body: &[u8]) -> Self {
+        let text = String::from_utf8_lossy(body);
+        Self {
+            size: body.len() as u64,
+            words: text.split_whitespace().count() as u64,
+            lines: text.lines().count() as u64,
+        }
+    }
+}
+
+/// Predicados y filtros sobre una medida del cuerpo (`--match-size`/`--filter-size`...).
+#[derive(Debug, Clone, Default)]
+pub struct BodyMatchers {
+    pub size: NumberSet,
+    pub words: NumberSet,
+    pub lines: NumberSet,
+}
+
+impl BodyMatchers {
+    /// Número de medidas con lista.
+    pub fn len(&self) -> usize {
+        [&self.size, &self.words, &self.lines].iter().filter(|set| !set.is_empty()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Resultado de cada medida con lista, en orden tamaño, palabras, líneas.
+    fn results(&self, body: &BodyStats) -> Vec<bool> {
+        [(&self.size, body.size), (&self.words, body.words), (&self.lines, body.lines)]
+            .into_iter()
+            .filter(|(set, _)| !set.is_empty())
+            .map(|(set, value)| set.contains(value))
+            .collect()
+    }
+}
+
+/// Respuesta "por defecto" del servidor aprendida con `--auto-calibrate`:
+/// el código y la primera medida del cuerpo (tamaño, palabras o líneas)
+/// común a todas las respuestas de calibración.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Calibration {
+    pub status: u16,
+    pub metric: CalibratedMetric,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalibratedMetric {
+    Size(u64),
+    Words(u64),
+    Lines(u64),
+}
+
+impl Calibration {
+    /// Firma común de las respuestas a rutas inexistentes, o `None` si no
+    /// comparten código o ninguna medida coincide en todas (p.ej. el cuerpo
+    /// refleja la ruta pedida y cambia de tamaño, palabras y líneas).
+    pub fn learn(samples: &[(u16, BodyStats)]) -> Option<Self> {
+        let (status, first) = *samples.first()?;
+        if samples.iter().any(|(s, _)| *s != status) {
+            return None;
+        }
+        let same = |f: fn(&BodyStats) -> u64| samples.iter().all(|(_, b)| f(b) == f(&first));
+        let metric = if same(|b| b.size) {
+            CalibratedMetric::Size(first.size)
+        } else if same(|b| b.words) {
+            CalibratedMetric::Words(first.words)
+        } else if same(|b| b.lines) {
+            CalibratedMetric::Lines(first.lines)
+        } else {
+            return None;
+        };
+        Some(Self { status, metric })
+    }
+
+    pub fn matches(&self, status: u16, body: &BodyStats) -> bool {
+        status == self.status
+            && match self.metric {
+                CalibratedMetric::Size(size) => body.size == size,
+                CalibratedMetric::Words(words) => body.words == words,
+                CalibratedMetric::Lines(lines) => body.lines == lines,
+            }
+    }
+}
+
+impl std::fmt::Display for Calibration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (name, value) = match self.metric {
+            CalibratedMetric::Size(v) => ("size", v),
+            CalibratedMetric::Words(v) => ("words", v),
+            CalibratedMetric::Lines(v) => ("lines", v),
+        };
+        write!(f, "status {}, {} {}", self.status, name, value)
+    }
+}
+
+/// Predicados que marcan una respuesta como coincidencia en webrequest.
+/// `codes` y `body` cuentan como un predicado más cada lista; `filter_codes`
+/// y `filter_body` descartan la respuesta si cualquiera de sus listas encaja,
+/// cumpla lo que cumpla, igual que `calibration` si la respuesta tiene la firma aprendida.
+#[derive(Debug, Clone, Default)]
+pub struct ResponseMatchers {
+    pub headers: Vec<HeaderMatcher>,
+    pub codes: StatusCodes,
+    pub filter_codes: StatusCodes,
+    pub body: BodyMatchers,
+    pub filter_body: BodyMatchers,
+    pub calibration: Option<Calibration>,
+    pub mode: FilterMode,
+}
+
+impl ResponseMatchers {
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty()
+            && self.codes.is_empty()
+            && self.filter_codes.is_empty()
+            && self.body.is_empty()
+            && self.filter_body.is_empty()
+            && self.calibration.is_none()
+    }
+
+    /// Hace falta leer el cuerpo para medirlo; si no, se descarta sin leerlo.
+    pub fn needs_body(&self) -> bool {
+        !self.body.is_empty() || !self.filter_body.is_empty() || self.calibration.is_some()
+    }
+
+    /// `true` si la respuesta no está filtrada y cumple los predicados según
+    /// `mode`. Sin predicados ni filtros no hay nada que marcar y devuelve
+    /// `false`; con solo filtros, coincide todo lo no filtrado. Si el cuerpo
+    /// no se pudo leer (`body` es `None`), sus predicados no se cumplen.
+    pub fn matches(&self, status: u16, headers: &HeaderMap, body: Option<&BodyStats>) -> bool {
+        if self.is_empty() || self.filter_codes.contains(status as u64) {
+            return false;
+        }
+        if let Some(body) = body {
+            if self.filter_body.results(body).contains(&true)
+                || self.calibration.is_some_and(|c| c.matches(status, body))
+            {
+                return false;
+            }
+        }
+        let code = (!self.codes.is_empty()).then(|| self.codes.contains(status as u64));
+        let body_results = match body {
+            Some(body) => self.body.results(body),
+            None => vec![false; self.body.len()],
+        };
+        let results: Vec<bool> = self
+            .headers
+            .iter()
+            .map(|m| m.matches(headers))
+            .chain(code)
+            .chain(body_results)
+            .collect();
+        match self.mode {
+            _ if results.is_empty() => true,
+            FilterMode::And => results.iter().all(|hit| *hit),
+            FilterMode::Or => results.iter().any(|hit| *hit),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.append(*name, HeaderValue::from_static(value));
+        }
+        map
+    }
+
+    #[test]
+    fn header_presence_ignores_the_value() {
+        let matcher = HeaderMatcher::parse("Set-Cookie").unwrap();
+        assert!(matcher.matches(&headers(&[("set-cookie", "sid=1")])));
+        assert!(matcher.matches(&headers(&[("set-cookie", "")])));
+        assert!(!matcher.matches(&headers(&[("server", "nginx")])));
+    }
+
+    #[test]
+    fn header_value_regex_checks_every_value() {
+        let matcher = HeaderMatcher::parse("Server: ^nginx(/1\\.2)?").unwrap();
+        assert!(matcher.matches(&headers(&[("server", "nginx/1.2.3")])));
+        assert!(!matcher.matches(&headers(&[("server", "Apache")])));
+        assert!(!matcher.matches(&HeaderMap::new()));
+        let cookie = HeaderMatcher::parse("set-cookie: admin=").unwrap();
+        assert!(cookie.matches(&headers(&[("set-cookie", "sid=1"), ("set-cookie", "admin=true")])));
+    }
+
+    #[test]
+    fn invalid_header_specs_are_rejected() {
+        assert!(HeaderMatcher::parse("Bad Name").is_err());
+        assert!(HeaderMatcher::parse("Server: (").is_err());
+        // Un valor vacío equivale a comprobar solo la presencia
+        assert!(HeaderMatcher::parse("Server:").unwrap().matches(&headers(&[("server", "x")])));
+    }
+
+    #[test]
+    fn header_predicates_compose_with_the_filter_mode() {
+        let mut matchers = ResponseMatchers {
+            headers: vec![HeaderMatcher::parse("Server: nginx").unwrap()],
+            codes: NumberSet::parse("200", "--match-code").unwrap(),
+            ..Default::default()
+        };
+        let nginx = headers(&[("server", "nginx")]);
+        assert!(matchers.matches(200, &nginx, None));
+        assert!(!matchers.matches(404, &nginx, None));
+        assert!(!matchers.matches(200, &HeaderMap::new(), None));
+        matchers.mode = FilterMode::Or;
+        assert!(matchers.matches(404, &nginx, None));
+        assert!(matchers.matches(200, &HeaderMap::new(), None));
+        assert!(!matchers.matches(404, &HeaderMap::new(), None));
+    }
+
+    fn codes(spec: &str) -> StatusCodes {
+        NumberSet::parse(spec, "--match-code").unwrap()
+    }
+
+    #[test]
+    fn status_lists_accept_codes_and_ranges() {
+        let set = codes("200, 301-303,403");
+        assert_eq!(set.values(), [200, 301, 302, 303, 403]);
+        assert!(set.contains(302) && !set.contains(404));
+        for bad in ["", "abc", "99", "1000", "404-400", "200-"] {
+            assert!(NumberSet::parse(bad, "--match-code").is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn match_codes_only() {
+        let matchers = ResponseMatchers { codes: codes("200,301"), ..Default::default() };
+        assert!(matchers.matches(200, &HeaderMap::new(), None));
+        assert!(matchers.matches(301, &HeaderMap::new(), None));
+        assert!(!matchers.matches(404, &HeaderMap::new(), None));
+    }
+
+    #[test]
+    fn filter_codes_only() {
+        let matchers = ResponseMatchers { filter_codes: codes("404,500-599"), ..Default::default() };
+        assert!(matchers.matches(200, &HeaderMap::new(), None));
+        assert!(matchers.matches(403, &HeaderMap::new(), None));
+        assert!(!matchers.matches(404, &HeaderMap::new(), None));
+        assert!(!matchers.matches(503, &HeaderMap::new(), None));
+    }
+
+    #[test]
+    fn filter_wins_over_match() {
+        let matchers = ResponseMatchers { codes: codes("200-299"), filter_codes: codes("204"), ..Default::default() };
+        assert!(matchers.matches(200, &HeaderMap::new(), None));
+        assert!(!matchers.matches(204, &HeaderMap::new(), None));
+        assert!(!matchers.matches(404, &HeaderMap::new(), None));
+    }
+
+    #[test]
+    fn without_predicates_nothing_matches() {
+        assert!(!ResponseMatchers::default().matches(200, &HeaderMap::new(), None));
+    }
+
+    #[tokio::test]
+    async fn webrequest_marks_only_the_matching_codes() {
+        use crate::testutil::{self, MockServer, Response};
+
+        let server = MockServer::start(|req| Response::new(req.path.trim_start_matches('/').parse().unwrap())).await;
+        let dir = tempfile::tempdir().unwrap();
+        let report = dir.path().join("report.json");
+        let mut config = testutil::mode_config("webrequest", dir.path());
+        config.matchers = ResponseMatchers { codes: codes("200-299"), filter_codes: codes("204"), ..Default::default() };
+        config.ordered_output = true;
+        config.report = Some(report.clone());
+        let urls: Vec<String> = ["/200", "/204", "/404"].iter().map(|p| server.url(p)).collect();
+        testutil::run(config, urls).await;
+
+        let rows: Vec<serde_json::Value> = serde_json::from_str(&std::fs::read_to_string(report).unwrap()).unwrap();
+        let outcomes: Vec<&str> = rows.iter().map(|row| row["outcome"].as_str().unwrap()).collect();
+        assert_eq!(outcomes, ["match", "success", "http_error"]);
+    }
+
+    fn counts(spec: &str) -> NumberSet {
+        NumberSet::parse_counts(spec, "--match-size").unwrap()
+    }
+
+    #[test]
+    fn body_stats_count_bytes_words_and_lines() {
+        let stats = BodyStats::of(b"hello  world\nsecond line\n");
+        assert_eq!((stats.size, stats.words, stats.lines), (25, 4, 2));
+        let empty = BodyStats::of(b"");
+        assert_eq!((empty.size, empty.words, empty.lines), (0, 0, 0));
+    }
+
+    #[test]
+    fn only_body_predicates_need_the_body() {
+        assert!(!ResponseMatchers { codes: codes("200"), ..Default::default() }.needs_body());
+        let size = ResponseMatchers { body: BodyMatchers { size: counts("10"), ..Default::default() }, ..Default::default() };
+        assert!(size.needs_body());
+        let lines = ResponseMatchers { filter_body: BodyMatchers { lines: counts("1"), ..Default::default() }, ..Default::default() };
+        assert!(lines.needs_body());
+    }
+
+    #[test]
+    fn unread_bodies_fail_their_predicates() {
+        let matchers = ResponseMatchers { body: BodyMatchers { size: counts("0-100"), ..Default::default() }, ..Default::default() };
+        assert!(!matchers.matches(200, &HeaderMap::new(), None));
+    }
+
+    /// Outcome de cada ruta de `/short`, `/medium` y `/long` con `matchers`.
+    async fn body_outcomes(matchers: ResponseMatchers) -> Vec<String> {
+        use crate::testutil::{self, MockServer, Response};
+
+        let server = MockServer::start(|req| match req.path.as_str() {
+            "/short" => Response::new(200).body("ok"),
+            "/medium" => Response::new(200).body("one two three\nfour"),
+            _ => Response::new(200).body("a b\nc d\ne f\ng h"),
+        })
+        .await;
+        let dir = tempfile::tempdir().unwrap();
+        let report = dir.path().join("report.json");
+        let mut config = testutil::mode_config("webrequest", dir.path());
+        config.matchers = matchers;
+        config.ordered_output = true;
+        config.report = Some(report.clone());
+        let urls: Vec<String> = ["/short", "/medium", "/long"].iter().map(|p| server.url(p)).collect();
+        testutil::run(config, urls).await;
+
+        let rows: Vec<serde_json::Value> = serde_json::from_str(&std::fs::read_to_string(report).unwrap()).unwrap();
+        rows.iter().map(|row| row["outcome"].as_str().unwrap().to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn match_size_selects_by_body_length() {
+        let body = BodyMatchers { size: counts("2,18"), ..Default::default() };
+        let outcomes = body_outcomes(ResponseMatchers { body, ..Default::default() }).await;
+        assert_eq!(outcomes, ["match", "match", "success"]);
+    }
+
+    #[tokio::test]
+    async fn filter_words_drops_by_word_count() {
+        let filter_body = BodyMatchers { words: counts("4"), ..Default::default() };
+        let outcomes = body_outcomes(ResponseMatchers { filter_body, ..Default::default() }).await;
+        assert_eq!(outcomes, ["match", "success", "match"]);
+    }
+
+    #[tokio::test]
+    async fn match_lines_selects_by_line_count() {
+        let body = BodyMatchers { lines: counts("3-10"), ..Default::default() };
+        let outcomes = body_outcomes(ResponseMatchers { body, ..Default::default() }).await;
+        assert_eq!(outcomes, ["success", "success", "match"]);
+    }
+
+    #[test]
+    fn calibration_learns_the_first_shared_metric() {
+        let fixed = BodyStats::of(b"Not found");
+        let learned = Calibration::learn(&[(200, fixed), (200, fixed), (200, fixed)]).unwrap();
+        assert_eq!(learned, Calibration { status: 200, metric: CalibratedMetric::Size(9) });
+        assert!(learned.matches(200, &fixed));
+        assert!(!learned.matches(200, &BodyStats::of(b"Welcome, admin")));
+        assert!(!learned.matches(404, &fixed));
+
+        // Un cuerpo que refleja la ruta cambia de tamaño pero no de palabras
+        let reflected = [(200, BodyStats::of(b"no /abc here")), (200, BodyStats::of(b"no /abcdefgh here"))];
+        assert_eq!(Calibration::learn(&reflected).unwrap().metric, CalibratedMetric::Words(3));
+    }
+
+    #[test]
+    fn calibration_needs_a_common_signature() {
+        assert_eq!(Calibration::learn(&[]), None);
+        let body = BodyStats::of(b"x");
+        assert_eq!(Calibration::learn(&[(200, body), (404, body)]), None);
+        let different = [(200, BodyStats::of(b"a")), (200, BodyStats::of(b"b c\nd e f"))];
+        assert_eq!(Calibration::learn(&different), None);
+    }
+}
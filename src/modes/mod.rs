@@ -2,10 +2,27 @@ pub mod download;
 pub mod webrequest;
 pub mod portscan;
 pub mod network;
+pub mod ftp;
+pub mod smtp;
+pub mod telnet;
+pub mod matchers;
+pub mod recursion;
 
 use anyhow::Result;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
-use crate::core::Downzer;
+use std::time::Duration;
+use crate::core::{Downzer, Targets};
+use crate::core::filename::NameTemplate;
+use crate::core::body::BodyTemplate;
+use crate::core::cookies::CookieJar;
+use crate::core::credentials::CredentialTemplate;
+use crate::core::headers::HeaderTemplates;
+use crate::core::live::LiveStats;
+use crate::core::retry::RetryPolicy;
+use crate::core::sigv4::SigV4;
+use crate::core::throttle::Delay;
+use matchers::ResponseMatchers;
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
 
@@ -14,38 +31,140 @@ pub struct ModeConfig {
     pub mode: String,
     pub url_or_target: String,
     pub method: Option<String>,
-    pub data: Option<String>,
-    pub data_file: Option<PathBuf>,
+    /// Cuerpo de `--data`/`--data-file` con sus placeholders (webrequest)
+    pub body: Option<BodyTemplate>,
     pub download_body: bool,
+    pub content_types: Vec<String>,
+    pub max_filename_length: usize,
+    /// Plantilla de ruta de las descargas (`--name-template`)
+    pub name_template: Option<NameTemplate>,
+    /// Replicar la ruta de la URL en el directorio de salida (`--preserve-path`)
+    pub preserve_path: bool,
+    /// Cabeceras de `--header` y cookies de `--cookie` para download y webrequest
+    pub headers: HeaderTemplates,
+    /// Credenciales de `--auth` para los modos de red (ssh...)
+    pub credentials: Option<CredentialTemplate>,
+    /// Pares usuario/contraseña de `--combo`, que se prueban contra cada objetivo
+    pub combos: Option<Vec<(String, String)>>,
+    /// Dejar de probar credenciales contra un host tras su primer login
+    /// válido (`--stop-on-success`)
+    pub stop_on_success: bool,
+    /// Probar el login anónimo de cada host y listar su raíz (`--ftp-anon`; ftp)
+    pub ftp_anon: bool,
+    /// Comprobar si cada host hace de relay abierto (`--smtp-relay-test`; smtp)
+    pub smtp_relay_test: bool,
+    /// Texto que marca un login telnet correcto (`--success-regex`; por
+    /// defecto, un prompt de shell)
+    pub success_regex: Option<regex::Regex>,
+    /// Cookies compartidas por las peticiones de la tarea (`--cookie-jar`)
+    pub cookie_jar: Option<Arc<CookieJar>>,
+    /// Conservar `Authorization`/`Cookie` en redirecciones a otro origen (`--location-trusted`)
+    pub location_trusted: bool,
+    pub exec: Option<String>,
+    /// Orden de la tabla de códigos de estado en webrequest: "count" o "code"
+    pub summary_by_status: Option<String>,
+    pub ordered_output: bool,
+    pub quiet_errors: bool,
+    /// Deduplicar descargas por contenido (`--dedup-by content-hash`)
+    pub dedup_content: bool,
+    pub hardlink_dupes: bool,
+    /// Límites de tamaño en bytes de los archivos descargados
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    /// Reanudar descargas parciales con HTTP Range (`--resume`)
+    pub resume: bool,
+    /// Bytes por segundo entre todas las descargas (`--rate-limit`)
+    pub rate_limit: Option<u64>,
+    /// Pausa entre peticiones (`--delay`; download y webrequest)
+    pub delay: Option<Delay>,
+    /// Máximo de archivos por extensión (`--cap-per-extension`)
+    pub extension_caps: HashMap<String, usize>,
+    /// Peticiones de calentamiento excluidas de las estadísticas (webrequest)
+    pub warm_up: usize,
+    /// Aprender la respuesta a rutas inexistentes y filtrarla (webrequest)
+    pub auto_calibrate: bool,
+    /// Repetir la pasada bajo cada directorio descubierto, hasta este nivel
+    /// (`--recursive`, `--recursion-depth`; webrequest)
+    pub recursion_depth: Option<usize>,
+    /// Directorio donde guardar el cuerpo de cada respuesta (webrequest)
+    pub save_responses: Option<PathBuf>,
+    /// CSV donde añadir una fila de estadísticas por segundo (`--interval-stats`)
+    pub interval_stats: Option<PathBuf>,
+    /// Contadores de la ejecución, compartidos con la persistencia del progreso
+    pub live: Arc<LiveStats>,
+    /// Informe por URL con resultado y procedencia (`--report`)
+    pub report: Option<PathBuf>,
+    /// Manifiesto con lo descargado (`--manifest`)
+    pub manifest: Option<PathBuf>,
+    /// Omitir lo que ya está en el manifiesto y ampliarlo (`--only-new`)
+    pub manifest_append: bool,
+    /// Segundos de espera para lo pendiente tras lanzar todas las peticiones
+    pub drain_timeout: Option<u64>,
+    /// Predicados que marcan respuestas como coincidencia en webrequest
+    pub matchers: ResponseMatchers,
+    /// Firma AWS SigV4 de cada petición (`--sigv4`)
+    pub sigv4: Option<SigV4>,
+    /// Reintentos de respuestas 429/503 según `Retry-After`
+    pub retry: RetryPolicy,
     pub mac: Option<Vec<String>>,
     pub ua: Option<Vec<String>>,
     pub no_dns: bool,
     pub timeout: u64,
     pub max_concurrent: usize,
+    /// p90 de latencia que persigue la concurrencia adaptativa (`--target-latency`)
+    pub target_latency: Option<Duration>,
+    /// Descargas simultáneas por host (`--max-per-host`)
+    pub max_per_host: Option<usize>,
+    /// Puertos con los que se combina cada host en portscan (`--ports`, `--top-ports`)
+    pub ports: Option<Vec<u16>>,
+    /// Informe del escaneo en XML de nmap (`--scan-output-xml`; portscan)
+    pub scan_output_xml: Option<PathBuf>,
+    /// Escaneo UDP en vez de TCP connect (`--udp`; portscan)
+    pub udp: bool,
+    /// Bytes a leer del banner de cada puerto abierto (`--banner`; portscan)
+    pub banner_bytes: Option<usize>,
+    /// Enviar `HEAD / HTTP/1.0` si el servicio no saluda (`--banner-probe`)
+    pub banner_probe: bool,
+    /// Conexiones TCP simultáneas en portscan (por defecto `max_concurrent`)
+    pub connect_concurrency: Option<usize>,
+    /// Lecturas de banner simultáneas en portscan (por defecto `max_concurrent`)
+    pub read_concurrency: Option<usize>,
     pub verbose: u8,
     pub quiet: bool,
     pub outdir: PathBuf,
-    pub proxy: Option<String>,
 }
 
 pub async fn execute_mode(
     mode_config: ModeConfig,
     downzer: Arc<Downzer>,
-    urls: Vec<String>,
+    targets: Targets,
     shutdown: Arc<AtomicBool>,
     task_id: u32,
 ) -> Result<ModeResult> {
     match mode_config.mode.to_lowercase().as_str() {
-        "download" => download::execute(mode_config, downzer, urls, shutdown, task_id).await,
-        "webrequest" | "web" => webrequest::execute(mode_config, downzer, urls, shutdown, task_id).await,
-        "portscan" | "port" => portscan::execute(mode_config, downzer, urls, shutdown, task_id).await,
+        "download" => download::execute(mode_config, downzer, targets, shutdown, task_id).await,
+        "webrequest" | "web" => webrequest::execute(mode_config, downzer, targets, shutdown, task_id).await,
+        "portscan" | "port" => portscan::execute(mode_config, downzer, targets, shutdown, task_id).await,
         "ssh" | "ftp" | "telnet" | "mail" | "imap" | "pop3" | "smtp" => {
-            network::execute(mode_config, downzer, urls, shutdown, task_id).await
+            network::execute(mode_config, downzer, targets, shutdown, task_id).await
         }
         _ => anyhow::bail!("Unknown mode: {}. Available: download, webrequest, portscan, ssh, ftp, telnet, mail", mode_config.mode),
     }
 }
 
+/// Resuelve el valor de `--data` al estilo curl: `@ruta` lee el cuerpo del
+/// archivo y `@@texto` envía el literal `@texto`.
+pub fn resolve_data_arg(data: &str) -> Result<String> {
+    if let Some(literal) = data.strip_prefix("@@") {
+        return Ok(format!("@{}", literal));
+    }
+    if let Some(path) = data.strip_prefix('@') {
+        return std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Could not read data file {}: {}", path, e));
+    }
+    Ok(data.to_string())
+}
+
 #[derive(Debug, Clone)]
 pub struct ModeResult {
     pub mode: String,
@@ -53,5 +172,102 @@ pub struct ModeResult {
     pub successful: usize,
     pub failed: usize,
     pub errors: Vec<String>,
+    /// Histograma `código HTTP → respuestas` (webrequest; vacío en el resto)
+    pub status_counts: BTreeMap<u16, usize>,
     pub custom_data: Option<String>,
 }
+
+/// Resumen de `--json`, con las claves en este orden.
+#[derive(serde::Serialize)]
+pub struct RunSummary<'a> {
+    pub task_id: u32,
+    pub mode: &'a str,
+    pub total: usize,
+    pub successful: usize,
+    pub failed: usize,
+    /// Solo en webrequest; el código 0 aparece como `error`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub status_counts: BTreeMap<String, usize>,
+    pub errors: &'a [String],
+    pub details: Option<&'a str>,
+}
+
+impl ModeResult {
+    /// Resumen de fin de tarea para `--json`.
+    pub fn summary(&self, task_id: u32) -> RunSummary<'_> {
+        RunSummary {
+            task_id,
+            mode: &self.mode,
+            total: self.total,
+            successful: self.successful,
+            failed: self.failed,
+            status_counts: self
+                .status_counts
+                .iter()
+                .map(|(code, count)| (webrequest::status_label(*code), *count))
+                .collect(),
+            errors: &self.errors,
+            details: self.custom_data.as_deref(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_summary_carries_the_status_histogram() {
+        let result = ModeResult {
+            mode: "webrequest".to_string(),
+            total: 4,
+            successful: 2,
+            failed: 2,
+            errors: vec![],
+            status_counts: BTreeMap::from([(0, 1), (200, 2), (404, 1)]),
+            custom_data: Some("Velocidad: 1.00 req/s".to_string()),
+        };
+        let json = serde_json::to_string(&result.summary(7)).unwrap();
+        assert_eq!(
+            json,
+            r#"{"task_id":7,"mode":"webrequest","total":4,"successful":2,"failed":2,"status_counts":{"200":2,"404":1,"error":1},"errors":[],"details":"Velocidad: 1.00 req/s"}"#
+        );
+    }
+
+    #[test]
+    fn json_summary_omits_an_empty_histogram() {
+        let result = ModeResult {
+            mode: "download".to_string(),
+            total: 1,
+            successful: 1,
+            failed: 0,
+            errors: vec![],
+            status_counts: BTreeMap::new(),
+            custom_data: None,
+        };
+        let json: serde_json::Value = serde_json::to_value(result.summary(1)).unwrap();
+        assert!(json.get("status_counts").is_none());
+        assert_eq!(json["details"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn data_at_file_reads_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("body.json");
+        std::fs::write(&path, "{\"user\":\"FUZZW1\"}").unwrap();
+        let arg = format!("@{}", path.display());
+        assert_eq!(resolve_data_arg(&arg).unwrap(), "{\"user\":\"FUZZW1\"}");
+    }
+
+    #[test]
+    fn data_double_at_is_a_literal_at() {
+        assert_eq!(resolve_data_arg("@@x").unwrap(), "@x");
+        assert_eq!(resolve_data_arg("a=@b").unwrap(), "a=@b");
+    }
+
+    #[test]
+    fn data_missing_file_is_an_error() {
+        let err = resolve_data_arg("@/nonexistent/downzer-body").unwrap_err();
+        assert!(err.to_string().contains("/nonexistent/downzer-body"), "{}", err);
+    }
+}
@@ -1,23 +1,166 @@
 use anyhow::Result;
+use std::collections::{BTreeMap, VecDeque};
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
 use std::sync::Arc;
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 use colored::*;
 
-use crate::core::Downzer;
-use super::{ModeConfig, ModeResult};
+use tokio::sync::Semaphore;
+
+use crate::core::{Downzer, Targets};
+use crate::core::drain::join_before;
+use crate::core::live::IntervalSampler;
+use crate::core::output::OutputLine;
+use super::{ftp, smtp, telnet, ModeConfig, ModeResult};
+
+/// Protocolos con login que prueban los modos de red.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Protocol {
+    Ssh,
+    Ftp,
+    Smtp,
+    Telnet,
+}
+
+impl Protocol {
+    fn default_port(self) -> u16 {
+        match self {
+            Protocol::Ssh => 22,
+            Protocol::Ftp => 21,
+            Protocol::Smtp => 25,
+            Protocol::Telnet => 23,
+        }
+    }
+}
+
+/// Comprobación sin credenciales que se hace una vez por host, con su
+/// primer objetivo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HostCheck {
+    /// Login `anonymous` y listado de la raíz (`--ftp-anon`)
+    FtpAnonymous,
+    /// Relay de correo entre dominios ajenos (`--smtp-relay-test`)
+    SmtpRelay,
+}
+
+impl HostCheck {
+    /// Nombre del hallazgo en el resumen
+    fn label(self) -> &'static str {
+        match self {
+            HostCheck::FtpAnonymous => "Acceso anónimo",
+            HostCheck::SmtpRelay => "Relay abierto",
+        }
+    }
+}
+
+/// Resultado de un intento de login.
+#[derive(Debug)]
+pub(super) enum Attempt {
+    /// El servidor aceptó las credenciales
+    Valid,
+    /// El servidor las rechazó
+    Invalid,
+    /// No se llegó a probarlas (conexión, handshake, timeout...)
+    Error(String),
+}
+
+/// Resultado de un objetivo para el colector.
+struct LoginResult {
+    /// `host:puerto`, usuario y contraseña de un login válido
+    valid: Option<(String, String, String)>,
+    /// `host:puerto` en el que la comprobación por host dio positivo
+    finding: Option<String>,
+    /// El servidor rechazó las credenciales
+    rejected: bool,
+    /// No se probó: el host ya tenía un login válido (`--stop-on-success`)
+    skipped: bool,
+    error: bool,
+    lines: Vec<OutputLine>,
+}
+
+/// Separa `host[:puerto]` (IPv6 entre corchetes, `[::1]:22`); sin puerto se usa `default`.
+fn parse_host_port(target: &str, default: u16) -> Option<(String, u16)> {
+    let target = target.trim();
+    if let Some(rest) = target.strip_prefix('[') {
+        let (host, tail) = rest.split_once(']')?;
+        let port = match tail.strip_prefix(':') {
+            Some(port) => port.parse().ok()?,
+            None if tail.is_empty() => default,
+            None => return None,
+        };
+        return Some((host.to_string(), port));
+    }
+    match target.rsplit_once(':') {
+        // Una IPv6 sin corchetes no lleva puerto
+        Some((host, _)) if host.contains(':') => Some((target.to_string(), default)),
+        Some((host, port)) if !host.is_empty() => Some((host.to_string(), port.parse().ok()?)),
+        Some(_) => None,
+        None if !target.is_empty() => Some((target.to_string(), default)),
+        None => None,
+    }
+}
+
+/// Resuelve `host:puerto` a la primera dirección; con `no_dns` solo acepta IPs.
+async fn resolve(host: &str, port: u16, no_dns: bool) -> std::result::Result<SocketAddr, String> {
+    if let Ok(ip) = host.parse() {
+        return Ok(SocketAddr::new(ip, port));
+    }
+    if no_dns {
+        return Err("not an IP address (DNS disabled)".to_string());
+    }
+    tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| format!("cannot resolve: {}", e))?
+        .next()
+        .ok_or_else(|| "no address for host".to_string())
+}
+
+/// Código de libssh2 para credenciales rechazadas.
+const LIBSSH2_ERROR_AUTHENTICATION_FAILED: std::os::raw::c_int = -18;
+
+/// Login SSH por contraseña con libssh2. Es bloqueante: se llama desde
+/// `spawn_blocking`. `timeout` vale para la conexión y para cada operación.
+fn ssh_login(addr: SocketAddr, user: &str, pass: &str, timeout: Duration) -> Attempt {
+    let tcp = match std::net::TcpStream::connect_timeout(&addr, timeout) {
+        Ok(tcp) => tcp,
+        Err(e) => return Attempt::Error(format!("connect: {}", e)),
+    };
+    let mut session = match ssh2::Session::new() {
+        Ok(session) => session,
+        Err(e) => return Attempt::Error(e.to_string()),
+    };
+    session.set_timeout(timeout.as_millis().min(u32::MAX as u128) as u32);
+    session.set_tcp_stream(tcp);
+    if let Err(e) = session.handshake() {
+        return Attempt::Error(format!("handshake: {}", e.message()));
+    }
+    // Solo el rechazo de la autenticación dice algo de las credenciales; un
+    // timeout o una desconexión (p.ej. por exceso de intentos) son errores
+    match session.userauth_password(user, pass) {
+        Ok(()) if session.authenticated() => Attempt::Valid,
+        Ok(()) => Attempt::Invalid,
+        Err(e) if e.code() == ssh2::ErrorCode::Session(LIBSSH2_ERROR_AUTHENTICATION_FAILED) => Attempt::Invalid,
+        Err(e) => Attempt::Error(e.message().to_string()),
+    }
+}
 
 pub async fn execute(
     config: ModeConfig,
     _downzer: Arc<Downzer>,
-    urls: Vec<String>,
-    _shutdown: Arc<AtomicBool>,
+    targets: Targets,
+    shutdown: Arc<AtomicBool>,
     _task_id: u32,
 ) -> Result<ModeResult> {
     let protocol = config.mode.to_lowercase();
-    
+
     if !config.quiet {
         println!("{} Modo: Protocolo de Red ({})", "[*]".blue(), protocol.cyan());
-        println!("  Objetivos: {}", urls.len());
+        println!("  Objetivos: {}", targets.total());
+        if let Some(combos) = &config.combos {
+            println!("  Combos: {} por objetivo ({} intentos)", combos.len(), targets.total().saturating_mul(combos.len()));
+        }
         if config.verbose >= 2 {
             println!("  Concurrencia: {}", config.max_concurrent);
             println!("  Timeout: {}s", config.timeout);
@@ -30,25 +173,411 @@ pub async fn execute(
         }
     }
 
-    match protocol.as_str() {
-        "ssh" => {
-            // TODO: Implementar SSH con ssh2 crate
-            anyhow::bail!("SSH mode not yet implemented. Install ssh2 crate for support.")
+    let protocol = match protocol.as_str() {
+        "ssh" => Protocol::Ssh,
+        "ftp" => Protocol::Ftp,
+        "telnet" => Protocol::Telnet,
+        "mail" | "smtp" => Protocol::Smtp,
+        "imap" | "pop3" => {
+            // TODO: Implementar IMAP/POP3 con async-imap, async-pop3
+            anyhow::bail!("IMAP/POP3 mode not yet implemented. Install async-imap or async-pop3 for support.")
+        }
+        _ => {
+            anyhow::bail!("Unknown network protocol: {}. Available: ssh, ftp, telnet, imap, pop3, smtp", protocol)
+        }
+    };
+    if config.ftp_anon && protocol != Protocol::Ftp {
+        anyhow::bail!("--ftp-anon only applies to ftp mode");
+    }
+    if config.smtp_relay_test && protocol != Protocol::Smtp {
+        anyhow::bail!("--smtp-relay-test only applies to smtp/mail mode");
+    }
+    if config.success_regex.is_some() && protocol != Protocol::Telnet {
+        anyhow::bail!("--success-regex only applies to telnet mode");
+    }
+    let host_check = if config.ftp_anon {
+        Some(HostCheck::FtpAnonymous)
+    } else if config.smtp_relay_test {
+        Some(HostCheck::SmtpRelay)
+    } else {
+        None
+    };
+    let credentials = config.credentials.clone();
+    let combos = config.combos.clone().unwrap_or_default();
+    if credentials.is_none() && combos.is_empty() && host_check.is_none() {
+        anyhow::bail!(
+            "{} mode needs credentials: --auth user:pass (placeholders allowed, e.g. --auth FUZZW1:FUZZW2) or --combo <file>",
+            config.mode
+        );
+    }
+    // Con --combo cada objetivo se prueba con todos los pares, en orden
+    let per_target = combos.len().max(1);
+    let attempts = targets.flat_map(move |target| (0..per_target).map(move |i| (target.clone(), i)));
+
+    let live = config.live.clone();
+    let sampler = match &config.interval_stats {
+        Some(path) => Some(IntervalSampler::start(path, live.clone(), shutdown.clone())?),
+        None => None,
+    };
+
+    let semaphore = Arc::new(Semaphore::new(config.max_concurrent.max(1)));
+    let timeout = Duration::from_secs(config.timeout);
+    let mut pending = VecDeque::new();
+    let mut processed: usize = 0;
+    let mut errors: usize = 0;
+    let mut rejected: usize = 0;
+    let mut valid: Vec<(String, String, String)> = Vec::new();
+    let mut findings: Vec<String> = Vec::new();
+    let mut checked_hosts: HashSet<String> = HashSet::new();
+    // Con --stop-on-success, una bandera por host que se activa con su
+    // primer login válido; los intentos pendientes contra él se omiten
+    let mut solved_hosts: HashMap<String, Arc<AtomicBool>> = HashMap::new();
+    let mut skipped: usize = 0;
+    let mut skipped_in_flight: usize = 0;
+    let start = Instant::now();
+
+    let mut record = |result: Option<LoginResult>| {
+        let Some(result) = result else {
+            processed += 1;
+            errors += 1;
+            live.record(true, 0);
+            return;
+        };
+        live.record(result.error, 0);
+        if result.skipped {
+            skipped_in_flight += 1;
+        } else {
+            processed += 1;
         }
-        "ftp" => {
-            // TODO: Implementar FTP con ftp crate
-            anyhow::bail!("FTP mode not yet implemented. Install ftp crate for support.")
+        for line in result.lines {
+            line.emit();
         }
-        "telnet" => {
-            // TODO: Implementar Telnet con telnet crate
-            anyhow::bail!("Telnet mode not yet implemented. Install telnet crate for support.")
+        if result.error {
+            errors += 1;
         }
-        "mail" | "imap" | "pop3" | "smtp" => {
-            // TODO: Implementar IMAP/POP3/SMTP con async-imap, async-pop3, lettre
-            anyhow::bail!("Mail protocol mode not yet implemented. Install async-imap or lettre for support.")
+        if result.rejected {
+            rejected += 1;
         }
-        _ => {
-            anyhow::bail!("Unknown network protocol: {}. Available: ssh, ftp, telnet, imap, pop3, smtp", protocol)
+        if let Some(pair) = result.valid {
+            valid.push(pair);
+        }
+        if let Some(endpoint) = result.finding {
+            findings.push(endpoint);
         }
+    };
+
+    for (idx, (target, combo)) in attempts.enumerate() {
+        // Check for shutdown before spawning each task
+        if shutdown.load(Ordering::SeqCst) {
+            break;
+        }
+
+        let mut login = match combos.get(combo) {
+            Some(pair) => Some(Some(pair.clone())),
+            None => credentials.as_ref().map(|c| c.resolve(&target.words)),
+        };
+        let check_host = host_check.is_some() && checked_hosts.insert(target.url.trim().to_string());
+        let solved = config.stop_on_success.then(|| solved_hosts.entry(target.url.trim().to_string()).or_default().clone());
+        if login.is_some() && solved.as_ref().is_some_and(|s| s.load(Ordering::SeqCst)) {
+            skipped += 1;
+            live.record(false, 0);
+            login = None;
+        }
+        if login.is_none() && !check_host {
+            // Sin --auth solo queda la comprobación por host, y este ya se comprobó
+            continue;
+        }
+
+        // El permiso se obtiene antes de lanzar el intento para no
+        // adelantarse al generador de objetivos
+        let permit = semaphore.clone().acquire_owned().await?;
+        let verbose = config.verbose;
+        let ordered = config.ordered_output;
+        let show_errors = verbose >= 1 && !config.quiet_errors;
+        let no_dns = config.no_dns;
+        let success = config.success_regex.clone();
+        let active = live.begin();
+
+        let handle = tokio::spawn(async move {
+            let _permit = permit;
+            let _active = active;
+            let label = format!("[{}]", idx + 1).cyan();
+
+            let mut result = LoginResult { valid: None, finding: None, rejected: false, skipped: false, error: false, lines: Vec::new() };
+            let fail = |result: &mut LoginResult, line: String| {
+                result.error = true;
+                if show_errors {
+                    result.lines.extend(OutputLine::Stderr(line).deliver(ordered));
+                }
+            };
+            let Some((host, port)) = parse_host_port(&target.url, protocol.default_port()) else {
+                fail(&mut result, format!("  {} {} - {}", label, target.url.red(), "expected host[:port]".red()));
+                return result;
+            };
+            let endpoint = if host.contains(':') { format!("[{}]:{}", host, port) } else { format!("{}:{}", host, port) };
+            let addr = match resolve(&host, port, no_dns).await {
+                Ok(addr) => addr,
+                Err(e) => {
+                    fail(&mut result, format!("  {} {} - {}", label, endpoint.red(), e.red()));
+                    return result;
+                }
+            };
+
+            match host_check.filter(|_| check_host) {
+                None => {}
+                Some(HostCheck::FtpAnonymous) => match ftp::anonymous(addr, timeout).await {
+                    Ok(Some(listing)) => {
+                        let line = format!("  {} {} anonymous [{}] ({} entradas)", label, endpoint, "ANONYMOUS".green().bold(), listing.len());
+                        result.lines.extend(OutputLine::Stdout(line).deliver(ordered));
+                        if verbose >= 1 {
+                            for entry in &listing {
+                                result.lines.extend(OutputLine::Stdout(format!("      {}", entry)).deliver(ordered));
+                            }
+                        }
+                        result.finding = Some(endpoint.clone());
+                    }
+                    Ok(None) => {
+                        if verbose >= 2 {
+                            let line = format!("  {} {} anonymous [{}]", label, endpoint, "denied".red());
+                            result.lines.extend(OutputLine::Stdout(line).deliver(ordered));
+                        }
+                    }
+                    Err(e) => fail(&mut result, format!("  {} {} anonymous - {}", label, endpoint.red(), e.red())),
+                },
+                Some(HostCheck::SmtpRelay) => match smtp::relay_open(addr, timeout).await {
+                    Ok(true) => {
+                        let line = format!("  {} {} relay [{}]", label, endpoint, "OPEN RELAY".green().bold());
+                        result.lines.extend(OutputLine::Stdout(line).deliver(ordered));
+                        result.finding = Some(endpoint.clone());
+                    }
+                    Ok(false) => {
+                        if verbose >= 2 {
+                            let line = format!("  {} {} relay [{}]", label, endpoint, "denied".red());
+                            result.lines.extend(OutputLine::Stdout(line).deliver(ordered));
+                        }
+                    }
+                    Err(e) => fail(&mut result, format!("  {} {} relay - {}", label, endpoint.red(), e.red())),
+                },
+            }
+
+            let Some(login) = login else {
+                return result;
+            };
+            let Some((user, pass)) = login else {
+                fail(&mut result, format!("  {} {} - {}", label, endpoint.red(), "credentials without ':' (expected user:pass)".red()));
+                return result;
+            };
+            // Otro intento pudo dar con la clave mientras este esperaba turno
+            if solved.as_ref().is_some_and(|s| s.load(Ordering::SeqCst)) {
+                result.skipped = true;
+                return result;
+            }
+            let attempt = match protocol {
+                Protocol::Ssh => {
+                    let (user, pass) = (user.clone(), pass.clone());
+                    tokio::task::spawn_blocking(move || ssh_login(addr, &user, &pass, timeout))
+                        .await
+                        .unwrap_or_else(|e| Attempt::Error(e.to_string()))
+                }
+                Protocol::Ftp => ftp::login(addr, &user, &pass, timeout).await,
+                Protocol::Smtp => smtp::login(addr, &user, &pass, timeout).await,
+                Protocol::Telnet => telnet::login(addr, &user, &pass, timeout, success.as_ref()).await,
+            };
+
+            // Los logins válidos se muestran siempre; los rechazados, con -vv
+            let shown = format!("{} {}:{}", endpoint, user, pass);
+            match attempt {
+                Attempt::Valid => {
+                    if let Some(solved) = &solved {
+                        solved.store(true, Ordering::SeqCst);
+                    }
+                    let line = format!("  {} {} [{}]", label, shown, "VALID".green().bold());
+                    result.lines.extend(OutputLine::Stdout(line).deliver(ordered));
+                    result.valid = Some((endpoint, user, pass));
+                }
+                Attempt::Invalid => {
+                    result.rejected = true;
+                    if verbose >= 2 {
+                        let line = format!("  {} {} [{}]", label, shown, "invalid".red());
+                        result.lines.extend(OutputLine::Stdout(line).deliver(ordered));
+                    }
+                }
+                Attempt::Error(message) => fail(&mut result, format!("  {} {} - {}", label, shown.red(), message.red())),
+            }
+            result
+        });
+
+        pending.push_back(handle);
+
+        // Recoger en orden los resultados ya terminados para no acumular handles
+        while pending.front().is_some_and(|h: &tokio::task::JoinHandle<_>| h.is_finished()) {
+            if let Some(handle) = pending.pop_front() {
+                record(handle.await.ok());
+            }
+        }
+    }
+
+    // Con --drain-timeout, lo que siga pendiente al vencer el plazo se aborta y cuenta como error
+    let deadline = config.drain_timeout.map(|t| tokio::time::Instant::now() + Duration::from_secs(t));
+    let mut aborted = 0;
+    while let Some(mut handle) = pending.pop_front() {
+        if shutdown.load(Ordering::SeqCst) {
+            break;
+        }
+        match join_before(&mut handle, deadline).await {
+            Some(result) => record(result.ok()),
+            None => {
+                handle.abort();
+                aborted += 1;
+                record(None);
+            }
+        }
+    }
+    if aborted > 0 && !config.quiet {
+        eprintln!("{} Drain timeout: aborted {} pending attempt(s)", "[!]".yellow(), aborted);
+    }
+
+    let elapsed = start.elapsed();
+    if let Some(sampler) = sampler {
+        sampler.stop().await?;
+    }
+
+    let skipped = skipped + skipped_in_flight;
+    let pairs: Vec<String> = valid.iter().map(|(endpoint, user, pass)| format!("{} {}:{}", endpoint, user, pass)).collect();
+    if config.verbose >= 1 || !config.quiet {
+        println!();
+        println!("{}", "═══════════════════════════════════════".green());
+        println!("{} Intentos completados en {:.2}s", "[✓]".green(), elapsed.as_secs_f64());
+        println!("  Credenciales válidas: {}", valid.len().to_string().green());
+        println!("  Rechazadas: {}", rejected);
+        if let Some(check) = host_check {
+            println!("  {}: {}", check.label(), findings.len().to_string().green());
+        }
+        if errors > 0 {
+            println!("  Errores: {}", errors.to_string().yellow());
+        }
+        if config.stop_on_success {
+            println!("  Omitidos tras un login válido: {}", skipped);
+        }
+        println!("  Velocidad: {:.2} intentos/s", processed as f64 / elapsed.as_secs_f64());
+        for pair in &pairs {
+            println!("    {}", pair.green());
+        }
+        for endpoint in &findings {
+            let what = match host_check {
+                Some(HostCheck::SmtpRelay) => "open relay",
+                _ => "anonymous",
+            };
+            println!("    {} {}", endpoint.green(), what);
+        }
+        println!("{}", "═══════════════════════════════════════".green());
+    }
+
+    let mut details = format!(
+        "Credenciales válidas: {}, Rechazadas: {}, Errores: {}, Pares: [{}]",
+        valid.len(),
+        rejected,
+        errors,
+        pairs.join(", ")
+    );
+    if let Some(check) = host_check {
+        details.push_str(&format!(", {}: [{}]", check.label(), findings.join(", ")));
+    }
+    if config.stop_on_success {
+        details.push_str(&format!(", Omitidos: {}", skipped));
+    }
+
+    Ok(ModeResult {
+        mode: config.mode.to_lowercase(),
+        total: processed,
+        successful: valid.len() + findings.len(),
+        failed: errors,
+        errors: vec![],
+        status_counts: BTreeMap::new(),
+        custom_data: Some(details),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::credentials::CredentialTemplate;
+    use crate::testutil;
+    use tokio::io::AsyncWriteExt;
+
+    /// Puerto libre en loopback: se reserva y se suelta enseguida.
+    fn closed_addr() -> SocketAddr {
+        std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap()
+    }
+
+    /// Servicio que saluda con `greeting` y cierra.
+    async fn talker(greeting: &'static [u8]) -> SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let _ = stream.write_all(greeting).await;
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn host_port_parsing() {
+        assert_eq!(parse_host_port("h", 22), Some(("h".to_string(), 22)));
+        assert_eq!(parse_host_port(" h:2222 ", 22), Some(("h".to_string(), 2222)));
+        assert_eq!(parse_host_port("[::1]:2222", 22), Some(("::1".to_string(), 2222)));
+        assert_eq!(parse_host_port("[::1]", 22), Some(("::1".to_string(), 22)));
+        assert_eq!(parse_host_port("fe80::1", 22), Some(("fe80::1".to_string(), 22)));
+        for bad in ["", ":22", "h:x", "h:70000", "[::1]x"] {
+            assert_eq!(parse_host_port(bad, 22), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn ssh_errors_before_authenticating_are_not_rejections() {
+        let connect = ssh_login(closed_addr(), "root", "toor", Duration::from_secs(2));
+        assert!(matches!(&connect, Attempt::Error(e) if e.starts_with("connect")));
+    }
+
+    #[tokio::test]
+    async fn ssh_handshake_failure_is_an_error() {
+        let addr = talker(b"220 this is not ssh\r\n").await;
+        let attempt = tokio::task::spawn_blocking(move || ssh_login(addr, "root", "toor", Duration::from_secs(2))).await.unwrap();
+        assert!(matches!(&attempt, Attempt::Error(e) if e.starts_with("handshake")));
+    }
+
+    #[tokio::test]
+    async fn network_modes_need_credentials() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = testutil::mode_config("ssh", dir.path());
+        let err = execute(config, testutil::downzer(), Targets::from_urls(vec!["h".into()]), Arc::new(AtomicBool::new(false)), 1)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("needs credentials"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn ssh_mode_counts_unreachable_hosts_as_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testutil::mode_config("ssh", dir.path());
+        config.credentials = Some(CredentialTemplate::new("root:toor".to_string(), 0));
+        let result = testutil::run(config, vec![closed_addr().to_string()]).await;
+        assert_eq!((result.total, result.successful, result.failed), (1, 0, 1));
+    }
+
+    /// Login contra un servidor SSH real, que no se puede simular aquí:
+    /// `DOWNZER_TEST_SSH='host:puerto usuario contraseña' cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn ssh_login_against_a_real_server() {
+        let spec = std::env::var("DOWNZER_TEST_SSH").expect("DOWNZER_TEST_SSH='host:port user pass'");
+        let parts: Vec<&str> = spec.split_whitespace().collect();
+        let [addr, user, pass] = parts[..] else {
+            panic!("DOWNZER_TEST_SSH='host:port user pass'");
+        };
+        let addr: SocketAddr = addr.parse().unwrap();
+        assert!(matches!(ssh_login(addr, user, pass, Duration::from_secs(10)), Attempt::Valid));
+        assert!(matches!(ssh_login(addr, user, "downzer-wrong-password", Duration::from_secs(10)), Attempt::Invalid));
     }
 }
@@ -1,23 +1,251 @@
 use anyhow::Result;
+use std::collections::{BTreeMap, VecDeque};
+use std::io;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+use chrono::Utc;
 use colored::*;
 
-use crate::core::Downzer;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::sync::Semaphore;
+
+use crate::core::{Downzer, Targets};
+use crate::core::drain::join_before;
+use crate::core::live::IntervalSampler;
+use crate::core::output::OutputLine;
+use crate::core::scanxml::{ScanRun, ScannedHost, ScannedPort};
+use super::matchers::NumberSet;
 use super::{ModeConfig, ModeResult};
 
+/// Límites de concurrencia del escaneo: muchas conexiones TCP en vuelo, pero
+/// pocas lecturas de banner simultáneas.
+pub struct ScanLimits {
+    pub connect: Arc<Semaphore>,
+    pub read: Arc<Semaphore>,
+}
+
+impl ScanLimits {
+    /// `--connect-concurrency` / `--read-concurrency`; por defecto `--max-concurrent`.
+    pub fn from_config(config: &ModeConfig) -> Self {
+        let connect = config.connect_concurrency.unwrap_or(config.max_concurrent).max(1);
+        let read = config.read_concurrency.unwrap_or(config.max_concurrent).max(1);
+        Self {
+            connect: Arc::new(Semaphore::new(connect)),
+            read: Arc::new(Semaphore::new(read)),
+        }
+    }
+}
+
+/// Estado de un puerto en el escaneo TCP connect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortState {
+    /// La conexión se completó
+    Open,
+    /// El host la rechazó (RST)
+    Closed,
+    /// Sin respuesta antes del timeout, o un error de red por el camino
+    Filtered,
+    /// UDP sin respuesta: puede estar abierto (el servicio ignora la sonda) o filtrado
+    OpenFiltered,
+}
+
+impl PortState {
+    fn label(self) -> &'static str {
+        match self {
+            PortState::Open => "open",
+            PortState::Closed => "closed",
+            PortState::Filtered => "filtered",
+            PortState::OpenFiltered => "open|filtered",
+        }
+    }
+}
+
+/// Resultado de un objetivo. `state` es `None` si el objetivo no es un
+/// `host:puerto` válido o el host no se pudo resolver.
+struct ScanResult {
+    endpoint: Option<(String, u16)>,
+    /// Dirección contra la que se escaneó (el host ya resuelto)
+    addr: Option<IpAddr>,
+    state: Option<PortState>,
+    /// Lo que envió el servicio tras conectar (`--banner`)
+    banner: Option<String>,
+    line: Option<OutputLine>,
+}
+
+/// Espera máxima por el saludo del servicio con `--banner` (sin pasar de `--timeout`).
+const BANNER_WAIT: Duration = Duration::from_secs(2);
+
+/// Espera por más datos cuando ya ha llegado parte del banner.
+const BANNER_GRACE: Duration = Duration::from_millis(200);
+
+/// Sonda de `--banner-probe` para servicios que esperan a que hable el cliente (HTTP).
+const HTTP_PROBE: &[u8] = b"HEAD / HTTP/1.0\r\n\r\n";
+
+/// Puertos TCP más frecuentes, de más a menos (la lista de nmap), para `--top-ports`.
+pub const TOP_PORTS: [u16; 100] = [
+    80, 23, 443, 21, 22, 25, 3389, 110, 445, 139, 143, 53, 135, 3306, 8080, 1723, 111, 995, 993, 5900,
+    1025, 587, 8888, 199, 1720, 465, 548, 113, 81, 6001, 10000, 514, 5060, 179, 1026, 2000, 8443, 8000, 32768, 554,
+    26, 1433, 49152, 2001, 515, 8008, 49154, 1027, 5666, 646, 5000, 5631, 631, 49153, 8081, 2049, 88, 79, 5800, 106,
+    2121, 1110, 49155, 6000, 513, 990, 5357, 427, 49156, 543, 544, 5101, 144, 7, 389, 8009, 3128, 444, 9999, 5009,
+    7070, 5190, 3000, 5432, 1900, 3986, 13, 1029, 9, 5051, 6646, 49157, 1028, 873, 1755, 2717, 4899, 9100, 119, 37,
+];
+
+/// Host sin los corchetes de una IPv6 (`[::1]` → `::1`).
+fn strip_brackets(host: &str) -> &str {
+    host.strip_prefix('[').and_then(|h| h.strip_suffix(']')).unwrap_or(host)
+}
+
+/// Extremo `(host, puerto)` de un objetivo, o el motivo por el que no vale.
+type Endpoint = Result<(String, u16), String>;
+
+/// Puertos de un objetivo. Con `ports` (`--ports`, `--top-ports`) el objetivo
+/// es un host y se combina con cada puerto; si no, es `host:puertos`, donde
+/// los puertos admiten la sintaxis de `--ports` (`10.0.0.1:20-25`,
+/// `host:22,80`). Cada elemento lleva el texto con el que se muestra.
+pub fn expand_target(target: &str, ports: Option<&[u16]>) -> Vec<(String, Endpoint)> {
+    let target = target.trim();
+    let (host, ports) = match ports {
+        Some(ports) => (strip_brackets(target), ports.to_vec()),
+        None => {
+            let parsed = target.rsplit_once(':').and_then(|(host, spec)| {
+                let ports = NumberSet::parse_ports(spec, "port").ok()?;
+                Some((strip_brackets(host), ports.values().into_iter().map(|p| p as u16).collect()))
+            });
+            match parsed {
+                Some(parsed) => parsed,
+                None => return vec![(target.to_string(), Err("expected host:port".to_string()))],
+            }
+        }
+    };
+    if host.is_empty() {
+        return vec![(target.to_string(), Err("empty host".to_string()))];
+    }
+    ports
+        .into_iter()
+        .map(|port| {
+            let display = if host.contains(':') { format!("[{}]:{}", host, port) } else { format!("{}:{}", host, port) };
+            (display, Ok((host.to_string(), port)))
+        })
+        .collect()
+}
+
+/// Intenta una conexión TCP completa a `addr` y clasifica el puerto. Si está
+/// abierto devuelve también la conexión, por si hay que leer el banner.
+async fn connect_scan(addr: SocketAddr, timeout: Duration) -> (PortState, Option<TcpStream>) {
+    match tokio::time::timeout(timeout, TcpStream::connect(addr)).await {
+        Ok(Ok(stream)) => (PortState::Open, Some(stream)),
+        Ok(Err(e)) if e.kind() == io::ErrorKind::ConnectionRefused => (PortState::Closed, None),
+        // Host o red inalcanzable, ICMP prohibido...: algo filtra el paquete
+        Ok(Err(_)) | Err(_) => (PortState::Filtered, None),
+    }
+}
+
+/// Petición NTP v3 en modo cliente: 48 bytes con solo la cabecera.
+const NTP_PROBE: [u8; 48] = {
+    let mut probe = [0u8; 48];
+    probe[0] = 0x1b;
+    probe
+};
+
+/// Sonda UDP para `port`: una consulta válida para los servicios que solo
+/// responden a su protocolo (DNS, NTP) y un datagrama vacío para el resto.
+fn udp_probe(port: u16) -> &'static [u8] {
+    match port {
+        // Consulta DNS estándar de los NS de la raíz
+        53 => b"\x12\x34\x01\x00\x00\x01\x00\x00\x00\x00\x00\x00\x00\x00\x02\x00\x01",
+        123 => &NTP_PROBE,
+        _ => b"",
+    }
+}
+
+/// Escaneo UDP de `addr`: envía la sonda por un socket conectado y espera
+/// respuesta. Una respuesta es `Open`; el ICMP port unreachable llega como
+/// `ConnectionRefused` y es `Closed`; el silencio es `OpenFiltered`, como en
+/// nmap. Devuelve también la respuesta, que sirve de banner.
+async fn udp_scan(addr: SocketAddr, timeout: Duration) -> (PortState, Option<Vec<u8>>) {
+    let local: SocketAddr = if addr.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { ([0u16; 8], 0).into() };
+    let socket = match UdpSocket::bind(local).await {
+        Ok(socket) => socket,
+        Err(_) => return (PortState::Filtered, None),
+    };
+    if socket.connect(addr).await.is_err() || socket.send(udp_probe(addr.port())).await.is_err() {
+        return (PortState::Filtered, None);
+    }
+    let mut buf = vec![0u8; 4096];
+    match tokio::time::timeout(timeout, socket.recv(&mut buf)).await {
+        Ok(Ok(n)) => {
+            buf.truncate(n);
+            (PortState::Open, Some(buf))
+        }
+        Ok(Err(e)) if e.kind() == io::ErrorKind::ConnectionRefused => (PortState::Closed, None),
+        // Otros ICMP (host o red inalcanzable, prohibido)
+        Ok(Err(_)) => (PortState::Filtered, None),
+        Err(_) => (PortState::OpenFiltered, None),
+    }
+}
+
+/// Lee hasta `max` bytes de lo que envía el servicio al conectar (SSH, FTP y
+/// SMTP saludan solos). Si en `wait` no llega nada y `probe` está activo,
+/// envía una petición `HEAD` de HTTP y lo vuelve a intentar.
+async fn grab_banner(stream: &mut TcpStream, max: usize, wait: Duration, probe: bool) -> Option<String> {
+    let mut buf = vec![0u8; max];
+    let mut len = read_some(stream, &mut buf, wait).await;
+    if len == 0 && probe {
+        stream.write_all(HTTP_PROBE).await.ok()?;
+        len = read_some(stream, &mut buf, wait).await;
+    }
+    let banner = clean_banner(&buf[..len]);
+    (!banner.is_empty()).then_some(banner)
+}
+
+/// Llena `buf` con lo que llegue: espera `wait` por el primer fragmento y
+/// `BANNER_GRACE` por cada uno de los siguientes. Devuelve los bytes leídos.
+async fn read_some(stream: &mut TcpStream, buf: &mut [u8], wait: Duration) -> usize {
+    let mut len = 0;
+    while len < buf.len() {
+        let limit = if len == 0 { wait } else { BANNER_GRACE };
+        match tokio::time::timeout(limit, stream.read(&mut buf[len..])).await {
+            Ok(Ok(n)) if n > 0 => len += n,
+            _ => break,
+        }
+    }
+    len
+}
+
+/// Banner en una línea: texto con pérdidas, sin espacios en los extremos y con
+/// los caracteres de control escapados (`220 ftp ready\nhelp`).
+fn clean_banner(bytes: &[u8]) -> String {
+    let text = String::from_utf8_lossy(bytes).replace("\r\n", "\n");
+    text.trim()
+        .chars()
+        .flat_map(|c| -> Vec<char> { if c.is_control() { c.escape_default().collect() } else { vec![c] } })
+        .collect()
+}
+
 pub async fn execute(
     config: ModeConfig,
     _downzer: Arc<Downzer>,
-    urls: Vec<String>,
-    _shutdown: Arc<AtomicBool>,
+    targets: Targets,
+    shutdown: Arc<AtomicBool>,
     _task_id: u32,
 ) -> Result<ModeResult> {
+    let limits = ScanLimits::from_config(&config);
+
     if !config.quiet {
-        println!("{} Modo: Port Scanning", "[*]".blue());
-        println!("  Objetivos: {}", urls.len());
+        println!("{} Modo: Port Scanning ({})", "[*]".blue(), if config.udp { "UDP" } else { "TCP connect" });
+        match &config.ports {
+            Some(ports) => println!("  Objetivos: {} hosts x {} puertos", targets.total(), ports.len()),
+            None => println!("  Objetivos: {}", targets.total()),
+        }
         if config.verbose >= 2 {
-            println!("  Concurrencia: {}", config.max_concurrent);
+            println!(
+                "  Concurrencia: {} conexiones, {} lecturas",
+                limits.connect.available_permits(),
+                limits.read.available_permits()
+            );
             println!("  Timeout: {}s", config.timeout);
             if config.no_dns {
                 println!("  DNS: deshabilitado");
@@ -25,8 +253,439 @@ pub async fn execute(
         }
     }
 
-    // TODO: Implementar escaneo de puertos con técnicas SYN/ACK
-    // Por ahora, devolvemos un error informativo
-    
-    anyhow::bail!("Port scanning mode not yet implemented. Use raw sockets for SYN/ACK scanning on supported platforms.")
+    let live = config.live.clone();
+    let sampler = match &config.interval_stats {
+        Some(path) => Some(IntervalSampler::start(path, live.clone(), shutdown.clone())?),
+        None => None,
+    };
+
+    let timeout = Duration::from_secs(config.timeout);
+    let mut pending = VecDeque::new();
+    let mut processed: usize = 0;
+    let mut closed: usize = 0;
+    let mut filtered: usize = 0;
+    let mut open_filtered: usize = 0;
+    let mut errors: usize = 0;
+    let mut open: Vec<(String, u16, Option<String>)> = Vec::new();
+    // Todos los puertos por host, solo si hay que escribir --scan-output-xml
+    let mut xml_hosts: Option<BTreeMap<String, ScannedHost>> = config.scan_output_xml.as_ref().map(|_| BTreeMap::new());
+    let start = Instant::now();
+    let started_at = Utc::now();
+
+    let mut record = |result: Option<ScanResult>| {
+        processed += 1;
+        let Some(result) = result else {
+            errors += 1;
+            live.record(true, 0);
+            return;
+        };
+        live.record(result.state.is_none(), 0);
+        if let Some(line) = result.line {
+            line.emit();
+        }
+        if let (Some(hosts), Some((host, port)), Some(addr), Some(state)) =
+            (&mut xml_hosts, &result.endpoint, result.addr, result.state)
+        {
+            hosts
+                .entry(host.clone())
+                .or_insert_with(|| ScannedHost { addr, ports: Vec::new() })
+                .ports
+                .push(ScannedPort { port: *port, state: state.label(), banner: result.banner.clone() });
+        }
+        match (result.state, result.endpoint) {
+            (Some(PortState::Open), Some((host, port))) => open.push((host, port, result.banner)),
+            (Some(PortState::Closed), _) => closed += 1,
+            (Some(PortState::Filtered), _) => filtered += 1,
+            (Some(PortState::OpenFiltered), _) => open_filtered += 1,
+            _ => errors += 1,
+        }
+    };
+
+    let ports = config.ports.clone();
+    let endpoints = targets.flat_map(move |target| expand_target(&target.url, ports.as_deref()));
+    for (idx, (display, endpoint)) in endpoints.enumerate() {
+        // Check for shutdown before spawning each task
+        if shutdown.load(std::sync::atomic::Ordering::SeqCst) {
+            break;
+        }
+
+        // El permiso se obtiene antes de lanzar la conexión para no
+        // adelantarse al generador de objetivos
+        let permit = limits.connect.clone().acquire_owned().await?;
+        let verbose = config.verbose;
+        let ordered = config.ordered_output;
+        let show_errors = verbose >= 1 && !config.quiet_errors;
+        let no_dns = config.no_dns;
+        let banner_bytes = config.banner_bytes;
+        let banner_probe = config.banner_probe;
+        let udp = config.udp;
+        let read_limit = limits.read.clone();
+        let active = live.begin();
+
+        let handle = tokio::spawn(async move {
+            let _permit = permit;
+            let _active = active;
+            let label = format!("[{}]", idx + 1).cyan();
+
+            let error = |message: String| {
+                let mut line = None;
+                if show_errors {
+                    line = OutputLine::Stderr(format!("  {} {} - {}", label, display.red(), message.red())).deliver(ordered);
+                }
+                ScanResult { endpoint: None, addr: None, state: None, banner: None, line }
+            };
+            let (host, port) = match endpoint {
+                Ok(endpoint) => endpoint,
+                Err(message) => return error(message),
+            };
+            // Con --nodns solo se aceptan IPs
+            let addr = match host.parse::<IpAddr>() {
+                Ok(ip) => SocketAddr::new(ip, port),
+                Err(_) if no_dns => return error("not an IP address (DNS disabled)".to_string()),
+                Err(_) => {
+                    let resolved = tokio::time::timeout(timeout, tokio::net::lookup_host((host.as_str(), port))).await;
+                    match resolved {
+                        Ok(Ok(mut addrs)) => match addrs.next() {
+                            Some(addr) => addr,
+                            None => return error("no address for host".to_string()),
+                        },
+                        Ok(Err(e)) => return error(format!("cannot resolve: {}", e)),
+                        Err(_) => return error("DNS timeout".to_string()),
+                    }
+                }
+            };
+
+            let mut banner = None;
+            let state = if udp {
+                let (state, response) = udp_scan(addr, timeout).await;
+                if let (Some(response), Some(max)) = (response, banner_bytes) {
+                    banner = Some(clean_banner(&response[..response.len().min(max)])).filter(|b| !b.is_empty());
+                }
+                state
+            } else {
+                let (state, stream) = connect_scan(addr, timeout).await;
+                // Las lecturas de banner tienen su propio límite (--read-concurrency)
+                if let (Some(mut stream), Some(max)) = (stream, banner_bytes) {
+                    if let Ok(_read) = read_limit.acquire().await {
+                        banner = grab_banner(&mut stream, max, BANNER_WAIT.min(timeout), banner_probe).await;
+                    }
+                }
+                state
+            };
+            // Los abiertos se muestran siempre (con su banner desde -v); el resto, con -vv
+            let mut line = None;
+            if state == PortState::Open || verbose >= 2 {
+                let text = match state {
+                    PortState::Open => state.label().green(),
+                    PortState::Closed => state.label().red(),
+                    PortState::Filtered | PortState::OpenFiltered => state.label().yellow(),
+                };
+                let shown = match &banner {
+                    Some(banner) if verbose >= 1 => format!(" {}", banner.dimmed()),
+                    _ => String::new(),
+                };
+                line = OutputLine::Stdout(format!("  {} {} [{}]{}", label, display, text, shown)).deliver(ordered);
+            }
+            ScanResult { endpoint: Some((host, port)), addr: Some(addr.ip()), state: Some(state), banner, line }
+        });
+
+        pending.push_back(handle);
+
+        // Recoger en orden los resultados ya terminados para no acumular handles
+        while pending.front().is_some_and(|h: &tokio::task::JoinHandle<_>| h.is_finished()) {
+            if let Some(handle) = pending.pop_front() {
+                record(handle.await.ok());
+            }
+        }
+    }
+
+    // Con --drain-timeout, lo que siga pendiente al vencer el plazo se aborta y cuenta como error
+    let deadline = config.drain_timeout.map(|t| tokio::time::Instant::now() + Duration::from_secs(t));
+    let mut aborted = 0;
+    while let Some(mut handle) = pending.pop_front() {
+        if shutdown.load(std::sync::atomic::Ordering::SeqCst) {
+            break;
+        }
+        match join_before(&mut handle, deadline).await {
+            Some(result) => record(result.ok()),
+            None => {
+                handle.abort();
+                aborted += 1;
+                record(None);
+            }
+        }
+    }
+    if aborted > 0 && !config.quiet {
+        eprintln!("{} Drain timeout: aborted {} pending connection(s)", "[!]".yellow(), aborted);
+    }
+
+    let elapsed = start.elapsed();
+    if let Some(sampler) = sampler {
+        sampler.stop().await?;
+    }
+    if let (Some(path), Some(hosts)) = (&config.scan_output_xml, xml_hosts) {
+        let protocol = if config.udp { "udp" } else { "tcp" };
+        let run = ScanRun { protocol, start: started_at, end: Utc::now(), hosts };
+        run.write(path).map_err(|e| anyhow::anyhow!("Cannot write {}: {}", path.display(), e))?;
+        if !config.quiet {
+            println!("{} XML (formato nmap) guardado en {}", "[*]".blue(), path.display());
+        }
+    }
+
+    open.sort();
+    let open_list: Vec<String> = open
+        .iter()
+        .map(|(host, port, _)| if host.contains(':') { format!("[{}]:{}", host, port) } else { format!("{}:{}", host, port) })
+        .collect();
+    if config.verbose >= 1 || !config.quiet {
+        println!();
+        println!("{}", "═══════════════════════════════════════".green());
+        println!("{} Escaneo completado en {:.2}s", "[✓]".green(), elapsed.as_secs_f64());
+        println!("  Abiertos: {}", open.len().to_string().green());
+        println!("  Cerrados: {}", closed);
+        println!("  Filtrados: {}", filtered);
+        if config.udp {
+            println!("  Abiertos|filtrados: {}", open_filtered);
+        }
+        if errors > 0 {
+            println!("  Errores: {}", errors.to_string().yellow());
+        }
+        println!("  Velocidad: {:.2} puertos/s", processed as f64 / elapsed.as_secs_f64());
+        for (endpoint, (_, _, banner)) in open_list.iter().zip(&open) {
+            match banner {
+                Some(banner) => println!("    {}  {}", endpoint.green(), banner),
+                None => println!("    {}", endpoint.green()),
+            }
+        }
+        println!("{}", "═══════════════════════════════════════".green());
+    }
+
+    Ok(ModeResult {
+        mode: "portscan".to_string(),
+        total: processed,
+        successful: open.len(),
+        failed: errors,
+        errors: vec![],
+        status_counts: BTreeMap::new(),
+        custom_data: Some(format!(
+            "Abiertos: {}, Cerrados: {}, Filtrados: {}{}, Puertos abiertos: [{}]",
+            open.len(),
+            closed,
+            filtered,
+            if config.udp { format!(", Abiertos|filtrados: {}", open_filtered) } else { String::new() },
+            open_list.join(", ")
+        )),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil;
+
+    #[test]
+    fn connect_and_read_limits_are_sized_independently() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testutil::mode_config("portscan", dir.path());
+        config.max_concurrent = 50;
+        config.connect_concurrency = Some(200);
+        config.read_concurrency = Some(5);
+        let limits = ScanLimits::from_config(&config);
+        assert_eq!((limits.connect.available_permits(), limits.read.available_permits()), (200, 5));
+
+        // Sin las opciones, ambos toman --max-concurrent
+        config.connect_concurrency = None;
+        config.read_concurrency = None;
+        let limits = ScanLimits::from_config(&config);
+        assert_eq!((limits.connect.available_permits(), limits.read.available_permits()), (50, 50));
+    }
+
+    /// Puerto libre en loopback: se reserva y se suelta enseguida.
+    fn unused_port() -> u16 {
+        std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+    }
+
+    #[tokio::test]
+    async fn a_local_listener_is_open_and_an_unused_port_closed() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let open = listener.local_addr().unwrap().port();
+        let closed = unused_port();
+        let dir = tempfile::tempdir().unwrap();
+        let targets = vec![format!("127.0.0.1:{}", open), format!("127.0.0.1:{}", closed)];
+        let result = testutil::run(testutil::mode_config("portscan", dir.path()), targets).await;
+
+        assert_eq!((result.total, result.successful, result.failed), (2, 1, 0));
+        let details = result.custom_data.unwrap();
+        assert!(details.starts_with("Abiertos: 1, Cerrados: 1, Filtrados: 0"), "{}", details);
+        assert!(details.ends_with(&format!("Puertos abiertos: [127.0.0.1:{}]", open)), "{}", details);
+    }
+
+    #[tokio::test]
+    async fn invalid_targets_count_as_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testutil::mode_config("portscan", dir.path());
+        config.no_dns = true;
+        let targets = vec!["127.0.0.1".to_string(), "example.invalid:80".to_string()];
+        let result = testutil::run(config, targets).await;
+        assert_eq!((result.total, result.successful, result.failed), (2, 0, 2));
+    }
+
+    #[tokio::test]
+    async fn shutdown_stops_before_spawning() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target = format!("127.0.0.1:{}", listener.local_addr().unwrap().port());
+        let dir = tempfile::tempdir().unwrap();
+        let config = testutil::mode_config("portscan", dir.path());
+        let shutdown = Arc::new(AtomicBool::new(true));
+        let result = execute(config, testutil::downzer(), Targets::from_urls(vec![target]), shutdown, 1).await.unwrap();
+        assert_eq!((result.total, result.successful), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn connect_scan_classifies_the_port() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let (state, stream) = connect_scan(listener.local_addr().unwrap(), Duration::from_secs(2)).await;
+        assert_eq!(state, PortState::Open);
+        assert!(stream.is_some());
+        let closed: SocketAddr = ([127, 0, 0, 1], unused_port()).into();
+        assert_eq!(connect_scan(closed, Duration::from_secs(2)).await.0, PortState::Closed);
+    }
+
+    fn ports_of(target: &str, ports: Option<&[u16]>) -> Vec<u16> {
+        expand_target(target, ports).into_iter().map(|(_, endpoint)| endpoint.unwrap().1).collect()
+    }
+
+    #[test]
+    fn port_ranges_expand_in_the_target() {
+        assert_eq!(ports_of("10.0.0.1:20-23", None), [20, 21, 22, 23]);
+        assert_eq!(ports_of("host:22,80,8000-8002", None), [22, 80, 8000, 8001, 8002]);
+        // Los solapes no repiten puertos
+        assert_eq!(ports_of("host:80,79-81", None), [80, 79, 81]);
+    }
+
+    #[test]
+    fn ports_option_applies_to_every_host() {
+        let ports = NumberSet::parse_ports("22,8080-8081", "--ports").unwrap().values();
+        let ports: Vec<u16> = ports.into_iter().map(|p| p as u16).collect();
+        let expanded = expand_target("[::1]", Some(&ports));
+        let shown: Vec<&str> = expanded.iter().map(|(display, _)| display.as_str()).collect();
+        assert_eq!(shown, ["[::1]:22", "[::1]:8080", "[::1]:8081"]);
+        assert_eq!(expanded[0].1, Ok(("::1".to_string(), 22)));
+    }
+
+    #[test]
+    fn invalid_port_specs_are_errors() {
+        for target in ["host", "host:0", "host:70000", "host:90-80", ":80"] {
+            let expanded = expand_target(target, None);
+            assert_eq!(expanded.len(), 1, "{}", target);
+            assert!(expanded[0].1.is_err(), "{}", target);
+        }
+    }
+
+    #[test]
+    fn top_ports_start_with_the_most_common_and_do_not_repeat() {
+        assert_eq!(TOP_PORTS[..5], [80, 23, 443, 21, 22]);
+        let unique: std::collections::HashSet<u16> = TOP_PORTS.iter().copied().collect();
+        assert_eq!(unique.len(), TOP_PORTS.len());
+    }
+
+    /// Listener que envía `greeting` a cada conexión y, si recibe algo, responde `reply`.
+    async fn greeter(greeting: &'static [u8], reply: &'static [u8]) -> SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let _ = stream.write_all(greeting).await;
+                    let mut buf = [0u8; 64];
+                    if matches!(stream.read(&mut buf).await, Ok(n) if n > 0) {
+                        let _ = stream.write_all(reply).await;
+                    }
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                });
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn banner_is_read_from_a_greeting_service() {
+        let addr = greeter(b"220 mail.local ESMTP ready\r\n", b"").await;
+        let (_, stream) = connect_scan(addr, Duration::from_secs(2)).await;
+        let banner = grab_banner(&mut stream.unwrap(), 256, Duration::from_secs(1), false).await;
+        assert_eq!(banner.as_deref(), Some("220 mail.local ESMTP ready"));
+
+        // El límite de bytes corta el banner
+        let (_, stream) = connect_scan(addr, Duration::from_secs(2)).await;
+        let banner = grab_banner(&mut stream.unwrap(), 8, Duration::from_secs(1), false).await;
+        assert_eq!(banner.as_deref(), Some("220 mail"));
+    }
+
+    #[tokio::test]
+    async fn banner_probe_makes_a_silent_service_talk() {
+        let addr = greeter(b"", b"HTTP/1.0 200 OK\r\nServer: test\r\n\r\n").await;
+        let (_, stream) = connect_scan(addr, Duration::from_secs(2)).await;
+        assert_eq!(grab_banner(&mut stream.unwrap(), 256, Duration::from_millis(200), false).await, None);
+        let (_, stream) = connect_scan(addr, Duration::from_secs(2)).await;
+        let banner = grab_banner(&mut stream.unwrap(), 256, Duration::from_millis(200), true).await;
+        assert_eq!(banner.as_deref(), Some("HTTP/1.0 200 OK\\nServer: test"));
+    }
+
+    #[tokio::test]
+    async fn open_ports_keep_their_banner() {
+        let addr = greeter(b"SSH-2.0-OpenSSH_9.6\r\n", b"").await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testutil::mode_config("portscan", dir.path());
+        config.banner_bytes = Some(256);
+        let xml = dir.path().join("scan.xml");
+        config.scan_output_xml = Some(xml.clone());
+        testutil::run(config, vec![addr.to_string()]).await;
+        let xml = std::fs::read_to_string(xml).unwrap();
+        assert!(xml.contains("SSH-2.0-OpenSSH_9.6"), "{}", xml);
+    }
+
+    #[test]
+    fn banners_are_shown_on_one_line() {
+        assert_eq!(clean_banner(b"  220 ready\r\nhelp\tme\x01 \n"), "220 ready\\nhelp\\tme\\u{1}");
+        assert_eq!(clean_banner(b"\xffok"), "\u{fffd}ok");
+    }
+
+    #[tokio::test]
+    async fn udp_echo_is_open_and_silence_open_filtered() {
+        let echo = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let echo_addr = echo.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            while let Ok((_, from)) = echo.recv_from(&mut buf).await {
+                let _ = echo.send_to(b"pong", from).await;
+            }
+        });
+        // Socket que recibe pero nunca contesta
+        let silent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+        let (state, response) = udp_scan(echo_addr, Duration::from_millis(500)).await;
+        assert_eq!((state, response.as_deref()), (PortState::Open, Some(&b"pong"[..])));
+        let (state, response) = udp_scan(silent.local_addr().unwrap(), Duration::from_millis(300)).await;
+        assert_eq!((state, response), (PortState::OpenFiltered, None));
+    }
+
+    #[tokio::test]
+    async fn udp_mode_reports_open_filtered_separately() {
+        let silent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testutil::mode_config("portscan", dir.path());
+        config.udp = true;
+        config.timeout = 1;
+        let result = testutil::run(config, vec![silent.local_addr().unwrap().to_string()]).await;
+        assert_eq!((result.total, result.successful), (1, 0));
+        let details = result.custom_data.unwrap();
+        assert!(details.contains("Abiertos|filtrados: 1"), "{}", details);
+    }
+
+    #[test]
+    fn udp_probes_speak_the_protocol_of_well_known_ports() {
+        assert_eq!(udp_probe(53).len(), 17);
+        assert_eq!(udp_probe(123)[0], 0x1b);
+        assert!(udp_probe(9999).is_empty());
+    }
 }
@@ -0,0 +1,172 @@
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+
+use anyhow::Result;
+use url::Url;
+
+use crate::core::{Target, Targets};
+
+/// Descubrimiento recursivo de `--recursive` en webrequest.
+///
+/// La plantilla tiene que llevar todos sus placeholders en el último segmento
+/// de la ruta (`https://site.com/base/FUZZW1.php`): lo que va antes es la base
+/// de la primera pasada. Cada directorio descubierto se convierte en una base
+/// nueva y recibe otra pasada con las mismas combinaciones, sustituyendo la
+/// base de cada URL de la primera, hasta `--recursion-depth` niveles.
+pub struct Recursion {
+    /// Base de la plantilla, hasta la última `/` antes del primer placeholder
+    base: String,
+    max_depth: usize,
+    /// Objetivos de la primera pasada, con la URL sin la base
+    suffixes: Vec<Target>,
+    /// Los mismos, compartidos por las pasadas recursivas una vez completa la primera
+    shared: Option<Arc<Vec<Target>>>,
+    visited: HashSet<String>,
+    queue: VecDeque<(String, usize)>,
+}
+
+impl Recursion {
+    pub fn new(template: &str, max_depth: usize) -> Result<Self> {
+        let Some(first) = template.find("FUZZ") else {
+            anyhow::bail!("--recursive needs placeholders (FUZZ, FUZZW1...) in the URL template");
+        };
+        // La base tiene que quedar en la ruta, después de `esquema://host/`
+        let authority = template.find("://").map_or(0, |i| i + 3);
+        let path_start = template[authority..].find('/').map_or(template.len(), |i| authority + i);
+        let Some(slash) = template[..first].rfind('/').filter(|&i| i >= path_start) else {
+            anyhow::bail!("--recursive needs the placeholders in the URL path (e.g. https://site.com/FUZZW1)");
+        };
+        if template[slash + 1..].contains('/') {
+            anyhow::bail!("--recursive needs every placeholder in the last path segment of the URL template");
+        }
+        let base = template[..=slash].to_string();
+        Ok(Self {
+            visited: HashSet::from([base.clone()]),
+            base,
+            max_depth,
+            suffixes: Vec::new(),
+            shared: None,
+            queue: VecDeque::new(),
+        })
+    }
+
+    /// Guarda un objetivo de la primera pasada para repetirlo en las siguientes.
+    pub fn remember(&mut self, target: &Target) {
+        if let Some(suffix) = target.url.strip_prefix(&self.base) {
+            self.suffixes.push(Target { url: suffix.to_string(), ..target.clone() });
+        }
+    }
+
+    /// Encola el directorio `url`, hallado en una pasada de nivel `depth`, si
+    /// no se ha visitado ya y no supera la profundidad máxima. Devuelve el
+    /// nivel con el que se encola.
+    pub fn discover(&mut self, url: &str, depth: usize) -> Option<usize> {
+        let dir = if url.ends_with('/') { url.to_string() } else { format!("{}/", url) };
+        if depth >= self.max_depth || !self.visited.insert(dir.clone()) {
+            return None;
+        }
+        self.queue.push_back((dir, depth + 1));
+        Some(depth + 1)
+    }
+
+    /// Siguiente pasada pendiente: la base, su nivel y sus objetivos.
+    pub fn next_pass(&mut self) -> Option<(String, usize, Targets)> {
+        let (dir, depth) = self.queue.pop_front()?;
+        let suffixes = self.shared.get_or_insert_with(|| Arc::new(std::mem::take(&mut self.suffixes))).clone();
+        let total = suffixes.len();
+        let base = dir.clone();
+        let iter = (0..total).map(move |i| {
+            let target = &suffixes[i];
+            Target { url: format!("{}{}", base, target.url), ..target.clone() }
+        });
+        Some((dir, depth, Targets::new(iter, total)))
+    }
+}
+
+/// Criterio de directorio: la respuesta final es 2xx, 401 o 403 y, o bien la
+/// petición acabó redirigida a la misma URL con `/` al final (lo que hacen
+/// Apache y nginx con los directorios), o bien la URL pedida ya acababa en `/`.
+pub fn is_directory(requested: &str, final_url: &Url, status: u16) -> bool {
+    if !((200..300).contains(&status) || status == 401 || status == 403) {
+        return false;
+    }
+    // Se compara la forma normalizada (puerto por defecto, escapes...)
+    let Ok(requested) = Url::parse(requested) else {
+        return false;
+    };
+    if requested.path().ends_with('/') {
+        return *final_url == requested;
+    }
+    let mut slashed = requested.clone();
+    slashed.set_path(&format!("{}/", requested.path()));
+    *final_url == slashed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{self, MockServer, Response};
+
+    #[test]
+    fn template_base_is_the_path_before_the_placeholders() {
+        let recursion = Recursion::new("http://h/app/FUZZW1.php", 2).unwrap();
+        assert_eq!(recursion.base, "http://h/app/");
+        for template in ["http://h/static", "http://FUZZW1.h/x", "http://h/FUZZW1/index.php"] {
+            assert!(Recursion::new(template, 2).is_err(), "{}", template);
+        }
+    }
+
+    #[test]
+    fn directories_are_visited_once_up_to_the_depth() {
+        let mut recursion = Recursion::new("http://h/FUZZW1", 2).unwrap();
+        assert_eq!(recursion.discover("http://h/a", 0), Some(1));
+        assert_eq!(recursion.discover("http://h/a/", 0), None);
+        assert_eq!(recursion.discover("http://h/a/b", 1), Some(2));
+        assert_eq!(recursion.discover("http://h/a/b/c", 2), None);
+        assert_eq!(recursion.discover("http://h/", 0), None);
+    }
+
+    #[test]
+    fn directory_criterion() {
+        let url = |s: &str| Url::parse(s).unwrap();
+        assert!(is_directory("http://h/a", &url("http://h/a/"), 200));
+        assert!(is_directory("http://h/a", &url("http://h:80/a/"), 403));
+        assert!(is_directory("http://h/a/", &url("http://h/a/"), 401));
+        assert!(!is_directory("http://h/a", &url("http://h/a"), 200));
+        assert!(!is_directory("http://h/a", &url("http://h/a/"), 404));
+        assert!(!is_directory("http://h/a", &url("http://h/b/"), 200));
+    }
+
+    #[tokio::test]
+    async fn nested_directories_get_their_own_pass() {
+        // /admin/ y /admin/secret/ son directorios (redirigen a la versión con `/`)
+        let server = MockServer::start(|req| match req.path.as_str() {
+            "/admin" | "/admin/secret" => Response::new(301).header("Location", &format!("{}/", req.path)),
+            "/admin/" | "/admin/secret/" | "/admin/secret/key" => Response::new(200),
+            _ => Response::new(404),
+        })
+        .await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testutil::mode_config("webrequest", dir.path());
+        config.url_or_target = server.url("/FUZZW1");
+        config.recursion_depth = Some(2);
+        config.max_concurrent = 1;
+        let words = ["admin", "secret", "key"];
+        let targets: Vec<Target> = words
+            .iter()
+            .map(|w| Target { url: server.url(&format!("/{}", w)), words: vec![w.to_string()], indices: vec![] })
+            .collect();
+        testutil::run_targets(config, Targets::new(targets.into_iter(), words.len())).await;
+
+        // Sin las peticiones tras las redirecciones
+        let passes: Vec<String> = server.paths().into_iter().filter(|p| !p.ends_with('/')).collect();
+        assert_eq!(
+            passes,
+            [
+                "/admin", "/secret", "/key",
+                "/admin/admin", "/admin/secret", "/admin/key",
+                "/admin/secret/admin", "/admin/secret/secret", "/admin/secret/key",
+            ]
+        );
+    }
+}
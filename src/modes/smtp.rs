@@ -0,0 +1,276 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+use super::network::Attempt;
+
+/// Nombre con el que se presenta el cliente en `EHLO`.
+const CLIENT_NAME: &str = "downzer.localdomain";
+
+/// Remitente y destinatario de la prueba de relay: dominios reservados
+/// (RFC 2606) y distintos entre sí, ninguno local para el servidor.
+const RELAY_FROM: &str = "relay-test@example.com";
+const RELAY_TO: &str = "relay-test@example.net";
+
+/// Sesión SMTP mínima tras el saludo y el `EHLO`. Cada lectura y escritura
+/// tiene su propio `timeout`.
+struct Session {
+    stream: BufReader<TcpStream>,
+    timeout: Duration,
+    /// Líneas de la respuesta al `EHLO` (extensiones anunciadas), en mayúsculas
+    extensions: Vec<String>,
+}
+
+impl Session {
+    async fn connect(addr: SocketAddr, wait: Duration) -> Result<Self, String> {
+        let stream = match timeout(wait, TcpStream::connect(addr)).await {
+            Ok(Ok(stream)) => stream,
+            Ok(Err(e)) => return Err(format!("connect: {}", e)),
+            Err(_) => return Err("connect: timeout".to_string()),
+        };
+        let mut session = Self { stream: BufReader::new(stream), timeout: wait, extensions: Vec::new() };
+        let (code, text) = session.reply().await?;
+        if code != 220 {
+            return Err(format!("unexpected greeting: {} {}", code, text.join(" ")));
+        }
+        // Sin ESMTP se saluda con HELO y no hay extensiones (ni AUTH)
+        let (code, text) = session.command(&format!("EHLO {}", CLIENT_NAME)).await?;
+        if code == 250 {
+            session.extensions = text.iter().skip(1).map(|l| l.to_ascii_uppercase()).collect();
+        } else {
+            let (code, text) = session.command(&format!("HELO {}", CLIENT_NAME)).await?;
+            if code != 250 {
+                return Err(format!("HELO refused: {} {}", code, text.join(" ")));
+            }
+        }
+        Ok(session)
+    }
+
+    /// Lee una respuesta completa; devuelve el código y el texto de cada línea
+    /// (`250-PIPELINING` ... `250 HELP`).
+    async fn reply(&mut self) -> Result<(u16, Vec<String>), String> {
+        let mut lines = Vec::new();
+        loop {
+            let mut line = String::new();
+            match timeout(self.timeout, self.stream.read_line(&mut line)).await {
+                Ok(Ok(0)) => return Err("connection closed".to_string()),
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => return Err(e.to_string()),
+                Err(_) => return Err("timeout".to_string()),
+            }
+            let code: u16 = line.get(..3).and_then(|c| c.parse().ok()).ok_or_else(|| format!("malformed reply: {}", line.trim_end()))?;
+            lines.push(line.get(4..).unwrap_or("").trim_end().to_string());
+            if line.as_bytes().get(3) != Some(&b'-') {
+                return Ok((code, lines));
+            }
+        }
+    }
+
+    async fn command(&mut self, command: &str) -> Result<(u16, Vec<String>), String> {
+        let line = format!("{}\r\n", command);
+        match timeout(self.timeout, self.stream.get_mut().write_all(line.as_bytes())).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => return Err(e.to_string()),
+            Err(_) => return Err("timeout".to_string()),
+        }
+        self.reply().await
+    }
+
+    /// Mecanismos de `AUTH` anunciados en el `EHLO` (`AUTH PLAIN LOGIN`).
+    fn auth_mechanisms(&self) -> Vec<&str> {
+        self.extensions
+            .iter()
+            .filter_map(|l| l.strip_prefix("AUTH ").or_else(|| l.strip_prefix("AUTH=")))
+            .flat_map(|l| l.split_whitespace())
+            .collect()
+    }
+
+    /// `AUTH PLAIN` si se anuncia y, si no, `AUTH LOGIN`. 235 es válido y 535
+    /// un rechazo; cualquier otra respuesta (538 "requiere cifrado", 454...)
+    /// no dice nada de las credenciales y queda como error.
+    async fn login(&mut self, user: &str, pass: &str) -> Result<Attempt, String> {
+        let mechanisms = self.auth_mechanisms();
+        let (code, text) = if mechanisms.contains(&"PLAIN") {
+            let token = BASE64_STANDARD.encode(format!("\0{}\0{}", user, pass));
+            self.command(&format!("AUTH PLAIN {}", token)).await?
+        } else if mechanisms.contains(&"LOGIN") {
+            let (code, text) = self.command("AUTH LOGIN").await?;
+            if code != 334 {
+                return Ok(Attempt::Error(format!("AUTH LOGIN refused: {} {}", code, text.join(" "))));
+            }
+            let (code, text) = self.command(&BASE64_STANDARD.encode(user)).await?;
+            if code != 334 {
+                return Ok(Attempt::Error(format!("AUTH LOGIN refused: {} {}", code, text.join(" "))));
+            }
+            self.command(&BASE64_STANDARD.encode(pass)).await?
+        } else if self.extensions.iter().any(|l| l == "STARTTLS") {
+            return Ok(Attempt::Error("AUTH is only offered after STARTTLS (not supported)".to_string()));
+        } else {
+            return Ok(Attempt::Error("server does not offer AUTH PLAIN or LOGIN".to_string()));
+        };
+        Ok(match code {
+            235 => Attempt::Valid,
+            535 => Attempt::Invalid,
+            _ => Attempt::Error(format!("{} {}", code, text.join(" "))),
+        })
+    }
+
+    async fn quit(mut self) {
+        let _ = self.command("QUIT").await;
+    }
+}
+
+/// Prueba `user`/`pass` con `AUTH` contra el servidor SMTP de `addr`.
+pub(super) async fn login(addr: SocketAddr, user: &str, pass: &str, wait: Duration) -> Attempt {
+    let mut session = match Session::connect(addr, wait).await {
+        Ok(session) => session,
+        Err(e) => return Attempt::Error(e),
+    };
+    let attempt = session.login(user, pass).await.unwrap_or_else(Attempt::Error);
+    session.quit().await;
+    attempt
+}
+
+/// Comprobación de `--smtp-relay-test`: `MAIL FROM` y `RCPT TO` con dominios
+/// ajenos, sin autenticar y sin llegar a `DATA`. `Ok(true)` si el servidor
+/// acepta el destinatario (relay abierto), `Ok(false)` si lo rechaza.
+pub(super) async fn relay_open(addr: SocketAddr, wait: Duration) -> Result<bool, String> {
+    let mut session = Session::connect(addr, wait).await?;
+    let (code, text) = session.command(&format!("MAIL FROM:<{}>", RELAY_FROM)).await?;
+    let result = if code != 250 {
+        Err(format!("MAIL FROM refused: {} {}", code, text.join(" ")))
+    } else {
+        match session.command(&format!("RCPT TO:<{}>", RELAY_TO)).await? {
+            (250 | 251, _) => Ok(true),
+            (500..=599, _) => Ok(false),
+            (code, text) => Err(format!("RCPT TO: {} {}", code, text.join(" "))),
+        }
+    };
+    let _ = session.command("RSET").await;
+    session.quit().await;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::net::TcpListener;
+
+    use crate::testutil;
+
+    /// Servidor SMTP guionizado: anuncia `auth` en el `EHLO` (vacío, sin
+    /// AUTH), acepta `admin`/`secret` y hace de relay si `relay`. Guarda los
+    /// comandos recibidos.
+    async fn smtp_server(auth: &'static str, relay: bool) -> (SocketAddr, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let commands = Arc::new(Mutex::new(Vec::new()));
+        let seen = commands.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(session(BufReader::new(stream), auth, relay, seen.clone()));
+            }
+        });
+        (addr, commands)
+    }
+
+    async fn session(mut stream: BufReader<TcpStream>, auth: &str, relay: bool, seen: Arc<Mutex<Vec<String>>>) -> std::io::Result<()> {
+        let plain = BASE64_STANDARD.encode("\0admin\0secret");
+        let mut login_step = 0;
+        let mut user = String::new();
+        stream.get_mut().write_all(b"220 mx.test ESMTP\r\n").await?;
+        loop {
+            let mut line = String::new();
+            if stream.read_line(&mut line).await? == 0 {
+                return Ok(());
+            }
+            let line = line.trim_end().to_string();
+            seen.lock().unwrap().push(line.clone());
+            let reply = if login_step == 1 {
+                login_step = 2;
+                user = line;
+                "334 UGFzc3dvcmQ6".to_string()
+            } else if login_step == 2 {
+                login_step = 0;
+                let valid = user == BASE64_STANDARD.encode("admin") && line == BASE64_STANDARD.encode("secret");
+                if valid { "235 ok" } else { "535 bad credentials" }.to_string()
+            } else if line.starts_with("EHLO") {
+                match auth {
+                    "" => "250-mx.test\r\n250 PIPELINING".to_string(),
+                    auth => format!("250-mx.test\r\n250-PIPELINING\r\n250 AUTH {}", auth),
+                }
+            } else if let Some(token) = line.strip_prefix("AUTH PLAIN ") {
+                if token == plain { "235 ok" } else { "535 bad credentials" }.to_string()
+            } else if line == "AUTH LOGIN" {
+                login_step = 1;
+                "334 VXNlcm5hbWU6".to_string()
+            } else if line.starts_with("MAIL FROM") {
+                "250 sender ok".to_string()
+            } else if line.starts_with("RCPT TO") {
+                if relay { "250 recipient ok" } else { "554 relay access denied" }.to_string()
+            } else if line == "QUIT" {
+                stream.get_mut().write_all(b"221 bye\r\n").await?;
+                return Ok(());
+            } else {
+                "250 ok".to_string()
+            };
+            stream.get_mut().write_all(format!("{}\r\n", reply).as_bytes()).await?;
+        }
+    }
+
+    const WAIT: Duration = Duration::from_secs(2);
+
+    #[tokio::test]
+    async fn auth_plain_is_preferred() {
+        let (addr, commands) = smtp_server("LOGIN PLAIN", false).await;
+        assert!(matches!(login(addr, "admin", "secret", WAIT).await, Attempt::Valid));
+        assert!(matches!(login(addr, "admin", "nope", WAIT).await, Attempt::Invalid));
+        assert!(commands.lock().unwrap().iter().any(|c| c.starts_with("AUTH PLAIN ")));
+        assert!(!commands.lock().unwrap().iter().any(|c| c == "AUTH LOGIN"));
+    }
+
+    #[tokio::test]
+    async fn auth_login_is_the_fallback() {
+        let (addr, _) = smtp_server("LOGIN", false).await;
+        assert!(matches!(login(addr, "admin", "secret", WAIT).await, Attempt::Valid));
+        assert!(matches!(login(addr, "root", "secret", WAIT).await, Attempt::Invalid));
+    }
+
+    #[tokio::test]
+    async fn without_auth_the_attempt_is_an_error() {
+        let (addr, _) = smtp_server("", false).await;
+        assert!(matches!(login(addr, "admin", "secret", WAIT).await, Attempt::Error(e) if e.contains("does not offer AUTH")));
+    }
+
+    #[tokio::test]
+    async fn relay_test_never_sends_data() {
+        let (open, commands) = smtp_server("", true).await;
+        assert_eq!(relay_open(open, WAIT).await, Ok(true));
+        let (closed, _) = smtp_server("", false).await;
+        assert_eq!(relay_open(closed, WAIT).await, Ok(false));
+
+        let commands = commands.lock().unwrap();
+        assert!(commands.contains(&format!("RCPT TO:<{}>", RELAY_TO)));
+        assert!(!commands.iter().any(|c| c == "DATA"));
+    }
+
+    #[tokio::test]
+    async fn smtp_mode_reports_open_relays_and_valid_credentials() {
+        let (addr, _) = smtp_server("PLAIN", true).await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testutil::mode_config("smtp", dir.path());
+        config.credentials = Some(crate::core::credentials::CredentialTemplate::new("admin:secret".to_string(), 0));
+        config.smtp_relay_test = true;
+        let result = testutil::run(config, vec![addr.to_string()]).await;
+
+        let details = result.custom_data.unwrap();
+        assert_eq!(result.successful, 2, "{}", details);
+        assert!(details.contains(&format!("Pares: [{} admin:secret]", addr)), "{}", details);
+        assert!(details.contains(&format!("Relay abierto: [{}]", addr)), "{}", details);
+    }
+}
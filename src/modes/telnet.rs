@@ -0,0 +1,357 @@
+use std::net::SocketAddr;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use regex::Regex;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::{timeout_at, Instant};
+
+use super::network::Attempt;
+
+// Bytes de control de telnet (RFC 854)
+const IAC: u8 = 255;
+const DONT: u8 = 254;
+const DO: u8 = 253;
+const WONT: u8 = 252;
+const WILL: u8 = 251;
+const SB: u8 = 250;
+const SE: u8 = 240;
+/// Opciones que se aceptan si el servidor las ofrece: eco y supresión de go-ahead
+const OPT_ECHO: u8 = 1;
+const OPT_SGA: u8 = 3;
+
+/// Texto que se conserva para buscar los prompts; lo anterior se descarta.
+const MAX_SCREEN: usize = 4096;
+
+fn login_prompt() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?i)(login|user ?name|user)\s*:\s*$").unwrap())
+}
+
+fn password_prompt() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?i)pass(word|code)?\s*:\s*$").unwrap())
+}
+
+fn failure() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?i)(incorrect|invalid|fail|denied|bad password|try again)").unwrap())
+}
+
+/// Prompt de shell por defecto cuando no hay `--success-regex`.
+fn shell_prompt() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"[$#>%]\s*$").unwrap())
+}
+
+/// Conexión telnet con la negociación mínima: se aceptan ECHO y SGA del
+/// servidor y se rechaza cualquier otra opción, de modo que lo que queda es
+/// texto plano. `screen` guarda el texto recibido desde el último envío.
+struct Connection {
+    stream: TcpStream,
+    screen: String,
+    /// Una secuencia IAC partida entre dos lecturas
+    pending: Vec<u8>,
+}
+
+impl Connection {
+    /// Lee hasta que `done` encaje con el texto recibido o venza `deadline`.
+    async fn read_until(&mut self, deadline: Instant, done: impl Fn(&str) -> bool) -> Result<(), String> {
+        let mut buf = [0u8; 1024];
+        while !done(&self.screen) {
+            let n = match timeout_at(deadline, self.stream.read(&mut buf)).await {
+                Ok(Ok(0)) => return Err("connection closed".to_string()),
+                Ok(Ok(n)) => n,
+                Ok(Err(e)) => return Err(e.to_string()),
+                Err(_) => return Err("timeout".to_string()),
+            };
+            let mut data = std::mem::take(&mut self.pending);
+            data.extend_from_slice(&buf[..n]);
+            let (text, replies) = self.negotiate(&data);
+            if !replies.is_empty() {
+                self.stream.write_all(&replies).await.map_err(|e| e.to_string())?;
+            }
+            self.screen.push_str(&String::from_utf8_lossy(&text).replace('\0', ""));
+            if self.screen.len() > MAX_SCREEN {
+                let cut = self.screen.len() - MAX_SCREEN;
+                let cut = (cut..self.screen.len()).find(|&i| self.screen.is_char_boundary(i)).unwrap_or(0);
+                self.screen.drain(..cut);
+            }
+        }
+        Ok(())
+    }
+
+    /// Separa el texto de los comandos IAC y prepara las respuestas.
+    fn negotiate(&mut self, data: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let mut text = Vec::new();
+        let mut replies = Vec::new();
+        let mut i = 0;
+        while i < data.len() {
+            if data[i] != IAC {
+                text.push(data[i]);
+                i += 1;
+                continue;
+            }
+            let Some(&command) = data.get(i + 1) else {
+                self.pending = data[i..].to_vec();
+                break;
+            };
+            match command {
+                IAC => {
+                    text.push(IAC);
+                    i += 2;
+                }
+                DO | DONT | WILL | WONT => {
+                    let Some(&option) = data.get(i + 2) else {
+                        self.pending = data[i..].to_vec();
+                        break;
+                    };
+                    match command {
+                        WILL if option == OPT_ECHO || option == OPT_SGA => replies.extend([IAC, DO, option]),
+                        WILL => replies.extend([IAC, DONT, option]),
+                        DO => replies.extend([IAC, WONT, option]),
+                        // A DONT y WONT no se responde: ya es el estado por defecto
+                        _ => {}
+                    }
+                    i += 3;
+                }
+                SB => match data[i..].windows(2).position(|w| w == [IAC, SE]) {
+                    Some(end) => i += end + 2,
+                    None => {
+                        self.pending = data[i..].to_vec();
+                        break;
+                    }
+                },
+                _ => i += 2,
+            }
+        }
+        (text, replies)
+    }
+
+    async fn send_line(&mut self, line: &str) -> Result<(), String> {
+        self.screen.clear();
+        self.stream.write_all(format!("{}\r\n", line).as_bytes()).await.map_err(|e| e.to_string())
+    }
+}
+
+/// Login telnet: espera el prompt de usuario (o directamente el de
+/// contraseña, en equipos que solo piden esta), envía las credenciales y
+/// decide por lo que responde el servidor. Es válido si aparece
+/// `success` (o, sin `--success-regex`, un prompt de shell) y un rechazo si
+/// hay un mensaje de error o vuelve a pedir usuario o contraseña. `wait`
+/// vale para cada espera.
+pub(super) async fn login(addr: SocketAddr, user: &str, pass: &str, wait: Duration, success: Option<&Regex>) -> Attempt {
+    let deadline = Instant::now() + wait;
+    let stream = match timeout_at(deadline, TcpStream::connect(addr)).await {
+        Ok(Ok(stream)) => stream,
+        Ok(Err(e)) => return Attempt::Error(format!("connect: {}", e)),
+        Err(_) => return Attempt::Error("connect: timeout".to_string()),
+    };
+    let mut conn = Connection { stream, screen: String::new(), pending: Vec::new() };
+
+    let prompt = |s: &str| login_prompt().is_match(s) || password_prompt().is_match(s);
+    if let Err(e) = conn.read_until(deadline, prompt).await {
+        return Attempt::Error(format!("no login prompt: {}", e));
+    }
+    if !password_prompt().is_match(&conn.screen) {
+        if let Err(e) = conn.send_line(user).await {
+            return Attempt::Error(e);
+        }
+        let deadline = Instant::now() + wait;
+        if let Err(e) = conn.read_until(deadline, |s| password_prompt().is_match(s)).await {
+            return Attempt::Error(format!("no password prompt: {}", e));
+        }
+    }
+    if let Err(e) = conn.send_line(pass).await {
+        return Attempt::Error(e);
+    }
+
+    // Se lee hasta un prompt (de shell, o de nuevo el de login) o hasta que
+    // el servidor cierre; un mensaje de error suelto no corta la lectura
+    let succeeded = |s: &str| match success {
+        Some(re) => re.is_match(s),
+        None => shell_prompt().is_match(s),
+    };
+    let deadline = Instant::now() + wait;
+    let result = conn.read_until(deadline, |s| succeeded(s) || prompt(s)).await;
+    // Se decide por lo recibido: volver a pedir credenciales es un rechazo
+    // seguro, y los mensajes de error solo cuentan si no hay prompt de shell
+    let screen = conn.screen.as_str();
+    if success.is_some_and(|re| re.is_match(screen)) {
+        Attempt::Valid
+    } else if prompt(screen) {
+        Attempt::Invalid
+    } else if succeeded(screen) {
+        Attempt::Valid
+    } else if failure().is_match(screen) {
+        Attempt::Invalid
+    } else {
+        Attempt::Error(format!("unrecognized reply after password: {}", result.err().unwrap_or_default()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    use tokio::net::TcpListener;
+
+    use crate::core::{Target, Targets};
+    use crate::testutil;
+
+    /// Lee una línea del cliente, descartando los comandos IAC que la preceden.
+    async fn read_line(stream: &mut BufReader<TcpStream>) -> std::io::Result<String> {
+        let mut line = Vec::new();
+        stream.read_until(b'\n', &mut line).await?;
+        let mut text = Vec::new();
+        let mut i = 0;
+        while i < line.len() {
+            if line[i] == IAC {
+                i += 3;
+            } else {
+                text.push(line[i]);
+                i += 1;
+            }
+        }
+        Ok(String::from_utf8_lossy(&text).trim_end().to_string())
+    }
+
+    /// Servidor telnet simulado: ofrece ECHO, pide una opción que el cliente
+    /// debe rechazar y, salvo con `password_only`, pide usuario antes de la
+    /// contraseña. Con `admin`/`secret` muestra `banner` y un prompt de shell.
+    async fn telnet_server(password_only: bool, banner: &'static str) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut stream = BufReader::new(stream);
+                    stream.get_mut().write_all(&[IAC, WILL, OPT_ECHO, IAC, DO, 24]).await?;
+                    let user = if password_only {
+                        "admin".to_string()
+                    } else {
+                        stream.get_mut().write_all(b"Ubuntu 22.04\r\nbox login: ").await?;
+                        read_line(&mut stream).await?
+                    };
+                    stream.get_mut().write_all(b"Password: ").await?;
+                    let pass = read_line(&mut stream).await?;
+                    let reply = if user == "admin" && pass == "secret" {
+                        format!("\r\n{}\r\nadmin@box:~$ ", banner)
+                    } else {
+                        "\r\nLogin incorrect\r\nbox login: ".to_string()
+                    };
+                    stream.get_mut().write_all(reply.as_bytes()).await?;
+                    // Sesión abierta hasta que el cliente cierre
+                    let _ = stream.read_until(b'\n', &mut Vec::new()).await;
+                    Ok::<_, std::io::Error>(())
+                });
+            }
+        });
+        addr
+    }
+
+    const WAIT: Duration = Duration::from_secs(2);
+
+    #[tokio::test]
+    async fn the_login_exchange_is_judged_by_the_reply() {
+        let addr = telnet_server(false, "Welcome").await;
+        assert!(matches!(login(addr, "admin", "secret", WAIT, None).await, Attempt::Valid));
+        assert!(matches!(login(addr, "admin", "wrong", WAIT, None).await, Attempt::Invalid));
+        assert!(matches!(login(addr, "root", "secret", WAIT, None).await, Attempt::Invalid));
+    }
+
+    #[tokio::test]
+    async fn a_password_only_device_skips_the_user() {
+        let addr = telnet_server(true, "Welcome").await;
+        assert!(matches!(login(addr, "ignored", "secret", WAIT, None).await, Attempt::Valid));
+        assert!(matches!(login(addr, "ignored", "wrong", WAIT, None).await, Attempt::Invalid));
+    }
+
+    #[tokio::test]
+    async fn success_regex_replaces_the_shell_prompt() {
+        let addr = telnet_server(false, "Last login: today").await;
+        let custom = Regex::new("Last login").unwrap();
+        assert!(matches!(login(addr, "admin", "secret", WAIT, Some(&custom)).await, Attempt::Valid));
+        // Con `--success-regex`, el prompt de shell ya no basta
+        let never = Regex::new("^never$").unwrap();
+        let attempt = login(addr, "admin", "secret", Duration::from_millis(300), Some(&never)).await;
+        assert!(matches!(&attempt, Attempt::Error(e) if e.contains("unrecognized reply")), "{:?}", attempt);
+    }
+
+    #[tokio::test]
+    async fn a_silent_service_has_no_login_prompt() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let _server = tokio::spawn(async move { listener.accept().await });
+        let attempt = login(addr, "admin", "secret", Duration::from_millis(200), None).await;
+        assert!(matches!(&attempt, Attempt::Error(e) if e.starts_with("no login prompt")), "{:?}", attempt);
+    }
+
+    #[tokio::test]
+    async fn telnet_mode_reports_the_valid_pair() {
+        let addr = telnet_server(false, "Welcome").await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testutil::mode_config("telnet", dir.path());
+        config.combos = Some(vec![("admin".into(), "123456".into()), ("admin".into(), "secret".into())]);
+        let result = testutil::run(config, vec![addr.to_string()]).await;
+        let details = result.custom_data.unwrap();
+        assert!(details.contains("Credenciales válidas: 1, Rechazadas: 1"), "{}", details);
+        assert!(details.contains(&format!("{} admin:secret", addr)), "{}", details);
+    }
+
+    #[tokio::test]
+    async fn combos_are_sent_without_substitution() {
+        let addr = telnet_server(false, "Welcome").await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testutil::mode_config("telnet", dir.path());
+        // Con `--combo` la contraseña va literal, aunque parezca un placeholder
+        config.combos = Some(vec![("admin".into(), "FUZZW1".into())]);
+        let target = Target { url: addr.to_string(), words: vec!["secret".to_string()], indices: vec![] };
+        let result = testutil::run_targets(config, Targets::new(vec![target].into_iter(), 1)).await;
+        let details = result.custom_data.unwrap();
+        assert!(details.contains("Credenciales válidas: 0, Rechazadas: 1"), "{}", details);
+    }
+
+    #[tokio::test]
+    async fn stop_on_success_skips_the_rest_of_each_host() {
+        let (first, second) = (telnet_server(false, "Welcome").await, telnet_server(false, "Welcome").await);
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testutil::mode_config("telnet", dir.path());
+        // Un intento cada vez, para que no haya ninguno en vuelo al acertar
+        config.max_concurrent = 1;
+        config.stop_on_success = true;
+        let mut combos = vec![("admin".to_string(), "123456".to_string()), ("admin".to_string(), "secret".to_string())];
+        combos.extend((0..4).map(|i| ("admin".to_string(), format!("guess{}", i))));
+        config.combos = Some(combos);
+        let result = testutil::run(config, vec![first.to_string(), second.to_string()]).await;
+        let details = result.custom_data.unwrap();
+        // El segundo host se sigue probando tras acertar en el primero
+        assert!(details.contains("Credenciales válidas: 2, Rechazadas: 2"), "{}", details);
+        assert!(details.contains(&format!("{} admin:secret", first)), "{}", details);
+        assert!(details.contains(&format!("{} admin:secret", second)), "{}", details);
+        assert!(details.ends_with("Omitidos: 8"), "{}", details);
+    }
+
+    #[tokio::test]
+    async fn negotiation_accepts_echo_and_refuses_the_rest() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let mut conn = Connection { stream, screen: String::new(), pending: Vec::new() };
+
+        let (text, replies) = conn.negotiate(&[b'a', IAC, WILL, OPT_ECHO, IAC, WILL, 31, IAC, DO, 24, IAC, IAC, b'b']);
+        assert_eq!(text, [b'a', IAC, b'b']);
+        assert_eq!(replies, [IAC, DO, OPT_ECHO, IAC, DONT, 31, IAC, WONT, 24]);
+
+        // Una secuencia partida queda pendiente para la lectura siguiente
+        let (text, replies) = conn.negotiate(&[b'x', IAC, WILL]);
+        assert_eq!((text, replies), (vec![b'x'], vec![]));
+        assert_eq!(conn.pending, [IAC, WILL]);
+        let mut data = std::mem::take(&mut conn.pending);
+        data.extend([OPT_SGA, b'y']);
+        assert_eq!(conn.negotiate(&data), (vec![b'y'], vec![IAC, DO, OPT_SGA]));
+
+        // Las subnegociaciones se descartan enteras
+        assert_eq!(conn.negotiate(&[IAC, SB, 24, 1, IAC, SE, b'z']), (vec![b'z'], vec![]));
+    }
+}
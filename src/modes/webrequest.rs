@@ -1,24 +1,55 @@
 use anyhow::Result;
+use std::collections::{BTreeMap, VecDeque};
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
 use std::time::Instant;
 use colored::*;
+use futures::StreamExt;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
 
-use crate::core::Downzer;
+use crate::core::{Downzer, Target, Targets};
+use crate::core::adaptive::{AdaptiveLimiter, LatencyController, CEILING_FACTOR};
+use crate::core::live::IntervalSampler;
+use crate::core::downzer::request_error_message;
+use crate::core::drain::join_before;
+use crate::core::output::OutputLine;
+use crate::core::redirect;
+use crate::core::filename::content_type_extension;
+use crate::core::report::{ReportRow, ReportSink, ResponseIndex};
+use crate::core::sigv4::SigV4;
+use crate::core::throttle::Pacer;
+use super::matchers::{BodyStats, Calibration};
+use super::recursion::{is_directory, Recursion};
 use super::{ModeConfig, ModeResult};
 
+/// Peticiones de calibración de `--auto-calibrate`.
+const CALIBRATION_REQUESTS: usize = 4;
+
+/// Posiciones de la combinación aleatoria de calibración: la mitad se toman
+/// como rangos y la otra mitad como wordlists, de modo que cualquier
+/// placeholder (`FUZZR3`, `FUZZW2`...) recibe un valor.
+const CALIBRATION_SLOTS: usize = 32;
+
 pub async fn execute(
-    config: ModeConfig,
+    mut config: ModeConfig,
     downzer: Arc<Downzer>,
-    urls: Vec<String>,
+    mut targets: Targets,
     shutdown: Arc<AtomicBool>,
     _task_id: u32,
 ) -> Result<ModeResult> {
     if !config.quiet {
-        println!("{} Modo: Peticiones Web ({} URLs)", "[*]".blue(), urls.len());
+        println!("{} Modo: Peticiones Web ({} URLs)", "[*]".blue(), targets.total());
         if config.verbose >= 2 {
-            println!("  Método: {}", config.method.as_deref().unwrap_or("GET").green());
+            println!("  Método: {}", default_method(&config).to_uppercase().green());
+            if let Some(body) = &config.body {
+                println!("  Cuerpo: {} bytes ({})", body.len(), body.content_type());
+            }
             println!("  Concurrencia: {}", config.max_concurrent);
+            if let Some(target) = config.target_latency {
+                println!("  Latencia objetivo (p90): {}ms", target.as_millis());
+            }
             println!("  Timeout: {}s", config.timeout);
             if config.download_body {
                 println!("  Descargar respuesta: sí");
@@ -35,119 +66,898 @@ pub async fn execute(
         }
     }
 
-    let method = config.method.as_deref().unwrap_or("GET").to_uppercase();
-    let semaphore = Arc::new(tokio::sync::Semaphore::new(config.max_concurrent));
-    let mut handles = vec![];
-    let mut successful = 0;
-    let mut failed = 0;
-    let start = Instant::now();
+    let method = default_method(&config).to_uppercase();
+    let mut recursion = match config.recursion_depth {
+        Some(depth) => Some(Recursion::new(&config.url_or_target, depth)?),
+        None => None,
+    };
+    // Con --target-latency la concurrencia arranca en --max-concurrent y se
+    // ajusta según el p90 de las latencias observadas
+    let limiter = match config.target_latency {
+        Some(target) => AdaptiveLimiter::new(
+            config.max_concurrent,
+            LatencyController::new(target, 1, config.max_concurrent * CEILING_FACTOR),
+        ),
+        None => AdaptiveLimiter::fixed(config.max_concurrent),
+    };
+    let pacer = config.delay.map(|delay| Pacer::new(delay, shutdown.clone()));
+    let mut pending = VecDeque::new();
+    let mut processed: usize = 0;
+    let mut successful: usize = 0;
+    let mut failed: usize = 0;
+    let mut hits: usize = 0;
+    let mut retries: u32 = 0;
+    let mut status_counts: BTreeMap<u16, usize> = BTreeMap::new();
 
-    for (idx, url) in urls.iter().enumerate() {
-        // Check for shutdown before spawning each task
-        if shutdown.load(std::sync::atomic::Ordering::SeqCst) {
-            break;
+    // Peticiones de calentamiento: se ejecutan antes de la pasada medida y
+    // sus resultados no cuentan en el resumen ni en el histograma. Se toman
+    // las primeras URLs del flujo y se devuelven después a su cabeza.
+    if config.warm_up > 0 {
+        let total = targets.total();
+        let head: Vec<Target> = targets.by_ref().take(config.warm_up).collect();
+        if !head.is_empty() {
+            if config.verbose >= 1 && !config.quiet {
+                println!("{} Calentamiento: {} peticiones", "[*]".blue(), config.warm_up);
+            }
+            let request_timeout = std::time::Duration::from_secs(config.timeout);
+            futures::stream::iter(0..config.warm_up)
+                .for_each_concurrent(config.max_concurrent, |i| {
+                    let target = &head[i % head.len()];
+                    let request = request_parts(&config, &target.words)
+                        .and_then(|(headers, body)| {
+                            build_request(&downzer.client, &method, &target.url, headers, body, config.sigv4.as_ref())
+                        })
+                        .ok();
+                    async move {
+                        if let Some(request) = request {
+                            let _ = tokio::time::timeout(request_timeout, request.send()).await;
+                        }
+                    }
+                })
+                .await;
         }
+        targets = Targets::new(head.into_iter().chain(targets), total);
+    }
 
-        let sem = semaphore.clone();
-        let client = downzer.client.clone();
-        let url = url.clone();
-        let method = method.clone();
-        let verbose = config.verbose;
-        let quiet = config.quiet;
-        let request_timeout = std::time::Duration::from_secs(config.timeout);
-
-        let handle = tokio::spawn(async move {
-            let _guard = sem.acquire().await.ok()?;
-
-            // Add timeout to prevent hanging requests
-            let result = match tokio::time::timeout(request_timeout, match method.as_str() {
-                "GET" => client.get(&url).send(),
-                "POST" => client.post(&url).send(),
-                "PUT" => client.put(&url).send(),
-                "DELETE" => client.delete(&url).send(),
-                "PATCH" => client.patch(&url).send(),
-                "HEAD" => client.head(&url).send(),
-                "OPTIONS" => client.request(reqwest::Method::OPTIONS, &url).send(),
-                _ => return Some((false, 0)),
-            }).await {
-                Ok(Ok(resp)) => Ok(resp),
-                Ok(Err(e)) => Err(e),
-                Err(_) => {
-                    if verbose >= 1 {
-                        eprintln!("  {} {} - {}", format!("[{}]", idx + 1).cyan(), url.red(), "Timeout".red());
-                    }
-                    return Some((false, 0));
+    if config.auto_calibrate {
+        match calibrate(&config, &downzer, &method).await? {
+            Some(calibration) => {
+                if !config.quiet {
+                    println!("{} Auto-calibración: se filtran las respuestas con {}", "[*]".blue(), calibration);
                 }
+                config.matchers.calibration = Some(calibration);
+            }
+            None => {
+                if !config.quiet {
+                    eprintln!(
+                        "{} Auto-calibración: las respuestas de prueba no comparten firma; no se filtra nada",
+                        "[!]".yellow()
+                    );
+                }
+            }
+        }
+    }
+
+    if config.verbose >= 2 && !config.quiet {
+        println!("{} Procesando {} peticiones...", "[*]".blue(), targets.total());
+    }
+
+    let live = config.live.clone();
+    let sampler = match &config.interval_stats {
+        Some(path) => Some(IntervalSampler::start(path, live.clone(), shutdown.clone())?),
+        None => None,
+    };
+
+    let mut report = match &config.report {
+        Some(path) => Some(ReportSink::create(path)
+            .map_err(|e| anyhow::anyhow!("Cannot create report {}: {}", path.display(), e))?),
+        None => None,
+    };
+    let mut saved_index = match &config.save_responses {
+        Some(dir) => Some(ResponseIndex::create(dir)
+            .map_err(|e| anyhow::anyhow!("Cannot create {}: {}", dir.display(), e))?),
+        None => None,
+    };
+    let mut saved: usize = 0;
+    let mut directories: usize = 0;
+    let quiet = config.quiet;
+
+    let start = Instant::now();
+
+    let mut record = |result: Option<RequestResult>, target: &Target, recursion: &mut Option<Recursion>| {
+        processed += 1;
+        let bytes = result.as_ref().and_then(|r| r.bytes);
+        live.record(!result.as_ref().is_some_and(|r| r.success), bytes.unwrap_or(0));
+        if let Some(sink) = &mut report {
+            let (status, outcome) = match &result {
+                Some(r) => ((r.status != 0).then_some(r.status), r.outcome),
+                None => (None, "error"),
             };
+            if let Err(e) = sink.write(&ReportRow::new(target, status, outcome, bytes)) {
+                eprintln!("[ERROR] --report: {}", e);
+                report = None;
+            }
+        }
+        if let (Some(index), Some(body)) = (&mut saved_index, result.as_ref().and_then(|r| r.saved.as_ref())) {
+            saved += 1;
+            let row = index.write(body.index, &target.url, body.status, &body.file, body.bytes, &body.content_type);
+            if let Err(e) = row {
+                eprintln!("[ERROR] --save-responses: {}", e);
+                saved_index = None;
+            }
+        }
+        if let Some(result) = result {
+            retries += result.retries;
+            if let Some(line) = result.line {
+                line.emit();
+            }
+            if let (Some(recursion), Some(depth)) = (recursion.as_mut(), result.directory) {
+                if let Some(level) = recursion.discover(&target.url, depth) {
+                    directories += 1;
+                    if !quiet {
+                        println!("  {} Directorio: {} (nivel {})", "[+]".green(), target.url, level);
+                    }
+                }
+            }
+            *status_counts.entry(result.status).or_insert(0) += 1;
+            if result.success {
+                successful += 1;
+            } else {
+                failed += 1;
+            }
+            if result.hit {
+                hits += 1;
+            }
+        } else {
+            failed += 1;
+        }
+    };
+
+    // Con --recursive, cada directorio descubierto abre otra pasada tras la
+    // actual; `depth` es el nivel de la pasada en curso (0 la de la plantilla)
+    let mut idx: usize = 0;
+    let mut depth: usize = 0;
+    loop {
+        for target in targets.by_ref() {
+            // Check for shutdown before spawning each task
+            if shutdown.load(std::sync::atomic::Ordering::SeqCst) {
+                break;
+            }
+            if depth == 0 {
+                if let Some(recursion) = &mut recursion {
+                    recursion.remember(&target);
+                }
+            }
 
-            match result {
-                Ok(resp) => {
-                    let status = resp.status().as_u16();
-                    let success = resp.status().is_success();
-                    
-                    if verbose >= 2 {
-                        if success {
-                            println!("  {} {} [{}]", format!("[{}]", idx + 1).cyan(), url, status.to_string().green());
+            // El permiso se obtiene antes de lanzar la petición para no
+            // adelantarse al generador de URLs
+            let permit = limiter.acquire().await?;
+            if let Some(pacer) = &pacer {
+                pacer.wait().await;
+                if shutdown.load(std::sync::atomic::Ordering::SeqCst) {
+                    break;
+                }
+            }
+            let client = downzer.client.clone();
+            let reported = target.clone();
+            let parts = request_parts(&config, &target.words);
+            let url = target.url;
+            let method = method.clone();
+            let verbose = config.verbose;
+            let ordered = config.ordered_output;
+            let show_errors = verbose >= 1 && !config.quiet_errors;
+            let request_timeout = std::time::Duration::from_secs(config.timeout);
+            let matchers = config.matchers.clone();
+            let save_dir = config.save_responses.clone();
+            let sigv4 = config.sigv4.clone();
+            let retry = config.retry.clone();
+            let cookie_jar = config.cookie_jar.clone();
+            let location_trusted = config.location_trusted;
+            let observer = config.target_latency.is_some().then(|| limiter.clone());
+            let recursive = recursion.is_some();
+            let active = live.begin();
+
+            let handle = tokio::spawn(async move {
+                let _permit = permit;
+                let _active = active;
+
+                // Los 429 (y 503 con --retry-503) se repiten tras esperar lo que
+                // indique Retry-After (o --backoff si no lo trae); la petición se reconstruye para volver a firmarla
+                let (headers, body) = match parts {
+                    Ok(parts) => parts,
+                    Err(e) => {
+                        let mut line = None;
+                        if show_errors {
+                            line = OutputLine::Stderr(format!("  {} {} - {}", format!("[{}]", idx + 1).cyan(), url.red(), e.to_string().red()))
+                                .deliver(ordered);
+                        }
+                        return Some(RequestResult { outcome: "error", line, ..Default::default() });
+                    }
+                };
+                let mut attempt = 0;
+                let result = loop {
+                    let request = match build_request(&client, &method, &url, headers.clone(), body.clone(), sigv4.as_ref()) {
+                        Ok(request) => request,
+                        Err(e) => {
+                            let mut line = None;
+                            if show_errors {
+                                line = OutputLine::Stderr(format!("  {} {} - {}", format!("[{}]", idx + 1).cyan(), url.red(), e.to_string().red()))
+                                    .deliver(ordered);
+                            }
+                            return Some(RequestResult { outcome: "error", line, ..Default::default() });
+                        }
+                    };
+
+                    // Add timeout to prevent hanging requests
+                    let sent = Instant::now();
+                    let response = tokio::time::timeout(
+                        request_timeout,
+                        redirect::send(&client, request, cookie_jar.as_deref(), location_trusted),
+                    )
+                    .await;
+                    // Los errores de conexión no dicen nada de la carga del servidor;
+                    // un timeout cuenta como la latencia máxima
+                    if let Some(limiter) = &observer {
+                        match &response {
+                            Ok(Ok(_)) => limiter.observe(sent.elapsed()),
+                            Err(_) => limiter.observe(request_timeout),
+                            Ok(Err(_)) => {}
+                        }
+                    }
+                    let result = match response {
+                        Ok(Ok(resp)) => Ok(resp),
+                        Ok(Err(e)) => Err(e),
+                        Err(_) => {
+                            let mut line = None;
+                            if show_errors {
+                                line = OutputLine::Stderr(format!("  {} {} - {}", format!("[{}]", idx + 1).cyan(), url.red(), "Timeout".red()))
+                                    .deliver(ordered);
+                            }
+                            return Some(RequestResult { outcome: "timeout", retries: attempt, line, ..Default::default() });
+                        }
+                    };
+
+                    if let Ok(resp) = &result {
+                        if let Some(wait) = retry.wait_for(resp.status(), resp.headers(), attempt) {
+                            attempt += 1;
+                            tokio::time::sleep(wait).await;
+                            continue;
+                        }
+                    }
+                    break result;
+                };
+
+                match result {
+                    Ok(resp) => {
+                        let status = resp.status().as_u16();
+                        let success = resp.status().is_success();
+                        let headers = resp.headers().clone();
+                        let final_url = resp.url().clone();
+                        let content_length = resp.content_length();
+                        // El cuerpo solo se lee si hay que medirlo (--match-size, --filter-words...)
+                        // o guardarlo (--save-responses)
+                        let bytes = if matchers.needs_body() || save_dir.is_some() {
+                            tokio::time::timeout(request_timeout, resp.bytes()).await.ok().and_then(|r| r.ok())
                         } else {
-                            println!("  {} {} [{}]", format!("[{}]", idx + 1).cyan(), url, status.to_string().red());
+                            None
+                        };
+                        // Sin leer el cuerpo, su tamaño es el que anuncia Content-Length
+                        let size = bytes.as_ref().map(|b| b.len() as u64).or(content_length);
+                        let body = bytes.as_deref().filter(|_| matchers.needs_body()).map(BodyStats::of);
+                        let hit = matchers.matches(status, &headers, body.as_ref());
+                        // Con predicados, solo se entra en los directorios que coinciden
+                        let directory = (recursive && (hit || matchers.is_empty()) && is_directory(&url, &final_url, status))
+                            .then_some(depth);
+
+                        // Cada cuerpo va a su propio archivo (`<índice>_<código>.<ext>`), así
+                        // que las escrituras simultáneas no se pisan
+                        let saved = match (&save_dir, &bytes) {
+                            (Some(dir), Some(bytes)) => {
+                                let content_type = headers.get(CONTENT_TYPE).and_then(|v| v.to_str().ok()).unwrap_or_default().to_string();
+                                let ext = content_type_extension(&content_type).unwrap_or("body");
+                                let file = format!("{:06}_{}.{}", idx, status, ext);
+                                match tokio::fs::write(dir.join(&file), bytes).await {
+                                    Ok(()) => Some(SavedResponse { index: idx, status, file, bytes: bytes.len() as u64, content_type }),
+                                    Err(e) => {
+                                        eprintln!("[ERROR] --save-responses {}: {}", file, e);
+                                        None
+                                    }
+                                }
+                            }
+                            _ => None,
+                        };
+
+                        // Las coincidencias se muestran siempre; el resto, con -vv
+                        let mut line = None;
+                        if hit || verbose >= 2 {
+                            let code = if success { status.to_string().green() } else { status.to_string().red() };
+                            let stats = body.map_or(String::new(), |b| {
+                                format!(" [Size: {}, Words: {}, Lines: {}]", b.size, b.words, b.lines)
+                            });
+                            let mark = if hit { format!(" {}", "[MATCH]".magenta().bold()) } else { String::new() };
+                            line = OutputLine::Stdout(format!("  {} {} [{}]{}{}", format!("[{}]", idx + 1).cyan(), url, code, stats, mark))
+                                .deliver(ordered);
                         }
+
+                        let outcome = match (hit, success) {
+                            (true, _) => "match",
+                            (false, true) => "success",
+                            (false, false) => "http_error",
+                        };
+                        Some(RequestResult { success, status, hit, outcome, retries: attempt, bytes: size, saved, directory, line })
                     }
-                    
-                    Some((success, status))
-                }
-                Err(e) => {
-                    if verbose >= 1 {
-                        eprintln!("  {} {} - {}", format!("[{}]", idx + 1).cyan(), url.red(), e.to_string().red());
+                    Err(e) => {
+                        let mut line = None;
+                        if show_errors {
+                            line = OutputLine::Stderr(format!("  {} {} - {}", format!("[{}]", idx + 1).cyan(), url.red(), request_error_message(&e).red()))
+                                .deliver(ordered);
+                        }
+                        Some(RequestResult { outcome: "error", retries: attempt, line, ..Default::default() })
                     }
-                    Some((false, 0))
                 }
-            }
-        });
+            });
 
-        handles.push(handle);
-    }
+            pending.push_back((handle, reported));
+            idx += 1;
 
-    if config.verbose >= 2 && !config.quiet {
-        println!("{} Procesando {} peticiones...", "[*]".blue(), urls.len());
+            // Recoger en orden los resultados ya terminados para no acumular handles
+            while pending.front().is_some_and(|(h, _): &(tokio::task::JoinHandle<_>, _)| h.is_finished()) {
+                if let Some((handle, target)) = pending.pop_front() {
+                    record(handle.await.ok().flatten(), &target, &mut recursion);
+                }
+            }
+        }
+
+        if recursion.is_none() || shutdown.load(std::sync::atomic::Ordering::SeqCst) {
+            break;
+        }
+        // Sin directorios en cola, se esperan los resultados pendientes hasta
+        // que aparezca alguno o no quede nada por recoger
+        let next = loop {
+            if let Some(pass) = recursion.as_mut().and_then(Recursion::next_pass) {
+                break Some(pass);
+            }
+            if shutdown.load(std::sync::atomic::Ordering::SeqCst) {
+                break None;
+            }
+            let Some((handle, target)) = pending.pop_front() else {
+                break None;
+            };
+            record(handle.await.ok().flatten(), &target, &mut recursion);
+        };
+        let Some((base, level, next_targets)) = next else {
+            break;
+        };
+        if !config.quiet {
+            println!("{} Recursión: {} (nivel {}, {} URLs)", "[*]".blue(), base, level, next_targets.total());
+        }
+        targets = next_targets;
+        depth = level;
     }
 
-    // Procesar resultados - también aquí checar shutdown
-    for handle in handles {
+    // Procesar resultados - también aquí checar shutdown. Con --drain-timeout,
+    // lo que siga pendiente al vencer el plazo se aborta y cuenta como fallo
+    let deadline = config.drain_timeout.map(|t| tokio::time::Instant::now() + std::time::Duration::from_secs(t));
+    let mut aborted = 0;
+    while let Some((mut handle, target)) = pending.pop_front() {
         if shutdown.load(std::sync::atomic::Ordering::SeqCst) {
             break;
         }
-        
-        if let Ok(Some((success, _status))) = handle.await {
-            if success {
-                successful += 1;
-            } else {
-                failed += 1;
+        match join_before(&mut handle, deadline).await {
+            Some(result) => record(result.ok().flatten(), &target, &mut recursion),
+            None => {
+                handle.abort();
+                aborted += 1;
+                record(Some(RequestResult { outcome: "timeout", ..Default::default() }), &target, &mut recursion);
             }
-        } else {
-            failed += 1;
         }
     }
+    if let Some(sink) = report {
+        sink.finish()?;
+    }
+    if let Some(index) = saved_index {
+        index.finish()?;
+    }
+    if aborted > 0 && !config.quiet {
+        eprintln!("{} Drain timeout: aborted {} pending request(s)", "[!]".yellow(), aborted);
+    }
 
     let elapsed = start.elapsed();
+    if let Some(sampler) = sampler {
+        sampler.stop().await?;
+    }
 
     if config.verbose >= 1 || !config.quiet {
         println!();
         println!("{}", "═══════════════════════════════════════".green());
         println!("{} Peticiones completadas en {:.2}s", "[✓]".green(), elapsed.as_secs_f64());
         println!("  Exitosas: {} ({}%)", successful.to_string().green(), 
-                 if urls.len() > 0 { (successful * 100 / urls.len()) as u32 } else { 0 });
+                 (successful * 100).checked_div(processed).unwrap_or(0));
         println!("  Fallidas: {} ({}%)", failed.to_string().yellow(), 
-                 if urls.len() > 0 { (failed * 100 / urls.len()) as u32 } else { 0 });
-        println!("  Velocidad: {:.2} req/s", (urls.len() as f64 / elapsed.as_secs_f64()));
+                 (failed * 100).checked_div(processed).unwrap_or(0));
+        println!("  Velocidad: {:.2} req/s", (processed as f64 / elapsed.as_secs_f64()));
+        if !config.matchers.is_empty() {
+            println!("  Coincidencias: {}", hits.to_string().magenta());
+        }
+        if retries > 0 {
+            println!("  Reintentos (429/503): {}", retries);
+        }
+        if recursion.is_some() {
+            println!("  Directorios descubiertos: {}", directories);
+        }
+        if let Some(dir) = &config.save_responses {
+            println!("  Respuestas guardadas: {} en {}", saved, dir.display());
+        }
+        if let Some(target) = config.target_latency {
+            println!("  Concurrencia final: {} (p90 objetivo {}ms)", limiter.limit(), target.as_millis());
+        }
+        if config.verbose >= 1 || config.summary_by_status.is_some() {
+            let by_code = config.summary_by_status.as_deref() == Some("code");
+            println!("  Códigos de estado:");
+            for line in format_status_counts(&status_counts, by_code) {
+                println!("    {}", line);
+            }
+        }
         println!("{}", "═══════════════════════════════════════".green());
     }
 
     Ok(ModeResult {
         mode: "webrequest".to_string(),
-        total: urls.len(),
+        total: processed,
         successful,
         failed,
         errors: vec![],
-        custom_data: Some(format!("Velocidad: {:.2} req/s", urls.len() as f64 / elapsed.as_secs_f64())),
+        status_counts: status_counts.clone(),
+        custom_data: Some(format!(
+            "Velocidad: {:.2} req/s, Estados: {}{}{}{}",
+            processed as f64 / elapsed.as_secs_f64(),
+            format_status_counts(&status_counts, true).join(", "),
+            if config.matchers.is_empty() { String::new() } else { format!(", Coincidencias: {}", hits) },
+            if retries == 0 { String::new() } else { format!(", Reintentos: {}", retries) },
+            if config.target_latency.is_none() { String::new() } else { format!(", Concurrencia final: {}", limiter.limit()) }
+        )),
     })
 }
+
+/// Pasada de `--auto-calibrate`: pide la plantilla con valores aleatorios que
+/// no deberían existir y aprende la firma común de las respuestas. Las
+/// peticiones fallidas se ignoran; con menos de dos respuestas no hay firma.
+async fn calibrate(config: &ModeConfig, downzer: &Downzer, method: &str) -> Result<Option<Calibration>> {
+    // Longitudes variables: si el servidor refleja la ruta, el tamaño cambia
+    // y la firma pasa a las palabras o las líneas
+    let random_combo = || -> Vec<String> {
+        let mut rng = rand::thread_rng();
+        (0..CALIBRATION_SLOTS)
+            .map(|_| {
+                let len = rng.gen_range(8..=32);
+                (&mut rng).sample_iter(&Alphanumeric).take(len).map(char::from).collect()
+            })
+            .collect()
+    };
+    let probe = random_combo();
+    if Downzer::substitute_placeholders(&config.url_or_target, &probe, CALIBRATION_SLOTS / 2) == config.url_or_target {
+        anyhow::bail!("--auto-calibrate needs placeholders (FUZZ, FUZZW1...) in the URL template");
+    }
+
+    let request_timeout = std::time::Duration::from_secs(config.timeout);
+    let probes = (0..CALIBRATION_REQUESTS).map(|_| {
+        let combo = random_combo();
+        let url = Downzer::substitute_placeholders(&config.url_or_target, &combo, CALIBRATION_SLOTS / 2);
+        let request = request_parts(config, &combo)
+            .and_then(|(headers, body)| build_request(&downzer.client, method, &url, headers, body, config.sigv4.as_ref()))
+            .ok();
+        async move {
+            let request = request?;
+            let send = redirect::send(&downzer.client, request, config.cookie_jar.as_deref(), config.location_trusted);
+            let resp = tokio::time::timeout(request_timeout, send).await.ok()?.ok()?;
+            let status = resp.status().as_u16();
+            let body = tokio::time::timeout(request_timeout, resp.bytes()).await.ok()?.ok()?;
+            Some((status, BodyStats::of(&body)))
+        }
+    });
+    let samples: Vec<(u16, BodyStats)> = futures::future::join_all(probes).await.into_iter().flatten().collect();
+    if config.verbose >= 2 && !config.quiet {
+        for (status, body) in &samples {
+            println!("  Calibración: [{}] [Size: {}, Words: {}, Lines: {}]", status, body.size, body.words, body.lines);
+        }
+    }
+    if samples.len() < 2 {
+        return Ok(None);
+    }
+    Ok(Calibration::learn(&samples))
+}
+
+/// Resultado de una petición individual. El estado 0 agrupa timeouts y
+/// errores de conexión.
+#[derive(Default)]
+struct RequestResult {
+    success: bool,
+    status: u16,
+    /// Cumple los predicados de `--match-header`
+    hit: bool,
+    /// Resultado para `--report`: success, http_error, match, timeout o error
+    outcome: &'static str,
+    /// Reintentos por 429/503 antes del resultado final
+    retries: u32,
+    /// Tamaño del cuerpo de la respuesta, si se conoce
+    bytes: Option<u64>,
+    /// Cuerpo guardado con `--save-responses`
+    saved: Option<SavedResponse>,
+    /// Nivel de la pasada en la que la respuesta resultó ser un directorio (`--recursive`)
+    directory: Option<usize>,
+    line: Option<OutputLine>,
+}
+
+/// Cuerpo de una respuesta guardado en el directorio de `--save-responses`.
+struct SavedResponse {
+    index: usize,
+    status: u16,
+    file: String,
+    bytes: u64,
+    content_type: String,
+}
+
+/// Método de `--method`; sin él, POST si hay cuerpo (como `curl --data`) y GET si no.
+fn default_method(config: &ModeConfig) -> &str {
+    config.method.as_deref().unwrap_or(if config.body.is_some() { "POST" } else { "GET" })
+}
+
+/// Cabeceras y cuerpo de la petición para la combinación `words`. Con cuerpo
+/// y sin `Content-Type` en `--header`, se añade el que corresponde al cuerpo.
+fn request_parts(config: &ModeConfig, words: &[String]) -> Result<(HeaderMap, Option<String>)> {
+    let mut headers = config.headers.resolve(words)?;
+    let body = config.body.as_ref().map(|body| {
+        if !headers.contains_key(CONTENT_TYPE) {
+            headers.insert(CONTENT_TYPE, HeaderValue::from_static(body.content_type()));
+        }
+        body.resolve(words)
+    });
+    Ok((headers, body))
+}
+
+/// Construye la petición para `method` con las cabeceras de `--header` y el
+/// cuerpo de `--data`. Con `--sigv4` se firma justo antes de enviarla, sobre
+/// la URL y el cuerpo ya sustituidos. Falla si el método no está soportado o
+/// si la petición no se puede firmar.
+fn build_request(
+    client: &reqwest::Client,
+    method: &str,
+    url: &str,
+    headers: HeaderMap,
+    body: Option<String>,
+    sigv4: Option<&SigV4>,
+) -> Result<reqwest::RequestBuilder> {
+    let method = match method {
+        "GET" => reqwest::Method::GET,
+        "POST" => reqwest::Method::POST,
+        "PUT" => reqwest::Method::PUT,
+        "DELETE" => reqwest::Method::DELETE,
+        "PATCH" => reqwest::Method::PATCH,
+        "HEAD" => reqwest::Method::HEAD,
+        "OPTIONS" => reqwest::Method::OPTIONS,
+        _ => anyhow::bail!("Unsupported HTTP method: {}", method),
+    };
+    let mut request = client.request(method.clone(), url).headers(headers);
+    if let Some(sigv4) = sigv4 {
+        let payload = body.as_deref().unwrap_or_default().as_bytes();
+        let signed = sigv4
+            .sign(method.as_str(), url, payload, chrono::Utc::now())
+            .map_err(|e| anyhow::anyhow!("Cannot sign request: {}", e))?;
+        for (name, value) in signed {
+            request = request.header(name, value);
+        }
+    }
+    if let Some(body) = body {
+        request = request.body(body);
+    }
+    Ok(request)
+}
+
+/// Formatea el histograma `código → cantidad`, ordenado por cantidad
+/// (descendente) o por código. El código 0 agrupa timeouts y errores de conexión.
+fn format_status_counts(counts: &BTreeMap<u16, usize>, by_code: bool) -> Vec<String> {
+    let mut entries: Vec<(u16, usize)> = counts.iter().map(|(c, n)| (*c, *n)).collect();
+    if !by_code {
+        entries.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    }
+    entries
+        .into_iter()
+        .map(|(code, count)| format!("{}: {}", status_label(code), count))
+        .collect()
+}
+
+/// Etiqueta de un código del histograma; el 0 (sin respuesta) es `error`.
+pub fn status_label(code: u16) -> String {
+    if code == 0 { "error".to_string() } else { code.to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn histogram(statuses: &[u16]) -> BTreeMap<u16, usize> {
+        let mut counts = BTreeMap::new();
+        for status in statuses {
+            *counts.entry(*status).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn status_histogram_sorted_by_count_or_code() {
+        let counts = histogram(&[404, 200, 404, 0, 500, 404, 200]);
+        assert_eq!(format_status_counts(&counts, false), ["404: 3", "200: 2", "error: 1", "500: 1"]);
+        assert_eq!(format_status_counts(&counts, true), ["error: 1", "200: 2", "404: 3", "500: 1"]);
+    }
+
+    #[test]
+    fn empty_histogram_formats_nothing() {
+        assert!(format_status_counts(&BTreeMap::new(), false).is_empty());
+    }
+
+    #[tokio::test]
+    async fn out_of_order_completions_are_collected_in_generation_order() {
+        use crate::testutil::{self, MockServer, Response};
+
+        // /0 tarda más que /1, y /1 más que /2: terminan al revés
+        let server = MockServer::start(|req| {
+            let n: u64 = req.path.trim_start_matches('/').parse().unwrap();
+            Response::new(200).delay(std::time::Duration::from_millis(300 - n * 150))
+        })
+        .await;
+        let dir = tempfile::tempdir().unwrap();
+        let report = dir.path().join("report.json");
+        let mut config = testutil::mode_config("webrequest", dir.path());
+        config.ordered_output = true;
+        config.report = Some(report.clone());
+        let urls: Vec<String> = (0..3).map(|n| server.url(&format!("/{}", n))).collect();
+        testutil::run(config, urls.clone()).await;
+
+        let rows: Vec<serde_json::Value> = serde_json::from_str(&std::fs::read_to_string(report).unwrap()).unwrap();
+        let order: Vec<&str> = rows.iter().map(|row| row["url"].as_str().unwrap()).collect();
+        assert_eq!(order, urls);
+    }
+
+    #[tokio::test]
+    async fn warm_up_requests_are_not_counted() {
+        use crate::testutil::{self, MockServer, Response};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // Las dos primeras peticiones (el calentamiento) reciben un 500
+        let hits = AtomicUsize::new(0);
+        let server = MockServer::start(move |_| {
+            let status = if hits.fetch_add(1, Ordering::SeqCst) < 2 { 500 } else { 200 };
+            Response::new(status)
+        })
+        .await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testutil::mode_config("webrequest", dir.path());
+        config.warm_up = 2;
+        let urls: Vec<String> = (0..3).map(|n| server.url(&format!("/{}", n))).collect();
+        let result = testutil::run(config, urls).await;
+
+        assert_eq!(server.requests().len(), 5);
+        assert_eq!((result.total, result.successful, result.failed), (3, 3, 0));
+        assert_eq!(result.status_counts, BTreeMap::from([(200, 3)]));
+    }
+
+    /// Separación entre los inicios de las peticiones que recibe el servidor
+    /// al ejecutar `n` peticiones con `--delay <spec>`.
+    async fn paced_gaps(spec: &str, n: usize) -> Vec<std::time::Duration> {
+        use crate::core::throttle::parse_delay;
+        use crate::testutil::{self, MockServer, Response};
+        use std::sync::{Arc, Mutex};
+
+        let arrivals = Arc::new(Mutex::new(Vec::new()));
+        let seen = arrivals.clone();
+        let server = MockServer::start(move |_| {
+            seen.lock().unwrap().push(std::time::Instant::now());
+            Response::new(200)
+        })
+        .await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testutil::mode_config("webrequest", dir.path());
+        config.delay = Some(parse_delay(spec).unwrap());
+        config.max_concurrent = 4;
+        let urls: Vec<String> = (0..n).map(|i| server.url(&format!("/{}", i))).collect();
+        let result = testutil::run(config, urls).await;
+        assert_eq!(result.successful, n);
+
+        let mut arrivals = arrivals.lock().unwrap().clone();
+        arrivals.sort();
+        arrivals.windows(2).map(|pair| pair[1] - pair[0]).collect()
+    }
+
+    #[tokio::test]
+    async fn fixed_delay_spaces_every_request() {
+        let gaps = paced_gaps("150ms", 4).await;
+        assert_eq!(gaps.len(), 3);
+        // Con concurrencia 4 salen igualmente de una en una, cada 150 ms
+        for gap in &gaps {
+            assert!(gap.as_millis() >= 130 && gap.as_millis() < 400, "{:?}", gaps);
+        }
+    }
+
+    #[tokio::test]
+    async fn every_n_delay_pauses_after_each_batch() {
+        // 1 s de pausa cada 2 peticiones: 0, 0, 1, 1, 2 s
+        let gaps = paced_gaps("1x2", 5).await;
+        let long: Vec<bool> = gaps.iter().map(|gap| gap.as_millis() >= 900).collect();
+        assert_eq!(long, [false, true, false, true], "{:?}", gaps);
+        for gap in gaps.iter().filter(|gap| gap.as_millis() >= 900) {
+            assert!(gap.as_millis() < 1300, "{:?}", gaps);
+        }
+    }
+
+    async fn output_lines(quiet_errors: bool) -> Vec<OutputLine> {
+        use crate::core::output;
+        use crate::testutil::{self, MockServer, Response};
+
+        let server = MockServer::start(|_| Response::new(200)).await;
+        // Puerto sin nadie escuchando: la conexión se rechaza
+        let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testutil::mode_config("webrequest", dir.path());
+        config.verbose = 2;
+        config.quiet = false;
+        config.quiet_errors = quiet_errors;
+        output::start_capture();
+        testutil::run(config, vec![server.url("/ok"), format!("http://{}/down", closed)]).await;
+        output::captured()
+    }
+
+    #[tokio::test]
+    async fn quiet_errors_hides_error_lines_only() {
+        let lines = output_lines(true).await;
+        assert!(lines.iter().any(|l| matches!(l, OutputLine::Stdout(s) if s.contains("/ok"))), "{:?}", lines);
+        assert!(!lines.iter().any(|l| matches!(l, OutputLine::Stderr(_))), "{:?}", lines);
+
+        let lines = output_lines(false).await;
+        assert!(lines.iter().any(|l| matches!(l, OutputLine::Stderr(s) if s.contains("/down"))), "{:?}", lines);
+    }
+
+    #[tokio::test]
+    async fn live_stats_count_response_bytes() {
+        use crate::testutil::{self, MockServer, Response};
+
+        let server = MockServer::start(|req| match req.path.as_str() {
+            "/big" => Response::new(200).body(vec![b'x'; 1000]),
+            _ => Response::new(404).body("missing"),
+        })
+        .await;
+        let dir = tempfile::tempdir().unwrap();
+        let live = Arc::new(crate::core::live::LiveStats::default());
+        let mut config = testutil::mode_config("webrequest", dir.path());
+        config.live = live.clone();
+        testutil::run(config.clone(), vec![server.url("/big"), server.url("/gone")]).await;
+        assert_eq!(live.bytes.load(std::sync::atomic::Ordering::Relaxed), 1007);
+
+        // También cuando el cuerpo se lee para medirlo
+        let live = Arc::new(crate::core::live::LiveStats::default());
+        config.live = live.clone();
+        config.matchers.body.size = crate::modes::matchers::NumberSet::parse_counts("0-10", "--match-size").unwrap();
+        testutil::run(config, vec![server.url("/big")]).await;
+        assert_eq!(live.bytes.load(std::sync::atomic::Ordering::Relaxed), 1000);
+    }
+
+    #[tokio::test]
+    async fn drain_timeout_aborts_a_hung_request() {
+        use crate::testutil::{self, MockServer, Response};
+
+        let server = MockServer::start(|req| match req.path.as_str() {
+            "/hang" => Response::new(200).delay(std::time::Duration::from_secs(60)),
+            _ => Response::new(200).body("ok"),
+        })
+        .await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testutil::mode_config("webrequest", dir.path());
+        config.timeout = 120;
+        config.drain_timeout = Some(1);
+        let started = Instant::now();
+        let result = testutil::run(config, vec![server.url("/hang"), server.url("/ok")]).await;
+        assert!(started.elapsed() < std::time::Duration::from_secs(5), "{:?}", started.elapsed());
+        assert_eq!((result.total, result.successful, result.failed), (2, 1, 1));
+    }
+
+    #[tokio::test]
+    async fn rate_limited_request_is_retried_after_retry_after() {
+        use crate::testutil::{self, MockServer, Response};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let calls = AtomicUsize::new(0);
+        let server = MockServer::start(move |_| match calls.fetch_add(1, Ordering::SeqCst) {
+            0 => Response::new(429).header("Retry-After", "1"),
+            _ => Response::new(200).body("ok"),
+        })
+        .await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = testutil::mode_config("webrequest", dir.path());
+        config.retry.max_retries = 3;
+        let started = Instant::now();
+        let result = testutil::run(config, vec![server.url("/api")]).await;
+        assert!(started.elapsed() >= std::time::Duration::from_secs(1));
+        assert_eq!((result.successful, result.failed), (1, 0));
+        assert_eq!(result.status_counts, BTreeMap::from([(200, 1)]));
+        assert_eq!(server.paths(), ["/api", "/api"]);
+    }
+
+    #[tokio::test]
+    async fn auto_calibration_filters_the_catch_all_response() {
+        use crate::core::targets::{Target, Targets};
+        use crate::testutil::{self, MockServer, Response};
+
+        // Cualquier ruta devuelve el mismo 200 salvo /admin
+        let server = MockServer::start(|req| match req.path.as_str() {
+            "/admin" => Response::new(200).body("Welcome to the admin panel"),
+            _ => Response::new(200).body("<html>Page not found</html>"),
+        })
+        .await;
+        let dir = tempfile::tempdir().unwrap();
+        let report = dir.path().join("report.json");
+        let mut config = testutil::mode_config("webrequest", dir.path());
+        config.url_or_target = server.url("/FUZZW1");
+        config.auto_calibrate = true;
+        config.ordered_output = true;
+        config.report = Some(report.clone());
+        let words = ["index", "admin", "backup"];
+        let targets: Vec<Target> = words
+            .iter()
+            .map(|w| Target { url: server.url(&format!("/{}", w)), words: vec![w.to_string()], indices: vec![] })
+            .collect();
+        testutil::run_targets(config, Targets::new(targets.into_iter(), words.len())).await;
+
+        // Las peticiones de calibración van a rutas aleatorias antes que las de la lista
+        let paths = server.paths();
+        assert_eq!(paths.len(), CALIBRATION_REQUESTS + words.len());
+        assert!(paths[..CALIBRATION_REQUESTS].iter().all(|p| !words.contains(&&p[1..])), "{:?}", paths);
+
+        let rows: Vec<serde_json::Value> = serde_json::from_str(&std::fs::read_to_string(report).unwrap()).unwrap();
+        let outcomes: Vec<&str> = rows.iter().map(|row| row["outcome"].as_str().unwrap()).collect();
+        assert_eq!(outcomes, ["success", "match", "success"]);
+    }
+
+    #[tokio::test]
+    async fn auto_calibration_needs_placeholders() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = crate::testutil::mode_config("webrequest", dir.path());
+        config.url_or_target = "http://127.0.0.1:9/static".to_string();
+        let err = calibrate(&config, &crate::testutil::downzer(), "GET").await.unwrap_err();
+        assert!(err.to_string().contains("needs placeholders"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn save_responses_writes_each_body_and_an_index() {
+        use crate::testutil::{self, MockServer, Response};
+
+        let server = MockServer::start(|req| match req.path.as_str() {
+            "/page" => Response::new(200).header("Content-Type", "text/html; charset=utf-8").body("<h1>hi</h1>"),
+            "/api" => Response::new(201).header("Content-Type", "application/json").body("{\"id\":1}"),
+            _ => Response::new(404).body("missing"),
+        })
+        .await;
+        let dir = tempfile::tempdir().unwrap();
+        let saved = dir.path().join("responses");
+        let mut config = testutil::mode_config("webrequest", dir.path());
+        config.save_responses = Some(saved.clone());
+        let urls: Vec<String> = ["/page", "/api", "/nope"].iter().map(|p| server.url(p)).collect();
+        testutil::run(config, urls.clone()).await;
+
+        let read = |file: &str| std::fs::read_to_string(saved.join(file)).unwrap();
+        assert_eq!(read("000000_200.html"), "<h1>hi</h1>");
+        assert_eq!(read("000001_201.json"), "{\"id\":1}");
+        assert_eq!(read("000002_404.body"), "missing");
+
+        let index = read(ResponseIndex::FILE);
+        let mut lines: Vec<&str> = index.lines().collect();
+        assert_eq!(lines.remove(0), "index,url,status,file,bytes,content_type");
+        lines.sort();
+        assert_eq!(
+            lines,
+            [
+                format!("0,{},200,000000_200.html,11,text/html; charset=utf-8", urls[0]),
+                format!("1,{},201,000001_201.json,8,application/json", urls[1]),
+                format!("2,{},404,000002_404.body,7,", urls[2]),
+            ]
+        );
+    }
+}
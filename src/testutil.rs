@@ -0,0 +1,323 @@
+//! Utilidades compartidas por los tests: un servidor HTTP mínimo sobre
+//! loopback, un `Downzer` con la base de datos en memoria y una
+//! `ModeConfig` con los valores por defecto de la línea de comandos.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rand::SeedableRng;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+use crate::core::db::Database;
+use crate::core::downzer::Config;
+use crate::core::headers::HeaderTemplates;
+use crate::core::live::LiveStats;
+use crate::core::retry::{Backoff, RetryPolicy};
+use crate::core::{ClientOptions, Downzer, Targets};
+use crate::modes::matchers::ResponseMatchers;
+use crate::modes::{self, ModeConfig, ModeResult};
+
+/// Petición tal como la recibió el servidor.
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Respuesta que devuelve el manejador del servidor.
+#[derive(Debug, Clone)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    pub delay: Option<Duration>,
+}
+
+impl Response {
+    pub fn new(status: u16) -> Self {
+        Self { status, headers: Vec::new(), body: Vec::new(), delay: None }
+    }
+
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Espera antes de responder
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+}
+
+type Handler = dyn Fn(&Request) -> Response + Send + Sync;
+
+/// Servidor HTTP/1.1 en `127.0.0.1` con un puerto libre. Cierra la conexión
+/// tras cada respuesta y guarda las peticiones recibidas.
+pub struct MockServer {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<Request>>>,
+    handle: JoinHandle<()>,
+}
+
+impl MockServer {
+    pub async fn start(handler: impl Fn(&Request) -> Response + Send + Sync + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let handler: Arc<Handler> = Arc::new(handler);
+        let recorded = requests.clone();
+        let handle = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let handler = handler.clone();
+                let recorded = recorded.clone();
+                tokio::spawn(async move {
+                    let _ = serve(stream, handler, recorded).await;
+                });
+            }
+        });
+        Self { addr, requests, handle }
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Rutas pedidas, en orden de llegada.
+    pub fn paths(&self) -> Vec<String> {
+        self.requests().into_iter().map(|r| r.path).collect()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+async fn serve(stream: TcpStream, handler: Arc<Handler>, recorded: Arc<Mutex<Vec<Request>>>) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).await?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        reader.read_line(&mut line).await?;
+        let trimmed = line.trim_end();
+        if trimmed.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = trimmed.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    let length = headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, v)| v.parse().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+
+    let request = Request { method, path, headers, body };
+    recorded.lock().unwrap().push(request.clone());
+    let response = handler(&request);
+    if let Some(delay) = response.delay {
+        tokio::time::sleep(delay).await;
+    }
+
+    let mut head = format!("HTTP/1.1 {} X\r\nContent-Length: {}\r\nConnection: close\r\n", response.status, response.body.len());
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    let mut stream = reader.into_inner();
+    stream.write_all(head.as_bytes()).await?;
+    if request.method != "HEAD" {
+        stream.write_all(&response.body).await?;
+    }
+    stream.shutdown().await
+}
+
+/// `Downzer` con la base de datos en memoria, para no tocar la del usuario.
+pub fn downzer() -> Arc<Downzer> {
+    let options = ClientOptions { timeout: 10, ..Default::default() };
+    Downzer::with_database(options, Config::default(), Database::open(Path::new(":memory:")).unwrap()).unwrap()
+}
+
+/// `ModeConfig` con los valores por defecto de la CLI.
+pub fn mode_config(mode: &str, outdir: &Path) -> ModeConfig {
+    ModeConfig {
+        mode: mode.to_string(),
+        url_or_target: String::new(),
+        method: None,
+        body: None,
+        download_body: false,
+        content_types: Vec::new(),
+        max_filename_length: 255,
+        name_template: None,
+        preserve_path: false,
+        headers: HeaderTemplates::default(),
+        credentials: None,
+        combos: None,
+        stop_on_success: false,
+        ftp_anon: false,
+        smtp_relay_test: false,
+        success_regex: None,
+        cookie_jar: None,
+        location_trusted: false,
+        exec: None,
+        summary_by_status: None,
+        ordered_output: false,
+        quiet_errors: false,
+        dedup_content: false,
+        hardlink_dupes: false,
+        min_size: None,
+        max_size: None,
+        resume: false,
+        rate_limit: None,
+        delay: None,
+        extension_caps: HashMap::new(),
+        warm_up: 0,
+        auto_calibrate: false,
+        recursion_depth: None,
+        save_responses: None,
+        interval_stats: None,
+        live: Arc::new(LiveStats::default()),
+        report: None,
+        manifest: None,
+        manifest_append: false,
+        drain_timeout: None,
+        matchers: ResponseMatchers::default(),
+        sigv4: None,
+        retry: RetryPolicy {
+            max_retries: 0,
+            on_503: false,
+            max_wait: Duration::from_secs(60),
+            backoff: Backoff::Exponential,
+            base: Duration::from_millis(10),
+            cap: Duration::from_millis(100),
+            rng: Arc::new(Mutex::new(rand::rngs::StdRng::seed_from_u64(0))),
+        },
+        mac: None,
+        ua: None,
+        no_dns: false,
+        timeout: 10,
+        max_concurrent: 4,
+        target_latency: None,
+        max_per_host: None,
+        ports: None,
+        scan_output_xml: None,
+        udp: false,
+        banner_bytes: None,
+        banner_probe: false,
+        connect_concurrency: None,
+        read_concurrency: None,
+        verbose: 0,
+        quiet: true,
+        outdir: outdir.to_path_buf(),
+    }
+}
+
+/// Ejecuta `config` sobre `urls` como lo haría la CLI.
+pub async fn run(config: ModeConfig, urls: Vec<String>) -> ModeResult {
+    run_targets(config, Targets::from_urls(urls)).await
+}
+
+/// Como `run`, con los objetivos ya construidos.
+pub async fn run_targets(config: ModeConfig, targets: Targets) -> ModeResult {
+    modes::execute_mode(config, downzer(), targets, Arc::new(AtomicBool::new(false)), 1).await.unwrap()
+}
+
+/// Archivos de `dir` (sin recorrer subdirectorios), ordenados.
+pub fn files_in(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| p.is_file()).collect())
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+thread_local! {
+    static ALLOCATED: Cell<isize> = const { Cell::new(0) };
+    static PEAK: Cell<isize> = const { Cell::new(0) };
+}
+
+/// Asignador que lleva la cuenta de la memoria viva de cada hilo, para
+/// comprobar que algo se procesa sin cargarlo entero en memoria.
+struct CountingAllocator;
+
+impl CountingAllocator {
+    fn track(delta: isize) {
+        let _ = ALLOCATED.try_with(|allocated| {
+            let now = allocated.get() + delta;
+            allocated.set(now);
+            let _ = PEAK.try_with(|peak| peak.set(peak.get().max(now)));
+        });
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        Self::track(layout.size() as isize);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        Self::track(-(layout.size() as isize));
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        Self::track(new_size as isize - layout.size() as isize);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Máximo de memoria viva que alcanza el hilo actual durante `f`, por encima
+/// de la que ya tenía al empezar. Los tests de tokio corren en un solo hilo.
+pub async fn peak_memory<F: std::future::Future>(f: F) -> (F::Output, usize) {
+    let start = ALLOCATED.with(Cell::get);
+    PEAK.with(|peak| peak.set(start));
+    let output = f.await;
+    (output, (PEAK.with(Cell::get) - start).max(0) as usize)
+}
@@ -1,6 +1,6 @@
 use dialoguer::{theme::ColorfulTheme, Select, Input, Confirm};
 use crate::core::downzer::Config;
-use crate::audio::sound::{get_available_sounds, validate_custom_sound};
+use crate::audio::sound::{get_available_sounds, play_source, resolve_sound, validate_custom_sound, SoundSource, MAX_FADE_MS};
 use anyhow::Result;
 use std::path::PathBuf;
 
@@ -10,9 +10,13 @@ pub fn show_config_panel(config: &mut Config) -> Result<bool> {
             "🔊 Enable/Disable Sound",
             "⏱️  Sound Minimum Duration",
             "🔉 Sound Volume",
+            "🌙 Sound Fade-out",
             "✅ Sound on Task Complete",
             "🎯 Sound on All Complete",
             "🎵 Change Completion Sound",
+            "❗ Sound on Task Failure",
+            "🔔 Change Failure Sound",
+            "🔊 Test Sound",
             "💾 Save and Exit",
             "❌ Exit without Saving",
         ];
@@ -54,6 +58,21 @@ pub fn show_config_panel(config: &mut Config) -> Result<bool> {
                 println!("✓ Volume set to {:.0}%", volume * 100.0);
             }
             3 => {
+                let fade: u64 = Input::with_theme(&ColorfulTheme::default())
+                    .with_prompt(format!("Fade-out length in ms (0 - {})", MAX_FADE_MS))
+                    .default(config.sound_fade_ms)
+                    .validate_with(|input: &u64| -> Result<(), &str> {
+                        if *input <= MAX_FADE_MS {
+                            Ok(())
+                        } else {
+                            Err("Fade-out is too long")
+                        }
+                    })
+                    .interact()?;
+                config.sound_fade_ms = fade;
+                println!("✓ Fade-out set to {} ms", fade);
+            }
+            4 => {
                 config.sound_on_task_complete = Confirm::with_theme(&ColorfulTheme::default())
                     .with_prompt("Play sound on each task completion?")
                     .default(config.sound_on_task_complete)
@@ -61,7 +80,7 @@ pub fn show_config_panel(config: &mut Config) -> Result<bool> {
                 println!("✓ Task completion sound {}", 
                     if config.sound_on_task_complete { "enabled" } else { "disabled" });
             }
-            4 => {
+            5 => {
                 config.sound_on_all_complete = Confirm::with_theme(&ColorfulTheme::default())
                     .with_prompt("Play sound when all tasks complete?")
                     .default(config.sound_on_all_complete)
@@ -69,16 +88,34 @@ pub fn show_config_panel(config: &mut Config) -> Result<bool> {
                 println!("✓ All tasks completion sound {}", 
                     if config.sound_on_all_complete { "enabled" } else { "disabled" });
             }
-            5 => {
-                if let Err(e) = change_sound(config) {
+            6 => {
+                if let Err(e) = change_sound("🎵 Select completion sound", &mut config.sound_type) {
                     println!("❌ Error: {}", e);
                 }
             }
-            6 => {
+            7 => {
+                config.sound_on_failure = Confirm::with_theme(&ColorfulTheme::default())
+                    .with_prompt("Play a different sound when a task fails?")
+                    .default(config.sound_on_failure)
+                    .interact()?;
+                println!("✓ Task failure sound {}",
+                    if config.sound_on_failure { "enabled" } else { "disabled" });
+            }
+            8 => {
+                if let Err(e) = change_sound("🔔 Select failure sound", &mut config.failure_sound_type) {
+                    println!("❌ Error: {}", e);
+                }
+            }
+            9 => {
+                if let Err(e) = test_sound(config) {
+                    println!("❌ Error: {}", e);
+                }
+            }
+            10 => {
                 println!("💾 Saving configuration...");
                 return Ok(true);
             }
-            7 => {
+            11 => {
                 println!("❌ Discarding changes...");
                 return Ok(false);
             }
@@ -89,14 +126,47 @@ pub fn show_config_panel(config: &mut Config) -> Result<bool> {
     }
 }
 
-fn change_sound(config: &mut Config) -> Result<()> {
+/// Reproduce el sonido elegido (el de completar o, si está activado, el de
+/// fallo) con el volumen y el fade-out actuales, aún sin guardar. Los
+/// errores, p.ej. un archivo propio que ya no existe, se muestran aquí mismo.
+fn test_sound(config: &Config) -> Result<()> {
+    let selection = if config.sound_on_failure {
+        let choices = [
+            format!("Completion sound ({})", config.sound_type),
+            format!("Failure sound ({})", config.failure_sound_type),
+        ];
+        Select::with_theme(&ColorfulTheme::default())
+            .with_prompt("🔊 Which sound?")
+            .items(&choices)
+            .default(0)
+            .interact()?
+    } else {
+        0
+    };
+
+    // Un nombre o archivo no válido se avisa antes de intentar reproducir
+    let (sound, source) = preview_source(config, selection)?;
+    println!("🔊 Playing {} at {:.0}% volume...", sound, config.sound_volume * 100.0);
+    play_source(source, config.sound_volume, config.sound_fade_ms)
+}
+
+/// Lo que suena al probar la opción `selection` de `test_sound` (0, el de
+/// completar; 1, el de fallo), con su nombre en la configuración.
+fn preview_source(config: &Config, selection: usize) -> Result<(&str, SoundSource)> {
+    let sound = if selection == 0 { &config.sound_type } else { &config.failure_sound_type };
+    Ok((sound, resolve_sound(sound)?))
+}
+
+/// Elige un sonido predefinido o un archivo propio y lo guarda en `sound`
+/// (el de completar o el de fallo).
+fn change_sound(prompt: &str, sound: &mut String) -> Result<()> {
     let sound_options = get_available_sounds();
-    let mut display_options: Vec<String> = sound_options.iter().cloned().collect();
+    let mut display_options: Vec<String> = sound_options.to_vec();
     display_options.push("📁 Load Custom Sound File...".to_string());
     display_options.push("🔙 Back".to_string());
     
     let selection = Select::with_theme(&ColorfulTheme::default())
-        .with_prompt("🎵 Select completion sound")
+        .with_prompt(prompt)
         .items(&display_options)
         .default(0)
         .interact()?;
@@ -108,19 +178,19 @@ fn change_sound(config: &mut Config) -> Result<()> {
     
     if selection == display_options.len() - 2 {
         // Custom sound
-        return load_custom_sound(config);
+        return load_custom_sound(sound);
     }
     
     // Sonido predefinido
     if selection < sound_options.len() {
-        config.sound_type = sound_options[selection].clone();
+        *sound = sound_options[selection].clone();
         println!("✓ Sound changed to: {}", sound_options[selection]);
     }
     
     Ok(())
 }
 
-fn load_custom_sound(config: &mut Config) -> Result<()> {
+fn load_custom_sound(sound: &mut String) -> Result<()> {
     println!("\n📁 Enter the path to your custom sound file:");
     println!("   Supported formats: MP3, WAV, OGG, FLAC");
     println!("   Example: /home/user/sounds/mysound.mp3");
@@ -135,7 +205,7 @@ fn load_custom_sound(config: &mut Config) -> Result<()> {
     // Validar el archivo
     match validate_custom_sound(&path) {
         Ok(_) => {
-            config.sound_type = path_str;
+            *sound = path_str;
             println!("✓ Custom sound loaded: {}", path.display());
             Ok(())
         }
@@ -144,4 +214,49 @@ fn load_custom_sound(config: &mut Config) -> Result<()> {
             Err(e)
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::sound::SoundType;
+
+    #[test]
+    fn preview_resolves_each_builtin_selection() {
+        let mut config = Config { sound_on_failure: true, ..Default::default() };
+        for name in get_available_sounds() {
+            config.sound_type = name.clone();
+            let (sound, source) = preview_source(&config, 0).unwrap();
+            assert_eq!(sound, name);
+            let SoundSource::Builtin(sound_type) = source else { panic!("{} no es predefinido", name) };
+            assert_eq!(sound_type, SoundType::from_name(&name).unwrap());
+        }
+        // La segunda opción es el sonido de fallo
+        let (sound, source) = preview_source(&config, 1).unwrap();
+        assert_eq!((sound, source), ("thuddry", SoundSource::Builtin(SoundType::Thuddry)));
+    }
+
+    #[test]
+    fn preview_resolves_custom_files_and_reports_bad_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let wav = dir.path().join("done.wav");
+        std::fs::write(&wav, SoundType::Complete.asset().bytes).unwrap();
+        let config = Config {
+            sound_type: wav.display().to_string(),
+            failure_sound_type: dir.path().join("gone.mp3").display().to_string(),
+            ..Default::default()
+        };
+        assert_eq!(preview_source(&config, 0).unwrap().1, SoundSource::Custom(wav));
+        let err = preview_source(&config, 1).unwrap_err();
+        assert!(err.to_string().starts_with("Sound file not found"), "{}", err);
+
+        let txt = dir.path().join("notes.txt");
+        std::fs::write(&txt, "x").unwrap();
+        let config = Config { sound_type: txt.display().to_string(), ..Default::default() };
+        let err = preview_source(&config, 0).unwrap_err();
+        assert!(err.to_string().contains("Unsupported audio format: txt"), "{}", err);
+        // Un nombre mal escrito se explica con la lista de disponibles
+        let config = Config { sound_type: "coins".to_string(), ..Default::default() };
+        assert!(preview_source(&config, 0).unwrap_err().to_string().starts_with("Unknown sound: coins"));
+    }
+}
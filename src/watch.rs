@@ -0,0 +1,143 @@
+use std::time::Duration;
+
+use colored::*;
+
+use crate::core::db::Database;
+use crate::core::progress::task_progress_bar;
+use crate::core::task::TaskStatus;
+use crate::ipc::{self, IpcCommand, IpcResponse, TaskSummary};
+
+/// Cada cuánto se pregunta el estado al servidor.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// `downzer watch`: pregunta por la tarea cada segundo y redibuja la barra
+/// hasta que termina. Si la instancia deja de responder (al acabar la tarea
+/// el proceso de `--add` sale), el estado final se lee de la base de datos;
+/// con una instancia remota solo queda lo último que se vio.
+pub async fn watch(id: u32) -> anyhow::Result<()> {
+    watch_with(id, ipc::is_remote(), ipc::send_command).await
+}
+
+/// `watch` con el envío de comandos aparte: `send` hace cada consulta y
+/// `remote` indica si va a una instancia remota.
+async fn watch_with(
+    id: u32,
+    remote: bool,
+    mut send: impl FnMut(&IpcCommand) -> anyhow::Result<IpcResponse>,
+) -> anyhow::Result<()> {
+    let mut bar: Option<indicatif::ProgressBar> = None;
+    // Lo último que se vio, por si una instancia remota deja de responder
+    let mut last: Option<TaskSummary> = None;
+    loop {
+        let task = match send(&IpcCommand::Status(id)) {
+            Ok(IpcResponse::TaskList(tasks)) => match tasks.into_iter().next() {
+                Some(task) => task,
+                None => anyhow::bail!("Task {} not found", id),
+            },
+            Ok(IpcResponse::Error(e)) => anyhow::bail!(e),
+            Ok(_) => anyhow::bail!("Unexpected IPC response"),
+            Err(e) if remote => {
+                if let Some(bar) = bar.take() {
+                    bar.finish_and_clear();
+                }
+                match &last {
+                    Some(task) => {
+                        print_final(task);
+                        anyhow::bail!("The instance stopped answering: {:#}", e);
+                    }
+                    None => return Err(e),
+                }
+            }
+            Err(_) => {
+                if let Some(bar) = bar.take() {
+                    bar.finish_and_clear();
+                } else {
+                    println!("{} No running instance found", "⚠".yellow());
+                }
+                return match Database::new()?.get_task(id)? {
+                    Some(record) => {
+                        print_final(&TaskSummary::from_record(record));
+                        Ok(())
+                    }
+                    None => anyhow::bail!("Task {} not found", id),
+                };
+            }
+        };
+
+        let pb = bar.get_or_insert_with(|| task_progress_bar(task.total as u64));
+        pb.set_length(task.total as u64);
+        pb.set_position(task.completed.min(task.total) as u64);
+        pb.set_message(match task.eta() {
+            Some(eta) => format!("{}, ETA {}", task.status, crate::format_hms(eta.as_secs())),
+            None => task.status.to_string(),
+        });
+        if task.status.is_terminal() {
+            pb.finish_and_clear();
+            print_final(&task);
+            return Ok(());
+        }
+        last = Some(task);
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+fn print_final(task: &TaskSummary) {
+    let status = match task.status {
+        TaskStatus::Completed => task.status.to_string().green(),
+        TaskStatus::Failed => task.status.to_string().red(),
+        _ => task.status.to_string().yellow(),
+    };
+    println!("Task #{}: {} ({}/{}, {:.1}%)", task.id, status, task.completed, task.total, task.percent());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `send` falso: a cada `Status` responde con la tarea `step` elementos
+    /// más avanzada, hasta completarla, y anota los comandos recibidos.
+    fn advancing_task(
+        total: usize,
+        step: usize,
+        seen: &mut Vec<String>,
+    ) -> impl FnMut(&IpcCommand) -> anyhow::Result<IpcResponse> + '_ {
+        let mut completed = 0;
+        move |cmd| {
+            seen.push(format!("{:?}", cmd));
+            completed = (completed + step).min(total);
+            let status = if completed == total { TaskStatus::Completed } else { TaskStatus::Running };
+            let task = TaskSummary { id: 5, status, url: "http://h/FUZZW1".to_string(), completed, total, elapsed_secs: 1 };
+            Ok(IpcResponse::TaskList(vec![task]))
+        }
+    }
+
+    #[tokio::test]
+    async fn watch_polls_until_the_task_finishes() {
+        let mut seen = Vec::new();
+        let start = std::time::Instant::now();
+        watch_with(5, true, advancing_task(30, 10, &mut seen)).await.unwrap();
+        // Tres consultas (10, 20 y 30 de 30), una por segundo
+        assert_eq!(seen, ["Status(5)", "Status(5)", "Status(5)"]);
+        assert!(start.elapsed() >= POLL_INTERVAL * 2, "{:?}", start.elapsed());
+    }
+
+    #[tokio::test]
+    async fn a_remote_instance_that_stops_answering_is_an_error() {
+        let mut seen = Vec::new();
+        let mut answer = advancing_task(30, 10, &mut seen);
+        let mut polls = 0;
+        let err = watch_with(5, true, |cmd| {
+            polls += 1;
+            if polls == 1 { answer(cmd) } else { anyhow::bail!("connection refused") }
+        })
+        .await
+        .unwrap_err();
+        assert!(err.to_string().starts_with("The instance stopped answering"), "{:#}", err);
+    }
+
+    #[tokio::test]
+    async fn an_unknown_task_is_an_error() {
+        let err = watch_with(9, true, |_| Ok(IpcResponse::TaskList(Vec::new()))).await.unwrap_err();
+        assert_eq!(err.to_string(), "Task 9 not found");
+    }
+}